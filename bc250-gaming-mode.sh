@@ -1,18 +1,32 @@
 #!/bin/bash
 
 CONTROL_FILE="/tmp/bc250-max-performance"
+GOVERNOR="bc-250-rust-governor"
+CLIENT="gaming-mode-$$"
+USE_SOCKET=0
 
-# Function to clean up the control file
+# Function to release max performance mode
 cleanup() {
-    rm -f "$CONTROL_FILE"
+    if [ "$USE_SOCKET" -eq 1 ]; then
+        "$GOVERNOR" perf-release "$CLIENT" > /dev/null 2>&1
+    else
+        rm -f "$CONTROL_FILE"
+    fi
     echo "BC-250: Returning to Normal Mode"
 }
 
 # Set a trap to run cleanup function on script exit
 trap cleanup EXIT
 
-# Create the control file to signal max performance mode
-touch "$CONTROL_FILE"
+# Prefer a refcounted request over the control socket so several games or
+# scripts can hold max performance at once. The request is tied to this
+# script's PID, so the daemon drops it even if we are killed without cleanup.
+if command -v "$GOVERNOR" > /dev/null 2>&1 && "$GOVERNOR" perf-request "$CLIENT" --pid $$ > /dev/null 2>&1; then
+    USE_SOCKET=1
+else
+    # Fall back to the control file for daemons without the control socket
+    touch "$CONTROL_FILE"
+fi
 echo "BC-250: Max Performance Mode Activated"
 
 # Run the game and wait for it to finish
@@ -24,4 +38,4 @@ GAME_EXIT_CODE=$?
 # No explicit cleanup is needed here
 
 # Exit with the same code as the game
-exit $GAME_EXIT_CODE
\ No newline at end of file
+exit $GAME_EXIT_CODE
@@ -0,0 +1,68 @@
+use std::{
+    fmt,
+    process::{Command, Stdio},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertKind {
+    ApplyFailures,
+    ThermalWarning,
+    ThermalEmergency,
+    SetterStalled,
+    FanStall,
+    FanFailure,
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AlertKind::ApplyFailures => "apply-failures",
+            AlertKind::ThermalWarning => "thermal-warning",
+            AlertKind::ThermalEmergency => "thermal-emergency",
+            AlertKind::SetterStalled => "setter-stalled",
+            AlertKind::FanStall => "fan-stall",
+            AlertKind::FanFailure => "fan-failure",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Raises health alerts: always logged, and optionally forwarded to a
+/// user-configured hook command (run through `sh -c` with the alert in
+/// BC250_ALERT / BC250_MESSAGE).
+#[derive(Debug, Clone, Default)]
+pub struct Alerter {
+    command: Option<String>,
+}
+
+impl Alerter {
+    pub fn new(command: Option<String>) -> Self {
+        Self { command: command.filter(|c| !c.trim().is_empty()) }
+    }
+
+    pub fn raise(&self, kind: AlertKind, message: &str) {
+        eprintln!("🚨 ALERT [{}]: {}", kind, message);
+
+        let Some(command) = &self.command else {
+            return;
+        };
+
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("BC250_ALERT", kind.to_string())
+            .env("BC250_MESSAGE", message)
+            .stdin(Stdio::null())
+            .spawn();
+
+        match child {
+            // Reap the hook in the background so a slow script never blocks the caller
+            Ok(mut child) => {
+                std::thread::spawn(move || {
+                    let _ = child.wait();
+                });
+            }
+            Err(e) => eprintln!("⚠️  Failed to run alert hook '{}': {}", command, e),
+        }
+    }
+}
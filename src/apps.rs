@@ -0,0 +1,160 @@
+use glob::glob;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{fdinfo::SharedClients, governor::PerformanceMode};
+
+const APP_SCOPE_GLOB: &str = "/sys/fs/cgroup/user.slice/user-*.slice/user@*.service/app.slice/**/*.scope";
+/// Matched app ids are logged up to this many characters
+const DISPLAY_CHARS: usize = 80;
+/// Busy share below which the top GPU client is not matched, so an idle desktop does not pick a rule
+const TOP_CLIENT_MIN_BUSY: f32 = 0.05;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AppSource {
+    /// Running systemd user app scopes (app-<launcher>-<id>-<n>.scope)
+    Cgroup,
+    /// App id reported by the compositor through the control socket
+    Focus,
+    /// Running processes, by name (/proc/<pid>/comm) and command line
+    Process,
+    /// The process using the most GPU time, by name and command line (needs `[gpu-clients]`)
+    #[serde(rename = "top-client")]
+    TopClient,
+}
+
+#[derive(Debug, Clone)]
+pub struct AppRule {
+    /// Substring matched against the app id / scope name / process name or command line
+    pub app: String,
+    pub mode: Option<PerformanceMode>,
+    /// Scaling profile used while the app is active
+    pub profile: Option<String>,
+}
+
+/// Resolves the mode and profile requested by tracked applications and
+/// publishes them for the governor. `None` means no rule matched and the
+/// default applies.
+pub struct AppTracker {
+    source: AppSource,
+    rules: Vec<AppRule>,
+    focused_app: Arc<Mutex<Option<String>>>,
+    gpu_clients: SharedClients,
+    pub app_mode: Arc<Mutex<Option<PerformanceMode>>>,
+    pub app_profile: Arc<Mutex<Option<String>>>,
+}
+
+impl AppTracker {
+    pub fn new(source: AppSource, rules: Vec<AppRule>, focused_app: Arc<Mutex<Option<String>>>, gpu_clients: SharedClients) -> Self {
+        Self {
+            source,
+            rules,
+            focused_app,
+            gpu_clients,
+            app_mode: Arc::new(Mutex::new(None)),
+            app_profile: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns the first rule matching any of the candidate app ids.
+    fn resolve(&self, candidates: &[String]) -> Option<(&AppRule, String)> {
+        self.rules.iter().find_map(|rule| {
+            candidates.iter()
+                .find(|app| app.contains(&rule.app))
+                .map(|app| (rule, app.clone()))
+        })
+    }
+
+    fn candidates(&self) -> Vec<String> {
+        match self.source {
+            AppSource::Focus => self.focused_app.lock().unwrap().iter().cloned().collect(),
+            AppSource::Cgroup => running_app_scopes(),
+            AppSource::Process => running_processes(),
+            AppSource::TopClient => {
+                let top = self.gpu_clients.lock().unwrap().first().filter(|c| c.busy >= TOP_CLIENT_MIN_BUSY).map(|c| c.pid);
+                top.map_or_else(Vec::new, |pid| process_names(&Path::new("/proc").join(pid.to_string())))
+            }
+        }
+    }
+
+    pub fn start(self, interval: Duration, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            let mut last_match: Option<String> = None;
+            while !shutdown.load(Ordering::SeqCst) {
+                let resolved = self.resolve(&self.candidates());
+                let current = resolved.as_ref().map(|(_, app)| app.clone());
+
+                if current != last_match {
+                    match &resolved {
+                        Some((rule, app)) => {
+                            let mut actions: Vec<String> = rule.mode.iter().map(|m| m.label()).collect();
+                            actions.extend(rule.profile.iter().map(|p| format!("profile '{p}'")));
+                            // Command lines can be long; the start names the program
+                            let app: String = app.chars().take(DISPLAY_CHARS).collect();
+                            println!("🎯 Tracked app '{}' matched '{}' → {}", app, rule.app, actions.join(", "));
+                        }
+                        None => println!("🎯 No tracked app active, using default mode and profile"),
+                    }
+                    last_match = current;
+                }
+                *self.app_mode.lock().unwrap() = resolved.as_ref().and_then(|(rule, _)| rule.mode);
+                *self.app_profile.lock().unwrap() = resolved.and_then(|(rule, _)| rule.profile.clone());
+
+                std::thread::sleep(interval);
+            }
+        })
+    }
+}
+
+/// Name and command line (arguments joined by spaces) of every running process.
+fn running_processes() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+        .flat_map(|entry| process_names(&entry.path()))
+        .collect()
+}
+
+/// Name and command line of the process at `/proc/<pid>`.
+fn process_names(path: &Path) -> Vec<String> {
+    let mut found = Vec::new();
+    if let Ok(comm) = fs::read_to_string(path.join("comm")) {
+        found.push(comm.trim_end().to_string());
+    }
+    // Kernel threads have an empty command line
+    if let Ok(cmdline) = fs::read(path.join("cmdline")).map(|c| String::from_utf8_lossy(&c).replace('\0', " ")) {
+        let cmdline = cmdline.trim_end();
+        if !cmdline.is_empty() {
+            found.push(cmdline.to_string());
+        }
+    }
+    found
+}
+
+/// Names of systemd user app scopes that still contain processes.
+fn running_app_scopes() -> Vec<String> {
+    let Ok(paths) = glob(APP_SCOPE_GLOB) else {
+        return Vec::new();
+    };
+    paths
+        .flatten()
+        .filter(|scope| {
+            fs::read_to_string(scope.join("cgroup.procs"))
+                .map(|procs| !procs.trim().is_empty())
+                .unwrap_or(false)
+        })
+        .filter_map(|scope| scope.file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect()
+}
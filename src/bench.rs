@@ -0,0 +1,220 @@
+use glob::glob;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{control, governor::PerformanceMode};
+
+const BENCH_CLIENT: &str = "bench";
+const POWER_GLOBS: [&str; 2] = [
+    "/sys/class/drm/card*/device/hwmon/hwmon*/power1_average",
+    "/sys/class/drm/card*/device/hwmon/hwmon*/power1_input",
+];
+
+/// Options for the `bench` subcommand.
+#[derive(Debug)]
+pub struct BenchOptions {
+    pub command: String,
+    pub modes: Vec<PerformanceMode>,
+    pub socket: PathBuf,
+    pub status_file: PathBuf,
+    /// Text preceding the score in the benchmark's output, e.g. "Score:"
+    pub score_pattern: String,
+    /// Idle time after switching modes, before the benchmark starts
+    pub settle: Duration,
+}
+
+/// Averages collected while the benchmark ran under one mode.
+#[derive(Debug, Default)]
+struct BenchResult {
+    mode: String,
+    observed_mode: Option<String>,
+    duration: Duration,
+    avg_freq: Option<f32>,
+    avg_temp: Option<f32>,
+    avg_power: Option<f32>,
+    score: Option<f64>,
+    exit_ok: bool,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    freq: Vec<f32>,
+    temp: Vec<f32>,
+    power: Vec<f32>,
+    modes: Vec<String>,
+}
+
+fn mean(values: &[f32]) -> Option<f32> {
+    (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+}
+
+/// Most frequently observed mode name.
+fn dominant(values: &[String]) -> Option<String> {
+    let mut counts: Vec<(&String, usize)> = Vec::new();
+    for v in values {
+        match counts.iter_mut().find(|(name, _)| *name == v) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((v, 1)),
+        }
+    }
+    counts.into_iter().max_by_key(|&(_, n)| n).map(|(name, _)| name.clone())
+}
+
+/// GPU package power in watts, from the amdgpu hwmon node.
+fn read_power_watts() -> Option<f32> {
+    POWER_GLOBS.iter()
+        .filter_map(|pattern| glob(pattern).ok())
+        .flat_map(|paths| paths.flatten())
+        .find_map(|path| fs::read_to_string(path).ok()?.trim().parse::<f32>().ok())
+        .map(|microwatts| microwatts / 1_000_000.0)
+}
+
+/// Polls the daemon's status file and hwmon power until `done` is set.
+fn start_sampler(status_file: PathBuf, done: Arc<AtomicBool>) -> JoinHandle<Samples> {
+    std::thread::spawn(move || {
+        let mut samples = Samples::default();
+        while !done.load(Ordering::SeqCst) {
+            if let Some(status) = fs::read_to_string(&status_file).ok()
+                .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+            {
+                if let Some(freq) = status.get("applied_freq").and_then(Value::as_f64) {
+                    samples.freq.push(freq as f32);
+                }
+                if let Some(temp) = status.pointer("/thermal/gpu_max").and_then(Value::as_f64) {
+                    samples.temp.push(temp as f32);
+                }
+                if let Some(mode) = status.get("mode").and_then(Value::as_str) {
+                    samples.modes.push(mode.to_string());
+                }
+            }
+            if let Some(power) = read_power_watts() {
+                samples.power.push(power);
+            }
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        samples
+    })
+}
+
+/// Number following the last occurrence of `pattern` in `output`.
+fn parse_score(output: &str, pattern: &str) -> Option<f64> {
+    output.rmatch_indices(pattern).find_map(|(idx, _)| {
+        output[idx + pattern.len()..]
+            .split_whitespace()
+            .next()?
+            .trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse()
+            .ok()
+    })
+}
+
+fn select_mode(socket: &Path, mode: PerformanceMode) -> Result<(), IoError> {
+    match mode {
+        PerformanceMode::MaxPerformance => {
+            let pid = std::process::id();
+            control::call(socket, "perf.request", json!({ "client": BENCH_CLIENT, "pid": pid }))?;
+        }
+        _ => {
+            control::call(socket, "perf.release", json!({ "client": BENCH_CLIENT }))?;
+        }
+    }
+    Ok(())
+}
+
+fn run_one(opts: &BenchOptions, mode: PerformanceMode) -> Result<BenchResult, IoError> {
+    select_mode(&opts.socket, mode)?;
+    println!("⏱️  [{}] settling for {}s", mode.name(), opts.settle.as_secs());
+    std::thread::sleep(opts.settle);
+
+    println!("🏁 [{}] running: {}", mode.name(), opts.command);
+    let done = Arc::new(AtomicBool::new(false));
+    let sampler = start_sampler(opts.status_file.clone(), Arc::clone(&done));
+    let started = Instant::now();
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&opts.command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output();
+    let duration = started.elapsed();
+    done.store(true, Ordering::SeqCst);
+    let samples = sampler.join().unwrap_or_default();
+    let output = output?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    print!("{}", stdout);
+
+    Ok(BenchResult {
+        mode: mode.name().to_string(),
+        observed_mode: dominant(&samples.modes),
+        duration,
+        avg_freq: mean(&samples.freq),
+        avg_temp: mean(&samples.temp),
+        avg_power: mean(&samples.power),
+        score: parse_score(&stdout, &opts.score_pattern),
+        exit_ok: output.status.success(),
+    })
+}
+
+fn fmt_opt<T: std::fmt::Display>(value: Option<T>, suffix: &str) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| format!("{v}{suffix}"))
+}
+
+fn print_table(results: &[BenchResult]) {
+    println!();
+    println!("{:<16} {:>8} {:>10} {:>9} {:>9} {:>12}  notes", "mode", "time", "avg freq", "avg temp", "avg power", "score");
+    for r in results {
+        let mut notes = Vec::new();
+        if !r.exit_ok {
+            notes.push("benchmark failed".to_string());
+        }
+        if let Some(observed) = r.observed_mode.as_ref().filter(|m| **m != r.mode) {
+            notes.push(format!("daemon ran {observed}"));
+        }
+        println!("{:<16} {:>7.1}s {:>10} {:>9} {:>9} {:>12}  {}",
+            r.mode,
+            r.duration.as_secs_f32(),
+            fmt_opt(r.avg_freq.map(|f| format!("{f:.0}")), "MHz"),
+            fmt_opt(r.avg_temp.map(|t| format!("{t:.1}")), "°C"),
+            fmt_opt(r.avg_power.map(|p| format!("{p:.1}")), "W"),
+            fmt_opt(r.score, ""),
+            notes.join(", "));
+    }
+}
+
+/// Runs the benchmark once per mode against a running daemon and prints a
+/// comparison table. The bench hold is always released afterwards.
+pub fn run(opts: &BenchOptions) -> Result<(), IoError> {
+    if opts.command.trim().is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidInput, "no benchmark command given"));
+    }
+
+    let mut results = Vec::new();
+    let mut outcome = Ok(());
+    for &mode in &opts.modes {
+        match run_one(opts, mode) {
+            Ok(result) => results.push(result),
+            Err(e) => {
+                outcome = Err(e);
+                break;
+            }
+        }
+    }
+
+    if let Err(e) = control::call(&opts.socket, "perf.release", json!({ "client": BENCH_CLIENT })) {
+        eprintln!("⚠️  Failed to release bench max-performance request: {}", e);
+    }
+    print_table(&results);
+    outcome
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::interop;
+
+/// How long a GPU that just failed may take to answer register reads again.
+const RECOVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options for the `calibrate` subcommand.
+#[derive(Debug)]
+pub struct CalibrateOptions {
+    /// (MHz, mV) points to start from, ascending by frequency
+    pub points: Vec<(u16, u16)>,
+    pub step_mv: u16,
+    /// Added back on top of the lowest stable voltage
+    pub margin_mv: u16,
+    /// Voltage the search never goes below
+    pub floor_mv: u16,
+    /// How long each frequency/voltage pair has to hold
+    pub hold: Duration,
+    /// Load run during each hold (through `sh -c`); restarted if it exits early
+    pub command: Option<String>,
+    /// Where to write the recommended safe-points block
+    pub output: Option<PathBuf>,
+}
+
+/// Outcome for one frequency.
+#[derive(Debug)]
+struct PointResult {
+    freq: u16,
+    start_mv: u16,
+    lowest_stable: Option<u16>,
+    failure: Option<String>,
+    recommended: u16,
+}
+
+/// Runs `command` (through `sh -c`, restarted if it exits cleanly) for
+/// `duration`, failing as soon as the GPU stops answering register reads,
+/// the load exits with an error or `tick` reports a problem. `tick` is
+/// called every 200ms.
+pub fn hold(
+    command: Option<&str>,
+    duration: Duration,
+    probe: &dyn Fn() -> Result<(), String>,
+    interrupted: &AtomicBool,
+    tick: &mut dyn FnMut() -> Result<(), String>,
+) -> Result<(), String> {
+    let spawn = |command: &str| -> Result<Child, String> {
+        Command::new("sh").arg("-c").arg(command)
+            .stdin(Stdio::null()).stdout(Stdio::null())
+            .spawn().map_err(|e| format!("cannot start load: {e}"))
+    };
+    let mut child = command.map(spawn).transpose()?;
+    let started = Instant::now();
+    let result = loop {
+        if interrupted.load(Ordering::SeqCst) {
+            break Err("interrupted".to_string());
+        }
+        if started.elapsed() >= duration {
+            break Ok(());
+        }
+        if let Err(e) = probe() {
+            break Err(format!("GPU stopped responding ({e})"));
+        }
+        if let Err(e) = tick() {
+            break Err(e);
+        }
+        if let Some(c) = child.as_mut() {
+            match c.try_wait() {
+                Ok(Some(status)) if !status.success() => break Err(format!("load failed ({status})")),
+                Ok(Some(_)) => *c = spawn(command.unwrap_or_default())?,
+                Ok(None) => {}
+                Err(e) => break Err(format!("cannot wait for load: {e}")),
+            }
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    };
+    if let Some(mut c) = child {
+        let _ = c.kill();
+        let _ = c.wait();
+    }
+    result
+}
+
+/// Prints the table of calibrated points.
+fn print_results(results: &[PointResult]) {
+    println!();
+    println!("{:>9} {:>9} {:>14} {:>12}  result", "frequency", "start", "lowest stable", "recommended");
+    for r in results {
+        println!("{:>6}MHz {:>7}mV {:>14} {:>10}mV  {}",
+            r.freq, r.start_mv,
+            r.lowest_stable.map_or_else(|| "n/a".to_string(), |v| format!("{v}mV")),
+            r.recommended,
+            r.failure.as_deref().unwrap_or("reached floor"));
+    }
+}
+
+/// Waits for the GPU to answer again after a failed step.
+pub fn recover(probe: &dyn Fn() -> Result<(), String>) -> Result<(), IoError> {
+    let started = Instant::now();
+    while let Err(e) = probe() {
+        if started.elapsed() >= RECOVERY_TIMEOUT {
+            return Err(IoError::other(format!("GPU did not recover within {}s: {}", RECOVERY_TIMEOUT.as_secs(), e)));
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Ok(())
+}
+
+/// Steps each frequency's voltage down by `step_mv` until the GPU fails
+/// (apply error, register reads failing as during a reset, or the load
+/// failing), then recommends the lowest stable voltage plus `margin_mv`,
+/// never above the starting voltage and never below a lower frequency's.
+/// `apply` writes one OD point; `probe` checks that the GPU still answers.
+/// The lowest starting point is written back when done.
+pub fn run(
+    opts: &CalibrateOptions,
+    apply: &mut dyn FnMut(u16, u16) -> Result<(), IoError>,
+    probe: &dyn Fn() -> Result<(), String>,
+) -> Result<(), IoError> {
+    let Some(&(base_freq, base_mv)) = opts.points.first() else {
+        return Err(IoError::new(ErrorKind::InvalidInput, "no frequencies to calibrate"));
+    };
+    if opts.step_mv == 0 {
+        return Err(IoError::new(ErrorKind::InvalidInput, "the voltage step must be at least 1mV"));
+    }
+    let interrupted = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&interrupted))?;
+    }
+
+    let mut results: Vec<PointResult> = Vec::new();
+    for &(freq, start_mv) in &opts.points {
+        let mut volt = start_mv;
+        let mut lowest_stable = None;
+        let failure = loop {
+            println!("🔬 {}MHz @ {}mV: holding for {}s", freq, volt, opts.hold.as_secs());
+            let outcome = apply(freq, volt)
+                .map_err(|e| format!("apply failed: {e}"))
+                .and_then(|_| hold(opts.command.as_deref(), opts.hold, probe, &interrupted, &mut || Ok(())));
+            match outcome {
+                Ok(()) => lowest_stable = Some(volt),
+                Err(reason) => {
+                    println!("💥 {}MHz @ {}mV: {}", freq, volt, reason);
+                    break Some(reason);
+                }
+            }
+            if volt < opts.floor_mv.saturating_add(opts.step_mv) {
+                println!("🧱 {}MHz reached the {}mV floor", freq, opts.floor_mv);
+                break None;
+            }
+            volt -= opts.step_mv;
+        };
+
+        // Get back to a known good point before deciding anything else
+        if failure.is_some() {
+            if let Err(e) = recover(probe).and_then(|()| apply(base_freq, base_mv)) {
+                print_results(&results);
+                return Err(e);
+            }
+        }
+
+        let floor = results.last().map_or(0, |r| r.recommended);
+        let recommended = lowest_stable.map_or(start_mv, |v| (v + opts.margin_mv).min(start_mv)).max(floor);
+        match lowest_stable {
+            Some(v) => println!("✅ {}MHz: stable down to {}mV, recommending {}mV", freq, v, recommended),
+            None => println!("⚠️  {}MHz failed at its starting voltage; keeping {}mV", freq, recommended),
+        }
+        results.push(PointResult { freq, start_mv, lowest_stable, failure, recommended });
+
+        if interrupted.load(Ordering::SeqCst) {
+            println!("🛑 Interrupted, stopping after {}MHz", freq);
+            break;
+        }
+    }
+
+    if let Err(e) = apply(base_freq, base_mv) {
+        eprintln!("⚠️  Failed to restore {}MHz @ {}mV: {}", base_freq, base_mv, e);
+    }
+
+    print_results(&results);
+
+    let points: Vec<(u16, u16)> = results.iter().map(|r| (r.freq, r.recommended)).collect();
+    let table = interop::to_safe_points_toml(&points);
+    println!();
+    print!("{}", table);
+    if let Some(path) = &opts.output {
+        fs::write(path, &table)?;
+        println!("💾 Wrote recommended safe points to {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use bc_250_rust_governor::{control, dbus, governor::PerformanceMode, interop, status, systemd};
+
+/// GPU frequency and thermal governor for the AMD BC-250.
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Without a subcommand the governor runs, as with `run`
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Config file; without one the built-in defaults are used, and a file that cannot be read or fails its checks stops the start
+    pub config: Option<PathBuf>,
+    /// Drive a mock GPU from a fault-injection scenario instead of the real one
+    #[arg(long, value_name = "SCENARIO")]
+    pub mock: Option<PathBuf>,
+    /// Log every busy sample, decision and apply to this CSV file, for `replay` or bug reports
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+    /// Sample, decide and log as usual, but only print the clock, fan and cpufreq writes
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SocketArgs {
+    /// Control socket of the running governor
+    #[arg(long, default_value = control::DEFAULT_SOCKET_PATH)]
+    pub socket: PathBuf,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the governor
+    Run(RunArgs),
+    /// Validate a config file and report every problem found
+    CheckConfig {
+        config: PathBuf,
+    },
+    /// List thermal sensors and fans
+    List,
+    /// Show current fan speeds
+    CurrentFan,
+    /// Test fan control by probing every PWM output
+    ProbeFans,
+    /// Pulse one fan to identify it
+    PulseFan {
+        /// Fan index as shown by `list`
+        index: usize,
+    },
+    /// Fan tools
+    Fan {
+        #[command(subcommand)]
+        command: FanCommand,
+    },
+    /// Install (and optionally enable) the systemd service
+    InstallService {
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Start the service right away
+        #[arg(long)]
+        enable: bool,
+    },
+    /// Report the focused application for per-app overrides
+    AppFocus {
+        app_id: Option<String>,
+        /// Report that nothing is focused
+        #[arg(long, conflicts_with = "app_id")]
+        clear: bool,
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Compare performance modes by running a benchmark under each
+    Bench {
+        /// Modes to compare, comma-separated
+        #[arg(long, value_delimiter = ',', default_value = "normal,max-performance", value_parser = parse_mode)]
+        modes: Vec<PerformanceMode>,
+        #[command(flatten)]
+        socket: SocketArgs,
+        /// Status file of the running governor
+        #[arg(long, default_value = status::DEFAULT_STATUS_PATH)]
+        status: PathBuf,
+        /// Text preceding the score in the benchmark's output
+        #[arg(long, default_value = "Score:")]
+        score_pattern: String,
+        /// Seconds to idle after switching modes
+        #[arg(long, default_value_t = 5)]
+        settle: u64,
+        /// Benchmark command line
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Print the card's OD table and the safe points it suggests
+    DumpOd {
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Read a mock GPU's table instead
+        #[arg(long, value_name = "SCENARIO")]
+        mock: Option<PathBuf>,
+    },
+    /// Print a safe-points table converted from another tool's config
+    Import {
+        /// lact or corectrl
+        #[arg(value_parser = parse_format)]
+        format: interop::Format,
+        file: PathBuf,
+        /// GPU entry to read from a LACT config holding several
+        #[arg(long)]
+        gpu: Option<String>,
+    },
+    /// Convert this config's safe points for another tool
+    Export {
+        /// lact, corectrl, amdgpu-clocks or script
+        #[arg(value_parser = parse_format)]
+        format: interop::Format,
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// GPU entry to write (LACT, CoreCtrl)
+        #[arg(long)]
+        gpu: Option<String>,
+        /// Clock to pin for static formats; defaults to the top safe point
+        #[arg(long)]
+        frequency: Option<u16>,
+    },
+    /// Search for the lowest stable voltage at each frequency and recommend safe points
+    Calibrate {
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Frequencies to calibrate, comma-separated; defaults to the config's safe points
+        #[arg(long, value_delimiter = ',')]
+        frequencies: Vec<u16>,
+        /// Voltage lowered per step (mV)
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..))]
+        step: u16,
+        /// Added back on top of the lowest stable voltage (mV)
+        #[arg(long, default_value_t = 25)]
+        margin: u16,
+        /// Lowest voltage ever tried (mV)
+        #[arg(long, default_value_t = 600)]
+        floor: u16,
+        /// Seconds each step has to hold
+        #[arg(long, default_value_t = 30)]
+        hold: u64,
+        /// Write the recommended safe-points block to this file
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Calibrate a mock GPU driven by a fault-injection scenario
+        #[arg(long, value_name = "SCENARIO")]
+        mock: Option<PathBuf>,
+        /// Acknowledge that the GPU will be pushed until it crashes
+        #[arg(long)]
+        yes: bool,
+        /// Load to run during each step, e.g. a GPU stress test
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+    /// Hold each safe point under load and report which ones pass
+    Stability {
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Frequencies to test, comma-separated; defaults to the config's safe points
+        #[arg(long, value_delimiter = ',')]
+        frequencies: Vec<u16>,
+        /// Seconds each point is held
+        #[arg(long, default_value_t = 300)]
+        duration: u64,
+        /// Write every temperature and power sample to this CSV file
+        #[arg(long)]
+        log: Option<PathBuf>,
+        /// Test a mock GPU driven by a fault-injection scenario
+        #[arg(long, value_name = "SCENARIO")]
+        mock: Option<PathBuf>,
+        /// Load to run while each point is held, e.g. a GPU stress test
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+    /// Pause or resume automatic mode selection
+    AutoMode {
+        action: AutoModeAction,
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Take a max-performance hold
+    PerfRequest {
+        client: String,
+        /// Release the hold when this process exits
+        #[arg(long)]
+        pid: Option<u32>,
+        /// Release the hold after this many seconds
+        #[arg(long)]
+        ttl: Option<u64>,
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Release a max-performance hold
+    PerfRelease {
+        client: String,
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Print the running governor's frequency, mode, temperatures, fan and stats
+    Status {
+        #[command(flatten)]
+        socket: SocketArgs,
+        /// Ask over D-Bus instead of the control socket
+        #[arg(long, value_name = "BUS")]
+        dbus: Option<dbus::Bus>,
+        /// Print the raw status JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Live dashboard of busy ratio, frequency, temperatures and fan speed
+    Tui {
+        #[command(flatten)]
+        socket: SocketArgs,
+        /// Poll interval in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// Print the running governor's counters
+    Stats {
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Switch the running governor's performance mode
+    SetMode {
+        #[arg(value_parser = parse_mode)]
+        mode: PerformanceMode,
+        /// Client name the hold is taken for
+        #[arg(long)]
+        client: Option<String>,
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Switch the running governor to a named scaling profile
+    SetProfile {
+        profile: String,
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// List the running governor's scaling profiles
+    Profiles {
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+    /// Run a recorded busy-sample trace through the scaling logic and print the decisions
+    Replay {
+        /// Trace written by `run --record`
+        trace: PathBuf,
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Scaling profile to replay with; defaults to the config's
+        #[arg(long)]
+        profile: Option<String>,
+        /// Print only the summary
+        #[arg(long)]
+        summary: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FanCommand {
+    /// Step each fan through its duty range, record the RPM and suggest curve points
+    Calibrate {
+        #[arg(long, default_value = systemd::DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Fans to calibrate by index as shown by `list`, comma-separated; defaults to every fan with a tachometer
+        #[arg(long, value_delimiter = ',')]
+        fans: Vec<usize>,
+        /// Duty step (percentage points)
+        #[arg(long, default_value_t = 10)]
+        step: u8,
+        /// Milliseconds each duty is held before the RPM is read
+        #[arg(long, default_value_t = 4000)]
+        settle_ms: u64,
+        /// Write the suggested fan curves to this file
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Calibrate the fans of a mock hwmon tree driven by a fault-injection scenario
+        #[arg(long, value_name = "SCENARIO")]
+        mock: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum AutoModeAction {
+    Pause,
+    Resume,
+}
+
+fn parse_mode(name: &str) -> Result<PerformanceMode, String> {
+    PerformanceMode::from_name(name.trim()).ok_or_else(|| {
+        let names: Vec<_> = PerformanceMode::ALL.iter().map(|m| m.name()).collect();
+        format!("unknown mode '{}', expected one of: {}", name, names.join(", "))
+    })
+}
+
+fn parse_format(name: &str) -> Result<interop::Format, String> {
+    interop::Format::from_name(name)
+        .ok_or_else(|| format!("unknown format '{}', expected lact, corectrl, amdgpu-clocks or script", name))
+}
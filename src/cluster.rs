@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Error as IoError, ErrorKind, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{governor::PerformanceMode, status::SharedStatus};
+
+pub const DEFAULT_PORT: u16 = 7250;
+/// Leader decisions older than this are ignored and the follower scales locally.
+pub const MIRROR_STALE_AFTER: Duration = Duration::from_secs(2);
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Standalone,
+    Leader,
+    Follower,
+}
+
+/// One decision broadcast by the leader, sent as a JSON line.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Decision {
+    target_freq: u16,
+    mode: String,
+}
+
+/// Latest decision received from the leader.
+#[derive(Debug, Clone, Copy)]
+pub struct Mirror {
+    pub target_freq: u16,
+    pub mode: PerformanceMode,
+    pub received: Instant,
+}
+
+pub type SharedMirror = Arc<Mutex<Option<Mirror>>>;
+
+/// The mirrored decision, if one arrived recently enough to follow.
+pub fn fresh(mirror: &SharedMirror) -> Option<Mirror> {
+    mirror.lock().unwrap().filter(|m| m.received.elapsed() < MIRROR_STALE_AFTER)
+}
+
+/// Accepts followers on `listen` and streams the local target frequency and
+/// mode to all of them until shutdown.
+pub fn start_leader(listen: &str, status: SharedStatus, shutdown: Arc<AtomicBool>) -> Result<JoinHandle<()>, IoError> {
+    let listener = TcpListener::bind(listen)?;
+    listener.set_nonblocking(true)?;
+    println!("🛰️  Cluster leader publishing decisions on {}", listen);
+
+    Ok(std::thread::spawn(move || {
+        let mut followers: Vec<TcpStream> = Vec::new();
+        while !shutdown.load(Ordering::SeqCst) {
+            loop {
+                match listener.accept() {
+                    Ok((stream, addr)) => {
+                        println!("🛰️  Follower {} connected", addr);
+                        let _ = stream.set_nodelay(true);
+                        let _ = stream.set_write_timeout(Some(PUBLISH_INTERVAL));
+                        followers.push(stream);
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        eprintln!("⚠️  Cluster accept failed: {}", e);
+                        break;
+                    }
+                }
+            }
+
+            let decision = {
+                let snapshot = status.lock().unwrap();
+                Decision { target_freq: snapshot.target_freq, mode: snapshot.mode.clone() }
+            };
+            if let Ok(line) = serde_json::to_string(&decision) {
+                followers.retain_mut(|stream| {
+                    let ok = stream.write_all(format!("{line}\n").as_bytes()).is_ok();
+                    if !ok {
+                        println!("🛰️  Follower {:?} disconnected", stream.peer_addr().ok());
+                    }
+                    ok
+                });
+            }
+            std::thread::sleep(PUBLISH_INTERVAL);
+        }
+    }))
+}
+
+fn follow(leader: &str, mirror: &SharedMirror, shutdown: &AtomicBool) -> Result<(), IoError> {
+    let addr = leader.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| IoError::new(ErrorKind::NotFound, format!("cannot resolve {leader}")))?;
+    let stream = TcpStream::connect_timeout(&addr, RECONNECT_DELAY)?;
+    stream.set_read_timeout(Some(MIRROR_STALE_AFTER))?;
+    println!("🛰️  Following cluster leader {}", leader);
+
+    for line in BufReader::new(stream).lines() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let decision: Decision = serde_json::from_str(&line?)
+            .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+        let Some(mode) = PerformanceMode::parse(&decision.mode) else {
+            continue;
+        };
+        *mirror.lock().unwrap() = Some(Mirror {
+            target_freq: decision.target_freq,
+            mode,
+            received: Instant::now(),
+        });
+    }
+    Err(IoError::new(ErrorKind::ConnectionAborted, "leader closed the connection"))
+}
+
+/// Keeps a connection to the leader open, reconnecting as needed, and
+/// publishes each received decision into `mirror`.
+pub fn start_follower(leader: String, mirror: SharedMirror, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut warned = false;
+        while !shutdown.load(Ordering::SeqCst) {
+            match follow(&leader, &mirror, &shutdown) {
+                Ok(()) => break,
+                Err(e) if !warned => {
+                    eprintln!("⚠️  Cluster leader {} unavailable: {}. Scaling locally until it returns", leader, e);
+                    warned = true;
+                }
+                Err(_) => {}
+            }
+            if mirror.lock().unwrap().is_some_and(|m| m.received.elapsed() < RECONNECT_DELAY) {
+                warned = false;
+            }
+            let retry_at = Instant::now() + RECONNECT_DELAY;
+            while Instant::now() < retry_at && !shutdown.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+    })
+}
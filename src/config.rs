@@ -0,0 +1,1921 @@
+//! The config file: every section with its defaults, and the checks a config
+//! must pass before the governor runs with it.
+
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    io::{Error as IoError, ErrorKind},
+    time::Duration,
+};
+
+use crate::{
+    thermal::{FanRef, TempLimits, ThermalBackend, ThermalStatus},
+    governor::{
+        BoostBudget, FrametimeTarget, LoadHysteresis,
+        LoadTarget, LoadTargetOverride, PidGains, RampRates, RampRatesOverride, EmaConfig,
+    },
+    control,
+    apps::AppSource,
+    status,
+    cluster::{self, Role},
+    memclk::DpmClock,
+    power::PowerBudget,
+    sampler::SamplerKind,
+    schedule,
+    pci,
+    dbus,
+    safe_points::SafePoint,
+    gpu::{CPC_BUSY_BIT, GPU_ACTIVE_BIT, GRBM_STATUS2_REG, GRBM_STATUS_REG},
+};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    pub timing: Timing,
+    #[serde(rename = "frequency-thresholds")]
+    pub frequency_thresholds: FrequencyThresholds,
+    #[serde(rename = "load-target")]
+    pub load_target: LoadTarget,
+    #[serde(rename = "mode-load-targets")]
+    pub mode_load_targets: BTreeMap<String, LoadTargetOverride>,
+    #[serde(rename = "safe-points")]
+    pub safe_points: Vec<SafePoint>,
+    /// Replace `safe-points` at startup with the points the card's OD table suggests
+    #[serde(rename = "safe-points-from-card")]
+    pub safe_points_from_card: bool,
+    /// Added to every voltage the setter writes; negative undervolts
+    #[serde(rename = "voltage-offset-mv")]
+    pub voltage_offset_mv: i16,
+    /// Lowest voltage the offset may reach; defaults to the lowest safe-point voltage
+    #[serde(rename = "voltage-floor-mv")]
+    pub voltage_floor_mv: Option<u16>,
+    pub thermal: Thermal,
+    #[serde(rename = "performance-mode")]
+    pub performance_mode: PerformanceModeConfig,
+    pub gpu: Gpu,
+    #[serde(rename = "apply-backoff")]
+    pub apply_backoff: ApplyBackoff,
+    pub alerts: Alerts,
+    pub control: ControlConfig,
+    #[serde(rename = "app-tracking")]
+    pub app_tracking: AppTracking,
+    pub gamemode: GameModeConfig,
+    #[serde(rename = "gpu-clients")]
+    pub gpu_clients: GpuClientsConfig,
+    pub status: StatusConfig,
+    pub startup: Startup,
+    pub shutdown: Shutdown,
+    #[serde(rename = "auto-mode")]
+    pub auto_mode: AutoMode,
+    pub schedule: ScheduleConfig,
+    #[serde(rename = "od-watch")]
+    pub od_watch: OdWatch,
+    pub cluster: ClusterConfig,
+    pub dbus: DbusConfig,
+    #[serde(rename = "config-watch")]
+    pub config_watch: ConfigWatch,
+    pub policy: Policy,
+    #[serde(rename = "memory-clocks")]
+    pub memory_clocks: MemoryClocks,
+    #[serde(rename = "cpu-coupling")]
+    pub cpu_coupling: CpuCouplingConfig,
+    #[serde(rename = "power-budget")]
+    pub power_budget: PowerBudgetConfig,
+    #[serde(rename = "boost-budget")]
+    pub boost_budget: BoostBudgetConfig,
+    #[serde(rename = "deep-idle")]
+    pub deep_idle: DeepIdle,
+    /// Profile the governor starts with
+    pub profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+    /// One governor per listed GPU; empty governs a single GPU
+    pub devices: BTreeMap<String, DeviceConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Timing {
+    pub intervals: Intervals,
+    #[serde(rename = "burst-samples")]
+    pub burst_samples: u8,
+    /// Busy samples among the last `burst-samples` that trigger a burst; unset requires all
+    #[serde(rename = "burst-busy-samples")]
+    pub burst_busy_samples: Option<u8>,
+    /// After a burst ends, another cannot start for this long (ms)
+    #[serde(rename = "burst-rearm-ms")]
+    pub burst_rearm_ms: u64,
+    #[serde(rename = "ramp-up-samples")]
+    pub ramp_up_samples: u16,
+    #[serde(rename = "ramp-down-samples")]
+    pub ramp_down_samples: u16,
+    #[serde(rename = "ramp-rates")]
+    pub ramp_rates: RampRates,
+    #[serde(rename = "max-applies-per-second")]
+    pub max_applies_per_second: u32,
+    #[serde(rename = "soft-start-secs")]
+    pub soft_start_secs: f32,
+    pub ema: EmaConfig,
+    #[serde(rename = "idle-sampling")]
+    pub idle_sampling: IdleSampling,
+}
+
+/// `[timing] idle-sampling`: a longer sample interval while the GPU idles.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct IdleSampling {
+    pub enabled: bool,
+    /// Sample interval while idle (μs)
+    pub interval: u64,
+    /// How long the busy ratio must stay under the lower load target first
+    #[serde(rename = "after-ms")]
+    pub after_ms: u64,
+}
+
+impl Default for IdleSampling {
+    fn default() -> Self {
+        Self { enabled: false, interval: 20_000, after_ms: 2000 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Intervals {
+    pub sample: u64,
+    pub adjust: u64,
+    pub finetune: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct FrequencyThresholds {
+    pub adjust: u16,
+    pub finetune: u16,
+    /// Differences below this (MHz) are only written once they have lasted `deadband-ms`
+    #[serde(rename = "deadband-mhz")]
+    pub deadband_mhz: u16,
+    #[serde(rename = "deadband-ms")]
+    pub deadband_ms: u64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Thermal {
+    /// Warning threshold (°C); unset derives it from the GPU sensors' limits, see [`Thermal::derive_thresholds`]
+    pub max_safe_temp: Option<f32>,
+    /// Emergency threshold (°C); unset derives it like `max_safe_temp`
+    pub emergency_temp: Option<f32>,
+    /// What crossing `emergency_temp` or a fan stall does
+    #[serde(rename = "emergency-action")]
+    pub emergency_action: EmergencyAction,
+    /// Command run through `sh -c` by `emergency-action = "script"`
+    #[serde(rename = "emergency-script")]
+    pub emergency_script: String,
+    pub monitor_interval: u64,
+    /// Seconds between scans for hwmon devices that appeared or vanished; 0 scans only at startup
+    #[serde(rename = "rescan-secs")]
+    pub rescan_secs: u64,
+    pub fan_control_index: usize,
+    pub include_cpu_temp: bool,
+    /// Alarm-only mode: thresholds log, alert and dump but never act on the hardware
+    pub rehearsal: bool,
+    /// Temperature source: hwmon sysfs or lm-sensors
+    pub backend: ThermalBackend,
+    pub burst_soft_limit: Option<f32>,
+    pub burst_soft_scale: f32,
+    /// [temperature, percent of the top safe point] points capping the frequency
+    pub throttle_curve: Vec<(f32, u8)>,
+    #[serde(rename = "fan-control")]
+    pub fan_control: FanControl,
+    #[serde(rename = "voltage-compensation")]
+    pub voltage_compensation: VoltageCompensation,
+    pub sensors: Sensors,
+    pub smoothing: Smoothing,
+    /// Thresholds derived from each GPU channel's own limits, by sensor name
+    #[serde(skip)]
+    pub channel_thresholds: BTreeMap<String, ChannelThresholds>,
+}
+
+/// Warning and emergency thresholds (°C) of one sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelThresholds {
+    pub warning: f32,
+    pub emergency: f32,
+}
+
+/// Thresholds used when neither the config nor the sensors give one (°C)
+const DEFAULT_MAX_SAFE_TEMP: f32 = 85.0;
+const DEFAULT_EMERGENCY_TEMP: f32 = 95.0;
+/// How far a derived emergency threshold stays below tempN_crit, and a
+/// derived warning below tempN_max (°C)
+const LIMIT_MARGIN: f32 = 5.0;
+/// Distance of a derived warning threshold below the emergency one when the
+/// sensors have no tempN_max (°C)
+const WARNING_GAP: f32 = 10.0;
+
+impl Thermal {
+    /// Warning threshold of the `gpu` sensor: configured, derived from its
+    /// limits or 85°C.
+    pub fn max_safe_temp(&self) -> f32 {
+        self.thresholds(&self.sensors.gpu).warning
+    }
+
+    /// Emergency threshold of the `gpu` sensor, like `max_safe_temp`
+    pub fn emergency_temp(&self) -> f32 {
+        self.thresholds(&self.sensors.gpu).emergency
+    }
+
+    /// Thresholds `sensor` is held to: its own derived ones, else the
+    /// configured ones or 85°C and 95°C.
+    pub fn thresholds(&self, sensor: &str) -> ChannelThresholds {
+        self.channel_thresholds.get(sensor).copied().unwrap_or(ChannelThresholds {
+            warning: self.max_safe_temp.unwrap_or(DEFAULT_MAX_SAFE_TEMP),
+            emergency: self.emergency_temp.unwrap_or(DEFAULT_EMERGENCY_TEMP),
+        })
+    }
+
+    /// Reading furthest over its warning threshold, as (°C, threshold)
+    pub fn warning_exceeded(&self, status: &ThermalStatus) -> Option<(f32, f32)> {
+        self.exceeded(status, |t| t.warning)
+    }
+
+    /// Reading furthest over its emergency threshold, as (°C, threshold)
+    pub fn emergency_exceeded(&self, status: &ThermalStatus) -> Option<(f32, f32)> {
+        self.exceeded(status, |t| t.emergency)
+    }
+
+    fn exceeded(&self, status: &ThermalStatus, level: fn(ChannelThresholds) -> f32) -> Option<(f32, f32)> {
+        status.gpu_readings.iter()
+            .map(|(name, temp)| (*temp, level(self.thresholds(name))))
+            .filter(|(temp, threshold)| temp > threshold)
+            .max_by(|a, b| (a.0 - a.1).total_cmp(&(b.0 - b.1)))
+    }
+
+    /// Fills in the thresholds the config leaves unset from each GPU
+    /// channel's own `limits`, so the edge is held to the edge's limits and
+    /// the junction to the junction's: the emergency threshold 5°C under
+    /// tempN_crit, the warning 5°C under tempN_max, or 10°C under the
+    /// emergency threshold derived from tempN_crit. A channel gets nothing
+    /// when the result would not leave its warning below its emergency
+    /// threshold; it then stays at 85°C and 95°C, as do channels without
+    /// limits. Returns what was derived, for the log.
+    pub fn derive_thresholds(&mut self, limits: &TempLimits) -> Vec<String> {
+        self.channel_thresholds.clear();
+        if self.max_safe_temp.is_some() && self.emergency_temp.is_some() {
+            return Vec::new();
+        }
+        let mut derived = Vec::new();
+        for (name, channel) in &limits.channels {
+            let emergency = channel.crit.map(|crit| crit - LIMIT_MARGIN);
+            let warning = channel.max.map(|max| max - LIMIT_MARGIN).or(emergency.map(|t| t - WARNING_GAP));
+            let thresholds = ChannelThresholds {
+                warning: self.max_safe_temp.or(warning).unwrap_or(DEFAULT_MAX_SAFE_TEMP),
+                emergency: self.emergency_temp.or(emergency).unwrap_or(DEFAULT_EMERGENCY_TEMP),
+            };
+            if thresholds.warning >= thresholds.emergency {
+                continue;
+            }
+            self.channel_thresholds.insert(name.clone(), thresholds);
+            derived.push(format!("{name} warning {:.1}°C, emergency {:.1}°C", thresholds.warning, thresholds.emergency));
+        }
+        derived
+    }
+
+    /// The fans to drive and their curves: the `fans` entries, or the fan at
+    /// `fan_control_index` following `curve` and the hottest sensor. Each
+    /// `group` member follows its entry's curves, shifted by its offset.
+    pub fn fan_curves(&self) -> Vec<FanCurve> {
+        let fan = &self.fan_control;
+        let curves = if !fan.fans.is_empty() {
+            fan.fans.clone()
+        } else if fan.curve.is_empty() {
+            return Vec::new();
+        } else {
+            vec![self.single_fan_curve()]
+        };
+        // Group members get loops of their own, so stall checks and write holds stay per fan
+        curves.into_iter().flat_map(|mut lead| {
+            let members: Vec<FanCurve> = std::mem::take(&mut lead.group).iter().map(|member| lead.for_member(member)).collect();
+            std::iter::once(lead).chain(members)
+        }).collect()
+    }
+
+    /// The top-level `curve` as an entry for the fan at `fan_control_index`.
+    fn single_fan_curve(&self) -> FanCurve {
+        let fan = &self.fan_control;
+        FanCurve {
+            down_curve: fan.down_curve.clone(),
+            down_hysteresis: fan.down_hysteresis,
+            performance_curve: fan.performance_curve.clone(),
+            min_duty: fan.min_duty,
+            kick_pwm: fan.kick_pwm,
+            kick_ms: fan.kick_ms,
+            unit: fan.unit,
+            ambient: fan.ambient.clone(),
+            group: fan.group.clone(),
+            ..FanCurve::new(FanRef::Index(self.fan_control_index), &default_fan_source(), fan.curve.clone())
+        }
+    }
+}
+
+impl Default for Thermal {
+    fn default() -> Self {
+        Self {
+            max_safe_temp: None,
+            emergency_temp: None,
+            emergency_action: EmergencyAction::Exit,
+            emergency_script: String::new(),
+            monitor_interval: 1000,
+            rescan_secs: 10,
+            fan_control_index: 0,
+            include_cpu_temp: true,
+            rehearsal: false,
+            backend: ThermalBackend::Sysfs,
+            burst_soft_limit: None,
+            burst_soft_scale: 0.0,
+            throttle_curve: Vec::new(),
+            fan_control: Default::default(),
+            voltage_compensation: Default::default(),
+            sensors: Default::default(),
+            smoothing: Default::default(),
+            channel_thresholds: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmergencyAction {
+    /// Shut the governor down, leaving the card as `[shutdown] policy` says
+    Exit,
+    /// Hold the lowest safe point until the GPU is back under `max_safe_temp`
+    MinClock,
+    /// Run `emergency-script` and hold the lowest safe point like `MinClock`
+    Script,
+    /// Shut the governor down and power the machine off through systemd
+    Poweroff,
+}
+
+impl EmergencyAction {
+    /// What the action does, for log lines such as "would power off".
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Exit => "shut down",
+            Self::MinClock => "hold the lowest safe point",
+            Self::Script => "run the emergency script and hold the lowest safe point",
+            Self::Poweroff => "power off",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PerformanceModeConfig {
+    pub enabled: bool,
+    pub control_file: String,
+    pub check_interval: u64,
+    pub lock_frequency: Option<u16>,
+    /// Follow the control file with inotify instead of checking it every check_interval
+    pub watch: bool,
+}
+
+impl Default for PerformanceModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            control_file: "/tmp/bc250-max-performance".to_string(),
+            check_interval: 500,
+            lock_frequency: None,
+            watch: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct FanControl {
+    pub enabled: bool,
+    pub curve: Vec<(f32, u8)>,
+    /// Curve followed while cooling down; empty means `curve`
+    #[serde(rename = "down-curve")]
+    pub down_curve: Vec<(f32, u8)>,
+    /// Degrees the falling curve is shifted up, so the fan slows down late
+    #[serde(rename = "down-hysteresis")]
+    pub down_hysteresis: f32,
+    /// Curve followed in max-performance mode, in both directions; empty
+    /// keeps `curve` and `down-curve`
+    #[serde(rename = "performance-curve")]
+    pub performance_curve: Vec<(f32, u8)>,
+    /// Lowest duty the fan is run at other than 0, for fans that will not turn below it
+    #[serde(rename = "min-duty")]
+    pub min_duty: u8,
+    /// Duty held for `kick-ms` whenever the fan starts from 0; 0 disables the kick
+    #[serde(rename = "kick-pwm")]
+    pub kick_pwm: u8,
+    #[serde(rename = "kick-ms")]
+    pub kick_ms: u64,
+    /// What the duties of the curves, `min-duty`, `kick-pwm` and group offsets are given in
+    pub unit: CurveUnit,
+    /// Fans that follow `curve` too, each shifted by its offset
+    pub group: Vec<GroupFan>,
+    /// Intake sensor whose reading is subtracted from the source's, so the
+    /// curve's temperatures are degrees above ambient
+    pub ambient: Option<String>,
+    /// Seconds the fan may read 0 RPM at `stall-pwm` or more before the
+    /// governor shuts down; 0 disables stall detection
+    #[serde(rename = "stall-secs")]
+    pub stall_secs: f32,
+    /// PWM duty (%) from which a fan is expected to turn
+    #[serde(rename = "stall-pwm")]
+    pub stall_pwm: u8,
+    /// PWM writes to one fan that may fail in a row before it counts as
+    /// failed; 0 only logs the failures
+    #[serde(rename = "max-write-failures")]
+    pub max_write_failures: u32,
+    /// Smallest PWM change (percentage points) worth writing
+    #[serde(rename = "min-step")]
+    pub min_step: u8,
+    /// Minimum time between two PWM writes to the same fan
+    #[serde(rename = "min-interval-ms")]
+    pub min_interval_ms: u64,
+    /// Fastest PWM change (percentage points per second); 0 jumps straight to the curve
+    #[serde(rename = "slew-rate")]
+    pub slew_rate: f32,
+    #[serde(rename = "zero-rpm")]
+    pub zero_rpm: ZeroRpm,
+    /// Fans with curves of their own; when set, `curve` and `fan_control_index` are ignored
+    pub fans: Vec<FanCurve>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmoothingFilter {
+    None,
+    /// Middle of the last `window` readings, which drops lone spikes entirely
+    Median,
+    /// Exponential moving average weighting each new reading by `alpha`
+    Ema,
+}
+
+/// Filtering of each sensor's readings before the fan curves and the
+/// thermal thresholds see them.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct Smoothing {
+    pub filter: SmoothingFilter,
+    /// Readings the median is taken over
+    pub window: usize,
+    /// Weight of the newest reading in the EMA (0.0-1.0]
+    pub alpha: f32,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Self { filter: SmoothingFilter::None, window: 3, alpha: 0.5 }
+    }
+}
+
+/// Which temperature sensors the governor reads, and what it calls them.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct Sensors {
+    /// Sensor (hwmon name or alias) that is the GPU temperature
+    pub gpu: String,
+    /// Sensor that is the CPU temperature, left out of GPU-side decisions by `include_cpu_temp = false`
+    pub cpu: String,
+    /// Sensors to read, by hwmon name or alias; empty reads them all
+    pub include: Vec<String>,
+    /// Sensors the `max` and `gpu-max` aggregates cover; empty covers every sensor read
+    #[serde(rename = "max-include")]
+    pub max_include: Vec<String>,
+    /// Sensors left out of the aggregates, still read for fan curve sources and the status
+    #[serde(rename = "max-exclude")]
+    pub max_exclude: Vec<String>,
+    /// Extra sensors naming one tempN of a chip
+    #[serde(rename = "alias")]
+    pub aliases: Vec<SensorAlias>,
+}
+
+impl Sensors {
+    /// Whether sensor `name` counts towards the `max` and `gpu-max` aggregates.
+    pub fn in_max(&self, name: &str) -> bool {
+        (self.max_include.is_empty() || self.max_include.iter().any(|n| n == name)) && !self.max_exclude.iter().any(|n| n == name)
+    }
+}
+
+impl Default for Sensors {
+    fn default() -> Self {
+        Self {
+            gpu: "amdgpu".to_string(),
+            cpu: "k10temp".to_string(),
+            include: Vec::new(),
+            max_include: Vec::new(),
+            max_exclude: Vec::new(),
+            aliases: Vec::new(),
+        }
+    }
+}
+
+/// One `[[thermal.sensors.alias]]` entry: a chip's tempN under a name of its own.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SensorAlias {
+    pub name: String,
+    /// hwmon name of the chip ("k10temp", "nct6798", ...)
+    pub chip: String,
+    /// tempN_label to pick ("Tctl", "CPUTIN", ...)
+    pub label: Option<String>,
+    /// N of tempN_input, when the input has no label
+    pub input: Option<u32>,
+}
+
+/// Semi-passive mode: fans stop below one temperature and restart above a
+/// higher one, with a kick so they start reliably.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ZeroRpm {
+    pub enabled: bool,
+    /// A running fan stops once its source temperature falls below this (°C)
+    #[serde(rename = "stop-below")]
+    pub stop_below: f32,
+    /// A stopped fan restarts once its source temperature reaches this (°C)
+    #[serde(rename = "start-above")]
+    pub start_above: f32,
+    /// Lowest duty (%) right after a restart, for fans that need more to start than to keep turning
+    #[serde(rename = "spin-up-pwm")]
+    pub spin_up_pwm: u8,
+    /// How long the spin-up duty is held
+    #[serde(rename = "spin-up-ms")]
+    pub spin_up_ms: u64,
+}
+
+impl Default for ZeroRpm {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stop_below: 45.0,
+            start_above: 50.0,
+            spin_up_pwm: 40,
+            spin_up_ms: 2000,
+        }
+    }
+}
+
+/// One `[[thermal.fan-control.fans]]` entry: a fan and the curve it follows.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FanCurve {
+    /// Fan index as `list` numbers them, or the fan's name ("nct6687_pwm2")
+    pub fan: FanRef,
+    /// Temperature the curve follows: "max", "gpu-max" or a sensor name such as "amdgpu"
+    #[serde(default = "default_fan_source")]
+    pub source: String,
+    pub curve: Vec<(f32, u8)>,
+    #[serde(default, rename = "down-curve")]
+    pub down_curve: Vec<(f32, u8)>,
+    #[serde(default, rename = "down-hysteresis")]
+    pub down_hysteresis: f32,
+    #[serde(default, rename = "performance-curve")]
+    pub performance_curve: Vec<(f32, u8)>,
+    #[serde(default, rename = "min-duty")]
+    pub min_duty: u8,
+    #[serde(default, rename = "kick-pwm")]
+    pub kick_pwm: u8,
+    #[serde(default = "default_kick_ms", rename = "kick-ms")]
+    pub kick_ms: u64,
+    #[serde(default)]
+    pub unit: CurveUnit,
+    #[serde(default)]
+    pub ambient: Option<String>,
+    #[serde(default)]
+    pub group: Vec<GroupFan>,
+}
+
+impl FanCurve {
+    /// `fan` following `curve` on `source`, with every other setting at the
+    /// default a config entry leaving it out gets.
+    pub fn new(fan: FanRef, source: &str, curve: Vec<(f32, u8)>) -> Self {
+        Self {
+            fan,
+            source: source.to_string(),
+            curve,
+            down_curve: Vec::new(),
+            down_hysteresis: 0.0,
+            performance_curve: Vec::new(),
+            min_duty: 0,
+            kick_pwm: 0,
+            kick_ms: default_kick_ms(),
+            unit: CurveUnit::default(),
+            ambient: None,
+            group: Vec::new(),
+        }
+    }
+
+    /// The curve a `group` member follows: this one, with every duty other
+    /// than 0 moved by the member's offset and kept within the unit's range.
+    pub fn for_member(&self, member: &GroupFan) -> FanCurve {
+        let full = i16::from(self.unit.full());
+        let shift = |points: &[(f32, u8)]| -> Vec<(f32, u8)> {
+            points.iter()
+                .map(|&(temp, duty)| (temp, if duty == 0 { 0 } else { (i16::from(duty) + member.offset).clamp(0, full) as u8 }))
+                .collect()
+        };
+        FanCurve {
+            fan: member.fan.clone(),
+            curve: shift(&self.curve),
+            down_curve: shift(&self.down_curve),
+            performance_curve: shift(&self.performance_curve),
+            group: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// A fan that follows another's curve, `offset` duty points above it (below
+/// when negative), e.g. an exhaust fan running a little faster than the intake.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GroupFan {
+    pub fan: FanRef,
+    #[serde(default)]
+    pub offset: i16,
+}
+
+/// What fan curve duties are given in. Raw values are written exactly as
+/// given, for chips that round percentages to a PWM value the governor then
+/// reads back as a different percentage.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CurveUnit {
+    /// 0-100 % of full speed
+    #[default]
+    Percent,
+    /// Raw PWM value, 0-255
+    Pwm,
+}
+
+impl CurveUnit {
+    /// The duty of full speed.
+    pub fn full(self) -> u8 {
+        match self {
+            CurveUnit::Percent => 100,
+            CurveUnit::Pwm => 255,
+        }
+    }
+
+    /// `percent` of full speed in this unit, for the settings that are
+    /// always given in percent (`min-step`, `spin-up-pwm`, ...).
+    pub fn from_percent(self, percent: u8) -> u8 {
+        match self {
+            CurveUnit::Percent => percent,
+            CurveUnit::Pwm => (percent.min(100) as u16 * 255 / 100) as u8,
+        }
+    }
+
+    /// The PWM value to write for `duty`.
+    pub fn to_raw(self, duty: u8) -> u8 {
+        match self {
+            CurveUnit::Percent => CurveUnit::Pwm.from_percent(duty),
+            CurveUnit::Pwm => duty,
+        }
+    }
+
+    /// `duty` for the log: "40%" or "102/255".
+    pub fn format(self, duty: u8) -> String {
+        match self {
+            CurveUnit::Percent => format!("{duty}%"),
+            CurveUnit::Pwm => format!("{duty}/255"),
+        }
+    }
+}
+
+fn default_fan_source() -> String {
+    "max".to_string()
+}
+
+fn default_kick_ms() -> u64 {
+    2000
+}
+
+impl Default for FanControl {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            curve: Vec::new(),
+            down_curve: Vec::new(),
+            down_hysteresis: 0.0,
+            performance_curve: Vec::new(),
+            min_duty: 0,
+            kick_pwm: 0,
+            kick_ms: default_kick_ms(),
+            unit: CurveUnit::Percent,
+            group: Vec::new(),
+            ambient: None,
+            stall_secs: 0.0,
+            stall_pwm: 40,
+            max_write_failures: 3,
+            min_step: 0,
+            min_interval_ms: 0,
+            slew_rate: 0.0,
+            zero_rpm: Default::default(),
+            fans: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct VoltageCompensation {
+    pub enabled: bool,
+    #[serde(rename = "above-temp")]
+    pub above_temp: f32,
+    #[serde(rename = "offset-mv")]
+    pub offset_mv: i16,
+    pub hysteresis: f32,
+}
+
+impl Default for VoltageCompensation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            above_temp: 75.0,
+            offset_mv: 10,
+            hysteresis: 3.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Gpu {
+    /// Bus of the GPU; found by its Cyan Skillfish device ID when unset
+    pub pci_bus: Option<u8>,
+    /// Also govern every detected Cyan Skillfish GPU without a [devices] section
+    pub all_devices: bool,
+    /// Where GPU activity is read from
+    pub sampler: SamplerKind,
+    /// Registers sampled for activity; a sample is busy if any masked bit is set
+    pub busy_registers: Vec<BusyRegister>,
+    /// Load is the weighted share of busy registers instead of any one of them
+    pub blend: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BusyRegister {
+    pub name: String,
+    pub offset: u32,
+    pub mask: u32,
+    /// Counts towards the compute share of the workload fingerprint
+    #[serde(default)]
+    pub compute: bool,
+    /// Share of the load this register stands for with `blend`
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl Default for Gpu {
+    fn default() -> Self {
+        Self {
+            pci_bus: None,
+            all_devices: false,
+            sampler: SamplerKind::Auto,
+            busy_registers: vec![
+                BusyRegister { name: "GRBM_STATUS".to_string(), offset: GRBM_STATUS_REG, mask: 1 << GPU_ACTIVE_BIT, compute: false, weight: 1.0 },
+                BusyRegister { name: "GRBM_STATUS2".to_string(), offset: GRBM_STATUS2_REG, mask: 1 << CPC_BUSY_BIT, compute: true, weight: 1.0 },
+            ],
+            blend: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ApplyBackoff {
+    #[serde(rename = "max-consecutive-failures")]
+    pub max_consecutive_failures: u32,
+    #[serde(rename = "hold-ms")]
+    pub hold_ms: u64,
+}
+
+impl Default for ApplyBackoff {
+    fn default() -> Self {
+        Self {
+            max_consecutive_failures: 5,
+            hold_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Alerts {
+    pub command: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub socket: String,
+    /// Named pipe taking plain-text commands; empty disables it
+    pub fifo: String,
+    /// Group allowed to use the socket and the FIFO besides the daemon's user; empty keeps the daemon's own group
+    pub group: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct AppTracking {
+    pub enabled: bool,
+    pub source: AppSource,
+    pub interval: u64,
+    pub rules: Vec<AppRuleConfig>,
+}
+
+impl Default for AppTracking {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: AppSource::Cgroup,
+            interval: 2000,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// `[gamemode]`: max performance while Feral GameMode has a game registered.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct GameModeConfig {
+    pub enabled: bool,
+    /// D-Bus address of the session bus gamemoded runs on; the service's own
+    /// environment has no session bus, so it must be given
+    #[serde(rename = "bus-address")]
+    pub bus_address: String,
+    /// How often the client count is read (ms)
+    pub interval: u64,
+}
+
+impl Default for GameModeConfig {
+    fn default() -> Self {
+        Self { enabled: false, bus_address: String::new(), interval: 1000 }
+    }
+}
+
+/// `[gpu-clients]`: GPU time per process, from the DRM fdinfo counters.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct GpuClientsConfig {
+    pub enabled: bool,
+    /// Accounting interval (ms)
+    pub interval: u64,
+    /// Busiest clients listed in the status file
+    pub top: usize,
+}
+
+impl Default for GpuClientsConfig {
+    fn default() -> Self {
+        Self { enabled: false, interval: 1000, top: 5 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AppRuleConfig {
+    pub app: String,
+    pub mode: Option<String>,
+    /// Profile from [profiles] used while the app is active
+    pub profile: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct ClusterConfig {
+    pub role: Role,
+    /// Address the leader accepts followers on; loopback only unless set
+    pub listen: String,
+    /// Leader address a follower connects to
+    pub leader: String,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            role: Role::Standalone,
+            listen: format!("127.0.0.1:{}", cluster::DEFAULT_PORT),
+            leader: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct DbusConfig {
+    pub enabled: bool,
+    pub bus: dbus::Bus,
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bus: dbus::Bus::System,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyKind {
+    /// Tiered ramp rates chosen by `load-target` thresholds
+    Ladder,
+    /// PID loop holding the busy ratio at `policy.pid.setpoint`
+    Pid,
+    /// Holds the frame rate MangoHud logs at `policy.frametime.target-fps`
+    Frametime,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Policy {
+    pub kind: PolicyKind,
+    pub pid: PidGains,
+    pub frametime: FrametimeConfig,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self { kind: PolicyKind::Ladder, pid: Default::default(), frametime: Default::default() }
+    }
+}
+
+/// `[policy.frametime]`: where frame times come from and the rate to hold.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct FrametimeConfig {
+    /// Directory MangoHud writes its CSV logs to (its `output_folder`)
+    #[serde(rename = "log-dir")]
+    pub log_dir: String,
+    /// Frame times older than this (ms) are ignored and the ladder scales instead
+    #[serde(rename = "stale-ms")]
+    pub stale_ms: u64,
+    #[serde(rename = "target-fps")]
+    pub target_fps: f32,
+    pub tolerance: f32,
+    #[serde(rename = "up-gain")]
+    pub up_gain: f32,
+    #[serde(rename = "down-rate")]
+    pub down_rate: f32,
+}
+
+impl Default for FrametimeConfig {
+    fn default() -> Self {
+        Self { log_dir: String::new(), stale_ms: 2000, target_fps: 60.0, tolerance: 0.03, up_gain: 100.0, down_rate: 50.0 }
+    }
+}
+
+impl FrametimeConfig {
+    pub fn target(&self) -> FrametimeTarget {
+        FrametimeTarget { target_fps: self.target_fps, tolerance: self.tolerance, up_gain: self.up_gain, down_rate: self.down_rate }
+    }
+}
+
+/// Named scaling parameters selectable at runtime (`[profiles.<name>]`).
+/// Unset fields keep the base `[load-target]` and `[timing.ramp-rates]`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Profile {
+    /// Ceiling for the target frequency (MHz)
+    #[serde(rename = "max-frequency")]
+    pub max_frequency: Option<u16>,
+    #[serde(rename = "load-target")]
+    pub load_target: LoadTargetOverride,
+    #[serde(rename = "ramp-rates")]
+    pub ramp_rates: RampRatesOverride,
+}
+
+fn default_profiles() -> BTreeMap<String, Profile> {
+    BTreeMap::from([
+        ("powersave".to_string(), Profile {
+            max_frequency: Some(1200),
+            load_target: LoadTargetOverride { upper: Some(0.95), medium: Some(0.90), slow: Some(0.85), crawl: Some(0.80), lower: Some(0.60) },
+            ..Default::default()
+        }),
+        ("balanced".to_string(), Profile::default()),
+        ("performance".to_string(), Profile {
+            load_target: LoadTargetOverride { upper: Some(0.70), medium: Some(0.60), slow: Some(0.50), crawl: Some(0.40), lower: Some(0.30) },
+            ..Default::default()
+        }),
+    ])
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigWatch {
+    /// Reload automatically when the config file is rewritten
+    pub enabled: bool,
+}
+
+impl Default for ConfigWatch {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OdConflictPolicy {
+    /// Only log the conflict
+    Warn,
+    /// Write the governor's frequency back
+    Reassert,
+    /// Take the external frequency as the new current state
+    Adopt,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct OdWatch {
+    pub enabled: bool,
+    pub policy: OdConflictPolicy,
+    pub interval: u64,
+    /// Readback differences up to this many MHz are not conflicts
+    pub tolerance: u16,
+    /// Read OD_SCLK back after every apply and treat a mismatch as a failure
+    pub verify: bool,
+}
+
+impl Default for OdWatch {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            policy: OdConflictPolicy::Warn,
+            interval: 1000,
+            tolerance: 10,
+            verify: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct MemoryClocks {
+    pub enabled: bool,
+    pub mclk: bool,
+    pub fclk: bool,
+    /// Busy ratio at which the highest memory/fabric level is selected
+    pub up: f32,
+    /// Busy ratio below which, after `hold-ms`, the lowest level is selected
+    pub down: f32,
+    #[serde(rename = "hold-ms")]
+    pub hold_ms: u64,
+}
+
+impl Default for MemoryClocks {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mclk: true,
+            fclk: true,
+            up: 0.80,
+            down: 0.40,
+            hold_ms: 3000,
+        }
+    }
+}
+
+impl MemoryClocks {
+    pub fn clocks(&self) -> Vec<DpmClock> {
+        DpmClock::ALL.into_iter()
+            .filter(|c| match c {
+                DpmClock::Mclk => self.mclk,
+                DpmClock::Fclk => self.fclk,
+            })
+            .collect()
+    }
+
+    pub fn hysteresis(&self) -> LoadHysteresis {
+        LoadHysteresis::new(self.up, self.down, Duration::from_millis(self.hold_ms))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct CpuCouplingConfig {
+    pub enabled: bool,
+    /// GPU busy ratio at which the CPU minimum frequency is raised
+    pub up: f32,
+    /// GPU busy ratio below which, after `hold-ms`, the original minimum returns
+    pub down: f32,
+    #[serde(rename = "hold-ms")]
+    pub hold_ms: u64,
+    /// Minimum CPU frequency (MHz) while raised; the cores' maximum when unset
+    #[serde(rename = "min-freq")]
+    pub min_freq: Option<u32>,
+}
+
+impl Default for CpuCouplingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            up: 0.85,
+            down: 0.40,
+            hold_ms: 5000,
+            min_freq: None,
+        }
+    }
+}
+
+impl CpuCouplingConfig {
+    pub fn hysteresis(&self) -> LoadHysteresis {
+        LoadHysteresis::new(self.up, self.down, Duration::from_millis(self.hold_ms))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PowerBudgetConfig {
+    pub enabled: bool,
+    /// Sustained board power (W) above which the frequency is lowered
+    #[serde(rename = "cap-watts")]
+    pub cap_watts: f32,
+    /// Span the power reading is averaged over
+    #[serde(rename = "window-secs")]
+    pub window_secs: u64,
+    /// How often board power is read (ms)
+    pub interval: u64,
+    /// MHz taken off the ceiling per watt over the cap, at most once per window
+    #[serde(rename = "mhz-per-watt")]
+    pub mhz_per_watt: f32,
+    /// MHz given back per reading once power is under the cap
+    #[serde(rename = "recover-mhz")]
+    pub recover_mhz: f32,
+}
+
+impl Default for PowerBudgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cap_watts: 140.0,
+            window_secs: 5,
+            interval: 500,
+            mhz_per_watt: 10.0,
+            recover_mhz: 50.0,
+        }
+    }
+}
+
+impl PowerBudgetConfig {
+    pub fn budget(&self) -> PowerBudget {
+        PowerBudget::new(self.cap_watts, Duration::from_secs(self.window_secs), self.mhz_per_watt, self.recover_mhz)
+    }
+}
+
+/// `[boost-budget]`: how long the GPU may stay above a boost frequency.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct BoostBudgetConfig {
+    pub enabled: bool,
+    /// Time above this frequency (MHz) is drawn from the budget
+    #[serde(rename = "boost-mhz")]
+    pub boost_mhz: u16,
+    /// Seconds above boost-mhz a full budget allows
+    #[serde(rename = "budget-secs")]
+    pub budget_secs: f32,
+    /// Seconds at or below boost-mhz that refill an empty budget while cool
+    #[serde(rename = "refill-secs")]
+    pub refill_secs: f32,
+    /// At or below this temperature (°C) the budget drains and refills at the base rate
+    #[serde(rename = "cool-temp")]
+    pub cool_temp: f32,
+    /// Share of the budget over which the ceiling eases down to boost-mhz
+    pub taper: f32,
+}
+
+impl Default for BoostBudgetConfig {
+    fn default() -> Self {
+        Self { enabled: false, boost_mhz: 1800, budget_secs: 20.0, refill_secs: 60.0, cool_temp: 60.0, taper: 0.25 }
+    }
+}
+
+impl BoostBudgetConfig {
+    pub fn budget(&self) -> BoostBudget {
+        BoostBudget::new(f32::from(self.boost_mhz), self.budget_secs, self.refill_secs, self.taper)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct DeepIdle {
+    pub enabled: bool,
+    /// Near-zero load has to last this long before dropping to the floor
+    #[serde(rename = "after-secs")]
+    pub after_secs: f32,
+    /// Busy ratio counted as near zero
+    #[serde(rename = "busy-below")]
+    pub busy_below: f32,
+}
+
+impl Default for DeepIdle {
+    fn default() -> Self {
+        Self { enabled: false, after_secs: 10.0, busy_below: 0.02 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct AutoMode {
+    pub enabled: bool,
+    /// Span of activity the workload fingerprint is computed over
+    #[serde(rename = "window-secs")]
+    pub window_secs: u64,
+    /// How long a different selection has to keep matching before the mode switches
+    #[serde(rename = "dwell-secs")]
+    pub dwell_secs: u64,
+    pub rules: Vec<AutoRuleConfig>,
+}
+
+impl Default for AutoMode {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 30,
+            dwell_secs: 10,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// `[schedule]`: profiles that apply during time windows of the day.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+    pub rules: Vec<ScheduleRuleConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleRuleConfig {
+    /// Local time the window opens ("HH:MM")
+    pub from: String,
+    /// Local time it closes; before `from` runs past midnight
+    pub to: String,
+    pub profile: String,
+    /// Days the window opens on ("mon", "tue", ...); empty means every day
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+impl ScheduleRuleConfig {
+    pub fn window(&self) -> Option<schedule::Window> {
+        Some(schedule::Window {
+            from: schedule::parse_time(&self.from)?,
+            to: schedule::parse_time(&self.to)?,
+            days: schedule::parse_days(&self.days)?,
+            profile: self.profile.clone(),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AutoRuleConfig {
+    pub mode: String,
+    #[serde(rename = "min-busy")]
+    pub min_busy: Option<f32>,
+    #[serde(rename = "max-busy")]
+    pub max_busy: Option<f32>,
+    #[serde(rename = "min-burstiness")]
+    pub min_burstiness: Option<f32>,
+    #[serde(rename = "max-burstiness")]
+    pub max_burstiness: Option<f32>,
+    #[serde(rename = "min-compute")]
+    pub min_compute: Option<f32>,
+    #[serde(rename = "max-compute")]
+    pub max_compute: Option<f32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct StatusConfig {
+    pub enabled: bool,
+    pub file: String,
+    pub interval: u64,
+    pub fifo: String,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file: status::DEFAULT_STATUS_PATH.to_string(),
+            interval: 1000,
+            fifo: status::DEFAULT_FIFO_PATH.to_string(),
+        }
+    }
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            socket: control::DEFAULT_SOCKET_PATH.to_string(),
+            fifo: control::DEFAULT_FIFO_PATH.to_string(),
+            group: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartupPolicy {
+    /// Trust whatever pp_od_clk_voltage reports
+    Current,
+    Minimum,
+    /// Last applied frequency persisted in `state-file`
+    Resume,
+    /// The safe point named by `safe-point`
+    SafePoint,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShutdownPolicy {
+    /// Write back the OD point read at startup, or reset when that point was
+    /// the driver's own or its write fails
+    Original,
+    /// Reset the OD table to the driver's defaults
+    Reset,
+    /// Leave the card at the lowest safe point
+    Minimum,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Shutdown {
+    pub policy: ShutdownPolicy,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { policy: ShutdownPolicy::Reset }
+    }
+}
+
+/// Per-GPU overrides in a `[devices.<name>]` section; unset keys fall back
+/// to the top-level config.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct DeviceConfig {
+    #[serde(rename = "pci-bus")]
+    pub pci_bus: Option<u8>,
+    #[serde(rename = "safe-points")]
+    pub safe_points: Option<Vec<SafePoint>>,
+    #[serde(rename = "voltage-offset-mv")]
+    pub voltage_offset_mv: Option<i16>,
+    pub profile: Option<String>,
+    pub socket: Option<String>,
+    #[serde(rename = "status-file")]
+    pub status_file: Option<String>,
+    #[serde(rename = "state-file")]
+    pub state_file: Option<String>,
+}
+
+/// One GPU governed alongside others in the same process.
+#[derive(Debug, Clone)]
+pub(crate) struct DeviceInstance {
+    pub(crate) name: String,
+    pub(crate) section: DeviceConfig,
+    /// The first device keeps the default paths and the host-wide duties
+    pub(crate) primary: bool,
+}
+
+impl DeviceInstance {
+    /// `path` for this device: unchanged on the primary, `<stem>-<name><ext>` elsewhere.
+    pub(crate) fn path(&self, path: &str) -> String {
+        if self.primary || path.is_empty() {
+            return path.to_string();
+        }
+        let path = std::path::Path::new(path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let file = match path.extension() {
+            Some(ext) => format!("{}-{}.{}", stem, self.name, ext.to_string_lossy()),
+            None => format!("{}-{}", stem, self.name),
+        };
+        path.with_file_name(file).to_string_lossy().to_string()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Startup {
+    pub policy: StartupPolicy,
+    #[serde(rename = "safe-point")]
+    pub safe_point: Option<String>,
+    #[serde(rename = "state-file")]
+    pub state_file: String,
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Self {
+            policy: StartupPolicy::Current,
+            safe_point: None,
+            state_file: "/var/lib/bc250-governor/last-frequency".to_string(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timing: Default::default(),
+            frequency_thresholds: Default::default(),
+            load_target: Default::default(),
+            mode_load_targets: Default::default(),
+            safe_points: vec![
+                SafePoint { frequency: 350, voltage: 700, name: None },
+                SafePoint { frequency: 2000, voltage: 1000, name: None },
+            ],
+            safe_points_from_card: false,
+            voltage_offset_mv: 0,
+            voltage_floor_mv: None,
+            thermal: Default::default(),
+            performance_mode: Default::default(),
+            gpu: Default::default(),
+            apply_backoff: Default::default(),
+            alerts: Default::default(),
+            control: Default::default(),
+            app_tracking: Default::default(),
+            gamemode: Default::default(),
+            gpu_clients: Default::default(),
+            status: Default::default(),
+            startup: Default::default(),
+            shutdown: Default::default(),
+            auto_mode: Default::default(),
+            schedule: Default::default(),
+            od_watch: Default::default(),
+            cluster: Default::default(),
+            dbus: Default::default(),
+            config_watch: Default::default(),
+            policy: Default::default(),
+            memory_clocks: Default::default(),
+            cpu_coupling: Default::default(),
+            power_budget: Default::default(),
+            boost_budget: Default::default(),
+            deep_idle: Default::default(),
+            profile: "balanced".to_string(),
+            profiles: default_profiles(),
+            devices: BTreeMap::new(),
+        }
+    }
+}
+
+impl Timing {
+    pub fn burst_busy(&self) -> usize {
+        self.burst_busy_samples.unwrap_or(self.burst_samples) as usize
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            intervals: Default::default(),
+            burst_samples: 6,
+            burst_busy_samples: None,
+            burst_rearm_ms: 0,
+            ramp_up_samples: 64,
+            ramp_down_samples: 256,
+            ramp_rates: Default::default(),
+            max_applies_per_second: 0,
+            soft_start_secs: 0.0,
+            ema: Default::default(),
+            idle_sampling: Default::default(),
+        }
+    }
+}
+
+impl Default for Intervals {
+    fn default() -> Self {
+        Self {
+            sample: 2000,
+            adjust: 8_000,
+            finetune: 50_000,
+        }
+    }
+}
+
+impl Default for FrequencyThresholds {
+    fn default() -> Self {
+        Self {
+            adjust: 100,
+            finetune: 10,
+            deadband_mhz: 0,
+            deadband_ms: 0,
+        }
+    }
+}
+
+impl Config {
+    /// This config as seen by one of several governed GPUs: the device's
+    /// overrides applied, its own socket, status and state files, and fan
+    /// control, CPU coupling, D-Bus and clustering left to the primary.
+    pub(crate) fn for_device(mut self, device: &DeviceInstance) -> Self {
+        let section = &device.section;
+        self.gpu.pci_bus = section.pci_bus;
+        if let Some(points) = &section.safe_points {
+            self.safe_points = points.clone();
+        }
+        if let Some(offset) = section.voltage_offset_mv {
+            self.voltage_offset_mv = offset;
+        }
+        if let Some(profile) = &section.profile {
+            self.profile = profile.clone();
+        }
+        self.control.socket = section.socket.clone().unwrap_or_else(|| device.path(&self.control.socket));
+        self.status.file = section.status_file.clone().unwrap_or_else(|| device.path(&self.status.file));
+        self.status.fifo = device.path(&self.status.fifo);
+        self.control.fifo = device.path(&self.control.fifo);
+        self.startup.state_file = section.state_file.clone().unwrap_or_else(|| device.path(&self.startup.state_file));
+        if !device.primary {
+            self.thermal.fan_control.enabled = false;
+            self.cpu_coupling.enabled = false;
+            self.dbus.enabled = false;
+            self.cluster.role = Role::Standalone;
+        }
+        self
+    }
+
+    /// Floor for `voltage-offset-mv`: `voltage-floor-mv`, or the lowest safe-point
+    /// voltage, and never below `MIN_VOLTAGE_MV`.
+    pub fn voltage_floor(&self) -> u16 {
+        self.voltage_floor_mv
+            .or_else(|| self.safe_points.iter().map(|p| p.voltage).min())
+            .unwrap_or_default()
+            .max(MIN_VOLTAGE_MV)
+    }
+}
+
+/// Reads, parses and validates a config file, failing instead of falling back to defaults.
+pub fn load_config(path: &std::path::Path) -> Result<Config, Box<dyn std::error::Error>> {
+    let config_str = std::fs::read_to_string(path)?;
+    let config: Config = toml::from_str(&config_str)?;
+    validate_config(&config)?;
+    Ok(config)
+}
+
+/// Rejects configs that parse but would misbehave, such as inverted load
+/// targets or a warning threshold above the emergency one.
+pub fn validate_config(config: &Config) -> Result<(), IoError> {
+    let problems = config_problems(config);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(IoError::new(ErrorKind::InvalidInput, problems.join("; ")))
+    }
+}
+
+/// Lowest voltage an offset may ever reach (mV): the OD_RANGE VDDC minimum
+/// of the BC-250's Cyan Skillfish GPU
+pub const MIN_VOLTAGE_MV: u16 = 700;
+/// Largest `voltage-offset-mv` either way (mV)
+const MAX_VOLTAGE_OFFSET_MV: i16 = 200;
+
+/// Every semantic problem in `config`, in file order.
+pub fn config_problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if config.safe_points.is_empty() && !config.safe_points_from_card {
+        problems.push("safe-points must not be empty".to_string());
+    }
+    let mut points: Vec<&SafePoint> = config.safe_points.iter().collect();
+    points.sort_by_key(|p| p.frequency);
+    for pair in points.windows(2) {
+        let (low, high) = (pair[0], pair[1]);
+        if low.frequency == high.frequency {
+            problems.push(format!("safe-points lists {}MHz more than once", low.frequency));
+        } else if low.voltage > high.voltage {
+            problems.push(format!("safe-points voltage drops from {}mV at {}MHz to {}mV at {}MHz",
+                low.voltage, low.frequency, high.voltage, high.frequency));
+        }
+    }
+
+    // Points taken from the card are checked once they are seeded
+    let lowest_voltage = config.safe_points.iter().map(|p| p.voltage).min()
+        .filter(|_| !config.safe_points_from_card).unwrap_or(u16::MAX);
+    if config.voltage_floor_mv.is_some_and(|floor| floor > lowest_voltage) {
+        problems.push(format!("voltage-floor-mv must not exceed the lowest safe-point voltage ({lowest_voltage}mV)"));
+    }
+    if config.voltage_floor_mv.is_some_and(|floor| floor < MIN_VOLTAGE_MV) {
+        problems.push(format!("voltage-floor-mv must be at least {MIN_VOLTAGE_MV}mV"));
+    }
+    if config.voltage_offset_mv.unsigned_abs() > MAX_VOLTAGE_OFFSET_MV.unsigned_abs() {
+        problems.push(format!("voltage-offset-mv must be between -{MAX_VOLTAGE_OFFSET_MV} and {MAX_VOLTAGE_OFFSET_MV}mV"));
+    }
+
+    let timing = &config.timing;
+    if timing.burst_samples == 0 || timing.ramp_up_samples == 0 || timing.ramp_down_samples == 0 {
+        problems.push("burst-samples, ramp-up-samples and ramp-down-samples must be at least 1".to_string());
+    }
+    if timing.burst_busy_samples.is_some_and(|n| n == 0 || n > timing.burst_samples) {
+        problems.push("burst-busy-samples must be between 1 and burst-samples".to_string());
+    }
+    let intervals = &timing.intervals;
+    if intervals.sample == 0 || intervals.adjust == 0 || intervals.finetune == 0
+        || (timing.idle_sampling.enabled && timing.idle_sampling.interval == 0) {
+        problems.push("timing intervals must be non-zero".to_string());
+    }
+    let rates = &timing.ramp_rates;
+    if [rates.burst, rates.up, rates.up_medium, rates.up_slow, rates.up_crawl, rates.down].iter().any(|r| !r.is_finite() || *r < 0.0) {
+        problems.push("ramp-rates must be non-negative".to_string());
+    }
+    let ema = &timing.ema;
+    if ema.enabled && ![ema.alpha_up, ema.alpha_down].iter().all(|a| *a > 0.0 && *a <= 1.0) {
+        problems.push("timing ema alpha-up and alpha-down must be in (0, 1]".to_string());
+    }
+
+    if !config.profiles.contains_key(&config.profile) {
+        problems.push(format!("profile '{}' is not defined in [profiles]", config.profile));
+    }
+    for rule in &config.schedule.rules {
+        if rule.window().is_none() {
+            problems.push(format!("schedule rule {}-{} needs HH:MM times and days among {}",
+                rule.from, rule.to, schedule::DAY_NAMES.join(", ")));
+        }
+        if !config.profiles.contains_key(&rule.profile) {
+            problems.push(format!("schedule rule {}-{} uses profile '{}', which is not defined in [profiles]", rule.from, rule.to, rule.profile));
+        }
+    }
+    for rule in &config.app_tracking.rules {
+        if rule.mode.is_none() && rule.profile.is_none() {
+            problems.push(format!("app-tracking rule '{}' sets neither mode nor profile", rule.app));
+        }
+        if let Some(name) = rule.profile.as_ref().filter(|p| !config.profiles.contains_key(*p)) {
+            problems.push(format!("app-tracking rule '{}' uses profile '{}', which is not defined in [profiles]", rule.app, name));
+        }
+    }
+    let min_freq = config.safe_points.iter().map(|p| p.frequency).min().unwrap_or_default();
+    for (name, profile) in &config.profiles {
+        if profile.max_frequency.is_some_and(|cap| cap < min_freq) {
+            problems.push(format!("profiles.{name} max-frequency is below the lowest safe point ({min_freq}MHz)"));
+        }
+        let r = config.timing.ramp_rates.with_override(&profile.ramp_rates);
+        if [r.burst, r.up, r.up_medium, r.up_slow, r.up_crawl, r.down].iter().any(|r| !r.is_finite() || *r < 0.0) {
+            problems.push(format!("profiles.{name} ramp-rates must be non-negative"));
+        }
+    }
+
+    let targets = std::iter::once(("load-target".to_string(), config.load_target))
+        .chain(config.profiles.iter()
+            .map(|(name, p)| (format!("profiles.{name}"), config.load_target.with_override(&p.load_target))))
+        .chain(config.mode_load_targets.iter()
+            .map(|(mode, o)| (format!("mode-load-targets.{mode}"), config.load_target.with_override(o))));
+    for (name, t) in targets {
+        let levels = [t.lower, t.crawl, t.slow, t.medium, t.upper];
+        if levels.iter().any(|v| !(0.0..=1.0).contains(v)) {
+            problems.push(format!("{name} values must be between 0.0 and 1.0"));
+        } else if levels.windows(2).any(|w| w[0] > w[1]) || t.lower >= t.upper {
+            problems.push(format!("{name} must satisfy lower < crawl <= slow <= medium <= upper"));
+        }
+    }
+
+    let pid = &config.policy.pid;
+    if !(pid.setpoint > 0.0 && pid.setpoint < 1.0) {
+        problems.push("policy.pid setpoint must be between 0.0 and 1.0".to_string());
+    }
+    if [pid.kp, pid.ki, pid.kd].iter().any(|g| !g.is_finite() || *g < 0.0) {
+        problems.push("policy.pid gains must be non-negative".to_string());
+    }
+    let frametime = &config.policy.frametime;
+    if config.policy.kind == PolicyKind::Frametime && frametime.log_dir.is_empty() {
+        problems.push("policy.frametime log-dir must be set for kind = \"frametime\"".to_string());
+    }
+    if frametime.target_fps <= 0.0 || !(0.0..1.0).contains(&frametime.tolerance)
+        || [frametime.up_gain, frametime.down_rate].iter().any(|r| !r.is_finite() || *r < 0.0) {
+        problems.push("policy.frametime needs a positive target-fps, a tolerance in [0, 1) and non-negative rates".to_string());
+    }
+
+    if config.performance_mode.enabled && config.performance_mode.check_interval == 0 {
+        problems.push("performance-mode check_interval must be non-zero".to_string());
+    }
+    if config.gamemode.enabled && config.gamemode.interval == 0 {
+        problems.push("gamemode interval must be non-zero".to_string());
+    }
+    if config.gamemode.enabled && config.gamemode.bus_address.is_empty() {
+        problems.push("gamemode needs a bus-address, e.g. \"unix:path=/run/user/1000/bus\"".to_string());
+    }
+    if config.gpu_clients.enabled && config.gpu_clients.interval == 0 {
+        problems.push("gpu-clients interval must be non-zero".to_string());
+    }
+    if config.app_tracking.enabled && config.app_tracking.source == AppSource::TopClient && !config.gpu_clients.enabled {
+        problems.push("app-tracking source = \"top-client\" needs [gpu-clients] enabled".to_string());
+    }
+    if config.status.enabled && config.status.interval == 0 {
+        problems.push("status interval must be non-zero".to_string());
+    }
+    if config.control.enabled && !config.control.group.is_empty() {
+        if let Err(e) = control::group_id(&config.control.group) {
+            problems.push(format!("control group: {e}"));
+        }
+    }
+    if config.od_watch.enabled && config.od_watch.interval == 0 {
+        problems.push("od-watch interval must be non-zero".to_string());
+    }
+    let memory = &config.memory_clocks;
+    if memory.enabled && !(0.0 <= memory.down && memory.down < memory.up && memory.up <= 1.0) {
+        problems.push(format!("memory-clocks thresholds must satisfy 0 <= down ({}) < up ({}) <= 1", memory.down, memory.up));
+    }
+    let cpu = &config.cpu_coupling;
+    if cpu.enabled && !(0.0 <= cpu.down && cpu.down < cpu.up && cpu.up <= 1.0) {
+        problems.push(format!("cpu-coupling thresholds must satisfy 0 <= down ({}) < up ({}) <= 1", cpu.down, cpu.up));
+    }
+    let deep_idle = &config.deep_idle;
+    if deep_idle.enabled && !(deep_idle.after_secs >= 0.0 && (0.0..=1.0).contains(&deep_idle.busy_below)) {
+        problems.push("deep-idle after-secs must be non-negative and busy-below between 0.0 and 1.0".to_string());
+    }
+    let boost = &config.boost_budget;
+    if boost.enabled {
+        if !(boost.budget_secs > 0.0 && boost.refill_secs > 0.0) {
+            problems.push("boost-budget budget-secs and refill-secs must be positive".to_string());
+        }
+        if !(boost.taper > 0.0 && boost.taper <= 1.0) {
+            problems.push("boost-budget taper must be in (0, 1]".to_string());
+        }
+        if boost.cool_temp >= config.thermal.max_safe_temp() {
+            problems.push(format!("boost-budget cool-temp must be below thermal max_safe_temp ({:.1})", config.thermal.max_safe_temp()));
+        }
+    }
+    let power = &config.power_budget;
+    if power.enabled {
+        if !power.cap_watts.is_finite() || power.cap_watts <= 0.0 {
+            problems.push("power-budget cap-watts must be positive".to_string());
+        }
+        if power.window_secs == 0 || power.interval == 0 {
+            problems.push("power-budget window-secs and interval must be non-zero".to_string());
+        }
+        if [power.mhz_per_watt, power.recover_mhz].iter().any(|v| !v.is_finite() || *v <= 0.0) {
+            problems.push("power-budget mhz-per-watt and recover-mhz must be positive".to_string());
+        }
+    }
+
+    let registers = &config.gpu.busy_registers;
+    if registers.iter().any(|r| !r.weight.is_finite() || r.weight < 0.0) {
+        problems.push("gpu busy_registers weights must be non-negative".to_string());
+    } else if config.gpu.blend && registers.iter().map(|r| r.weight).sum::<f32>() <= 0.0 {
+        problems.push("gpu blend needs at least one busy register with a positive weight".to_string());
+    }
+
+    let thermal = &config.thermal;
+    if thermal.max_safe_temp() >= thermal.emergency_temp() {
+        problems.push(format!("thermal max_safe_temp ({:.1}) must be below emergency_temp ({:.1})",
+            thermal.max_safe_temp(), thermal.emergency_temp()));
+    }
+    if thermal.emergency_action == EmergencyAction::Script && thermal.emergency_script.trim().is_empty() {
+        problems.push("thermal emergency-action \"script\" needs an emergency-script".to_string());
+    }
+    match thermal.smoothing.filter {
+        SmoothingFilter::Median if thermal.smoothing.window == 0 => {
+            problems.push("thermal smoothing window must be at least 1".to_string());
+        }
+        SmoothingFilter::Ema if !(thermal.smoothing.alpha > 0.0 && thermal.smoothing.alpha <= 1.0) => {
+            problems.push("thermal smoothing alpha must be above 0.0 and at most 1.0".to_string());
+        }
+        _ => {}
+    }
+    if thermal.monitor_interval == 0 {
+        problems.push("thermal monitor_interval must be non-zero".to_string());
+    }
+    let fan_control = &thermal.fan_control;
+    if fan_control.curve.windows(2).any(|w| w[0].0 >= w[1].0) {
+        problems.push("thermal fan-control curve temperatures must be strictly increasing".to_string());
+    }
+    if fan_control.performance_curve.windows(2).any(|w| w[0].0 >= w[1].0) {
+        problems.push("thermal fan-control performance-curve temperatures must be strictly increasing".to_string());
+    }
+    if fan_control.curve.iter().chain(&fan_control.down_curve).chain(&fan_control.performance_curve).any(|&(_, duty)| duty > fan_control.unit.full()) {
+        problems.push(format!("thermal fan-control curve duties must be at most {}", fan_control.unit.full()));
+    }
+    if fan_control.group.iter().any(|member| member.offset.unsigned_abs() > u16::from(fan_control.unit.full())) {
+        problems.push(format!("thermal fan-control group offsets must be between -{0} and {0}", fan_control.unit.full()));
+    }
+    let curves = thermal.fan_curves();
+    for (i, curve) in curves.iter().enumerate() {
+        if curves[..i].iter().any(|other| other.fan == curve.fan) {
+            problems.push(format!("thermal fan-control drives {} more than once", curve.fan));
+        }
+    }
+    if fan_control.min_duty.max(fan_control.kick_pwm) > fan_control.unit.full() {
+        problems.push(format!("thermal fan-control min-duty and kick-pwm must be at most {}", fan_control.unit.full()));
+    }
+    let ambient_problem = |ambient: &Option<String>, source: &str| match ambient.as_deref() {
+        Some("max" | "gpu-max" | "") => Some("must name a sensor"),
+        Some(ambient) if ambient == source => Some("must differ from the source"),
+        _ => None,
+    };
+    if let Some(problem) = ambient_problem(&fan_control.ambient, "max") {
+        problems.push(format!("thermal fan-control ambient {problem}"));
+    }
+    for (i, fan) in thermal.fan_control.fans.iter().enumerate() {
+        if let Some(problem) = ambient_problem(&fan.ambient, &fan.source) {
+            problems.push(format!("thermal fan-control fans[{i}] ambient {problem}"));
+        }
+        if fan.curve.is_empty() {
+            problems.push(format!("thermal fan-control fans[{i}] needs a curve"));
+        }
+        if [&fan.curve, &fan.down_curve, &fan.performance_curve].iter().any(|curve| curve.windows(2).any(|w| w[0].0 >= w[1].0)) {
+            problems.push(format!("thermal fan-control fans[{i}] curve temperatures must be strictly increasing"));
+        }
+        if fan.curve.iter().chain(&fan.down_curve).chain(&fan.performance_curve).any(|&(_, duty)| duty > fan.unit.full()) {
+            problems.push(format!("thermal fan-control fans[{i}] curve duties must be at most {}", fan.unit.full()));
+        }
+        if fan.min_duty.max(fan.kick_pwm) > fan.unit.full() {
+            problems.push(format!("thermal fan-control fans[{i}] min-duty and kick-pwm must be at most {}", fan.unit.full()));
+        }
+        if fan.source.is_empty() {
+            problems.push(format!("thermal fan-control fans[{i}] source must not be empty"));
+        }
+        if fan.group.iter().any(|member| member.offset.unsigned_abs() > u16::from(fan.unit.full())) {
+            problems.push(format!("thermal fan-control fans[{i}] group offsets must be between -{0} and {0}", fan.unit.full()));
+        }
+    }
+    if thermal.fan_control.stall_secs < 0.0 {
+        problems.push("thermal fan-control stall-secs must not be negative".to_string());
+    }
+    let sensors = &thermal.sensors;
+    if let Some(name) = sensors.max_include.iter().find(|name| sensors.max_exclude.contains(name)) {
+        problems.push(format!("thermal sensor {name} is in both max-include and max-exclude"));
+    }
+    if !sensors.in_max(&sensors.gpu) {
+        problems.push(format!("thermal sensors max-include/max-exclude leave the gpu sensor {} out of the aggregates", sensors.gpu));
+    }
+    for alias in &thermal.sensors.aliases {
+        if alias.label.is_some() == alias.input.is_some() {
+            problems.push(format!("thermal sensor alias {} needs exactly one of label and input", alias.name));
+        }
+        if ["max", "gpu-max"].contains(&alias.name.as_str()) {
+            problems.push(format!("thermal sensor alias {} clashes with a fan curve source", alias.name));
+        }
+    }
+    let zero_rpm = &thermal.fan_control.zero_rpm;
+    if zero_rpm.enabled && zero_rpm.start_above <= zero_rpm.stop_below {
+        problems.push("thermal fan-control zero-rpm start-above must be above stop-below".to_string());
+    }
+    if zero_rpm.spin_up_pwm > 100 {
+        problems.push("thermal fan-control zero-rpm spin-up-pwm must be at most 100".to_string());
+    }
+    if thermal.fan_control.slew_rate < 0.0 {
+        problems.push("thermal fan-control slew-rate must not be negative".to_string());
+    }
+    if thermal.fan_control.min_step > 100 {
+        problems.push("thermal fan-control min-step must be at most 100".to_string());
+    }
+    if thermal.fan_control.stall_pwm == 0 || thermal.fan_control.stall_pwm > 100 {
+        problems.push("thermal fan-control stall-pwm must be between 1 and 100".to_string());
+    }
+    if thermal.throttle_curve.windows(2).any(|w| w[0].0 >= w[1].0) {
+        problems.push("thermal throttle_curve temperatures must be strictly increasing".to_string());
+    }
+    if thermal.throttle_curve.iter().any(|&(_, pct)| pct == 0 || pct > 100) {
+        problems.push("thermal throttle_curve percentages must be between 1 and 100".to_string());
+    }
+
+    let mut buses = BTreeMap::new();
+    for (name, device) in &config.devices {
+        match device.pci_bus {
+            None => problems.push(format!("devices.{} needs a pci-bus", name)),
+            Some(bus) => if let Some(other) = buses.insert(bus, name) {
+                problems.push(format!("devices.{} and devices.{} both use pci-bus {}", other, name, bus));
+            },
+        }
+    }
+    problems
+}
+
+/// The GPUs to govern side by side: every `[devices]` section, then, with
+/// `[gpu] all_devices`, each detected Cyan Skillfish GPU not already listed.
+/// Empty when the config governs a single GPU the usual way.
+pub(crate) fn device_instances(config: &Config, mock: bool) -> Result<Vec<DeviceInstance>, IoError> {
+    let mut devices: Vec<DeviceInstance> = config.devices.iter()
+        .map(|(name, section)| DeviceInstance { name: name.clone(), section: section.clone(), primary: false })
+        .collect();
+    if config.gpu.all_devices && mock {
+        eprintln!("⚠️  [gpu] all_devices needs real GPUs, governing the [devices] sections only");
+    } else if config.gpu.all_devices {
+        for location in pci::find_all()? {
+            if devices.iter().any(|d| d.section.pci_bus == Some(location.bus)) {
+                continue;
+            }
+            let section = DeviceConfig { pci_bus: Some(location.bus), ..Default::default() };
+            devices.push(DeviceInstance { name: format!("bus{:02x}", location.bus), section, primary: false });
+        }
+    }
+    if let Some(first) = devices.first_mut() {
+        first.primary = true;
+    }
+    Ok(devices)
+}
@@ -0,0 +1,120 @@
+use std::{
+    ffi::{CString, OsString},
+    io::Error as IoError,
+    os::{fd::{AsRawFd, FromRawFd, OwnedFd}, unix::ffi::OsStrExt},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// Editors often write a file in several steps; wait for this much quiet first.
+const SETTLE: Duration = Duration::from_millis(250);
+const POLL_MS: i32 = 200;
+
+/// An inotify instance watching the directory that holds `path` for `mask`
+/// events, and the file name to look for in them.
+fn watch_dir(path: &Path, mask: u32) -> Result<(OwnedFd, OsString), IoError> {
+    let name = path.file_name()
+        .ok_or_else(|| IoError::other(format!("{} is not a file", path.display())))?
+        .to_owned();
+    let dir: PathBuf = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let dir_c = CString::new(dir.as_os_str().as_bytes()).map_err(IoError::other)?;
+
+    let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(IoError::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir_c.as_ptr(), mask) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+    Ok((fd, name))
+}
+
+/// Waits up to POLL_MS for events and reports whether any of them names `name`.
+fn poll_for(fd: &OwnedFd, name: &OsString, buf: &mut [u8]) -> bool {
+    let mut pfd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    if unsafe { libc::poll(&mut pfd, 1, POLL_MS) } <= 0 {
+        return false;
+    }
+    let n = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+    n > 0 && event_names(&buf[..n as usize]).any(|e| e == name.as_bytes())
+}
+
+/// Watches the directory holding `path`, so rewrites through a temp file and
+/// rename are seen as well as in-place saves, and raises `reload` once the
+/// file has settled after a change.
+pub fn start(path: &Path, reload: Arc<AtomicBool>, shutdown: Arc<AtomicBool>) -> Result<JoinHandle<()>, IoError> {
+    let (fd, name) = watch_dir(path, libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO)?;
+    println!("👀 Watching {} for changes", path.display());
+
+    Ok(std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut changed_at: Option<Instant> = None;
+        while !shutdown.load(Ordering::SeqCst) {
+            if poll_for(&fd, &name, &mut buf) {
+                changed_at = Some(Instant::now());
+            }
+            if changed_at.is_some_and(|t| t.elapsed() >= SETTLE) {
+                changed_at = None;
+                println!("📝 Config file changed, reloading");
+                reload.store(true, Ordering::SeqCst);
+            }
+        }
+    }))
+}
+
+/// Raised by an inotify watch on a file's directory whenever the file is
+/// created, written, removed or renamed.
+pub struct FileWatch {
+    pub path: PathBuf,
+    changed: Arc<AtomicBool>,
+}
+
+impl FileWatch {
+    /// True once after each change.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Watches `path`, so it only has to be read again after it changed.
+pub fn watch_file(path: &Path, shutdown: Arc<AtomicBool>) -> Result<(FileWatch, JoinHandle<()>), IoError> {
+    let mask = libc::IN_CREATE | libc::IN_CLOSE_WRITE | libc::IN_DELETE | libc::IN_MOVED_TO | libc::IN_MOVED_FROM;
+    let (fd, name) = watch_dir(path, mask)?;
+    let watch = FileWatch { path: path.to_path_buf(), changed: Arc::new(AtomicBool::new(false)) };
+    let changed = Arc::clone(&watch.changed);
+    println!("👀 Watching {}", path.display());
+
+    let jh = std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while !shutdown.load(Ordering::SeqCst) {
+            if poll_for(&fd, &name, &mut buf) {
+                changed.store(true, Ordering::SeqCst);
+            }
+        }
+    });
+    Ok((watch, jh))
+}
+
+/// File names carried by a buffer of inotify events.
+fn event_names(mut buf: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let header = std::mem::size_of::<libc::inotify_event>();
+    std::iter::from_fn(move || {
+        if buf.len() < header {
+            return None;
+        }
+        // `len` is the last u32 of the header and covers the NUL-padded name
+        let len = u32::from_ne_bytes(buf[header - 4..header].try_into().unwrap()) as usize;
+        let name = buf.get(header..header + len)?;
+        buf = &buf[(header + len).min(buf.len())..];
+        Some(name.split(|&b| b == 0).next().unwrap_or_default())
+    })
+}
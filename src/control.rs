@@ -0,0 +1,439 @@
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    ffi::CString,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Error as IoError, ErrorKind, Read, Write},
+    os::{
+        fd::AsRawFd,
+        unix::{
+            fs::{OpenOptionsExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{governor::PerformanceMode, status::{self, SharedStatus}};
+
+pub const DEFAULT_SOCKET_PATH: &str = "/run/bc250-governor/control.sock";
+pub const DEFAULT_FIFO_PATH: &str = "/run/bc250-governor/command.fifo";
+/// Client name `mode.set` holds max-performance under unless one is given.
+const RPC_CLIENT: &str = "rpc";
+/// Client name of max-performance holds taken through the command FIFO.
+const FIFO_CLIENT: &str = "fifo";
+
+/// A client's hold on max-performance mode.
+#[derive(Debug, Clone)]
+struct PerfHold {
+    /// Process whose lifetime bounds the hold; dropped once it exits.
+    pid: Option<u32>,
+    /// Hold expires at this instant unless renewed.
+    expires: Option<Instant>,
+}
+
+/// Refcounted max-performance requests. The governor stays in max-performance
+/// while at least one live client holds a request.
+#[derive(Debug, Default)]
+pub struct PerfRequests {
+    holds: BTreeMap<String, PerfHold>,
+}
+
+impl PerfRequests {
+    /// Adds or renews a hold for `client`.
+    pub fn request(&mut self, client: &str, pid: Option<u32>, ttl: Option<Duration>) {
+        let expires = ttl.map(|t| Instant::now() + t);
+        self.holds.insert(client.to_string(), PerfHold { pid, expires });
+    }
+
+    /// Releases the hold for `client`, returning whether it existed.
+    pub fn release(&mut self, client: &str) -> bool {
+        self.holds.remove(client).is_some()
+    }
+
+    /// Drops holds whose process has exited or whose TTL has passed.
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        self.holds.retain(|client, hold| {
+            let alive = hold.pid.is_none_or(|pid| Path::new(&format!("/proc/{pid}")).exists());
+            let fresh = hold.expires.is_none_or(|e| e > now);
+            if !alive || !fresh {
+                println!("🧹 Dropping stale max-performance request from '{}'", client);
+            }
+            alive && fresh
+        });
+    }
+
+    pub fn active(&mut self) -> usize {
+        self.prune();
+        self.holds.len()
+    }
+
+    pub fn clients(&self) -> Vec<String> {
+        self.holds.keys().cloned().collect()
+    }
+
+    /// Selects `mode` on behalf of `client`: max-performance takes an
+    /// open-ended hold, normal releases it. Holds of other clients still apply.
+    pub fn set_mode(&mut self, client: &str, mode: PerformanceMode) {
+        match mode {
+            PerformanceMode::MaxPerformance => {
+                self.request(client, None, None);
+                println!("⚡ Max-performance requested by '{}' ({} active)", client, self.active());
+            }
+            // Clients only select among PerformanceMode::ALL; anything else releases too
+            _ => {
+                if self.release(client) {
+                    println!("⚡ Max-performance released by '{}' ({} active)", client, self.active());
+                }
+            }
+        }
+    }
+}
+
+/// Named scaling profiles and the one the governor should run.
+#[derive(Debug, Default)]
+pub struct ProfileSelection {
+    pub active: String,
+    pub available: Vec<String>,
+}
+
+impl ProfileSelection {
+    /// Replaces the known profiles, e.g. after a config reload.
+    pub fn reset(&mut self, active: &str, available: Vec<String>) {
+        self.active = active.to_string();
+        self.available = available;
+    }
+
+    pub fn select(&mut self, name: &str) -> Result<(), String> {
+        if !self.available.iter().any(|p| p == name) {
+            return Err(format!("unknown profile '{}', expected one of: {}", name, self.available.join(", ")));
+        }
+        self.active = name.to_string();
+        Ok(())
+    }
+}
+
+/// Shared state the control server dispatches requests against.
+#[derive(Clone, Default)]
+pub struct ControlContext {
+    pub perf_requests: Arc<Mutex<PerfRequests>>,
+    /// App id of the focused window, as reported by a compositor helper
+    pub focused_app: Arc<Mutex<Option<String>>>,
+    /// Set to ask the governor thread for a state dump (shared with SIGQUIT)
+    pub dump_request: Arc<AtomicBool>,
+    /// Set to ask the main thread to re-read the config file
+    pub reload_request: Arc<AtomicBool>,
+    /// Manual override suspending automatic mode selection
+    pub auto_mode_paused: Arc<AtomicBool>,
+    /// Latest snapshot published by the governor and thermal threads
+    pub status: SharedStatus,
+    /// Scaling profile selection, applied by the governor thread
+    pub profiles: Arc<Mutex<ProfileSelection>>,
+    /// Frequency (MHz) the governor holds instead of scaling, until unlocked
+    pub freq_lock: Arc<Mutex<Option<u16>>>,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    const INTERNAL_ERROR: i64 = -32603;
+    const INVALID_PARAMS: i64 = -32602;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const PARSE_ERROR: i64 = -32700;
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self { code: Self::INVALID_PARAMS, message: message.into() }
+    }
+}
+
+fn dispatch(method: &str, params: &Value, ctx: &ControlContext) -> Result<Value, RpcError> {
+    match method {
+        "perf.request" => {
+            let client = params.get("client").and_then(Value::as_str)
+                .ok_or_else(|| RpcError::invalid_params("missing 'client'"))?;
+            let pid = params.get("pid").and_then(Value::as_u64).map(|p| p as u32);
+            let ttl = params.get("ttl_secs").and_then(Value::as_u64).map(Duration::from_secs);
+            let mut requests = ctx.perf_requests.lock().unwrap();
+            requests.request(client, pid, ttl);
+            println!("⚡ Max-performance requested by '{}' ({} active)", client, requests.active());
+            Ok(json!({ "active": requests.active() }))
+        }
+        "perf.release" => {
+            let client = params.get("client").and_then(Value::as_str)
+                .ok_or_else(|| RpcError::invalid_params("missing 'client'"))?;
+            let mut requests = ctx.perf_requests.lock().unwrap();
+            let released = requests.release(client);
+            if released {
+                println!("⚡ Max-performance released by '{}' ({} active)", client, requests.active());
+            }
+            Ok(json!({ "released": released, "active": requests.active() }))
+        }
+        "perf.list" => {
+            let mut requests = ctx.perf_requests.lock().unwrap();
+            requests.prune();
+            Ok(json!({ "clients": requests.clients() }))
+        }
+        "status" => {
+            serde_json::to_value(&*ctx.status.lock().unwrap())
+                .map_err(|e| RpcError { code: RpcError::INTERNAL_ERROR, message: e.to_string() })
+        }
+        "stats" => {
+            serde_json::to_value(&ctx.status.lock().unwrap().stats)
+                .map_err(|e| RpcError { code: RpcError::INTERNAL_ERROR, message: e.to_string() })
+        }
+        "mode.set" => {
+            let name = params.get("mode").and_then(Value::as_str)
+                .ok_or_else(|| RpcError::invalid_params("missing 'mode'"))?;
+            let mode = PerformanceMode::from_name(name)
+                .ok_or_else(|| RpcError::invalid_params(format!("unknown mode '{name}'")))?;
+            let client = params.get("client").and_then(Value::as_str).unwrap_or(RPC_CLIENT);
+            let mut requests = ctx.perf_requests.lock().unwrap();
+            requests.set_mode(client, mode);
+            Ok(json!({ "mode": mode.name(), "client": client, "active": requests.active() }))
+        }
+        "profile.set" => {
+            let name = params.get("profile").and_then(Value::as_str)
+                .ok_or_else(|| RpcError::invalid_params("missing 'profile'"))?;
+            ctx.profiles.lock().unwrap().select(name).map_err(RpcError::invalid_params)?;
+            Ok(json!({ "profile": name }))
+        }
+        "profile.list" => {
+            let profiles = ctx.profiles.lock().unwrap();
+            Ok(json!({ "active": profiles.active, "available": profiles.available }))
+        }
+        "freq.lock" => {
+            let freq = params.get("frequency").and_then(Value::as_u64)
+                .and_then(|f| u16::try_from(f).ok())
+                .ok_or_else(|| RpcError::invalid_params("missing or invalid 'frequency'"))?;
+            *ctx.freq_lock.lock().unwrap() = Some(freq);
+            println!("🔒 Frequency locked at {}MHz", freq);
+            Ok(json!({ "frequency": freq }))
+        }
+        "freq.unlock" => {
+            let released = ctx.freq_lock.lock().unwrap().take();
+            if released.is_some() {
+                println!("🔓 Frequency unlocked");
+            }
+            Ok(json!({ "released": released.is_some() }))
+        }
+        "app.focus" => {
+            // A null/missing app_id clears focus (e.g. desktop or lock screen)
+            let app_id = params.get("app_id").and_then(Value::as_str).map(str::to_string);
+            *ctx.focused_app.lock().unwrap() = app_id.clone();
+            Ok(json!({ "app_id": app_id }))
+        }
+        "reload" => {
+            ctx.reload_request.store(true, Ordering::SeqCst);
+            Ok(json!({ "requested": true }))
+        }
+        "auto.pause" => {
+            let paused = params.get("paused").and_then(Value::as_bool).unwrap_or(true);
+            ctx.auto_mode_paused.store(paused, Ordering::SeqCst);
+            println!("🧠 Automatic mode selection {}", if paused { "paused" } else { "resumed" });
+            Ok(json!({ "paused": paused }))
+        }
+        "dump" => {
+            ctx.dump_request.store(true, Ordering::SeqCst);
+            Ok(json!({ "requested": true }))
+        }
+        _ => Err(RpcError { code: RpcError::METHOD_NOT_FOUND, message: format!("unknown method '{method}'") }),
+    }
+}
+
+/// Handles one JSON-RPC 2.0 request line and returns the response line.
+fn handle_line(line: &str, ctx: &ControlContext) -> Value {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": Value::Null,
+                "error": { "code": RpcError::PARSE_ERROR, "message": e.to_string() },
+            });
+        }
+    };
+
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    match dispatch(method, &params, ctx) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    }
+}
+
+fn serve_connection(stream: UnixStream, ctx: &ControlContext) -> Result<(), IoError> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle_line(&line, ctx);
+        writer.write_all(format!("{response}\n").as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Looks up the id of the group named `name`.
+pub fn group_id(name: &str) -> Result<u32, IoError> {
+    let name_c = CString::new(name).map_err(|_| IoError::new(ErrorKind::InvalidInput, format!("invalid group name '{name}'")))?;
+    let entry = unsafe { libc::getgrnam(name_c.as_ptr()) };
+    if entry.is_null() {
+        return Err(IoError::new(ErrorKind::NotFound, format!("no group '{name}'")));
+    }
+    Ok(unsafe { (*entry).gr_gid })
+}
+
+/// Gives `path` to `group` (the daemon's own group when empty) and sets `mode`.
+fn restrict(path: &Path, group: &str, mode: u32) -> Result<(), IoError> {
+    if !group.is_empty() {
+        std::os::unix::fs::chown(path, None, Some(group_id(group)?))?;
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+/// Binds the control socket and serves requests until `shutdown` is set.
+/// Only the daemon's user and `group` may connect.
+pub fn start_server(
+    socket_path: PathBuf,
+    group: &str,
+    ctx: ControlContext,
+    shutdown: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>, IoError> {
+    if let Some(dir) = socket_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Remove a stale socket left behind by a previous run
+    let _ = fs::remove_file(&socket_path);
+
+    let listener = UnixListener::bind(&socket_path)?;
+    listener.set_nonblocking(true)?;
+    // Unprivileged clients (e.g. the gaming-mode wrapper) connect through the group
+    restrict(&socket_path, group, 0o660)?;
+    println!("🔌 Control socket listening on {}", socket_path.display());
+
+    Ok(std::thread::spawn(move || {
+        while !shutdown.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let ctx = ctx.clone();
+                    std::thread::spawn(move || {
+                        let _ = stream.set_nonblocking(false);
+                        if let Err(e) = serve_connection(stream, &ctx) {
+                            eprintln!("⚠️  Control connection error: {}", e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => {
+                    eprintln!("⚠️  Control socket accept failed: {}", e);
+                    std::thread::sleep(Duration::from_millis(500));
+                }
+            }
+        }
+        let _ = fs::remove_file(&socket_path);
+    }))
+}
+
+/// Translates a command FIFO line into the control method it stands for.
+fn fifo_command(line: &str) -> Result<(&'static str, Value), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["mode", "max" | "max-performance"] => Ok(("mode.set", json!({ "mode": "max-performance", "client": FIFO_CLIENT }))),
+        ["mode", "normal"] => Ok(("mode.set", json!({ "mode": "normal", "client": FIFO_CLIENT }))),
+        ["lock", freq] => freq.parse::<u16>()
+            .map(|f| ("freq.lock", json!({ "frequency": f })))
+            .map_err(|_| format!("invalid frequency '{freq}'")),
+        ["unlock"] => Ok(("freq.unlock", json!({}))),
+        ["profile", name] => Ok(("profile.set", json!({ "profile": name }))),
+        ["reload"] => Ok(("reload", json!({}))),
+        ["dump"] => Ok(("dump", json!({}))),
+        _ => Err("expected mode max|normal, lock <MHz>, unlock, profile <name>, reload or dump".to_string()),
+    }
+}
+
+/// Creates a named pipe at `path` and runs every line written to it as a
+/// command until `shutdown` is set. It is opened read-write so that writers
+/// coming and going never leave it at EOF. Only the daemon's user and
+/// `group` may write to it.
+pub fn start_fifo(path: PathBuf, group: &str, ctx: ControlContext, shutdown: Arc<AtomicBool>) -> Result<JoinHandle<()>, IoError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    status::mkfifo(&path)?;
+    restrict(&path, group, 0o620)?;
+    let mut pipe = OpenOptions::new().read(true).write(true).custom_flags(libc::O_NONBLOCK).open(&path)?;
+    println!("📮 Command FIFO listening on {}", path.display());
+
+    Ok(std::thread::spawn(move || {
+        let mut pending = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !shutdown.load(Ordering::SeqCst) {
+            let mut pfd = libc::pollfd { fd: pipe.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            if unsafe { libc::poll(&mut pfd, 1, 200) } <= 0 {
+                continue;
+            }
+            match pipe.read(&mut buf) {
+                Ok(n) => pending.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
+                Err(e) => {
+                    eprintln!("⚠️  Command FIFO {} failed: {}", path.display(), e);
+                    break;
+                }
+            }
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let result = fifo_command(&line)
+                    .and_then(|(method, params)| dispatch(method, &params, &ctx).map_err(|e| e.message));
+                if let Err(e) = result {
+                    eprintln!("⚠️  Command FIFO: '{}': {}", line.trim(), e);
+                }
+            }
+        }
+        let _ = fs::remove_file(&path);
+    }))
+}
+
+/// Sends a single JSON-RPC call to a running daemon and returns its result.
+pub fn call(socket_path: &Path, method: &str, params: Value) -> Result<Value, IoError> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    stream.write_all(format!("{request}\n").as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: Value = serde_json::from_str(&line)
+        .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+
+    if let Some(error) = response.get("error") {
+        let message = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+        return Err(IoError::other(message.to_string()));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
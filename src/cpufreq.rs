@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+use crate::governor::LoadLevel;
+
+pub const DEFAULT_CPUFREQ_ROOT: &str = "/sys/devices/system/cpu/cpufreq";
+
+/// One cpufreq policy and the minimum it had before the governor touched it (kHz).
+#[derive(Debug)]
+struct CpuPolicy {
+    path: PathBuf,
+    original_min: u32,
+}
+
+/// Raises the `scaling_min_freq` of every cpufreq policy while the GPU is
+/// busy, and puts the original minimum back when it is idle again.
+#[derive(Debug)]
+pub struct CpuCoupling {
+    policies: Vec<CpuPolicy>,
+}
+
+fn read_khz(path: &Path) -> Result<u32, IoError> {
+    fs::read_to_string(path)?.trim().parse().map_err(IoError::other)
+}
+
+impl CpuCoupling {
+    /// Finds the policyN directories under `root`.
+    pub fn discover(root: &Path) -> Result<Self, IoError> {
+        let mut policies = Vec::new();
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            if !path.file_name().is_some_and(|n| n.to_string_lossy().starts_with("policy")) {
+                continue;
+            }
+            let original_min = read_khz(&path.join("scaling_min_freq"))?;
+            policies.push(CpuPolicy { path, original_min });
+        }
+        if policies.is_empty() {
+            return Err(IoError::other(format!("no cpufreq policies in {}", root.display())));
+        }
+        policies.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self { policies })
+    }
+
+    pub fn policy_count(&self) -> usize {
+        self.policies.len()
+    }
+
+    /// High raises the minimum to `boost_mhz` (the policy's maximum when
+    /// None), clamped to the current `scaling_max_freq`; Low restores it.
+    fn minimum_khz(policy: &CpuPolicy, level: LoadLevel, boost_mhz: Option<u32>) -> Result<u32, IoError> {
+        Ok(match level {
+            LoadLevel::Low => policy.original_min,
+            LoadLevel::High => {
+                let max = read_khz(&policy.path.join("scaling_max_freq"))?;
+                boost_mhz.map_or(max, |mhz| (mhz * 1000).min(max)).max(policy.original_min)
+            }
+        })
+    }
+
+    /// The minimum `apply` would write to the first policy, in MHz, without writing it.
+    pub fn minimum_mhz(&self, level: LoadLevel, boost_mhz: Option<u32>) -> Result<u32, IoError> {
+        self.policies.first().map_or(Ok(0), |policy| Self::minimum_khz(policy, level, boost_mhz).map(|khz| khz / 1000))
+    }
+
+    /// Writes the minimum for `level` to every policy. Returns the minimum
+    /// written to the first policy, in MHz.
+    pub fn apply(&self, level: LoadLevel, boost_mhz: Option<u32>) -> Result<u32, IoError> {
+        let mut first = None;
+        for policy in &self.policies {
+            let khz = Self::minimum_khz(policy, level, boost_mhz)?;
+            fs::write(policy.path.join("scaling_min_freq"), khz.to_string())?;
+            first.get_or_insert(khz);
+        }
+        Ok(first.unwrap_or_default() / 1000)
+    }
+
+    /// Puts back the minimum every policy had at startup.
+    pub fn restore(&self) -> Result<(), IoError> {
+        self.apply(LoadLevel::Low, None).map(|_| ())
+    }
+}
@@ -0,0 +1,2342 @@
+//! The governor daemon: the sampling, setter, thermal and service threads
+//! for each governed GPU.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    io::Error as IoError,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    thermal::{FanLoop, FanRef, FanResidency, SharedThermalStatus, TemperatureFilter, ThermalCounters, ThermalManager, interpolate_curve, next_fan_speed},
+    governor::{
+        ApplyDecision, BusySample, GovCommand, GovernorPolicy, GovernorState, GovernorStats, FrametimePolicy, LadderPolicy, LoadLevel,
+        LoadTarget, LoadTargetOverride, PidPolicy, PolicyInput, RollingWindow,
+        Scaler, ScalingSettings, SetterAck, PerformanceMode, OVERRUN_FACTOR,
+    },
+    gpu_metrics_fix::GpuUsageFix,
+    alerts::{AlertKind, Alerter},
+    control::{self, ControlContext},
+    apps::{AppRule, AppTracker},
+    status::{self, FanStatus, SharedStatus, ThermalSnapshot},
+    workload::{AutoRule, FingerprintWindow},
+    cluster::{self, Role, SharedMirror},
+    mock::FaultInjector,
+    replay::TraceRecorder,
+    fdinfo::{self, ClientAccounting, SharedClients},
+    memclk::{self, DpmClock},
+    cpufreq::{self, CpuCoupling},
+    power,
+    schedule,
+    od,
+    config_watch,
+    dbus,
+    gamemode,
+    frametime,
+    systemd,
+    workload,
+    config::{ApplyBackoff, CurveUnit, FanCurve, BoostBudgetConfig, Config, CpuCouplingConfig, DeepIdle, DeviceInstance, EmergencyAction, FrequencyThresholds, MemoryClocks, OdConflictPolicy, OdWatch, PerformanceModeConfig, Policy, PolicyKind, PowerBudgetConfig, Profile, ScheduleRuleConfig, ShutdownPolicy, StartupPolicy, Thermal, Timing, device_instances, load_config, validate_config},
+    safe_points::{apply_voltage_offset, interpolate_voltage, lowest_safe_point, seed_safe_points},
+    gpu::{DpmLevels, GpuDevice, LoadSampler, open_gpu, read_od_sclk, reset_od, write_dpm, write_od},
+};
+
+/// Governor parameters that can be swapped at runtime without discarding the
+/// sample history, applied frequency or stats.
+#[derive(Debug, Clone)]
+pub(crate) struct GovernorTuning {
+    pub(crate) timing: Timing,
+    pub(crate) load_target: LoadTarget,
+    pub(crate) mode_load_targets: BTreeMap<String, LoadTargetOverride>,
+    pub(crate) frequency_thresholds: FrequencyThresholds,
+    pub(crate) performance_mode: PerformanceModeConfig,
+    pub(crate) perf_lock_freq: u16,
+    pub(crate) apply_backoff: ApplyBackoff,
+    pub(crate) burst_soft_limit: Option<f32>,
+    pub(crate) burst_soft_scale: f32,
+    pub(crate) throttle_curve: Vec<(f32, u8)>,
+    /// Thermal rehearsal: the throttle is logged but not applied
+    pub(crate) rehearsal: bool,
+    pub(crate) auto_mode_enabled: bool,
+    pub(crate) auto_window: Duration,
+    pub(crate) auto_dwell: Duration,
+    pub(crate) auto_rules: Vec<AutoRule>,
+    /// Time windows with their profiles; empty when the schedule is off
+    pub(crate) schedule: Vec<schedule::Window>,
+    pub(crate) od_watch: OdWatch,
+    pub(crate) policy: Policy,
+    pub(crate) memory_clocks: MemoryClocks,
+    pub(crate) cpu_coupling: CpuCouplingConfig,
+    pub(crate) power_budget: PowerBudgetConfig,
+    pub(crate) boost_budget: BoostBudgetConfig,
+    /// Busiest GPU clients listed in the status file
+    pub(crate) gpu_clients_top: usize,
+    /// Warning threshold (°C); the boost budget is at its most restrictive here
+    pub(crate) max_safe_temp: f32,
+    pub(crate) deep_idle: DeepIdle,
+    pub(crate) profile: String,
+    pub(crate) profiles: BTreeMap<String, Profile>,
+    /// Range spanned by the safe points
+    pub(crate) min_freq: u16,
+    pub(crate) max_freq: u16,
+    /// Followers stop mirroring the leader above the local warning threshold
+    pub(crate) mirror_override_temp: f32,
+}
+
+impl GovernorTuning {
+    pub(crate) fn from_config(config: &Config, min_freq: u16, max_freq: u16) -> Self {
+        for name in config.mode_load_targets.keys() {
+            if !PerformanceMode::is_named(name) {
+                eprintln!("⚠️  Unknown mode '{}' in mode-load-targets, ignoring", name);
+            }
+        }
+
+        // Max performance locks to the top safe point unless a sustained clock is configured
+        let perf_lock_freq = match config.performance_mode.lock_frequency {
+            Some(freq) if freq < min_freq || freq > max_freq => {
+                eprintln!("⚠️  performance-mode lock_frequency {}MHz is outside the safe-points range {}-{}MHz, clamping",
+                    freq, min_freq, max_freq);
+                freq.clamp(min_freq, max_freq)
+            }
+            Some(freq) => freq,
+            None => max_freq,
+        };
+
+        let auto_rules = config.auto_mode.rules.iter().filter_map(|r| {
+            let Some(mode) = PerformanceMode::parse(&r.mode) else {
+                eprintln!("⚠️  Unknown mode '{}' in auto-mode rules, ignoring", r.mode);
+                return None;
+            };
+            Some(AutoRule {
+                mode,
+                min_busy: r.min_busy,
+                max_busy: r.max_busy,
+                min_burstiness: r.min_burstiness,
+                max_burstiness: r.max_burstiness,
+                min_compute: r.min_compute,
+                max_compute: r.max_compute,
+            })
+        }).collect();
+
+        Self {
+            timing: config.timing.clone(),
+            load_target: config.load_target,
+            mode_load_targets: config.mode_load_targets.clone(),
+            frequency_thresholds: config.frequency_thresholds.clone(),
+            performance_mode: config.performance_mode.clone(),
+            perf_lock_freq,
+            apply_backoff: config.apply_backoff.clone(),
+            burst_soft_limit: config.thermal.burst_soft_limit,
+            burst_soft_scale: config.thermal.burst_soft_scale.clamp(0.0, 1.0),
+            throttle_curve: config.thermal.throttle_curve.clone(),
+            rehearsal: config.thermal.rehearsal,
+            auto_mode_enabled: config.auto_mode.enabled,
+            auto_window: Duration::from_secs(config.auto_mode.window_secs.max(1)),
+            auto_dwell: Duration::from_secs(config.auto_mode.dwell_secs),
+            auto_rules,
+            schedule: if config.schedule.enabled {
+                config.schedule.rules.iter().filter_map(ScheduleRuleConfig::window).collect()
+            } else {
+                Vec::new()
+            },
+            od_watch: config.od_watch.clone(),
+            policy: config.policy.clone(),
+            memory_clocks: config.memory_clocks.clone(),
+            cpu_coupling: config.cpu_coupling.clone(),
+            power_budget: config.power_budget.clone(),
+            boost_budget: config.boost_budget.clone(),
+            gpu_clients_top: config.gpu_clients.top,
+            max_safe_temp: config.thermal.max_safe_temp(),
+            deep_idle: config.deep_idle.clone(),
+            profile: config.profile.clone(),
+            profiles: config.profiles.clone(),
+            min_freq,
+            max_freq,
+            mirror_override_temp: config.thermal.max_safe_temp(),
+        }
+    }
+
+    /// Resolves the effective load target for a profile and mode; the bool tells whether the mode overrides it.
+    pub(crate) fn load_target_for(&self, profile: &Profile, mode: PerformanceMode) -> (LoadTarget, bool) {
+        let base = self.load_target.with_override(&profile.load_target);
+        match self.mode_load_targets.get(mode.name()) {
+            Some(o) => (base.with_override(o), true),
+            None => (base, false),
+        }
+    }
+
+    /// The named profile, or the startup profile if it no longer exists.
+    pub(crate) fn profile(&self, name: &str) -> (String, Profile) {
+        match self.profiles.get(name) {
+            Some(profile) => (name.to_string(), profile.clone()),
+            None => (self.profile.clone(), self.profiles.get(&self.profile).cloned().unwrap_or_default()),
+        }
+    }
+
+    pub(crate) fn build_policy(&self, profile: &Profile) -> Box<dyn GovernorPolicy> {
+        let rates = self.timing.ramp_rates.with_override(&profile.ramp_rates);
+        match self.policy.kind {
+            PolicyKind::Ladder => Box::new(LadderPolicy::new(rates)),
+            PolicyKind::Pid => Box::new(PidPolicy::new(self.policy.pid, rates.burst)),
+            PolicyKind::Frametime => Box::new(FrametimePolicy::new(self.policy.frametime.target(), rates)),
+        }
+    }
+
+    /// Settings of the scaling step around the policy.
+    pub(crate) fn scaling(&self) -> ScalingSettings {
+        let (timing, thresholds) = (&self.timing, &self.frequency_thresholds);
+        ScalingSettings {
+            sample_interval: Duration::from_micros(timing.intervals.sample),
+            up_samples: timing.ramp_up_samples as usize,
+            down_samples: timing.ramp_down_samples as usize,
+            burst_samples: timing.burst_samples as usize,
+            burst_busy: timing.burst_busy(),
+            burst_rearm: Duration::from_millis(timing.burst_rearm_ms),
+            ema: timing.ema,
+            adjust_mhz: thresholds.adjust,
+            finetune_mhz: thresholds.finetune,
+            deadband_mhz: thresholds.deadband_mhz,
+            deadband: Duration::from_millis(thresholds.deadband_ms),
+            adjust_interval: Duration::from_micros(timing.intervals.adjust),
+            finetune_interval: Duration::from_micros(timing.intervals.finetune),
+            max_applies_per_second: timing.max_applies_per_second,
+        }
+    }
+}
+
+/// How often the governor looks up the active schedule window
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a setter command may stay unacknowledged before watchdog pings stop
+const SETTER_STALL_LIMIT: Duration = Duration::from_secs(5);
+
+/// What one GPU's threads report to [`run`], which speaks to systemd for
+/// the whole process: READY=1 once every GPU is up, and WATCHDOG=1 only
+/// while every GPU's sampling loop is moving and no setter is stuck.
+#[derive(Default)]
+struct DeviceHealth {
+    /// Whether systemd expects watchdog pings, so a stall is worth an alert
+    watchdog: bool,
+    /// Set once the GPU's threads are all up
+    ready: AtomicBool,
+    /// Set while the setter leaves a write unanswered past [`SETTER_STALL_LIMIT`]
+    setter_stalled: AtomicBool,
+    /// Passes of the sampling loop
+    beats: AtomicU64,
+}
+
+/// Sends READY=1 once every device is ready, then WATCHDOG=1 every
+/// `watchdog` interval for as long as every device is healthy, until `done`.
+fn notify_service_manager(devices: Vec<Arc<DeviceHealth>>, watchdog: Option<Duration>, done: Arc<AtomicBool>) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let beats = || devices.iter().map(|d| d.beats.load(Ordering::SeqCst)).collect::<Vec<u64>>();
+        let mut ready = false;
+        let mut last_ping = Instant::now();
+        let mut last_beats = Vec::new();
+        while !done.load(Ordering::SeqCst) {
+            if !ready && devices.iter().all(|d| d.ready.load(Ordering::SeqCst)) {
+                if let Err(e) = systemd::notify("READY=1") {
+                    eprintln!("⚠️  Failed to notify systemd: {}", e);
+                }
+                ready = true;
+                last_ping = Instant::now();
+                last_beats = beats();
+            }
+            if let Some(interval) = watchdog.filter(|_| ready) {
+                if last_ping.elapsed() >= interval {
+                    // A hung sampling loop or setter on any GPU lets the watchdog expire
+                    let current = beats();
+                    let moving = current.iter().zip(&last_beats).all(|(now, before)| now != before);
+                    if moving && !devices.iter().any(|d| d.setter_stalled.load(Ordering::SeqCst)) {
+                        let _ = systemd::notify("WATCHDOG=1");
+                    }
+                    last_ping = Instant::now();
+                    last_beats = current;
+                }
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    })
+}
+
+/// How long an emergency shutdown waits for the state dump to go out
+const EMERGENCY_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Stands in for the hardware writes under `--dry-run`: each one is
+/// printed instead, once per change of value, and reported as successful.
+struct DryRun {
+    enabled: bool,
+    /// Last value printed per target
+    last: HashMap<String, String>,
+}
+
+impl DryRun {
+    fn new(enabled: bool) -> Self {
+        Self { enabled, last: HashMap::new() }
+    }
+
+    /// Runs `write`, or prints `value` as written to `target` when dry-running.
+    fn write(&mut self, target: impl Display, value: impl Display, write: impl FnOnce() -> Result<(), IoError>) -> Result<(), IoError> {
+        if !self.enabled {
+            return write();
+        }
+        let (target, value) = (target.to_string(), value.to_string());
+        if self.last.get(&target) != Some(&value) {
+            println!("🧪 Dry run: would write {} to {}", value, target);
+            self.last.insert(target, value);
+        }
+        Ok(())
+    }
+}
+
+/// Starts `emergency-action` for the emergency in `message`, once as it
+/// begins. Returns true when the governor has to shut down, which
+/// `emergency_shutdown` then carries out.
+fn start_emergency_action(thermal: &Thermal, message: &str, temperature: f32, clamp: &AtomicBool) -> bool {
+    match thermal.emergency_action {
+        EmergencyAction::Exit => {
+            eprintln!("🚨 EMERGENCY: {}. Shutting down!", message);
+            true
+        }
+        EmergencyAction::MinClock => {
+            eprintln!("🚨 EMERGENCY: {}. Holding the lowest safe point until below {:.1}°C", message, thermal.max_safe_temp());
+            clamp.store(true, Ordering::SeqCst);
+            false
+        }
+        EmergencyAction::Script => {
+            eprintln!("🚨 EMERGENCY: {}. Running '{}' and holding the lowest safe point until below {:.1}°C",
+                message, thermal.emergency_script, thermal.max_safe_temp());
+            // The script may take its time, or fail; the GPU is not left at full clock meanwhile
+            clamp.store(true, Ordering::SeqCst);
+            let child = Command::new("sh")
+                .arg("-c")
+                .arg(&thermal.emergency_script)
+                .env("BC250_TEMPERATURE", format!("{temperature:.1}"))
+                .env("BC250_MESSAGE", message)
+                .stdin(Stdio::null())
+                .spawn();
+            match child {
+                // Reaped in the background, so a slow script never holds up the thermal checks
+                Ok(mut child) => {
+                    std::thread::spawn(move || {
+                        let _ = child.wait();
+                    });
+                }
+                Err(e) => eprintln!("⚠️  Failed to run the emergency script: {}", e),
+            }
+            false
+        }
+        EmergencyAction::Poweroff => {
+            eprintln!("🚨 EMERGENCY: {}. Powering off!", message);
+            true
+        }
+    }
+}
+
+/// The way out of an emergency that ends the governor: the setter moves to the
+/// lowest safe point, every fan is pinned at full speed and the state dump gets
+/// a moment to go out, all before the shutdown flag stops the threads (and,
+/// for `poweroff`, before the machine goes down). Under `--mock` and
+/// `--dry-run` (`simulated`) the poweroff is only printed.
+fn emergency_shutdown(tm: &ThermalManager, thermal: &Thermal, setter: &mpsc::Sender<GovCommand>, fan_writes: &mut DryRun,
+    fans_pinned: &AtomicBool, dump_request: &AtomicBool, simulated: bool) {
+    // Commands are handled in order, so the setter holds the floor before it sees the shutdown
+    if setter.send(GovCommand::HoldLowestSafePoint).is_err() {
+        eprintln!("⚠️  Setter thread is gone, the clock stays where it is");
+    }
+    eprintln!("🌀 Pinning every fan at 100% until the governor is restarted");
+    for (idx, fan) in tm.fans.iter().enumerate() {
+        if let Err(e) = fan_writes.write(format_args!("fan {idx} PWM"), "100%", || tm.set_fan_speed(idx, 100)) {
+            eprintln!("⚠️  Failed to pin fan {} at 100%: {}", fan.name, e);
+        }
+    }
+    fans_pinned.store(true, Ordering::SeqCst);
+    // The governor thread writes the dump on its next pass and clears the request
+    let start = Instant::now();
+    while dump_request.load(Ordering::SeqCst) && start.elapsed() < EMERGENCY_FLUSH_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    if thermal.emergency_action == EmergencyAction::Poweroff {
+        if simulated {
+            println!("🧪 Dry run: would run systemctl poweroff");
+        } else if let Err(e) = Command::new("systemctl").arg("poweroff").status() {
+            eprintln!("⚠️  systemctl poweroff failed: {}. Shutting down the governor only", e);
+        }
+    }
+}
+
+/// What the performance-mode control file asks for: None while it does not
+/// exist, max performance when it is empty, and the text itself when it
+/// names no mode.
+fn read_control_file(path: &str) -> Option<Result<PerformanceMode, String>> {
+    if path.is_empty() {
+        return None;
+    }
+    let text = std::fs::read_to_string(path).ok()?;
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(Ok(PerformanceMode::MaxPerformance));
+    }
+    Some(PerformanceMode::parse(text).ok_or_else(|| text.to_string()))
+}
+
+/// Saves the applied frequency for `startup.policy = "resume"`.
+fn persist_frequency(path: &std::path::Path, freq: u16) {
+    let result = path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| status::write_atomic(path, freq.to_string().as_bytes()));
+    if let Err(e) = result {
+        eprintln!("⚠️  Failed to persist frequency to {}: {}", path.display(), e);
+    }
+}
+
+/// Rejects curves that drive one fan twice. An index and a name can point at
+/// the same fan, which only the discovered fans tell; `check-config` can
+/// only compare the refs as written.
+fn check_fan_curves(tm: Option<&ThermalManager>, thermal: &Thermal) -> Result<(), String> {
+    let duplicates = tm.map(|tm| tm.duplicate_fans(&thermal.fan_curves())).unwrap_or_default();
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("thermal fan-control drives {} more than once", duplicates.join(", ")))
+    }
+}
+
+/// Loops for the fans of `thermal`'s curves. Without any curve the fan at
+/// `fan_control_index` is still watched, so its stall check runs.
+fn thermal_fan_loops(tm: &ThermalManager, thermal: &Thermal, previous: Vec<FanLoop>) -> Vec<FanLoop> {
+    let mut curves = thermal.fan_curves();
+    if curves.is_empty() && thermal.fan_control_index < tm.fans.len() {
+        curves.push(FanCurve::new(FanRef::Index(thermal.fan_control_index), "max", Vec::new()));
+    }
+    tm.fan_loops(curves, previous)
+}
+
+/// What [`run`] governs with, besides the config file's contents.
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    /// Config file, re-read on reload; None governs with the built-in defaults,
+    /// and a file that cannot be read, parsed or validated stops the start
+    pub config: Option<PathBuf>,
+    /// Drive a mock GPU from this fault-injection scenario instead of the real one
+    pub mock: Option<PathBuf>,
+    /// Log every busy sample, decision and apply to this CSV file
+    pub record: Option<PathBuf>,
+    /// Print the OD, DPM, PWM and cpufreq writes instead of making them
+    pub dry_run: bool,
+    /// Set to re-read the config file, like the control socket's "reload" method
+    pub reload: Arc<AtomicBool>,
+    /// Set to dump the governor state to the log, like the control socket's "dump" method
+    pub dump: Arc<AtomicBool>,
+}
+
+/// Governs the GPU, or every GPU of a multi-device config side by side,
+/// until `shutdown` is set. The card is then left as `[shutdown] policy`
+/// says and the fans go back to automatic control before this returns.
+///
+/// Stopping is up to the caller: the binary sets `shutdown` on SIGTERM and
+/// SIGINT, and the `reload` and `dump` options on SIGHUP and SIGQUIT.
+pub fn run(options: RunOptions, shutdown: Arc<AtomicBool>) -> Result<(), Box<dyn std::error::Error>> {
+    // A config that was given but cannot be used stops the start, rather than governing with defaults
+    let config_str = match &options.config {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read config file {}: {}", path.display(), e))?,
+        None => String::new(),
+    };
+    let config: Config = toml::from_str(&config_str)
+        .map_err(|e| format!("invalid config file: {}", e))?;
+
+    validate_config(&config)?;
+
+    let devices = device_instances(&config, options.mock.is_some())?;
+    let watchdog = systemd::watchdog_interval();
+    let new_health = || Arc::new(DeviceHealth { watchdog: watchdog.is_some(), ..Default::default() });
+    let notified = Arc::new(AtomicBool::new(false));
+    if devices.is_empty() {
+        let health = new_health();
+        let notify_jh = notify_service_manager(vec![Arc::clone(&health)], watchdog, Arc::clone(&notified));
+        let result = run_device(config, options, None, health, shutdown);
+        notified.store(true, Ordering::SeqCst);
+        let _ = notify_jh.join();
+        return result;
+    }
+
+    let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+    println!("🖥️  Governing {} GPUs: {}", devices.len(), names.join(", "));
+    let mut device_requests = Vec::new();
+    let mut device_health = Vec::new();
+    let handles: Vec<_> = devices.into_iter().map(|device| {
+        let config = toml::from_str::<Config>(&config_str).unwrap_or_default().for_device(&device);
+        let device_options = RunOptions {
+            record: options.record.as_ref().map(|p| device.path(&p.to_string_lossy()).into()),
+            reload: Arc::default(),
+            dump: Arc::default(),
+            ..options.clone()
+        };
+        device_requests.push((Arc::clone(&device_options.reload), Arc::clone(&device_options.dump)));
+        let health = new_health();
+        device_health.push(Arc::clone(&health));
+        let shutdown = Arc::clone(&shutdown);
+        let name = device.name.clone();
+        let jh = std::thread::spawn(move || {
+            // Box<dyn Error> is not Send, so only the message crosses threads
+            run_device(config, device_options, Some(device), health, shutdown).map_err(|e| e.to_string())
+        });
+        (name, jh)
+    }).collect();
+    let notify_jh = notify_service_manager(device_health, watchdog, Arc::clone(&notified));
+
+    // Each GPU consumes its own request flags, so one reload or dump from the caller goes to all of them
+    let governed = Arc::new(AtomicBool::new(true));
+    let forward_jh = {
+        let (governed, shutdown) = (Arc::clone(&governed), Arc::clone(&shutdown));
+        std::thread::spawn(move || {
+            while governed.load(Ordering::SeqCst) && !shutdown.load(Ordering::SeqCst) {
+                let (reload, dump) = (options.reload.swap(false, Ordering::SeqCst), options.dump.swap(false, Ordering::SeqCst));
+                for (device_reload, device_dump) in &device_requests {
+                    device_reload.fetch_or(reload, Ordering::SeqCst);
+                    device_dump.fetch_or(dump, Ordering::SeqCst);
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })
+    };
+
+    let mut failed = Vec::new();
+    for (name, jh) in handles {
+        match jh.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                eprintln!("❌ [{}] {}", name, e);
+                failed.push(name);
+            }
+            Err(_) => {
+                eprintln!("❌ [{}] governor panicked", name);
+                failed.push(name);
+            }
+        }
+    }
+    governed.store(false, Ordering::SeqCst);
+    let _ = forward_jh.join();
+    notified.store(true, Ordering::SeqCst);
+    let _ = notify_jh.join();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(IoError::other(format!("governing failed on {}", failed.join(", "))).into())
+    }
+}
+
+/// Runs the governor, setter, thermal and service threads for one GPU until
+/// `shutdown_flag` is set. `device` is set when several GPUs are governed
+/// side by side; `config` then already carries its overrides and `options`
+/// this GPU's trace file and request flags. Readiness and liveness go to
+/// `health`, for [`run`] to tell systemd.
+fn run_device(
+    mut config: Config,
+    options: RunOptions,
+    device: Option<DeviceInstance>,
+    health: Arc<DeviceHealth>,
+    shutdown_flag: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let RunOptions { config: config_path, mock: mock_scenario, record: record_trace, dry_run, reload, dump } = options;
+    let label = device.as_ref().map_or_else(String::new, |d| format!("[{}] ", d.name));
+    if device.is_some() {
+        validate_config(&config).map_err(|e| IoError::new(e.kind(), format!("{}{}", label, e)))?;
+    }
+
+    let mock = match &mock_scenario {
+        Some(scenario) => {
+            let mut injector = FaultInjector::load(scenario)?;
+            // Each device gets its own mock sysfs tree
+            if let Some(d) = device.as_ref().filter(|d| !d.primary) {
+                injector.root = std::path::PathBuf::from(format!("{}-{}", injector.root.display(), d.name));
+            }
+            Some(Arc::new(injector))
+        }
+        None => None,
+    };
+
+    let lowest = config.safe_points.iter().map(|p| p.frequency).min().unwrap_or_default();
+    let GpuDevice { _card, registers: register_source, sysfs_path, min_engine_clock, max_engine_clock } =
+        open_gpu(config.gpu.pci_bus, mock.as_ref(), lowest)?;
+    seed_safe_points(&mut config, &sysfs_path.join("pp_od_clk_voltage"))?;
+
+    // Secondary GPUs only watch their own sensors; fans and CPU temperatures stay with the primary
+    let mut thermal_manager = match (&mock, &device) {
+        (_, Some(d)) if !d.primary => ThermalManager::new_with_root(&sysfs_path.join("hwmon").to_string_lossy()).ok(),
+        (Some(injector), _) => ThermalManager::new_with_root(&injector.hwmon_root().to_string_lossy()).ok(),
+        (None, _) => ThermalManager::new().ok(),
+    };
+    if let (Some(tm), None) = (&mut thermal_manager, &mock) {
+        if let Err(e) = tm.set_backend(config.thermal.backend) {
+            eprintln!("⚠️  {:?} thermal backend unavailable: {}. Reading temperatures from sysfs.", config.thermal.backend, e);
+        }
+    }
+    // A secondary GPU's hwmon only has its own amdgpu sensor, which the defaults already name
+    if let (Some(tm), true) = (&mut thermal_manager, device.as_ref().is_none_or(|d| d.primary)) {
+        tm.set_sensors(&config.thermal.sensors);
+    }
+    // Thresholds the config leaves unset come from the GPU sensors' own limits
+    let gpu_limits = thermal_manager.as_ref().map(ThermalManager::gpu_limits).unwrap_or_default();
+    let derived = config.thermal.derive_thresholds(&gpu_limits);
+    if !derived.is_empty() {
+        println!("🌡️  Thresholds from the sensor limits: {}", derived.join(", "));
+    }
+    check_fan_curves(thermal_manager.as_ref(), &config.thermal)?;
+    let safe_points: BTreeMap<u16, u16> = config.safe_points.iter().map(|p| (p.frequency, p.voltage)).collect();
+    let voltage_offset = (config.voltage_offset_mv, config.voltage_floor());
+    // Nothing is written under --dry-run, so a readback would always disagree
+    let verify_applies = config.od_watch.verify.then_some(config.od_watch.tolerance).filter(|_| !dry_run);
+    // Captured before the first write, so shutdown can hand the card back as it was; a card
+    // still at the driver's defaults is handed back with a reset rather than a pinned point
+    let original_od = od::read(&sysfs_path.join("pp_od_clk_voltage")).ok()
+        .filter(|table| !table.at_driver_default())
+        .and_then(|table| Some((*table.sclk.first()?, *table.vddc.first()?)));
+    let shutdown_policy = config.shutdown.policy;
+
+    let mut min_freq = safe_points.first_key_value().map(|(&k, _)| k).unwrap_or(min_engine_clock as u16);
+    let mut max_freq = safe_points.last_key_value().map(|(&k, _)| k).unwrap_or(max_engine_clock as u16);
+
+    let od_path = sysfs_path.join("pp_od_clk_voltage");
+
+    // Memory and fabric clocks are only governed when the driver lists at least two levels
+    let mut dpm_levels: Vec<DpmLevels> = if config.memory_clocks.enabled {
+        config.memory_clocks.clocks().into_iter().filter_map(|clock| {
+            let path = sysfs_path.join(clock.file_name());
+            match memclk::read_levels(&path) {
+                Ok(freqs) if freqs.len() >= 2 => {
+                    println!("🧠 Governing {}: {} levels, {}-{}MHz", clock.name(), freqs.len(),
+                        freqs.iter().min().unwrap(), freqs.iter().max().unwrap());
+                    Some(DpmLevels { clock, path, freqs })
+                }
+                Ok(_) => {
+                    eprintln!("⚠️  {} has a single level, leaving {} to the driver", path.display(), clock.name());
+                    None
+                }
+                Err(e) => {
+                    eprintln!("⚠️  Cannot read {}: {}, leaving {} to the driver", path.display(), e, clock.name());
+                    None
+                }
+            }
+        }).collect()
+    } else {
+        Vec::new()
+    };
+    // Forced levels only take effect in the manual performance level; the previous one comes back on shutdown
+    let level_path = sysfs_path.join(memclk::PERFORMANCE_LEVEL_FILE);
+    let previous_level = if dpm_levels.is_empty() {
+        None
+    } else {
+        let switched = memclk::read_performance_level(&level_path).and_then(|previous| {
+            DryRun::new(dry_run).write(level_path.display(), "manual", || memclk::write_performance_level(&level_path, "manual"))
+                .map(|()| previous)
+        });
+        match switched {
+            Ok(previous) => {
+                println!("🧠 Performance level {} -> manual", previous);
+                Some(previous)
+            }
+            Err(e) => {
+                eprintln!("⚠️  Cannot switch {} to manual: {}, leaving memory and fabric clocks to the driver", level_path.display(), e);
+                dpm_levels.clear();
+                None
+            }
+        }
+    };
+    let mut dpm_clocks: Vec<DpmClock> = dpm_levels.iter().map(|d| d.clock).collect();
+
+    // Looked up even when disabled, so a reload can turn the budget on
+    let power_input = match (&mock, &device) {
+        (_, Some(_)) => power::find_power_input(&sysfs_path.join("hwmon")),
+        (Some(injector), None) => power::find_power_input(&injector.hwmon_root()),
+        (None, None) => power::find_power_input(std::path::Path::new("/sys/class/hwmon")),
+    };
+    // Opened before the MangoHUD fix shadows gpu_metrics
+    let mut load_sampler = LoadSampler::new(&config.gpu, register_source, &sysfs_path);
+    if config.power_budget.enabled && power_input.is_none() && load_sampler.metrics().and_then(|m| m.watts).is_none() {
+        eprintln!("⚠️  No amdgpu power reading found, power budget disabled");
+    }
+
+    let cpu_coupling = if config.cpu_coupling.enabled {
+        let root = mock.as_ref().map_or_else(|| std::path::PathBuf::from(cpufreq::DEFAULT_CPUFREQ_ROOT), |m| m.cpufreq_root());
+        match CpuCoupling::discover(&root) {
+            Ok(coupling) => {
+                println!("🧮 CPU coupling: raising the minimum of {} cpufreq policies under GPU load", coupling.policy_count());
+                Some(coupling)
+            }
+            Err(e) => {
+                eprintln!("⚠️  CPU coupling unavailable: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let reported_freq = read_od_sclk(&od_path).unwrap_or(min_freq);
+    
+    let startup_freq = match config.startup.policy {
+        StartupPolicy::Current => None,
+        StartupPolicy::Minimum => Some(min_freq),
+        StartupPolicy::Resume => {
+            let resumed = std::fs::read_to_string(&config.startup.state_file)
+                .ok()
+                .and_then(|s| s.trim().parse::<u16>().ok());
+            if resumed.is_none() {
+                eprintln!("⚠️  No persisted frequency in {}, starting at minimum", config.startup.state_file);
+            }
+            Some(resumed.unwrap_or(min_freq))
+        }
+        StartupPolicy::SafePoint => {
+            let wanted = config.startup.safe_point.as_deref().unwrap_or_default();
+            let point = config.safe_points.iter().find(|p| p.name.as_deref() == Some(wanted));
+            if point.is_none() {
+                eprintln!("⚠️  No safe point named '{}', starting at minimum", wanted);
+            }
+            Some(point.map(|p| p.frequency).unwrap_or(min_freq))
+        }
+    }.map(|f| f.clamp(min_freq, max_freq));
+    let current_freq = startup_freq.unwrap_or(reported_freq);
+    // Only "resume" reads the state file back; a dry run must not change where the real governor resumes
+    let state_file = (!dry_run && config.startup.policy == StartupPolicy::Resume)
+        .then(|| std::path::PathBuf::from(&config.startup.state_file));
+
+    println!("🚀 {}Initial frequency: {}MHz (min: {}MHz, max: {}MHz, policy: {:?})",
+        label, current_freq, min_freq, max_freq, config.startup.policy);
+    if config.voltage_offset_mv != 0 {
+        println!("🔋 Voltage offset {:+}mV on every safe point (floor {}mV)", config.voltage_offset_mv, config.voltage_floor());
+    }
+
+    if dry_run {
+        println!("🧪 {}Dry run: pp_od_clk_voltage, DPM, PWM and cpufreq writes are only printed", label);
+    }
+    // Read-only under --dry-run, so nothing can reach the OD table by accident
+    let pp_file = std::fs::OpenOptions::new().write(!dry_run).read(dry_run).open(&od_path)?;
+    let verify_path = od_path.clone();
+
+    let (gov_send, gov_recv) = mpsc::channel::<GovCommand>();
+    let (ack_send, ack_recv) = mpsc::channel::<SetterAck>();
+
+    let mock_jh = mock.as_ref().map(|injector| Arc::clone(injector).start_driver(Arc::clone(&shutdown_flag)));
+    let mut thermal_manager_clone = thermal_manager.clone();
+    let shared_thermal = SharedThermalStatus::default();
+    // Set by emergency-action = "min-clock": the sampling loop holds the floor while it is
+    let emergency_clamp = Arc::new(AtomicBool::new(false));
+    // Set once an emergency shutdown pinned the fans at full speed, so shutdown leaves them there
+    let fans_pinned = Arc::new(AtomicBool::new(false));
+    let thermal_counters = ThermalCounters::default();
+    let shared_status = SharedStatus::default();
+    let tuning = GovernorTuning::from_config(&config, min_freq, max_freq);
+    let (reload_send, reload_recv) = mpsc::channel::<Box<GovernorTuning>>();
+    let (thermal_reload_send, thermal_reload_recv) = mpsc::channel::<Thermal>();
+    let alerter = Alerter::new(config.alerts.command);
+
+    // The caller's requests and the control socket's "reload" and "dump" methods share one flag each
+    let control_ctx = ControlContext { status: Arc::clone(&shared_status), dump_request: dump, reload_request: reload, ..Default::default() };
+    control_ctx.profiles.lock().unwrap().reset(&config.profile, config.profiles.keys().cloned().collect());
+    let dump_request = Arc::clone(&control_ctx.dump_request);
+
+    let thermal_jh = if let Some(mut tm) = thermal_manager {
+        let mut thermal_config = config.thermal;
+        let shutdown_flag_thermal = Arc::clone(&shutdown_flag);
+        let trim_send = gov_send.clone();
+        let shared_thermal = shared_thermal.clone();
+        let emergency_clamp = Arc::clone(&emergency_clamp);
+        let fans_pinned = Arc::clone(&fans_pinned);
+        let simulated = dry_run || mock.is_some();
+        let shared_status = Arc::clone(&shared_status);
+        let alerter = alerter.clone();
+        let dump_request = Arc::clone(&dump_request);
+        let counters = thermal_counters.clone();
+        let mut fan_writes = DryRun::new(dry_run);
+        if thermal_config.rehearsal {
+            println!("🎭 Thermal rehearsal mode: thresholds only alert, no hardware action is taken");
+        }
+        Some(std::thread::spawn(move || {
+            let mut last_thermal_check = Instant::now();
+            let mut trim_offset: i16 = 0;
+            let mut warning_active = false;
+            let mut emergency_active = false;
+            // A fan stalled or stopped taking PWM writes: the others run flat out and the clock holds the floor
+            let mut fan_failover = false;
+            let mut fan_residency: Vec<FanResidency> = tm.fans.iter().map(|f| FanResidency::new(&f.name)).collect();
+            let mut last_residency_sample: Option<Instant> = None;
+            let mut fan_loops = thermal_fan_loops(&tm, &thermal_config, Vec::new());
+            let mut temperature_filter = TemperatureFilter::new(&thermal_config.smoothing);
+            let mut last_rescan = Instant::now();
+            // Max-performance mode switches the fans with a performance-curve over to it
+            let mut performance_curves = false;
+            loop {
+                // Check for shutdown signal
+                if shutdown_flag_thermal.load(Ordering::SeqCst) {
+                    eprintln!("🛑 Thermal thread received shutdown signal");
+                    break;
+                }
+
+                while let Ok(new_config) = thermal_reload_recv.try_recv() {
+                    if new_config.backend != thermal_config.backend {
+                        eprintln!("⚠️  Thermal backend changes only take effect after a restart");
+                    }
+                    if new_config.sensors != thermal_config.sensors {
+                        eprintln!("⚠️  [thermal.sensors] changes only take effect after a restart");
+                    }
+                    println!("🔄 Thermal config reloaded: warning {:.1}°C, emergency {:.1}°C, {} fan curves{}",
+                        new_config.max_safe_temp(), new_config.emergency_temp(), new_config.fan_curves().len(),
+                        if new_config.rehearsal { " (rehearsal)" } else { "" });
+                    fan_loops = thermal_fan_loops(&tm, &new_config, fan_loops);
+                    if new_config.smoothing != thermal_config.smoothing {
+                        temperature_filter = TemperatureFilter::new(&new_config.smoothing);
+                    }
+                    thermal_config = new_config;
+                }
+
+                if thermal_config.rescan_secs > 0 && last_rescan.elapsed() >= Duration::from_secs(thermal_config.rescan_secs) {
+                    last_rescan = Instant::now();
+                    if let Some(old_fans) = tm.rescan() {
+                        // Fan indexes shift as devices come and go, so per-fan state follows the fan's name
+                        let previous = fan_loops.into_iter().filter_map(|mut fan_loop| {
+                            fan_loop.index = tm.find_fan(&FanRef::Name(old_fans[fan_loop.index].name.clone()))?;
+                            Some(fan_loop)
+                        }).collect();
+                        fan_loops = thermal_fan_loops(&tm, &thermal_config, previous);
+                        fan_residency = tm.fans.iter()
+                            .map(|f| fan_residency.iter().find(|r| r.name == f.name).cloned().unwrap_or_else(|| FanResidency::new(&f.name)))
+                            .collect();
+                    }
+                }
+
+                if last_thermal_check.elapsed() >= Duration::from_millis(thermal_config.monitor_interval) {
+                    let thermal_status = tm.get_smoothed_status(thermal_config.include_cpu_temp, &mut temperature_filter);
+                    shared_thermal.store(&thermal_status);
+                    let stall = &thermal_config.fan_control;
+                    let fans: Vec<FanStatus> = fan_loops.iter_mut().map(|fan_loop| {
+                        let pwm = tm.get_primary_fan_info(fan_loop.index).0;
+                        let pwm_percent = pwm.map(|raw| ((raw as f32) * 100.0 / 255.0).round() as u8);
+                        let rpm = tm.fan_rpm(fan_loop.index);
+                        FanStatus {
+                            name: tm.fans[fan_loop.index].name.clone(),
+                            pwm,
+                            pwm_percent,
+                            rpm,
+                            temperature: thermal_status.source_temperature(&fan_loop.curve.source),
+                            ambient: fan_loop.curve.ambient.as_deref().and_then(|a| thermal_status.source_temperature(a)),
+                            stalled: stall.stall_secs > 0.0 && fan_loop.stall.update(pwm_percent, rpm, stall.stall_pwm,
+                                Duration::from_secs_f32(stall.stall_secs)),
+                        }
+                    }).collect();
+                    let primary = fans.first();
+                    let pwm_pct_str = |fan: &FanStatus| fan.pwm_percent.map(|p| format!("{}%", p)).unwrap_or_else(|| "N/A".to_string());
+                    let fans_str: String = fans.iter()
+                        .map(|fan| format!(" {}:{}{}", fan.name, pwm_pct_str(fan), fan.rpm.map(|r| format!("/{}RPM", r)).unwrap_or_default()))
+                        .collect();
+
+                    // Charge the time since the previous check to each fan's current PWM band
+                    if let Some(last) = last_residency_sample {
+                        let elapsed = last.elapsed();
+                        for (idx, residency) in fan_residency.iter_mut().enumerate() {
+                            if let (Some(pwm), _) = tm.get_primary_fan_info(idx) {
+                                residency.record(pwm, elapsed);
+                            }
+                        }
+                    }
+                    last_residency_sample = Some(Instant::now());
+
+                    shared_status.lock().unwrap().thermal = ThermalSnapshot {
+                        amdgpu: thermal_status.amdgpu_temperature,
+                        gpu_channels: thermal_status.gpu_channels.clone(),
+                        cpu: thermal_status.cpu_temperature,
+                        max: thermal_status.max_temperature,
+                        gpu_max: thermal_status.gpu_max_temperature,
+                        fan_pwm_percent: primary.and_then(|fan| fan.pwm_percent),
+                        fan_rpm: primary.and_then(|fan| fan.rpm),
+                        fan_stalled: fans.iter().any(|fan| fan.stalled),
+                        fans: fans.clone(),
+                        fan_residency: fan_residency.clone(),
+                    };
+                    println!("🌡️  Temps: AMD:{:.1}°C CPU:{:.1}°C Max:{:.1}°C GPU-Max:{:.1}°C - Fans:{}",
+                        thermal_status.amdgpu_temperature, thermal_status.cpu_temperature, thermal_status.max_temperature,
+                        thermal_status.gpu_max_temperature, if fans.is_empty() { " N/A" } else { &fans_str });
+
+                    let rehearsal = thermal_config.rehearsal;
+                    let rehearsal_tag = if rehearsal { "[REHEARSAL] " } else { "" };
+                    // A fan that stopped turning leaves the GPU without cooling, so it takes the emergency path
+                    if let Some(i) = fans.iter().position(|fan| fan.stalled) {
+                        let (fan, fan_loop) = (&fans[i], &mut fan_loops[i]);
+                        if !fan_loop.stall_active {
+                            let message = format!("{}Fan {} at 0 RPM for {:.0}s with PWM {}", rehearsal_tag,
+                                fan.name, fan_loop.stall.stalled_for().as_secs_f32(), pwm_pct_str(fan));
+                            alerter.raise(AlertKind::FanStall, &message);
+                            ThermalCounters::bump(&counters.fan_stalls);
+                            dump_request.store(true, Ordering::SeqCst);
+                            fan_loop.stall_active = true;
+                            if rehearsal {
+                                eprintln!("🎭 FAN STALL (rehearsal): would {}", thermal_config.emergency_action.describe());
+                            } else if start_emergency_action(&thermal_config, &format!("Fan {} stalled at PWM {}", fan.name, pwm_pct_str(fan)),
+                                thermal_status.gpu_max_temperature, &emergency_clamp) {
+                                emergency_shutdown(&tm, &thermal_config, &trim_send, &mut fan_writes, &fans_pinned, &dump_request, simulated);
+                                shutdown_flag_thermal.store(true, Ordering::SeqCst);
+                                break;
+                            }
+                        }
+                    }
+                    for (fan_loop, fan) in fan_loops.iter_mut().zip(&fans) {
+                        fan_loop.stall_active &= fan.stalled;
+                    }
+                    let failing = fan_loops.iter().any(|fan_loop| fan_loop.failing(thermal_config.fan_control.max_write_failures));
+                    if failing != fan_failover {
+                        if failing {
+                            eprintln!("🌀 {}Fan failure: running every fan at 100% and holding the lowest safe point", rehearsal_tag);
+                            if !rehearsal {
+                                emergency_clamp.store(true, Ordering::SeqCst);
+                            }
+                        } else {
+                            println!("🌀 Fans recovered, back to the fan curves");
+                        }
+                        fan_failover = failing;
+                    }
+
+                    if let Some((temp, threshold)) = thermal_config.emergency_exceeded(&thermal_status) {
+                        if !emergency_active {
+                            let message = format!("{}Temp {:.1}°C > {:.1}°C", rehearsal_tag, temp, threshold);
+                            alerter.raise(AlertKind::ThermalEmergency, &message);
+                            ThermalCounters::bump(&counters.emergencies);
+                            dump_request.store(true, Ordering::SeqCst);
+                            emergency_active = true;
+                            if rehearsal {
+                                eprintln!("🎭 EMERGENCY (rehearsal): would {} at {:.1}°C", thermal_config.emergency_action.describe(), temp);
+                            } else if start_emergency_action(&thermal_config, &format!("Temp {:.1}°C > {:.1}°C", temp, threshold),
+                                temp, &emergency_clamp) {
+                                emergency_shutdown(&tm, &thermal_config, &trim_send, &mut fan_writes, &fans_pinned, &dump_request, simulated);
+                                shutdown_flag_thermal.store(true, Ordering::SeqCst);
+                                break;
+                            }
+                        }
+                    } else {
+                        emergency_active = false;
+                    }
+                    // The hold on the lowest safe point lasts until the GPU is back under the warning threshold with every fan working
+                    let warning = thermal_config.warning_exceeded(&thermal_status);
+                    if warning.is_none() && !fan_failover && emergency_clamp.swap(false, Ordering::SeqCst) {
+                        println!("🌡️  Emergency over at {:.1}°C, releasing the lowest safe point", thermal_status.gpu_max_temperature);
+                    }
+
+                    if let Some((temp, threshold)) = warning {
+                        eprintln!("🔥 {}THERMAL WARNING: {:.1}°C > {:.1}°C", rehearsal_tag, temp, threshold);
+                        if !warning_active {
+                            ThermalCounters::bump(&counters.warnings);
+                            alerter.raise(AlertKind::ThermalWarning, &format!("{}Temp {:.1}°C > {:.1}°C", rehearsal_tag,
+                                temp, threshold));
+                            warning_active = true;
+                        }
+                    } else {
+                        warning_active = false;
+                    }
+
+                    // Voltage compensation is issued as a trim so it doesn't wait for the next frequency change
+                    let comp = &thermal_config.voltage_compensation;
+                    if comp.enabled {
+                        let hot = if trim_offset == 0 {
+                            thermal_status.gpu_max_temperature > comp.above_temp
+                        } else {
+                            thermal_status.gpu_max_temperature > comp.above_temp - comp.hysteresis
+                        };
+                        let wanted = if hot { comp.offset_mv } else { 0 };
+                        if wanted != trim_offset {
+                            println!("🔧 {}Voltage compensation: {:+}mV at {:.1}°C", rehearsal_tag, wanted, thermal_status.gpu_max_temperature);
+                            if rehearsal || trim_send.send(GovCommand::TrimVoltage(wanted)).is_ok() {
+                                trim_offset = wanted;
+                            }
+                        }
+                    }
+
+                    if thermal_config.fan_control.enabled {
+                        let fan_control = &thermal_config.fan_control;
+                        let (min_step, min_interval, slew_rate) = (fan_control.min_step,
+                            Duration::from_millis(fan_control.min_interval_ms), fan_control.slew_rate);
+                        let full_speed = fan_failover && !rehearsal;
+                        let max_performance = shared_status.lock().unwrap().mode == PerformanceMode::MaxPerformance.name();
+                        if max_performance != performance_curves && fan_loops.iter().any(|f| !f.curve.performance_curve.is_empty()) {
+                            println!("🌀 {}", if max_performance { "Max performance: fans on their performance curves" } else { "Back to the quiet fan curves" });
+                        }
+                        performance_curves = max_performance;
+                        for (fan_loop, fan) in fan_loops.iter_mut().zip(&fans) {
+                            let curve = &fan_loop.curve;
+                            if curve.curve.is_empty() {
+                                continue;
+                            }
+                            // Everything below is in the curve's unit, so a raw curve is compared against the raw PWM
+                            let unit = curve.unit;
+                            let current = match unit {
+                                CurveUnit::Percent => fan.pwm_percent,
+                                CurveUnit::Pwm => fan.pwm,
+                            };
+                            let temperature = fan.temperature.unwrap_or(thermal_status.max_temperature);
+                            // An ambient curve follows the rise over the intake; without an intake
+                            // reading the absolute temperature errs on the loud side
+                            let curve_temperature = match (&curve.ambient, fan.ambient) {
+                                (Some(_), Some(ambient)) => temperature - ambient,
+                                _ => temperature,
+                            };
+                            let target_speed = if full_speed {
+                                fan_loop.stopped = false;
+                                unit.full()
+                            } else {
+                                // Leaving max performance, the quiet curve takes over as a falling curve would
+                                let (up_curve, down_curve) = if performance_curves && !curve.performance_curve.is_empty() {
+                                    (&curve.performance_curve, &[][..])
+                                } else {
+                                    (&curve.curve, &curve.down_curve[..])
+                                };
+                                let target_speed = next_fan_speed(curve_temperature, fan_loop.speed,
+                                    up_curve, down_curve, curve.down_hysteresis);
+                                let was_stopped = fan_loop.stopped;
+                                let target_speed = fan_loop.zero_rpm(temperature, target_speed, &fan_control.zero_rpm);
+                                let target_speed = fan_loop.start_assist(current, target_speed);
+                                if fan_loop.stopped != was_stopped {
+                                    println!("🌀 Fan {} {} at {:.1}°C", fan.name,
+                                        if fan_loop.stopped { "stopping (zero RPM)" } else { "spinning up" }, temperature);
+                                }
+                                target_speed
+                            };
+                            // A held change leaves the falling curve starting from the speed the fan still runs at
+                            if !fan_loop.should_write(current, target_speed, unit.from_percent(min_step), min_interval) {
+                                if current == Some(target_speed) {
+                                    fan_loop.speed = Some(target_speed);
+                                }
+                                continue;
+                            }
+                            // Ramping up from a standstill would not start the fan, and a failed fan leaves no time to ramp
+                            let target_speed = if full_speed || fan_loop.spinning_up() {
+                                target_speed
+                            } else {
+                                fan_loop.slewed(current, target_speed, slew_rate)
+                            };
+                            fan_loop.speed = Some(target_speed);
+                            fan_loop.last_write = Some(Instant::now());
+                            let set_idx = fan_loop.index;
+                            match fan_writes.write(format_args!("fan {set_idx} PWM"), unit.format(target_speed),
+                                || tm.set_fan_pwm(set_idx, unit.to_raw(target_speed))) {
+                                Ok(()) => {
+                                    if fan_control.max_write_failures > 0 && fan_loop.write_failures >= fan_control.max_write_failures {
+                                        println!("🌀 Fan {} takes PWM writes again", fan.name);
+                                    }
+                                    fan_loop.write_failures = 0;
+                                }
+                                Err(e) => {
+                                    eprintln!("Failed to set fan {} speed: {}", fan.name, e);
+                                    ThermalCounters::bump(&counters.fan_failures);
+                                    fan_loop.write_failures += 1;
+                                    if fan_loop.write_failures == fan_control.max_write_failures {
+                                        alerter.raise(AlertKind::FanFailure, &format!("{}Fan {}: {} PWM writes in a row failed: {}",
+                                            rehearsal_tag, fan.name, fan_loop.write_failures, e));
+                                        dump_request.store(true, Ordering::SeqCst);
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    last_thermal_check = Instant::now();
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+
+            for residency in fan_residency.iter().filter(|r| r.total_secs() > 0.0) {
+                eprintln!("🌀 Fan residency {}: {}", residency.name, residency.summary());
+            }
+            // Shutdown hands back the fans as the last scan found them
+            tm
+        }))
+    } else {
+        None
+    };
+
+    let control_jh = if config.control.enabled {
+        match control::start_server(config.control.socket.clone().into(), &config.control.group, control_ctx.clone(), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  Control socket {} unavailable: {}", config.control.socket, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let command_fifo_jh = if config.control.enabled && !config.control.fifo.is_empty() {
+        match control::start_fifo(config.control.fifo.clone().into(), &config.control.group, control_ctx.clone(), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  Command FIFO {} unavailable: {}", config.control.fifo, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let dbus_jh = if config.dbus.enabled {
+        match dbus::start(config.dbus.bus, Arc::clone(&shared_status), control_ctx.clone(), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  D-Bus service {} unavailable: {}", dbus::BUS_NAME, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let gamemode_jh = config.gamemode.enabled.then(|| gamemode::start(config.gamemode.bus_address.clone(),
+        Duration::from_millis(config.gamemode.interval), Arc::clone(&control_ctx.perf_requests), Arc::clone(&shutdown_flag)));
+    let gpu_clients: SharedClients = Default::default();
+    let gpu_clients_jh = config.gpu_clients.enabled.then(|| {
+        // fdinfo names the GPU by its PCI address, the name of the device directory; mock clients match any amdgpu
+        let pdev = mock.is_none().then(|| std::fs::canonicalize(&sysfs_path).ok()
+            .and_then(|p| Some(p.file_name()?.to_string_lossy().to_string()))).flatten();
+        fdinfo::start(ClientAccounting::new(pdev), Duration::from_millis(config.gpu_clients.interval),
+            Arc::clone(&gpu_clients), Arc::clone(&shutdown_flag))
+    });
+    let frame_rate = frametime::SharedFrameRate::default();
+    let frametime_jh = (config.policy.kind == PolicyKind::Frametime && !config.policy.frametime.log_dir.is_empty())
+        .then(|| frametime::start(config.policy.frametime.log_dir.clone().into(), frame_rate.clone(), Arc::clone(&shutdown_flag)));
+    let config_watch_jh = match config_path.as_deref().filter(|_| config.config_watch.enabled) {
+        Some(path) => match config_watch::start(path, Arc::clone(&control_ctx.reload_request), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  Cannot watch {} for changes: {}", path.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+    let perf_mode = &config.performance_mode;
+    let (control_watch, control_watch_jh) = if perf_mode.enabled && perf_mode.watch && !perf_mode.control_file.is_empty() {
+        match config_watch::watch_file(std::path::Path::new(&perf_mode.control_file), Arc::clone(&shutdown_flag)) {
+            Ok((watch, jh)) => (Some(watch), Some(jh)),
+            Err(e) => {
+                eprintln!("⚠️  Cannot watch {}, checking it every {}ms instead: {}", perf_mode.control_file, perf_mode.check_interval, e);
+                (None, None)
+            }
+        }
+    } else {
+        (None, None)
+    };
+    let perf_requests = Arc::clone(&control_ctx.perf_requests);
+    let freq_lock = Arc::clone(&control_ctx.freq_lock);
+    let auto_mode_paused = Arc::clone(&control_ctx.auto_mode_paused);
+    let profile_selection = Arc::clone(&control_ctx.profiles);
+
+    let status_jh = if config.status.enabled {
+        match status::start_writer(config.status.file.clone().into(), Arc::clone(&shared_status),
+            Duration::from_millis(config.status.interval), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  Status file {} unavailable: {}", config.status.file, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // An empty fifo path disables the status pipe
+    let fifo_jh = if config.status.enabled && !config.status.fifo.is_empty() {
+        match status::start_fifo(config.status.fifo.clone().into(), Arc::clone(&shared_status), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  Status FIFO {} unavailable: {}", config.status.fifo, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let gov_status = Arc::clone(&shared_status);
+
+    let app_tracking = config.app_tracking;
+    let (app_mode, app_profile, apps_jh) = if app_tracking.enabled {
+        let rules: Vec<AppRule> = app_tracking.rules.iter().filter_map(|r| {
+            let mode = match r.mode.as_deref().map(|name| (name, PerformanceMode::parse(name))) {
+                Some((name, None)) => {
+                    eprintln!("⚠️  Unknown mode '{}' for app rule '{}', ignoring", name, r.app);
+                    return None;
+                }
+                Some((_, mode)) => mode,
+                None => None,
+            };
+            Some(AppRule { app: r.app.clone(), mode, profile: r.profile.clone() })
+        }).collect();
+        let tracker = AppTracker::new(app_tracking.source, rules, Arc::clone(&control_ctx.focused_app), Arc::clone(&gpu_clients));
+        let app_mode = Arc::clone(&tracker.app_mode);
+        let app_profile = Arc::clone(&tracker.app_profile);
+        println!("🎯 App tracking enabled ({:?} source, {} rules)", app_tracking.source, app_tracking.rules.len());
+        (app_mode, app_profile, Some(tracker.start(Duration::from_millis(app_tracking.interval), Arc::clone(&shutdown_flag))))
+    } else {
+        (Arc::new(std::sync::Mutex::new(None)), Arc::new(std::sync::Mutex::new(None)), None)
+    };
+
+    let mirror: SharedMirror = Default::default();
+    let follower = config.cluster.role == Role::Follower;
+    let cluster_jh = match config.cluster.role {
+        Role::Standalone => None,
+        Role::Leader => match cluster::start_leader(&config.cluster.listen, Arc::clone(&shared_status), Arc::clone(&shutdown_flag)) {
+            Ok(jh) => Some(jh),
+            Err(e) => {
+                eprintln!("⚠️  Cluster leader cannot listen on {}: {}", config.cluster.listen, e);
+                None
+            }
+        },
+        Role::Follower if config.cluster.leader.is_empty() => {
+            eprintln!("⚠️  Cluster follower without a leader address, scaling locally");
+            None
+        }
+        Role::Follower => Some(cluster::start_follower(config.cluster.leader.clone(), Arc::clone(&mirror), Arc::clone(&shutdown_flag))),
+    };
+
+    let gpu_fix = if mock.is_some() || dry_run {
+        None
+    } else {
+        match GpuUsageFix::start(sysfs_path) {
+            Ok(fix) => Some(fix),
+            Err(e) => {
+                eprintln!("⚠️  GPU metrics fix unavailable: {}. MangoHUD may show incorrect GPU usage.", e);
+                None
+            }
+        }
+    };
+
+    let mut trace_recorder = record_trace.and_then(|path| {
+        match TraceRecorder::create(&path, Duration::from_micros(config.timing.intervals.sample)) {
+            Ok(recorder) => {
+                println!("⏺️  {}Recording busy samples to {}", label, path.display());
+                Some(recorder)
+            }
+            Err(e) => {
+                eprintln!("⚠️  {}Cannot record a trace to {}: {}", label, path.display(), e);
+                None
+            }
+        }
+    });
+
+    // Clone for governor thread
+    let gov_send_clone = gov_send.clone();
+    let shutdown_flag_gov = Arc::clone(&shutdown_flag);
+    let health_gov = Arc::clone(&health);
+
+    let stats_label = label.clone();
+    let jh_gov: JoinHandle<()> = std::thread::spawn(move || {
+        let gov_send = gov_send_clone;
+        let mut tuning = tuning;
+        let mut gpu_fix = gpu_fix;
+        let started = Instant::now();
+        let mut state = GovernorState::new(current_freq);
+        if startup_freq.is_some() {
+            // The card still runs at the reported clock; apply the policy's frequency right away
+            state.applied_freq = reported_freq;
+            if gov_send.send(GovCommand::SetFrequency(current_freq)).is_ok() {
+                state.pending_freq = Some(current_freq);
+            }
+        }
+        if tuning.timing.soft_start_secs > 0.0 {
+            // Soft start ramps up from the bottom of the table, whatever the card reported
+            state.target_freq = f32::from(min_freq);
+            println!("🐢 Soft start: ramping {}→{}MHz over {:.0}s", min_freq, max_freq, tuning.timing.soft_start_secs);
+        }
+        let mut scaler = Scaler::new(tuning.scaling(), Instant::now());
+        let mut last_perf_check = Instant::now();
+        let mut scheduled: Option<String> = None;
+        let mut last_schedule_check: Option<Instant> = None;
+        let mut control_file_state: Option<Result<PerformanceMode, String>> = None;
+        let mut control_file_read = false;
+        let mut last_metrics_update = Instant::now();
+        // Set while a command sent to the setter is still unacknowledged
+        let mut setter_stalled_since: Option<Instant> = None;
+        let mut watchdog_withheld = false;
+        let mut last_status_update = Instant::now();
+        let mut last_summary = Instant::now();
+        let mut freq_window = RollingWindow::new(Duration::from_secs(60));
+        let mut busy_window = RollingWindow::new(Duration::from_secs(60));
+        let mut temp_window = RollingWindow::new(Duration::from_secs(60));
+        let mut stats = GovernorStats::default();
+        let (mut profile_name, mut profile) = tuning.profile(&tuning.profile);
+        let (mut active_load, mut load_overridden) = tuning.load_target_for(&profile, state.performance_mode);
+
+        let mut last_sample_at: Option<Instant> = None;
+        // Interval slept after the previous sample: the nominal one, or the idle one
+        let mut loop_interval = Duration::from_micros(tuning.timing.intervals.sample);
+        let mut idle_since: Option<Instant> = None;
+        let mut near_zero_since: Option<Instant> = None;
+        let mut deep_idle = false;
+        let mut throttled = false;
+        let mut fingerprint_window = FingerprintWindow::new(tuning.auto_window);
+        let mut auto_mode: Option<PerformanceMode> = None;
+        // A selection that differs from auto_mode, and since when it has matched
+        let mut auto_candidate: Option<(Option<PerformanceMode>, Instant)> = None;
+        let mut last_od_check = Instant::now();
+        let mut od_conflict: Option<u16> = None;
+        let mut mirror_local = true;
+        let (mut min_freq, mut max_freq) = (tuning.min_freq, tuning.max_freq);
+        let mut mirror_override_temp = tuning.mirror_override_temp;
+
+        let mut policy = tuning.build_policy(&profile);
+        let mut mem_hysteresis = tuning.memory_clocks.hysteresis();
+        let mut cpu_hysteresis = tuning.cpu_coupling.hysteresis();
+        let mut cpu_coupling = cpu_coupling;
+        let mut cpu_writes = DryRun::new(dry_run);
+        let mut cpu_min_freq: Option<u32> = None;
+        let mut power_budget = tuning.power_budget.budget();
+        let mut power_ceiling: Option<f32> = None;
+        let mut boost_budget = tuning.boost_budget.budget();
+        let mut boost_ceiling: Option<f32> = None;
+        let mut power_watts: Option<f32> = None;
+        let mut last_power_check = Instant::now();
+        let mut thermal_ceiling: Option<u16> = None;
+
+        let scaling = scaler.settings();
+        println!("🎯 Governor config: burst={}/{} samples, up={} samples, down={} samples, policy={}, profile={}",
+                 scaling.burst_busy, scaling.burst_samples, scaling.up_samples, scaling.down_samples, policy.name(), profile_name);
+        if tuning.timing.ema.enabled {
+            println!("📉 EMA load filter: alpha-up {}, alpha-down {}", tuning.timing.ema.alpha_up, tuning.timing.ema.alpha_down);
+        }
+        if tuning.performance_mode.enabled {
+            println!("⚡ Max Performance mode enabled - control file: {}", tuning.performance_mode.control_file);
+        }
+
+        loop {
+            // Check for shutdown signal
+            if shutdown_flag_gov.load(Ordering::SeqCst) {
+                eprintln!("🛑 Governor thread received shutdown signal");
+                break;
+            }
+
+            // Swap in reloaded parameters without touching history, frequency or stats
+            while let Ok(new_tuning) = reload_recv.try_recv() {
+                tuning = *new_tuning;
+                (min_freq, max_freq) = (tuning.min_freq, tuning.max_freq);
+                mirror_override_temp = tuning.mirror_override_temp;
+                (profile_name, profile) = tuning.profile(&profile_name);
+                let selected = tuning.profile(&profile_selection.lock().unwrap().active).0;
+                profile_selection.lock().unwrap().reset(&selected, tuning.profiles.keys().cloned().collect());
+                policy = tuning.build_policy(&profile);
+                mem_hysteresis = tuning.memory_clocks.hysteresis();
+                cpu_hysteresis = tuning.cpu_coupling.hysteresis();
+                power_budget = tuning.power_budget.budget();
+                power_ceiling = None;
+                boost_budget = tuning.boost_budget.budget();
+                boost_ceiling = None;
+                last_schedule_check = None;
+
+                // Keep the newest samples when the window shrinks
+                scaler.reconfigure(tuning.scaling());
+
+                fingerprint_window.set_span(tuning.auto_window);
+                (active_load, load_overridden) = tuning.load_target_for(&profile, state.performance_mode);
+                let scaling = scaler.settings();
+                println!("🔄 Governor config reloaded: burst={}/{} up={} down={} samples, {} samples kept, {}MHz applied",
+                    scaling.burst_busy, scaling.burst_samples, scaling.up_samples, scaling.down_samples,
+                    scaler.history().len(), state.applied_freq);
+            }
+
+            // The time of day only matters to the minute
+            if last_schedule_check.is_none_or(|t| t.elapsed() >= SCHEDULE_CHECK_INTERVAL) {
+                let window = schedule::active(&tuning.schedule);
+                let profile = window.map(|w| w.profile.clone());
+                if profile != scheduled {
+                    match window {
+                        Some(w) => println!("🕐 Schedule: profile '{}' from {} to {}",
+                            w.profile, schedule::format_time(w.from), schedule::format_time(w.to)),
+                        None => println!("🕐 Schedule window over"),
+                    }
+                    scheduled = profile;
+                }
+                last_schedule_check = Some(Instant::now());
+            }
+
+            // Switch profiles requested through the control interface; a tracked app's profile wins while it
+            // runs, and a scheduled one over both
+            let requested = scheduled.clone()
+                .or_else(|| app_profile.lock().unwrap().clone())
+                .unwrap_or_else(|| profile_selection.lock().unwrap().active.clone());
+            if requested != profile_name {
+                (profile_name, profile) = tuning.profile(&requested);
+                policy = tuning.build_policy(&profile);
+                (active_load, load_overridden) = tuning.load_target_for(&profile, state.performance_mode);
+                match profile.max_frequency {
+                    Some(cap) => println!("🎚️  Profile '{}' active, capped at {}MHz", profile_name, cap),
+                    None => println!("🎚️  Profile '{}' active", profile_name),
+                }
+            }
+
+            // Check for performance mode file and client requests; a watched control file is checked as soon as it changes
+            let control_file = &tuning.performance_mode.control_file;
+            let watched = control_watch.as_ref().filter(|w| w.path.as_os_str() == control_file.as_str());
+            let file_changed = watched.is_some_and(|w| w.take_changed());
+            if tuning.performance_mode.enabled && (file_changed
+                || last_perf_check.elapsed() >= Duration::from_millis(tuning.performance_mode.check_interval)) {
+                // A watched control file is only read again after it changed
+                if watched.is_none() || file_changed || !control_file_read {
+                    let read = read_control_file(control_file);
+                    if read != control_file_state {
+                        if let Some(Err(text)) = &read {
+                            eprintln!("⚠️  Control file {} holds unknown '{}', treating it as max-performance", control_file, text);
+                        }
+                    }
+                    (control_file_state, control_file_read) = (read, true);
+                }
+                // The control file's mode comes first, then any live client request for max performance;
+                // otherwise a tracked app's rule wins over the automatic fingerprint selection
+                let file_mode = control_file_state.clone().map(|r| r.unwrap_or(PerformanceMode::MaxPerformance));
+                let manual_perf = perf_requests.lock().unwrap().active() > 0;
+
+                let auto_active = tuning.auto_mode_enabled && !auto_mode_paused.load(Ordering::SeqCst);
+                let selected = if auto_active {
+                    fingerprint_window.fingerprint().and_then(|fp| workload::select(&tuning.auto_rules, &fp))
+                } else {
+                    None
+                };
+                // A new selection has to hold for dwell-secs, so a workload on the edge of a rule
+                // does not flip the mode every check; pausing or disabling takes effect at once
+                let settled = if selected == auto_mode || !auto_active {
+                    auto_candidate = None;
+                    true
+                } else {
+                    match auto_candidate {
+                        Some((mode, since)) if mode == selected => since.elapsed() >= tuning.auto_dwell,
+                        _ => {
+                            auto_candidate = Some((selected, Instant::now()));
+                            tuning.auto_dwell.is_zero()
+                        }
+                    }
+                };
+                if selected != auto_mode && settled {
+                    auto_candidate = None;
+                    match selected {
+                        Some(mode) => println!("🧠 Workload fingerprint selects {}", mode.label()),
+                        None => println!("🧠 No auto-mode rule matches the workload"),
+                    }
+                    auto_mode = selected;
+                }
+
+                // A follower takes the leader's mode so the whole cluster behaves the same
+                let new_mode = if let Some(m) = cluster::fresh(&mirror) {
+                    m.mode
+                } else if let Some(mode) = file_mode {
+                    mode
+                } else if manual_perf {
+                    PerformanceMode::MaxPerformance
+                } else {
+                    app_mode.lock().unwrap().or(auto_mode).unwrap_or(PerformanceMode::Normal)
+                };
+                
+                if new_mode != state.performance_mode {
+                    state.performance_mode = new_mode;
+                    (active_load, load_overridden) = tuning.load_target_for(&profile, new_mode);
+                    match new_mode {
+                        PerformanceMode::MaxPerformance if load_overridden => {
+                            println!("🚀 MAX PERFORMANCE MODE ACTIVATED - Scaling with load targets {:?}", active_load);
+                        }
+                        PerformanceMode::MaxPerformance => {
+                            println!("🚀 MAX PERFORMANCE MODE ACTIVATED - Locking to {}MHz", tuning.perf_lock_freq);
+                        }
+                        PerformanceMode::Powersave if load_overridden => {
+                            println!("🌙 POWERSAVE MODE ACTIVATED - Scaling with load targets {:?}", active_load);
+                        }
+                        PerformanceMode::Powersave => {
+                            println!("🌙 POWERSAVE MODE ACTIVATED - Holding {}MHz", min_freq);
+                        }
+                        PerformanceMode::Locked(freq) => {
+                            println!("🔒 Mode lock: holding {}MHz", freq);
+                        }
+                        PerformanceMode::Capped(freq) => {
+                            println!("🧢 Mode cap: scaling up to {}MHz", freq);
+                        }
+                        PerformanceMode::Normal => {
+                            println!("🔄 Returning to normal dynamic frequency scaling");
+                        }
+                    }
+                }
+                last_perf_check = Instant::now();
+            }
+
+            while let Ok(ack) = ack_recv.try_recv() {
+                setter_stalled_since = None;
+                // A rejected apply is a failed one, except that the driver says what it kept
+                let ack = match ack {
+                    SetterAck::Rejected { freq, actual } => {
+                        stats.record_rejected();
+                        if let Some(actual) = actual {
+                            state.applied_freq = actual;
+                        }
+                        let error = actual.map_or_else(|| "OD_SCLK unreadable after the write".to_string(),
+                            |a| format!("driver kept {a}MHz"));
+                        SetterAck::Failed { freq, error }
+                    }
+                    ack => ack,
+                };
+                match ack {
+                    SetterAck::Applied { freq, voltage, latency_us } => {
+                        if let Some(recorder) = trace_recorder.as_mut() {
+                            recorder.applied(Instant::now(), freq, latency_us);
+                        }
+                        state.applied_freq = freq;
+                        state.applied_voltage = Some(voltage);
+                        state.pending_freq = None;
+                        state.last_ack = Instant::now();
+                        state.consecutive_failures = 0;
+                        
+                        stats.record_apply(latency_us);
+                        
+                        #[cfg(feature = "debug-transitions")]
+                        if latency_us > 10_000 {
+                            eprintln!("⚠️  Slow apply detected: {}μs", latency_us);
+                        }
+                    }
+                    SetterAck::Trimmed { freq, voltage, offset_mv } => {
+                        state.applied_voltage = Some(voltage);
+                        println!("🔧 Voltage trimmed: {}MHz @ {}mV ({:+}mV)", freq, voltage, offset_mv);
+                    }
+                    SetterAck::TrimFailed { offset_mv, error } => {
+                        eprintln!("❌ Voltage trim {:+}mV failed: {}", offset_mv, error);
+                        stats.record_failure();
+                    }
+                    SetterAck::DpmApplied { clock, freq } => {
+                        let mut snapshot = gov_status.lock().unwrap();
+                        match clock {
+                            DpmClock::Mclk => snapshot.mclk = Some(freq),
+                            DpmClock::Fclk => snapshot.fclk = Some(freq),
+                        }
+                    }
+                    SetterAck::Rejected { .. } => unreachable!("mapped to Failed above"),
+                    SetterAck::DpmFailed { clock, error } => {
+                        eprintln!("⚠️  Setting {} failed: {}. Leaving it to the driver", clock.name(), error);
+                        dpm_clocks.retain(|&c| c != clock);
+                    }
+                    SetterAck::Failed { freq, error } => {
+                        eprintln!("❌ Apply failed for {}MHz: {}", freq, error);
+                        if let Some(recorder) = trace_recorder.as_mut() {
+                            recorder.failed(Instant::now(), freq, &error);
+                        }
+                        state.pending_freq = None;
+                        state.consecutive_failures += 1;
+                        stats.record_failure();
+
+                        let limit = tuning.apply_backoff.max_consecutive_failures;
+                        if limit > 0 && state.consecutive_failures >= limit && state.backoff_until.is_none() {
+                            alerter.raise(AlertKind::ApplyFailures, &format!(
+                                "{} consecutive apply failures (last: {}MHz: {}). Holding {}MHz for {}ms",
+                                state.consecutive_failures, freq, error, min_freq, tuning.apply_backoff.hold_ms));
+                            state.backoff_until = Some(Instant::now() + Duration::from_millis(tuning.apply_backoff.hold_ms));
+                            state.target_freq = f32::from(min_freq);
+                            stats.record_backoff();
+
+                            if gov_send.send(GovCommand::SetFrequency(min_freq)).is_ok() {
+                                state.pending_freq = Some(min_freq);
+                            }
+                        }
+                    }
+                }
+            }
+            
+            if let Some(until) = state.backoff_until {
+                if Instant::now() >= until {
+                    println!("🔄 Apply backoff expired, resuming frequency scaling");
+                    state.backoff_until = None;
+                    state.consecutive_failures = 0;
+                }
+            }
+
+            // sysfs attributes never raise inotify events for writes by other processes,
+            // so external changes to the OD table are detected by reading it back
+            let od_watch = &tuning.od_watch;
+            if od_watch.enabled && !dry_run && state.pending_freq.is_none()
+                && last_od_check.elapsed() >= Duration::from_millis(od_watch.interval)
+                && state.last_ack.elapsed() >= Duration::from_millis(200)
+            {
+                let external = read_od_sclk(&od_path).filter(|&f| f.abs_diff(state.applied_freq) > od_watch.tolerance);
+                if let Some(freq) = external {
+                    if od_conflict != Some(freq) {
+                        eprintln!("⚔️  pp_od_clk_voltage conflict: OD_SCLK is {}MHz but the governor applied {}MHz. \
+                            Another tool (LACT, CoreCtrl, amdgpu-clocks, a script) is writing the OD table; policy: {:?}",
+                            freq, state.applied_freq, od_watch.policy);
+                        stats.record_external_write();
+                    }
+                    match od_watch.policy {
+                        OdConflictPolicy::Warn => {}
+                        OdConflictPolicy::Reassert => {
+                            if gov_send.send(GovCommand::SetFrequency(state.applied_freq)).is_ok() {
+                                state.pending_freq = Some(state.applied_freq);
+                            }
+                        }
+                        OdConflictPolicy::Adopt => {
+                            let adopted = freq.clamp(min_freq, max_freq);
+                            state.applied_freq = adopted;
+                            state.target_freq = f32::from(adopted);
+                        }
+                    }
+                }
+                od_conflict = external;
+                last_od_check = Instant::now();
+            }
+
+            if state.pending_freq.is_some() && state.last_ack.elapsed() > Duration::from_millis(100) {
+                eprintln!("⚠️  Setter thread appears stuck! Last ack: {}ms ago",
+                         state.last_ack.elapsed().as_millis());
+                state.pending_freq = None;
+                setter_stalled_since.get_or_insert(state.last_ack);
+            }
+            
+            // Read GPU activity with graceful error handling
+            let (gui_busy, compute_busy) = load_sampler.sample();
+            let metrics = load_sampler.metrics().unwrap_or_default();
+            let latest_thermal = shared_thermal.load();
+            // gpu_metrics carries the GPU temperature already; the thermal thread's reading is the fallback
+            let gpu_temp = metrics.temperature.or_else(|| latest_thermal.as_ref().map(|t| t.gpu_max_temperature));
+            if let Some(temp) = gpu_temp {
+                stats.record_gpu_temp(temp);
+            }
+            fingerprint_window.push_sample(gui_busy, compute_busy);
+            let sampled_at = Instant::now();
+
+            // Detect iterations that ran late (preemption, slow register reads)
+            let sample_interval = Duration::from_micros(tuning.timing.intervals.sample);
+            let since_last = last_sample_at.map_or(loop_interval, |t| sampled_at.duration_since(t));
+            if since_last > loop_interval * OVERRUN_FACTOR {
+                stats.record_overrun((since_last - loop_interval).as_micros() as u64);
+            }
+            last_sample_at = Some(sampled_at);
+
+            // Ramp by the time that actually passed, capped so a long stall can't cause one huge jump
+            let BusySample { burst, busy_up, busy_down, dt_ms: delta_time_ms } =
+                scaler.sample(sampled_at, gui_busy, since_last.min(loop_interval * OVERRUN_FACTOR));
+
+            // Above the soft thermal limit, burst is scaled down (or disabled at scale 0)
+            let burst_scale = match (tuning.burst_soft_limit, gpu_temp) {
+                (Some(limit), Some(temp)) if temp > limit => tuning.burst_soft_scale,
+                _ => 1.0,
+            };
+            if burst_scale < 1.0 && !throttled {
+                stats.record_throttle();
+            }
+            throttled = burst_scale < 1.0;
+            let burst = burst && burst_scale > 0.0;
+            if burst {
+                stats.record_burst();
+            }
+
+            // Update patched gpu_metrics every 200ms so MangoHUD shows correct usage
+            if let Some(ref mut fix) = gpu_fix {
+                if last_metrics_update.elapsed() >= Duration::from_millis(200) {
+                    if let Err(e) = fix.set_usage_percent(busy_up * 100.0) {
+                        eprintln!("⚠️  GPU metrics fix write failed: {}", e);
+                    }
+                    last_metrics_update = Instant::now();
+                }
+            }
+
+            let fps = frame_rate.fresh(Duration::from_millis(tuning.policy.frametime.stale_ms));
+            
+            // Followers mirror the leader's target unless the local board runs hot
+            let mirrored = cluster::fresh(&mirror).filter(|_| {
+                gpu_temp.is_none_or(|t| t <= mirror_override_temp)
+            });
+            if follower && mirrored.is_none() != mirror_local {
+                mirror_local = mirrored.is_none();
+                if mirror_local {
+                    println!("🛰️  Not mirroring the leader (stale or local temperature above {:.0}°C), scaling locally", mirror_override_temp);
+                } else {
+                    println!("🛰️  Mirroring the leader's decisions");
+                }
+            }
+
+            // While backing off after repeated apply failures, hold the minimum safe point
+            if state.backoff_until.is_some() {
+                state.target_freq = f32::from(min_freq);
+                policy.reset();
+            } else if let Some(m) = mirrored {
+                state.target_freq = f32::from(m.target_freq);
+                policy.reset();
+            } else if let Some(lock) = *freq_lock.lock().unwrap() {
+                // A manual lock from the control interface, still clamped to the safe range and ceilings below
+                state.target_freq = f32::from(lock);
+                policy.reset();
+            } else if let PerformanceMode::Locked(lock) = state.performance_mode {
+                state.target_freq = f32::from(lock);
+                policy.reset();
+            } else if state.performance_mode == PerformanceMode::MaxPerformance && !load_overridden {
+                // If in max performance mode without its own load targets, lock to the configured frequency
+                state.target_freq = f32::from(tuning.perf_lock_freq);
+                policy.reset();
+            } else if state.performance_mode == PerformanceMode::Powersave && !load_overridden {
+                state.target_freq = f32::from(min_freq);
+                policy.reset();
+            } else {
+                // Normal dynamic frequency scaling
+                state.target_freq = policy.next_target(&PolicyInput {
+                    target_freq: state.target_freq,
+                    busy_up,
+                    busy_down,
+                    burst,
+                    burst_scale,
+                    load_target: active_load,
+                    dt_ms: delta_time_ms,
+                    fps,
+                    gpu_temp,
+                    cpu_temp: latest_thermal.as_ref().map(|t| t.cpu_temperature).filter(|&t| t > 0.0),
+                    mode: state.performance_mode,
+                });
+            }
+
+            // Memory and fabric clocks follow the load with their own thresholds
+            if !dpm_clocks.is_empty() {
+                let locked = state.performance_mode == PerformanceMode::MaxPerformance && !load_overridden;
+                if let Some(level) = mem_hysteresis.update(busy_up, burst || locked, Instant::now()) {
+                    for &clock in &dpm_clocks {
+                        let _ = gov_send.send(GovCommand::SetDpmLevel(clock, level));
+                    }
+                }
+            }
+
+            // The CPU shares the APU's power budget; keep its cores clocked up while the GPU works hard
+            if let Some(coupling) = &cpu_coupling {
+                let max_perf = state.performance_mode == PerformanceMode::MaxPerformance;
+                if let Some(level) = cpu_hysteresis.update(busy_up, burst || max_perf, Instant::now()) {
+                    let boost = tuning.cpu_coupling.min_freq;
+                    let applied = coupling.minimum_mhz(level, boost).and_then(|mhz| cpu_writes.write("cpufreq scaling_min_freq",
+                        format_args!("{mhz}MHz"), || coupling.apply(level, boost).map(drop)).map(|()| mhz));
+                    match applied {
+                        Ok(mhz) => cpu_min_freq = Some(mhz),
+                        Err(e) => {
+                            eprintln!("⚠️  Setting the CPU minimum frequency failed: {}. Disabling CPU coupling", e);
+                            let _ = cpu_writes.write("cpufreq scaling_min_freq", "the original minimum", || coupling.restore());
+                            cpu_coupling = None;
+                            cpu_min_freq = None;
+                        }
+                    }
+                }
+            }
+
+            // During soft start the ceiling rises linearly from min to max frequency
+            let soft_start = tuning.timing.soft_start_secs;
+            let ceiling = if soft_start > 0.0 && started.elapsed().as_secs_f32() < soft_start {
+                let progress = started.elapsed().as_secs_f32() / soft_start;
+                f32::from(min_freq) + progress * f32::from(max_freq - min_freq)
+            } else {
+                f32::from(max_freq)
+            };
+            let ceiling = profile.max_frequency.map_or(ceiling, |cap| ceiling.min(f32::from(cap.max(min_freq))));
+            let ceiling = match state.performance_mode {
+                PerformanceMode::Capped(cap) => ceiling.min(f32::from(cap.max(min_freq))),
+                _ => ceiling,
+            };
+
+            // Sustained board power over the budget lowers the ceiling, whatever the busy ratio
+            if tuning.power_budget.enabled && last_power_check.elapsed() >= Duration::from_millis(tuning.power_budget.interval) {
+                // gpu_metrics already carries the board power; hwmon is read otherwise
+                let reading = metrics.watts.map(Ok).or_else(|| power_input.as_deref().map(|path| {
+                    power::read_watts(path).map_err(|e| format!("Reading {} failed: {}", path.display(), e))
+                }));
+                if let Some(reading) = reading {
+                    last_power_check = Instant::now();
+                    match reading {
+                        Ok(watts) => {
+                            power_watts = Some(watts);
+                            let capped = power_budget.update(Instant::now(), watts, f32::from(state.applied_freq),
+                                f32::from(min_freq), f32::from(max_freq));
+                            match (power_ceiling, capped) {
+                                (None, Some(c)) => println!("🔌 Board power {:.0}W over the {:.0}W budget, limiting to {:.0}MHz",
+                                    power_budget.average(), tuning.power_budget.cap_watts, c),
+                                (Some(_), None) => println!("🔌 Board power back within budget"),
+                                _ => {}
+                            }
+                            power_ceiling = capped;
+                        }
+                        Err(e) => eprintln!("⚠️  {}", e),
+                    }
+                }
+            }
+            let ceiling = power_ceiling.map_or(ceiling, |cap| ceiling.min(cap));
+
+            // Time above the boost frequency draws on a budget that refills more slowly the hotter the GPU is
+            if tuning.boost_budget.enabled {
+                let boost = &tuning.boost_budget;
+                let heat = gpu_temp.map_or(0.0, |t| (t - boost.cool_temp) / (tuning.max_safe_temp - boost.cool_temp));
+                let capped = boost_budget.update(delta_time_ms / 1000.0, f32::from(state.applied_freq), heat, f32::from(max_freq));
+                match (boost_ceiling, capped) {
+                    (None, Some(_)) => println!("⏳ Boost budget running low, easing down towards {}MHz", boost.boost_mhz),
+                    (Some(_), None) => println!("⏳ Boost budget recovered"),
+                    _ => {}
+                }
+                boost_ceiling = capped;
+            }
+            let ceiling = boost_ceiling.map_or(ceiling, |cap| ceiling.min(cap.max(f32::from(min_freq))));
+
+            // Approaching max_safe_temp, the throttle curve lowers the ceiling step by step
+            let throttle = gpu_temp
+                .map(|temp| (temp, interpolate_curve(temp, &tuning.throttle_curve)))
+                .filter(|&(_, pct)| !tuning.throttle_curve.is_empty() && pct < 100)
+                .map(|(temp, pct)| (temp, (f32::from(max_freq) * f32::from(pct) / 100.0).max(f32::from(min_freq)) as u16));
+            match (thermal_ceiling, throttle) {
+                (None, Some((temp, cap))) => {
+                    println!("🌡️  {}Thermal throttle at {:.1}°C: limiting to {}MHz",
+                        if tuning.rehearsal { "[REHEARSAL] " } else { "" }, temp, cap);
+                    stats.record_throttle_curve();
+                }
+                (Some(_), None) => println!("🌡️  Thermal throttle released"),
+                _ => {}
+            }
+            thermal_ceiling = throttle.map(|(_, cap)| cap);
+            let ceiling = match thermal_ceiling {
+                Some(cap) if !tuning.rehearsal => ceiling.min(f32::from(cap)),
+                _ => ceiling,
+            };
+            let clamped = emergency_clamp.load(Ordering::SeqCst);
+            let ceiling = if clamped { f32::from(min_freq) } else { ceiling };
+
+            state.target_freq = state.target_freq.clamp(
+                f32::from(min_freq),
+                ceiling
+            );
+
+            // Deep idle: after a stretch of near-zero load, go straight to the floor and stay there
+            let deep = &tuning.deep_idle;
+            let near_zero = deep.enabled && !burst && busy_up < deep.busy_below;
+            let was_deep_idle = deep_idle;
+            deep_idle = near_zero && near_zero_since.get_or_insert(sampled_at).elapsed().as_secs_f32() >= deep.after_secs;
+            if !near_zero {
+                near_zero_since = None;
+            }
+            match (was_deep_idle, deep_idle) {
+                (false, true) => println!("💤 Deep idle: no load for {:.0}s, dropping to {}MHz", deep.after_secs, min_freq),
+                (true, false) => println!("⏰ Load is back, leaving deep idle"),
+                _ => {}
+            }
+            if deep_idle {
+                state.target_freq = f32::from(min_freq);
+            }
+
+            if let Some(recorder) = trace_recorder.as_mut() {
+                recorder.sample(sampled_at, gui_busy, busy_up, busy_down, burst, state.target_freq);
+            }
+
+            let target_freq_u16 = state.target_freq as u16;
+            let now = Instant::now();
+            let blocked = state.pending_freq.is_some() || state.backoff_until.is_some();
+            match scaler.decide(now, state.applied_freq, target_freq_u16, burst, deep_idle || clamped, blocked) {
+                ApplyDecision::Hold => {}
+                ApplyDecision::RateLimited => stats.record_rate_limited(),
+                ApplyDecision::Apply => {
+                    if let Err(e) = gov_send.send(GovCommand::SetFrequency(target_freq_u16)) {
+                        eprintln!("❌ Failed to send command: {}", e);
+                        break;
+                    }
+                    state.pending_freq = Some(target_freq_u16);
+                    if let Some(recorder) = trace_recorder.as_mut() {
+                        recorder.apply(now, target_freq_u16);
+                    }
+                }
+            }
+
+            if last_status_update.elapsed() >= Duration::from_millis(100) {
+                let now = Instant::now();
+                freq_window.push(now, f32::from(state.applied_freq));
+                fingerprint_window.close_slice(now);
+                busy_window.push(now, busy_up);
+                if let Some(temp) = gpu_temp {
+                    temp_window.push(now, temp);
+                }
+
+                let mut snapshot = gov_status.lock().unwrap();
+                snapshot.applied_freq = state.applied_freq;
+                snapshot.applied_voltage = state.applied_voltage;
+                snapshot.target_freq = state.target_freq as u16;
+                snapshot.mode = state.performance_mode.label();
+                snapshot.profile = profile_name.clone();
+                snapshot.cpu_min_freq = cpu_min_freq;
+                snapshot.power_watts = power_watts;
+                snapshot.fps = fps;
+                snapshot.gpu_clients = gpu_clients.lock().unwrap().iter().take(tuning.gpu_clients_top).cloned().collect();
+                snapshot.power_ceiling = power_ceiling.map(|c| c as u16);
+                snapshot.boost_budget = tuning.boost_budget.enabled.then(|| boost_budget.level());
+                snapshot.boost_ceiling = boost_ceiling.map(|c| c as u16);
+                snapshot.thermal_ceiling = thermal_ceiling.filter(|_| !tuning.rehearsal);
+                snapshot.busy = busy_up;
+                stats.merge_thermal(&thermal_counters);
+                snapshot.stats = stats.clone();
+                snapshot.freq_1m = freq_window.stats();
+                snapshot.busy_1m = busy_window.stats();
+                snapshot.gpu_temp_1m = temp_window.stats();
+                snapshot.fingerprint = fingerprint_window.fingerprint();
+                last_status_update = Instant::now();
+            }
+
+            if last_summary.elapsed() >= Duration::from_secs(60) {
+                let (freq, busy, temp) = (freq_window.stats(), busy_window.stats(), temp_window.stats());
+                let temp = if temp_window.is_empty() {
+                    String::new()
+                } else {
+                    format!(", GPU temp min/avg/max {:.1}/{:.1}/{:.1}°C", temp.min, temp.avg, temp.max)
+                };
+                println!("📈 Last 60s: freq min/avg/max {:.0}/{:.0}/{:.0}MHz, busy min/avg/max {:.0}/{:.0}/{:.0}%{}, mode={}",
+                    freq.min, freq.avg, freq.max, busy.min * 100.0, busy.avg * 100.0, busy.max * 100.0, temp,
+                    state.performance_mode.name());
+                if let Some(path) = &state_file {
+                    persist_frequency(path, state.applied_freq);
+                }
+                last_summary = Instant::now();
+            }
+
+            if dump_request.swap(false, Ordering::SeqCst) {
+                let history = scaler.history();
+                let (since_adjust, since_finetune) = scaler.timers(Instant::now());
+                let busy_count = history.busy_count();
+                let thermal = gov_status.lock().unwrap().thermal.clone();
+                eprintln!("🧾 ===== STATE DUMP =====");
+                eprintln!("🧾 Samples: {}/{} buffered, {} busy ({:.1}%), busy_up={:.3} busy_down={:.3} burst={}",
+                    history.len(), scaler.settings().max_samples(), busy_count,
+                    busy_count as f32 * 100.0 / history.len().max(1) as f32, busy_up, busy_down, burst);
+                eprintln!("🧾 Governor: target={:.1}MHz applied={}MHz pending={:?} last_ack={}ms ago mode={}",
+                    state.target_freq, state.applied_freq, state.pending_freq,
+                    state.last_ack.elapsed().as_millis(), state.performance_mode.name());
+                eprintln!("🧾 Backoff: consecutive_failures={} active={} remaining={}ms",
+                    state.consecutive_failures, state.backoff_until.is_some(),
+                    state.backoff_until.map(|u| u.saturating_duration_since(Instant::now()).as_millis()).unwrap_or(0));
+                eprintln!("🧾 Load targets: {:?} (mode override: {})", active_load, load_overridden);
+                eprintln!("🧾 Auto mode: selected={:?} fingerprint={:?}", auto_mode.map(|m| m.name()), fingerprint_window.fingerprint());
+                eprintln!("🧾 Timers: since adjust={}ms since finetune={}ms sample interval={}μs",
+                    since_adjust.as_millis(), since_finetune.as_millis(), loop_interval.as_micros());
+                eprintln!("🧾 Thermal: {:?}", thermal);
+                eprintln!("🧾 Sensors: {:?} (gpu_metrics: {:?})", latest_thermal.as_ref().map(|t| &t.readings), metrics.temperature);
+                eprintln!("🧾 Stats: {:?} avg_latency={}μs success={:.1}%",
+                    stats, stats.avg_latency_us(), stats.success_rate());
+                eprintln!("🧾 ======================");
+            }
+
+            // The systemd watchdog is fed only while this loop keeps beating and the
+            // setter keeps answering; a hung setter lets the watchdog expire so
+            // systemd restarts the service
+            let stalled = setter_stalled_since.is_some_and(|t| t.elapsed() > SETTER_STALL_LIMIT);
+            if health_gov.watchdog && stalled && !watchdog_withheld {
+                eprintln!("🐕 {}Setter unresponsive for over {}s, withholding watchdog pings so systemd restarts the service",
+                    stats_label, SETTER_STALL_LIMIT.as_secs());
+                alerter.raise(AlertKind::SetterStalled, "setter thread unresponsive, watchdog pings withheld");
+            } else if health_gov.watchdog && !stalled && watchdog_withheld {
+                eprintln!("🐕 {}Setter responding again, resuming watchdog pings", stats_label);
+            }
+            watchdog_withheld = stalled;
+            health_gov.setter_stalled.store(stalled, Ordering::SeqCst);
+            health_gov.beats.fetch_add(1, Ordering::SeqCst);
+
+            // Once the GPU has idled for a while, sample less often; any busy sample restores the interval
+            let idle_sampling = &tuning.timing.idle_sampling;
+            let idling = if idle_sampling.enabled && !gui_busy && busy_up < active_load.lower {
+                idle_since.get_or_insert(sampled_at).elapsed() >= Duration::from_millis(idle_sampling.after_ms)
+            } else {
+                idle_since = None;
+                false
+            };
+            loop_interval = if idling { Duration::from_micros(idle_sampling.interval) } else { sample_interval };
+            std::thread::sleep(loop_interval);
+        }
+        
+        // Remove the bind mount before the process exits so sysfs is restored
+        if let Some(fix) = gpu_fix {
+            if let Err(e) = fix.shutdown() {
+                eprintln!("⚠️  GPU metrics fix shutdown failed: {}", e);
+            }
+        }
+
+        if let Some(coupling) = cpu_coupling {
+            match cpu_writes.write("cpufreq scaling_min_freq", "the original minimum", || coupling.restore()) {
+                Ok(()) => eprintln!("🛡️  Restored the CPU minimum frequency"),
+                Err(e) => eprintln!("⚠️  Failed to restore the CPU minimum frequency: {}", e),
+            }
+        }
+
+        if let Some(path) = &state_file {
+            persist_frequency(path, state.applied_freq);
+        }
+        let _ = gov_send.send(GovCommand::Shutdown);
+        eprintln!("🛑 Governor thread exiting");
+        stats.merge_thermal(&thermal_counters);
+        eprintln!("📊 {}Stats: Applies={} Failed={} Backoffs={} RateLimited={} Bursts={} Overruns={} MaxOverrun={}μs AvgLatency={}μs MaxLatency={}μs Success={:.1}%",
+                 stats_label, stats.total_applies, stats.failed_applies, stats.backoff_activations, stats.rate_limited, stats.burst_activations,
+                 stats.overruns, stats.max_overrun_us, stats.avg_latency_us(), stats.max_latency_us, stats.success_rate());
+        eprintln!("📊 {}Thermal: Warnings={} Emergencies={} BurstCaps={} Throttles={} FanFailures={} FanStalls={} MaxGpuTemp={:.1}°C",
+                 stats_label, stats.thermal_warnings, stats.thermal_emergencies, stats.throttle_activations,
+                 stats.throttle_curve_activations, stats.fan_failures,
+                 stats.fan_stalls, stats.max_gpu_temp);
+    });
+
+    let mock_setter = mock.clone();
+    let jh_set: JoinHandle<()> = std::thread::spawn(move || {
+        let mut pp_file = pp_file;
+        let mut safe_points = safe_points;
+        let (mut min_freq, mut max_freq) = (min_freq, max_freq);
+        let mut last_freq = current_freq.clamp(min_freq, max_freq);
+        let mut trim_offset: i16 = 0;
+        let (mut voltage_offset, mut voltage_floor) = voltage_offset;
+        let mut dpm_levels = dpm_levels;
+        let mut verify = verify_applies;
+        let mut writes = DryRun::new(dry_run);
+        let od_target = verify_path.display().to_string();
+        let mut shutdown_policy = shutdown_policy;
+        // Set by an emergency shutdown: every later apply goes to the lowest safe point
+        let mut holding_floor = false;
+
+        loop {
+            match gov_recv.recv() {
+                Ok(GovCommand::SetFrequency(freq)) => {
+                    let start = Instant::now();
+                    
+                    let freq = match safe_points.first_key_value() {
+                        Some((&lowest, _)) if holding_floor => lowest,
+                        _ => freq.clamp(min_freq, max_freq),
+                    };
+                    
+                    // Interpolate voltage between safe-points
+                    let vol = interpolate_voltage(freq, &safe_points)
+                        .map(|v| apply_voltage_offset(v, voltage_offset.saturating_add(trim_offset), voltage_floor, &safe_points));
+                    
+                    let vol = match vol {
+                        Some(v) => v,
+                        None => {
+                            eprintln!("⚠️  No safe voltage for {}MHz, skipping", freq);
+                            let _ = ack_send.send(SetterAck::Failed {
+                                freq,
+                                error: "No safe voltage found".into(),
+                            });
+                            continue;
+                        }
+                    };
+                    
+                    let result = writes.write(&od_target, format_args!("vc 0 {freq} {vol}"),
+                        || write_od(&mut pp_file, mock_setter.as_deref(), freq, vol));
+                    
+                    let latency = start.elapsed().as_micros() as u64;
+                    
+                    // The driver silently clamps or drops some values; only the readback tells
+                    let rejected = match (&result, verify) {
+                        (Ok(_), Some(tolerance)) => {
+                            let actual = read_od_sclk(&verify_path);
+                            actual.is_none_or(|a| a.abs_diff(freq) > tolerance).then_some(actual)
+                        }
+                        _ => None,
+                    };
+
+                    match result {
+                        Ok(_) if rejected.is_some() => {
+                            let actual = rejected.flatten();
+                            last_freq = actual.unwrap_or(last_freq);
+                            let _ = ack_send.send(SetterAck::Rejected { freq, actual });
+                        }
+                        Ok(_) => {
+                            last_freq = freq;
+                            let _ = ack_send.send(SetterAck::Applied {
+                                freq,
+                                voltage: vol,
+                                latency_us: latency,
+                            });
+                        }
+                        Err(e) => {
+                            eprintln!("⚠️  Failed to apply {}MHz @ {}mV: {}", freq, vol, e);
+                            
+                            if let Some((safe_freq, safe_vol)) = lowest_safe_point(&safe_points, voltage_offset.saturating_add(trim_offset), voltage_floor) {
+                                let _ = writes.write(&od_target, format_args!("vc 0 {safe_freq} {safe_vol}"),
+                                    || write_od(&mut pp_file, mock_setter.as_deref(), safe_freq, safe_vol));
+                            }
+                            
+                            let _ = ack_send.send(SetterAck::Failed {
+                                freq,
+                                error: e.to_string(),
+                            });
+                        }
+                    }
+                }
+                Ok(GovCommand::TrimVoltage(offset_mv)) => {
+                    trim_offset = offset_mv;
+                    let freq = last_freq;
+
+                    let Some(vol) = interpolate_voltage(freq, &safe_points)
+                        .map(|v| apply_voltage_offset(v, voltage_offset.saturating_add(offset_mv), voltage_floor, &safe_points)) else {
+                        let _ = ack_send.send(SetterAck::TrimFailed {
+                            offset_mv,
+                            error: "No safe voltage found".into(),
+                        });
+                        continue;
+                    };
+
+                    let result = writes.write(&od_target, format_args!("vc 0 {freq} {vol}"),
+                        || write_od(&mut pp_file, mock_setter.as_deref(), freq, vol));
+
+                    let _ = match result {
+                        Ok(_) => ack_send.send(SetterAck::Trimmed { freq, voltage: vol, offset_mv }),
+                        Err(e) => ack_send.send(SetterAck::TrimFailed { offset_mv, error: e.to_string() }),
+                    };
+                }
+                Ok(GovCommand::SetSafePoints(points)) => {
+                    if let (Some((&low, _)), Some((&high, _))) = (points.first_key_value(), points.last_key_value()) {
+                        (min_freq, max_freq) = (low, high);
+                        safe_points = points;
+                        println!("🔄 Safe points reloaded: {} points, {}-{}MHz", safe_points.len(), min_freq, max_freq);
+                    }
+                }
+                Ok(GovCommand::SetVoltageOffset { offset_mv, floor_mv }) => {
+                    if (offset_mv, floor_mv) != (voltage_offset, voltage_floor) {
+                        println!("🔄 Voltage offset {:+}mV (floor {}mV)", offset_mv, floor_mv);
+                    }
+                    (voltage_offset, voltage_floor) = (offset_mv, floor_mv);
+                }
+                Ok(GovCommand::SetVerify(tolerance)) => verify = tolerance.filter(|_| !dry_run),
+                Ok(GovCommand::SetDpmLevel(clock, level)) => {
+                    let Some(dpm) = dpm_levels.iter().find(|d| d.clock == clock) else { continue };
+                    let index = match level {
+                        LoadLevel::Low => 0,
+                        LoadLevel::High => dpm.freqs.len() - 1,
+                    };
+                    let _ = match writes.write(dpm.path.display(), index, || write_dpm(dpm, mock_setter.as_deref(), &[index])) {
+                        Ok(()) => ack_send.send(SetterAck::DpmApplied { clock, freq: dpm.freqs[index] }),
+                        Err(e) => {
+                            dpm_levels.retain(|d| d.clock != clock);
+                            ack_send.send(SetterAck::DpmFailed { clock, error: e.to_string() })
+                        }
+                    };
+                }
+                Ok(GovCommand::HoldLowestSafePoint) => {
+                    (holding_floor, shutdown_policy) = (true, ShutdownPolicy::Minimum);
+                    let Some((safe_freq, safe_vol)) = lowest_safe_point(&safe_points, voltage_offset.saturating_add(trim_offset), voltage_floor) else { continue };
+                    let start = Instant::now();
+                    match writes.write(&od_target, format_args!("vc 0 {safe_freq} {safe_vol}"),
+                        || write_od(&mut pp_file, mock_setter.as_deref(), safe_freq, safe_vol)) {
+                        Ok(()) => {
+                            eprintln!("🛡️  Emergency: holding the lowest safe point {}MHz @ {}mV", safe_freq, safe_vol);
+                            last_freq = safe_freq;
+                            let _ = ack_send.send(SetterAck::Applied {
+                                freq: safe_freq,
+                                voltage: safe_vol,
+                                latency_us: start.elapsed().as_micros() as u64,
+                            });
+                        }
+                        Err(e) => eprintln!("⚠️  Failed to apply the lowest safe point {}MHz @ {}mV: {}", safe_freq, safe_vol, e),
+                    }
+                }
+                Ok(GovCommand::Shutdown) => {
+                    eprintln!("🛑 Setter thread received shutdown signal");
+                    break;
+                }
+                Err(_) => {
+                    eprintln!("🛑 Setter thread: channel closed");
+                    break;
+                }
+            }
+        }
+
+        // Don't leave whatever clock and voltage were last written behind
+        let handed_back = match (shutdown_policy, original_od) {
+            (ShutdownPolicy::Original, Some((freq, vol))) => match writes.write(&od_target, format_args!("vc 0 {freq} {vol}"),
+                || write_od(&mut pp_file, mock_setter.as_deref(), freq, vol)) {
+                Ok(()) => {
+                    eprintln!("🛡️  Restored original OD point: {}MHz @ {}mV", freq, vol);
+                    true
+                }
+                Err(e) => {
+                    eprintln!("⚠️  Failed to restore original OD point {}MHz @ {}mV: {}. Resetting instead", freq, vol, e);
+                    false
+                }
+            },
+            (ShutdownPolicy::Minimum, _) => {
+                if let Some((safe_freq, safe_vol)) = lowest_safe_point(&safe_points, voltage_offset.saturating_add(trim_offset), voltage_floor) {
+                    match writes.write(&od_target, format_args!("vc 0 {safe_freq} {safe_vol}"),
+                        || write_od(&mut pp_file, mock_setter.as_deref(), safe_freq, safe_vol)) {
+                        Ok(()) => eprintln!("🛡️  Restored lowest safe point: {}MHz @ {}mV", safe_freq, safe_vol),
+                        Err(e) => eprintln!("⚠️  Failed to restore lowest safe point {}MHz @ {}mV: {}", safe_freq, safe_vol, e),
+                    }
+                }
+                true
+            }
+            // Reset, and an original point that was unreadable or the driver's own
+            _ => false,
+        };
+        if !handed_back {
+            match writes.write(&od_target, "r", || reset_od(&mut pp_file, mock_setter.as_deref())) {
+                Ok(()) => eprintln!("🛡️  Reset the OD table to driver defaults"),
+                Err(e) => eprintln!("⚠️  Failed to reset the OD table: {}", e),
+            }
+        }
+        // Hand memory and fabric clocks back to the driver
+        for dpm in &dpm_levels {
+            let all: Vec<usize> = (0..dpm.freqs.len()).collect();
+            let mask: Vec<String> = all.iter().map(|l| l.to_string()).collect();
+            match writes.write(dpm.path.display(), mask.join(" "), || write_dpm(dpm, mock_setter.as_deref(), &all)) {
+                Ok(()) => eprintln!("🛡️  Restored all {} levels", dpm.clock.name()),
+                Err(e) => eprintln!("⚠️  Failed to restore {} levels: {}", dpm.clock.name(), e),
+            }
+        }
+        if let Some(previous) = &previous_level {
+            match writes.write(level_path.display(), previous, || memclk::write_performance_level(&level_path, previous)) {
+                Ok(()) => eprintln!("🛡️  Restored the {} performance level", previous),
+                Err(e) => eprintln!("⚠️  Failed to restore the {} performance level: {}", previous, e),
+            }
+        }
+        
+        eprintln!("🛑 Setter thread exiting");
+    });
+
+    health.ready.store(true, Ordering::SeqCst);
+
+    let reload_request = Arc::clone(&control_ctx.reload_request);
+
+    // Wait for shutdown signal (blocking poll with timeout for graceful shutdown)
+    loop {
+        if shutdown_flag.load(Ordering::SeqCst) {
+            eprintln!("🛑 Shutdown initiated...");
+            let _ = systemd::notify("STOPPING=1");
+            break;
+        }
+
+        if reload_request.swap(false, Ordering::SeqCst) {
+            let reloaded = config_path.as_deref().map(|path| load_config(path).and_then(|c| match &device {
+                Some(d) => {
+                    let c = c.for_device(d);
+                    validate_config(&c)?;
+                    Ok(c)
+                }
+                None => Ok(c),
+            }).and_then(|c| {
+                check_fan_curves(thermal_manager_clone.as_ref(), &c.thermal)?;
+                Ok(c)
+            }));
+            match reloaded {
+                Some(Ok(mut new_config)) => {
+                    new_config.thermal.derive_thresholds(&gpu_limits);
+                    let points: BTreeMap<u16, u16> = new_config.safe_points.iter().map(|p| (p.frequency, p.voltage)).collect();
+                    match (points.first_key_value(), points.last_key_value()) {
+                        (Some((&low, _)), Some((&high, _))) => {
+                            (min_freq, max_freq) = (low, high);
+                            // Queued behind pending writes, so the setter switches tables between applies
+                            let _ = gov_send.send(GovCommand::SetSafePoints(points));
+                            let _ = gov_send.send(GovCommand::SetVoltageOffset {
+                                offset_mv: new_config.voltage_offset_mv,
+                                floor_mv: new_config.voltage_floor(),
+                            });
+                        }
+                        _ => eprintln!("⚠️  Reloaded config has no safe-points, keeping the current table"),
+                    }
+                    let _ = gov_send.send(GovCommand::SetVerify(new_config.od_watch.verify.then_some(new_config.od_watch.tolerance)));
+                    let tuning = GovernorTuning::from_config(&new_config, min_freq, max_freq);
+                    if reload_send.send(Box::new(tuning)).is_err() {
+                        eprintln!("⚠️  Governor thread is gone, reload dropped");
+                    }
+                    // Fails only when thermal monitoring is not running
+                    let _ = thermal_reload_send.send(new_config.thermal);
+                }
+                Some(Err(e)) => eprintln!("⚠️  Config reload failed, keeping current config: {}", e),
+                None => eprintln!("⚠️  No config file was given at startup, nothing to reload"),
+            }
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // Send shutdown command to setter thread
+    eprintln!("🛑 Stopping governor and setter threads...");
+    let _ = gov_send.send(GovCommand::Shutdown);
+
+    // Wait for threads to finish with timeout
+    let timeout = Duration::from_secs(5);
+    let start = Instant::now();
+
+    // Join governor thread
+    while start.elapsed() < timeout {
+        if jh_gov.is_finished() {
+            let _ = jh_gov.join();
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    // Join setter thread  
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if jh_set.is_finished() {
+            let _ = jh_set.join();
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    // Join thermal thread
+    if let Some(jh) = thermal_jh {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if jh.is_finished() {
+                // Fans that came or went since startup are in the thread's manager only
+                if let Ok(tm) = jh.join() {
+                    thermal_manager_clone = Some(tm);
+                }
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    // Join control socket and app tracking threads
+    if let Some(jh) = control_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = apps_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = cluster_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = command_fifo_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = dbus_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = gamemode_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = frametime_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = gpu_clients_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = config_watch_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = control_watch_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = mock_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = status_jh {
+        let _ = jh.join();
+    }
+    if let Some(jh) = fifo_jh {
+        let _ = jh.join();
+    }
+
+    // Restore fans to automatic control, unless an emergency pinned them at full speed
+    if fans_pinned.load(Ordering::SeqCst) {
+        eprintln!("🌀 Leaving the fans at 100% after the emergency shutdown");
+    } else if let Some(tm) = thermal_manager_clone {
+        eprintln!("🔄 Restoring fans to automatic control...");
+        if let Err(e) = DryRun::new(dry_run).write("fan PWM", "automatic control", || tm.restore_auto_fan_control()) {
+            eprintln!("⚠️  Failed to restore fan control: {}", e);
+        }
+    }
+
+    if let Some(injector) = mock {
+        injector.cleanup();
+    }
+
+    eprintln!("🛑 {}Shutdown complete.", label);
+    Ok(())
+}
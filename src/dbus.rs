@@ -0,0 +1,177 @@
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::Error as IoError,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+use zbus::{blocking::{connection, Connection}, fdo, interface, zvariant::Value};
+
+use crate::{control::ControlContext, governor::PerformanceMode, status::{SharedStatus, StatusSnapshot}};
+
+pub const BUS_NAME: &str = "org.bc250.Governor";
+const OBJECT_PATH: &str = "/org/bc250/Governor";
+pub const POLICY_PATH: &str = "/etc/dbus-1/system.d/org.bc250.Governor.conf";
+/// Max-performance holds taken through D-Bus share this client name.
+const DBUS_CLIENT: &str = "dbus";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// System bus policy letting root own the service name. Calls are taken from
+/// the same users as the control socket: root and members of `group` (none
+/// when empty). Everyone else is denied, since the methods switch modes and
+/// profiles.
+pub fn render_policy(group: &str) -> String {
+    let mut policy = String::from(r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <policy user="root">
+    <allow own="org.bc250.Governor"/>
+    <allow send_destination="org.bc250.Governor"/>
+  </policy>
+"#);
+    if !group.is_empty() {
+        policy.push_str(&format!("  <policy group=\"{group}\">\n    <allow send_destination=\"org.bc250.Governor\"/>\n  </policy>\n"));
+    }
+    policy.push_str(r#"  <policy context="default">
+    <deny send_destination="org.bc250.Governor"/>
+  </policy>
+</busconfig>
+"#);
+    policy
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Bus {
+    System,
+    Session,
+}
+
+struct Governor {
+    status: SharedStatus,
+    ctx: ControlContext,
+}
+
+#[interface(name = "org.bc250.Governor")]
+impl Governor {
+    /// "max-performance" takes a max-performance hold for D-Bus clients,
+    /// "normal" releases it. Other holds (control file, socket clients) are
+    /// not affected.
+    fn set_mode(&self, mode: &str) -> fdo::Result<()> {
+        let mode = PerformanceMode::from_name(mode)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("unknown mode '{mode}'")))?;
+        self.ctx.perf_requests.lock().unwrap().set_mode(DBUS_CLIENT, mode);
+        Ok(())
+    }
+
+    /// Switches to a named scaling profile from `[profiles]`.
+    fn set_profile(&self, profile: &str) -> fdo::Result<()> {
+        self.ctx.profiles.lock().unwrap().select(profile).map_err(fdo::Error::InvalidArgs)
+    }
+
+    /// The full status snapshot as JSON, in the status.json format.
+    fn get_status(&self) -> fdo::Result<String> {
+        serde_json::to_string(&*self.status.lock().unwrap()).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    #[zbus(property)]
+    fn applied_frequency(&self) -> u16 {
+        self.status.lock().unwrap().applied_freq
+    }
+
+    #[zbus(property)]
+    fn target_frequency(&self) -> u16 {
+        self.status.lock().unwrap().target_freq
+    }
+
+    #[zbus(property)]
+    fn mode(&self) -> String {
+        self.status.lock().unwrap().mode.clone()
+    }
+
+    #[zbus(property)]
+    fn profile(&self) -> String {
+        self.status.lock().unwrap().profile.clone()
+    }
+
+    #[zbus(property)]
+    fn busy(&self) -> f64 {
+        self.status.lock().unwrap().busy as f64
+    }
+
+    #[zbus(property)]
+    fn gpu_temperature(&self) -> f64 {
+        self.status.lock().unwrap().thermal.amdgpu as f64
+    }
+
+    #[zbus(property)]
+    fn max_temperature(&self) -> f64 {
+        self.status.lock().unwrap().thermal.max as f64
+    }
+}
+
+/// Property values as published, rounded so that noise does not flood the bus.
+fn properties(snapshot: &StatusSnapshot) -> Vec<(&'static str, Value<'static>)> {
+    let round = |v: f32, step: f32| ((v / step).round() * step) as f64;
+    vec![
+        ("AppliedFrequency", Value::from(snapshot.applied_freq)),
+        ("TargetFrequency", Value::from(snapshot.target_freq)),
+        ("Mode", Value::from(snapshot.mode.clone())),
+        ("Profile", Value::from(snapshot.profile.clone())),
+        ("Busy", Value::from(round(snapshot.busy, 0.05))),
+        ("GpuTemperature", Value::from(round(snapshot.thermal.amdgpu, 0.5))),
+        ("MaxTemperature", Value::from(round(snapshot.thermal.max, 0.5))),
+    ]
+}
+
+/// Client side: fetches the status JSON of a governor serving on `bus`.
+pub fn get_status(bus: Bus) -> Result<String, IoError> {
+    let conn = match bus {
+        Bus::System => Connection::system(),
+        Bus::Session => Connection::session(),
+    }.map_err(IoError::other)?;
+    let reply = conn.call_method(Some(BUS_NAME), OBJECT_PATH, Some(BUS_NAME), "GetStatus", &())
+        .map_err(IoError::other)?;
+    reply.body().deserialize::<String>().map_err(IoError::other)
+}
+
+/// Claims `org.bc250.Governor` on `bus` and serves it until shutdown, emitting
+/// PropertiesChanged whenever a published value changes.
+pub fn start(bus: Bus, status: SharedStatus, ctx: ControlContext, shutdown: Arc<AtomicBool>) -> Result<JoinHandle<()>, IoError> {
+    let governor = Governor { status: status.clone(), ctx };
+    let builder = match bus {
+        Bus::System => connection::Builder::system(),
+        Bus::Session => connection::Builder::session(),
+    };
+    let conn = builder
+        .and_then(|b| b.name(BUS_NAME))
+        .and_then(|b| b.serve_at(OBJECT_PATH, governor))
+        .and_then(|b| b.build())
+        .map_err(IoError::other)?;
+    println!("🚌 D-Bus service {} on the {:?} bus", BUS_NAME, bus);
+
+    Ok(std::thread::spawn(move || {
+        let mut published = properties(&status.lock().unwrap());
+        while !shutdown.load(Ordering::SeqCst) {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = properties(&status.lock().unwrap());
+            let changed: HashMap<&str, &Value> = current.iter()
+                .zip(&published)
+                .filter(|(new, old)| new.1 != old.1)
+                .map(|(new, _)| (new.0, &new.1))
+                .collect();
+            if changed.is_empty() {
+                continue;
+            }
+            let body = (BUS_NAME, changed, Vec::<&str>::new());
+            if let Err(e) = conn.emit_signal(None::<&str>, OBJECT_PATH, "org.freedesktop.DBus.Properties", "PropertiesChanged", &body) {
+                eprintln!("⚠️  Failed to emit D-Bus PropertiesChanged: {}", e);
+            }
+            published = current;
+        }
+    }))
+}
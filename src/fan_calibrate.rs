@@ -0,0 +1,182 @@
+//! `fan calibrate`: steps PWM outputs through their duty range, records the
+//! RPM each duty gives and suggests fan curve anchor points from it.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{config::Thermal, thermal::ThermalManager};
+
+/// Temperatures (°C) of the suggested curve points: quiet, half speed,
+/// three quarters and full speed.
+const ANCHOR_TEMPS: [f32; 4] = [45.0, 60.0, 75.0, 85.0];
+
+/// Options for the `fan calibrate` subcommand.
+#[derive(Debug)]
+pub struct FanCalibrateOptions {
+    /// Fan indexes as `list` numbers them; empty calibrates every fan with a tachometer
+    pub fans: Vec<usize>,
+    /// Duty step (percentage points)
+    pub step: u8,
+    /// Time each duty is held before the RPM is read
+    pub settle: Duration,
+    /// Where to write the suggested config snippet
+    pub output: Option<PathBuf>,
+}
+
+/// What one fan did across the duty range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanCalibration {
+    pub name: String,
+    /// (duty %, RPM) for each step, ascending by duty
+    pub samples: Vec<(u8, u32)>,
+}
+
+impl FanCalibration {
+    /// Lowest duty that got the fan turning from a standstill.
+    pub fn start_duty(&self) -> Option<u8> {
+        self.samples.iter().find(|&&(_, rpm)| rpm > 0).map(|&(duty, _)| duty)
+    }
+
+    pub fn max_rpm(&self) -> u32 {
+        self.samples.iter().map(|&(_, rpm)| rpm).max().unwrap_or(0)
+    }
+
+    /// Lowest duty reaching `share` of the top RPM.
+    fn duty_for(&self, share: f32) -> Option<u8> {
+        let wanted = self.max_rpm() as f32 * share;
+        self.samples.iter().find(|&&(_, rpm)| rpm as f32 >= wanted).map(|&(duty, _)| duty)
+    }
+
+    /// Curve points for `ANCHOR_TEMPS`: the start duty, the duties giving
+    /// half and three quarters of the top RPM, and 100%. None for a fan
+    /// that never turned.
+    pub fn suggested_curve(&self) -> Option<Vec<(f32, u8)>> {
+        let start = self.start_duty()?;
+        let mut duties = [start, self.duty_for(0.5)?, self.duty_for(0.75)?, 100];
+        // RPM dips between steps must not make the curve fall
+        for i in 1..duties.len() {
+            duties[i] = duties[i].max(duties[i - 1]);
+        }
+        Some(ANCHOR_TEMPS.into_iter().zip(duties).collect())
+    }
+}
+
+/// Config snippet with a `[[thermal.fan-control.fans]]` entry per fan that
+/// turned, and a `stall-pwm` every one of them turns at.
+pub fn to_config_snippet(fans: &[FanCalibration]) -> String {
+    let mut out = String::new();
+    let starts: Vec<u8> = fans.iter().filter_map(FanCalibration::start_duty).collect();
+    if let Some(&stall_pwm) = starts.iter().max() {
+        let _ = writeln!(out, "[thermal.fan-control]");
+        let _ = writeln!(out, "stall-pwm = {}  # Every calibrated fan turns from here", stall_pwm.max(1));
+    }
+    for fan in fans {
+        let _ = writeln!(out);
+        let Some(curve) = fan.suggested_curve() else {
+            let _ = writeln!(out, "# {}: never turned, check its tachometer and header", fan.name);
+            continue;
+        };
+        let rpm_at = |duty: u8| fan.samples.iter().find(|&&(d, _)| d == duty).map_or(0, |&(_, rpm)| rpm);
+        let start = curve[0].1;
+        let _ = writeln!(out, "# {}: turns from {}% ({} RPM), {} RPM at full speed", fan.name, start, rpm_at(start), fan.max_rpm());
+        let _ = writeln!(out, "[[thermal.fan-control.fans]]");
+        let _ = writeln!(out, "fan = \"{}\"", fan.name);
+        let points: Vec<String> = curve.iter().map(|(temp, duty)| format!("[{temp:.1}, {duty}]")).collect();
+        let _ = writeln!(out, "curve = [{}]", points.join(", "));
+    }
+    out
+}
+
+/// Fails once the GPU is over the warning threshold: a stopped or slow fan
+/// must not be held there while the GPU heats up.
+fn check_temperature(tm: &ThermalManager, thermal: &Thermal) -> Result<(), IoError> {
+    match thermal.warning_exceeded(&tm.get_thermal_status(thermal.include_cpu_temp)) {
+        Some((temp, threshold)) => Err(IoError::other(format!("GPU at {temp:.1}°C > {threshold:.1}°C, calibration aborted"))),
+        None => Ok(()),
+    }
+}
+
+/// (duty, RPM) of fan `index` at each of `duties`, or None when interrupted.
+/// Fails when the GPU crosses the warning threshold.
+fn sweep(tm: &ThermalManager, thermal: &Thermal, index: usize, duties: &[u8], settle: Duration, interrupted: &AtomicBool) -> Result<Option<Vec<(u8, u32)>>, IoError> {
+    let mut samples = Vec::new();
+    for &duty in duties {
+        if interrupted.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        check_temperature(tm, thermal)?;
+        tm.set_fan_speed(index, duty)?;
+        // A running fan takes longer to coast to a stop than to change speed
+        std::thread::sleep(if duty == 0 { settle * 2 } else { settle });
+        check_temperature(tm, thermal)?;
+        let rpm = tm.fan_rpm(index).unwrap_or(0);
+        println!("   {:>3}% → {} RPM", duty, rpm);
+        samples.push((duty, rpm));
+    }
+    Ok(Some(samples))
+}
+
+/// Calibrates the fans of `tm`: each is stopped, then raised from 0% to 100%
+/// in `step` increments, holding every duty for `settle` (the stop twice as
+/// long) before reading its RPM. Fans without a tachometer are skipped.
+/// Every fan is handed back to automatic control when done, interrupted or
+/// aborted because the GPU crossed `thermal`'s warning threshold.
+pub fn run(tm: &ThermalManager, thermal: &Thermal, opts: &FanCalibrateOptions) -> Result<Vec<FanCalibration>, IoError> {
+    if opts.step == 0 || opts.step > 100 {
+        return Err(IoError::new(ErrorKind::InvalidInput, "the duty step must be between 1 and 100"));
+    }
+    let indexes: Vec<usize> = if opts.fans.is_empty() { (0..tm.fans.len()).collect() } else { opts.fans.clone() };
+    if let Some(&bad) = indexes.iter().find(|&&i| i >= tm.fans.len()) {
+        return Err(IoError::new(ErrorKind::NotFound, format!("no fan {bad}; `list` shows {} fans", tm.fans.len())));
+    }
+    let interrupted = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&interrupted))?;
+    }
+
+    let mut duties: Vec<u8> = (0..=100).step_by(usize::from(opts.step)).collect();
+    if duties.last() != Some(&100) {
+        duties.push(100);
+    }
+    let mut results = Vec::new();
+    for &index in &indexes {
+        let name = tm.fans[index].name.clone();
+        if tm.fan_rpm(index).is_none() {
+            println!("⏭️  {}: no tachometer, skipped", name);
+            continue;
+        }
+        println!("🌀 Calibrating {}: {} steps of {}s", name, duties.len(), opts.settle.as_secs_f32());
+        match sweep(tm, thermal, index, &duties, opts.settle, &interrupted) {
+            Ok(Some(samples)) => results.push(FanCalibration { name, samples }),
+            Ok(None) => {
+                println!("🛑 Interrupted");
+                break;
+            }
+            Err(e) => {
+                let _ = tm.restore_auto_fan_control();
+                return Err(e);
+            }
+        }
+    }
+    if let Err(e) = tm.restore_auto_fan_control() {
+        eprintln!("⚠️  Failed to hand the fans back: {}", e);
+    }
+
+    let snippet = to_config_snippet(&results);
+    println!();
+    print!("{}", snippet);
+    if let Some(path) = &opts.output {
+        fs::write(path, &snippet)?;
+        println!("💾 Wrote the suggested fan curves to {}", path.display());
+    }
+    Ok(results)
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+const PROC_ROOT: &str = "/proc";
+
+/// GPU time one process used over the last accounting interval.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClientUsage {
+    pub pid: u32,
+    /// Process name (/proc/<pid>/comm)
+    pub name: String,
+    /// Share of the interval its DRM clients kept the GPU engines busy (0.0-1.0)
+    pub busy: f32,
+}
+
+/// Clients of the latest interval, busiest first.
+pub type SharedClients = Arc<Mutex<Vec<ClientUsage>>>;
+
+/// Client id and total engine time (ns) from one DRM fdinfo file. None for
+/// files that are not amdgpu clients, or belong to a GPU other than `pdev`.
+pub fn parse_fdinfo(text: &str, pdev: Option<&str>) -> Option<(u64, u64)> {
+    let (mut amdgpu, mut on_gpu) = (false, pdev.is_none());
+    let (mut client_id, mut engine_ns) = (None, 0u64);
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        match key {
+            "drm-driver" => amdgpu = value == "amdgpu",
+            "drm-pdev" => on_gpu |= pdev == Some(value),
+            "drm-client-id" => client_id = value.parse().ok(),
+            _ if key.starts_with("drm-engine-") && !key.starts_with("drm-engine-capacity-") => {
+                engine_ns += value.trim_end_matches("ns").trim().parse::<u64>().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+    (amdgpu && on_gpu).then_some(client_id).flatten().map(|id| (id, engine_ns))
+}
+
+/// Attributes GPU time to processes from the DRM engine counters in
+/// /proc/<pid>/fdinfo. A client shared by several file descriptors or
+/// processes is counted once, for the first process seen with it.
+pub struct ClientAccounting {
+    /// PCI address of the governed GPU, as in drm-pdev; None matches any amdgpu client
+    pdev: Option<String>,
+    /// Engine time (ns) per client id at the previous sample
+    last: HashMap<u64, u64>,
+    last_at: Option<Instant>,
+}
+
+impl ClientAccounting {
+    pub fn new(pdev: Option<String>) -> Self {
+        Self { pdev, last: HashMap::new(), last_at: None }
+    }
+
+    /// Engine time (ns) per client id, with the process holding it.
+    fn scan(&self) -> HashMap<u64, (u32, u64)> {
+        let mut clients = HashMap::new();
+        let Ok(entries) = fs::read_dir(PROC_ROOT) else {
+            return clients;
+        };
+        for entry in entries.flatten() {
+            let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else { continue };
+            // Only readable for processes we may ptrace, which is all of them as root
+            let Ok(fds) = fs::read_dir(entry.path().join("fdinfo")) else { continue };
+            for fd in fds.flatten() {
+                let Some((id, ns)) = fs::read_to_string(fd.path()).ok()
+                    .and_then(|text| parse_fdinfo(&text, self.pdev.as_deref())) else { continue };
+                clients.entry(id).or_insert((pid, ns));
+            }
+        }
+        clients
+    }
+
+    /// Busy share of every process that used the GPU since the previous
+    /// call, busiest first. The first call only sets the baseline.
+    pub fn sample(&mut self) -> Vec<ClientUsage> {
+        let now = Instant::now();
+        let clients = self.scan();
+        let elapsed = self.last_at.replace(now).map(|t| now.duration_since(t).as_nanos() as f32);
+
+        let mut per_pid: HashMap<u32, u64> = HashMap::new();
+        for (id, &(pid, ns)) in &clients {
+            // Client ids are not reused, so one missing from the previous scan started since
+            let delta = ns.saturating_sub(self.last.get(id).copied().unwrap_or(ns));
+            *per_pid.entry(pid).or_default() += delta;
+        }
+        self.last = clients.into_iter().map(|(id, (_, ns))| (id, ns)).collect();
+
+        let Some(elapsed) = elapsed.filter(|&e| e > 0.0) else {
+            return Vec::new();
+        };
+        let mut usage: Vec<ClientUsage> = per_pid.into_iter()
+            .filter(|&(_, ns)| ns > 0)
+            .map(|(pid, ns)| ClientUsage {
+                pid,
+                name: fs::read_to_string(Path::new(PROC_ROOT).join(pid.to_string()).join("comm"))
+                    .map(|c| c.trim_end().to_string()).unwrap_or_default(),
+                busy: (ns as f32 / elapsed).min(1.0),
+            })
+            .collect();
+        usage.sort_by(|a, b| b.busy.total_cmp(&a.busy));
+        usage
+    }
+}
+
+/// Samples client usage every `interval` and publishes it to `clients`.
+pub fn start(mut accounting: ClientAccounting, interval: Duration, clients: SharedClients, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+    println!("📊 Accounting GPU time per client every {}ms", interval.as_millis());
+    std::thread::spawn(move || {
+        while !shutdown.load(Ordering::SeqCst) {
+            let usage = accounting.sample();
+            *clients.lock().unwrap() = usage;
+            std::thread::sleep(interval);
+        }
+    })
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{Error as IoError, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the current log is read for new rows
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the directory is searched for a newer log
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+/// Frame times averaged into the published rate; a second at MangoHud's default log_interval of 100ms
+const AVERAGE_ROWS: usize = 10;
+
+/// Latest frame rate read from MangoHud's log, with when it was read.
+#[derive(Debug, Clone, Default)]
+pub struct SharedFrameRate(Arc<Mutex<Option<(Instant, f32)>>>);
+
+impl SharedFrameRate {
+    fn store(&self, fps: f32) {
+        *self.0.lock().unwrap() = Some((Instant::now(), fps));
+    }
+
+    /// The frame rate, unless no new rows arrived within `max_age`.
+    pub fn fresh(&self, max_age: Duration) -> Option<f32> {
+        self.0.lock().unwrap().filter(|(at, _)| at.elapsed() <= max_age).map(|(_, fps)| fps)
+    }
+}
+
+/// The most recently modified MangoHud CSV log in `dir`, skipping the per-session summaries.
+fn newest_log(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir).ok()?.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "csv")
+            && !path.file_stem().is_some_and(|s| s.to_string_lossy().ends_with("_summary")))
+        .max_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH))
+}
+
+/// Follows one MangoHud CSV log: a system info header, then a column header
+/// naming `frametime` (ms), then one row per log interval.
+struct LogTail {
+    file: File,
+    /// Bytes after the last complete line
+    pending: Vec<u8>,
+    column: Option<usize>,
+    frametimes: VecDeque<f32>,
+}
+
+impl LogTail {
+    fn open(path: &Path) -> Result<Self, IoError> {
+        Ok(Self { file: File::open(path)?, pending: Vec::new(), column: None, frametimes: VecDeque::new() })
+    }
+
+    /// Reads the rows appended since the last call and returns the average
+    /// frame rate over the latest ones, or None when no row was added.
+    fn read(&mut self) -> Result<Option<f32>, IoError> {
+        let start = self.pending.len();
+        self.file.read_to_end(&mut self.pending)?;
+        if self.pending.len() == start {
+            return Ok(None);
+        }
+        let Some(end) = self.pending.iter().rposition(|&b| b == b'\n') else { return Ok(None) };
+        let lines: Vec<u8> = self.pending.drain(..=end).collect();
+
+        let mut added = false;
+        for line in String::from_utf8_lossy(&lines).lines() {
+            let mut fields = line.split(',').map(str::trim);
+            match self.column {
+                None => self.column = fields.position(|f| f == "frametime"),
+                Some(column) => {
+                    if let Some(ms) = fields.nth(column).and_then(|f| f.parse::<f32>().ok()).filter(|&ms| ms > 0.0) {
+                        if self.frametimes.len() == AVERAGE_ROWS {
+                            self.frametimes.pop_front();
+                        }
+                        self.frametimes.push_back(ms);
+                        added = true;
+                    }
+                }
+            }
+        }
+        let average = self.frametimes.iter().sum::<f32>() / self.frametimes.len().max(1) as f32;
+        Ok(added.then(|| 1000.0 / average))
+    }
+
+    /// Reads past what the log already holds, so a finished session's rows are not taken as current.
+    fn skip_existing(&mut self) -> Result<(), IoError> {
+        self.read()?;
+        self.frametimes.clear();
+        Ok(())
+    }
+}
+
+/// Follows the newest MangoHud CSV log in `dir` and publishes the frame
+/// rate averaged over its latest rows. A newer log (MangoHud starts one per
+/// logging session) replaces the current one.
+pub fn start(dir: PathBuf, frame_rate: SharedFrameRate, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+    println!("🎞️  Reading frame times from MangoHud logs in {}", dir.display());
+    std::thread::spawn(move || {
+        let mut current: Option<(PathBuf, LogTail)> = None;
+        let mut last_scan: Option<Instant> = None;
+        while !shutdown.load(Ordering::SeqCst) {
+            if last_scan.is_none_or(|t| t.elapsed() >= SCAN_INTERVAL) {
+                last_scan = Some(Instant::now());
+                if let Some(path) = newest_log(&dir).filter(|p| current.as_ref().is_none_or(|(c, _)| c != p)) {
+                    match LogTail::open(&path).and_then(|mut tail| tail.skip_existing().map(|_| tail)) {
+                        Ok(tail) => {
+                            println!("🎞️  Following MangoHud log {}", path.display());
+                            current = Some((path, tail));
+                        }
+                        Err(e) => eprintln!("⚠️  Cannot open MangoHud log {}: {}", path.display(), e),
+                    }
+                }
+            }
+            if let Some((path, tail)) = current.as_mut() {
+                match tail.read() {
+                    Ok(Some(fps)) => frame_rate.store(fps),
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!("⚠️  Reading MangoHud log {} failed: {}", path.display(), e);
+                        current = None;
+                    }
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    })
+}
@@ -0,0 +1,76 @@
+use std::{
+    io::Error as IoError,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+use zbus::{blocking::{connection, Connection}, zvariant::OwnedValue};
+
+use crate::{control::PerfRequests, governor::PerformanceMode};
+
+const GAMEMODE_NAME: &str = "com.feralinteractive.GameMode";
+const GAMEMODE_PATH: &str = "/com/feralinteractive/GameMode";
+/// Max-performance holds taken for GameMode share this client name.
+const GAMEMODE_CLIENT: &str = "gamemode";
+
+fn connect(address: &str) -> Result<Connection, IoError> {
+    connection::Builder::address(address).and_then(|b| b.build()).map_err(IoError::other)
+}
+
+/// Number of games gamemoded currently has registered.
+fn client_count(conn: &Connection) -> Result<i32, IoError> {
+    let reply = conn.call_method(Some(GAMEMODE_NAME), GAMEMODE_PATH, Some("org.freedesktop.DBus.Properties"), "Get",
+        &(GAMEMODE_NAME, "ClientCount"))
+        .map_err(IoError::other)?;
+    let value: OwnedValue = reply.body().deserialize().map_err(IoError::other)?;
+    i32::try_from(value).map_err(IoError::other)
+}
+
+/// Holds max performance for client `gamemode` while gamemoded has at least
+/// one game registered, reading its client count from the bus at `address`
+/// every `interval`. The bus is reconnected
+/// after errors; while it or gamemoded is unreachable, no hold is taken.
+pub fn start(address: String, interval: Duration, perf_requests: Arc<Mutex<PerfRequests>>, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+    println!("🎮 Following GameMode on the {} bus", address);
+    std::thread::spawn(move || {
+        let mut conn: Option<Connection> = None;
+        let (mut holding, mut reachable) = (false, true);
+        while !shutdown.load(Ordering::SeqCst) {
+            let count = conn.take().map_or_else(|| connect(&address), Ok)
+                .and_then(|c| client_count(&c).map(|n| (c, n)));
+            let games = match count {
+                Ok((c, n)) => {
+                    conn = Some(c);
+                    if !reachable {
+                        println!("🎮 GameMode reachable again");
+                        reachable = true;
+                    }
+                    n
+                }
+                Err(e) => {
+                    if reachable {
+                        eprintln!("⚠️  GameMode unavailable: {}", e);
+                        reachable = false;
+                    }
+                    0
+                }
+            };
+
+            let mut requests = perf_requests.lock().unwrap();
+            if games > 0 && !holding {
+                println!("🎮 GameMode has {} game(s) registered", games);
+                requests.set_mode(GAMEMODE_CLIENT, PerformanceMode::MaxPerformance);
+            } else if games <= 0 && holding {
+                println!("🎮 No game registered with GameMode anymore");
+                requests.set_mode(GAMEMODE_CLIENT, PerformanceMode::Normal);
+            }
+            holding = games > 0;
+            drop(requests);
+
+            std::thread::sleep(interval);
+        }
+    })
+}
@@ -1,14 +1,93 @@
-use std::time::Instant;
+//! Scaling policies (ladder, PID, frametime) and the governor state they
+//! drive: performance modes, load targets, ramp rates and boost budgets.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use crate::{memclk::DpmClock, thermal::ThermalCounters};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PerformanceMode {
     Normal,
     MaxPerformance,
+    /// The lowest safe point, or its own load targets when configured
+    Powersave,
+    /// Hold this frequency (MHz)
+    Locked(u16),
+    /// Scale as usual, but no higher than this frequency (MHz)
+    Capped(u16),
+}
+
+impl PerformanceMode {
+    /// The modes clients can hold or release
+    pub const ALL: [PerformanceMode; 2] = [PerformanceMode::Normal, PerformanceMode::MaxPerformance];
+
+    /// Name used for this mode in config tables such as `[mode-load-targets.<name>]`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PerformanceMode::Normal => "normal",
+            PerformanceMode::MaxPerformance => "max-performance",
+            PerformanceMode::Powersave => "powersave",
+            PerformanceMode::Locked(_) => "locked",
+            PerformanceMode::Capped(_) => "capped",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    /// The name, with the frequency for a lock or cap ("lock:1500", "cap:1800"); `parse` reads it back.
+    pub fn label(&self) -> String {
+        match self {
+            PerformanceMode::Locked(freq) => format!("lock:{freq}"),
+            PerformanceMode::Capped(freq) => format!("cap:{freq}"),
+            mode => mode.name().to_string(),
+        }
+    }
+
+    /// Any mode: a name, "max", "lock:<MHz>" or "cap:<MHz>".
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let freq = |prefix: &str| text.strip_prefix(prefix).and_then(|f| f.trim().parse().ok());
+        match text {
+            "max" => Some(PerformanceMode::MaxPerformance),
+            "powersave" => Some(PerformanceMode::Powersave),
+            _ if text.starts_with("lock:") => freq("lock:").map(PerformanceMode::Locked),
+            _ if text.starts_with("cap:") => freq("cap:").map(PerformanceMode::Capped),
+            _ => Self::from_name(text),
+        }
+    }
+
+    /// True for modes that can key `[mode-load-targets.<name>]`.
+    pub fn is_named(name: &str) -> bool {
+        Self::parse(name).is_some_and(|m| m.name() == name)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum GovCommand {
     SetFrequency(u16),
+    /// Re-apply the current frequency with a voltage offset (mV) relative to
+    /// the interpolated safe-point voltage, without changing the clock.
+    TrimVoltage(i16),
+    /// Replace the frequency (MHz) to voltage (mV) table after a config reload
+    SetSafePoints(BTreeMap<u16, u16>),
+    /// Replace the global voltage offset (mV) and the floor it may not cross
+    SetVoltageOffset { offset_mv: i16, floor_mv: u16 },
+    /// Force a memory or fabric clock to its lowest or highest DPM level
+    SetDpmLevel(DpmClock, LoadLevel),
+    /// Read OD_SCLK back after every apply, accepting differences up to this
+    /// many MHz; None stops checking
+    SetVerify(Option<u16>),
+    /// Apply the lowest safe point ahead of an emergency shutdown, apply it in
+    /// place of every later frequency, and leave it behind at shutdown
+    /// whatever the shutdown policy says
+    HoldLowestSafePoint,
     Shutdown,
 }
 
@@ -16,20 +95,47 @@ pub enum GovCommand {
 pub enum SetterAck {
     Applied {
         freq: u16,
+        voltage: u16,
         latency_us: u64,
     },
     Failed {
         freq: u16,
         error: String,
     },
+    /// The write succeeded but OD_SCLK reads back something else (None: unreadable)
+    Rejected {
+        freq: u16,
+        actual: Option<u16>,
+    },
+    Trimmed {
+        freq: u16,
+        voltage: u16,
+        offset_mv: i16,
+    },
+    TrimFailed {
+        offset_mv: i16,
+        error: String,
+    },
+    DpmApplied {
+        clock: DpmClock,
+        freq: u16,
+    },
+    DpmFailed {
+        clock: DpmClock,
+        error: String,
+    },
 }
 
 pub struct GovernorState {
     pub target_freq: f32,
     pub applied_freq: u16,
+    /// Voltage (mV) of the last write, unknown until the governor has applied one
+    pub applied_voltage: Option<u16>,
     pub pending_freq: Option<u16>,
     pub last_ack: Instant,
     pub performance_mode: PerformanceMode,
+    pub consecutive_failures: u32,
+    pub backoff_until: Option<Instant>,
 }
 
 impl GovernorState {
@@ -37,20 +143,43 @@ impl GovernorState {
         Self {
             target_freq: f32::from(min_freq),
             applied_freq: min_freq,
+            applied_voltage: None,
             pending_freq: None,
             last_ack: Instant::now(),
             performance_mode: PerformanceMode::Normal,
+            consecutive_failures: 0,
+            backoff_until: None,
         }
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Serialize, Default, Debug, Clone)]
 pub struct GovernorStats {
     pub total_applies: u64,
     pub failed_applies: u64,
     pub burst_activations: u64,
+    pub backoff_activations: u64,
+    pub rate_limited: u64,
+    /// Sampling iterations that ran late by more than OVERRUN_FACTOR intervals
+    pub overruns: u64,
+    pub max_overrun_us: u64,
+    /// Times the burst ramp was capped by `burst_soft_limit`
+    pub throttle_activations: u64,
+    /// Times the thermal throttle curve started capping the frequency
+    pub throttle_curve_activations: u64,
+    pub thermal_warnings: u64,
+    pub thermal_emergencies: u64,
+    pub fan_failures: u64,
+    /// Fans found standing still at a high PWM duty
+    pub fan_stalls: u64,
+    /// OD table changes made by other tools
+    pub external_writes: u64,
+    /// Applies the driver accepted but did not take effect (also counted as failed)
+    pub rejected_applies: u64,
     pub total_latency_us: u64,
     pub max_latency_us: u64,
+    /// Hottest GPU-side temperature (°C) the governor decided at, 0 without a reading
+    pub max_gpu_temp: f32,
 }
 
 impl GovernorStats {
@@ -68,12 +197,49 @@ impl GovernorStats {
         self.burst_activations += 1;
     }
 
+    pub fn record_backoff(&mut self) {
+        self.backoff_activations += 1;
+    }
+
+    pub fn record_rate_limited(&mut self) {
+        self.rate_limited += 1;
+    }
+
+    pub fn record_overrun(&mut self, late_us: u64) {
+        self.overruns += 1;
+        self.max_overrun_us = self.max_overrun_us.max(late_us);
+    }
+
+    pub fn record_rejected(&mut self) {
+        self.rejected_applies += 1;
+    }
+
+    pub fn record_external_write(&mut self) {
+        self.external_writes += 1;
+    }
+
+    pub fn record_throttle(&mut self) {
+        self.throttle_activations += 1;
+    }
+
+    pub fn record_throttle_curve(&mut self) {
+        self.throttle_curve_activations += 1;
+    }
+
+    pub fn record_gpu_temp(&mut self, temp: f32) {
+        self.max_gpu_temp = self.max_gpu_temp.max(temp);
+    }
+
+    /// Copies the thermal thread's event counts into these stats.
+    pub fn merge_thermal(&mut self, counters: &ThermalCounters) {
+        self.thermal_warnings = counters.warnings.load(Ordering::Relaxed);
+        self.thermal_emergencies = counters.emergencies.load(Ordering::Relaxed);
+        self.fan_failures = counters.fan_failures.load(Ordering::Relaxed);
+        self.fan_stalls = counters.fan_stalls.load(Ordering::Relaxed);
+    }
+
     pub fn avg_latency_us(&self) -> u64 {
-        if self.total_applies > 0 {
-            self.total_latency_us / self.total_applies
-        } else {
-            0
-        }
+        self.total_latency_us.checked_div(self.total_applies).unwrap_or(0)
     }
 
     pub fn success_rate(&self) -> f32 {
@@ -85,3 +251,787 @@ impl GovernorStats {
         }
     }
 }
+
+/// Level requested from a clock coupled to the GPU load.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadLevel {
+    Low,
+    High,
+}
+
+/// Two-level demand derived from the busy ratio, for clocks coupled to the
+/// GPU load. Rises as soon as the busy ratio reaches `up`, and only drops
+/// once it has stayed at or below `down` for `hold`, so short dips don't
+/// bounce the coupled clock.
+#[derive(Debug)]
+pub struct LoadHysteresis {
+    up: f32,
+    down: f32,
+    hold: Duration,
+    level: Option<LoadLevel>,
+    /// Last time the load asked for the high level
+    demand_at: Instant,
+}
+
+impl LoadHysteresis {
+    pub fn new(up: f32, down: f32, hold: Duration) -> Self {
+        Self { up, down, hold, level: None, demand_at: Instant::now() }
+    }
+
+    /// Returns the new level when it changes. `force_high` (bursts,
+    /// max-performance) skips the threshold.
+    pub fn update(&mut self, busy: f32, force_high: bool, now: Instant) -> Option<LoadLevel> {
+        let high = self.level == Some(LoadLevel::High);
+        let demand = force_high || busy >= self.up;
+        if demand || (high && busy > self.down) {
+            self.demand_at = now;
+        }
+        let wanted = if demand || (high && now.duration_since(self.demand_at) < self.hold) {
+            LoadLevel::High
+        } else {
+            LoadLevel::Low
+        };
+        if self.level == Some(wanted) {
+            return None;
+        }
+        self.level = Some(wanted);
+        Some(wanted)
+    }
+}
+
+/// Sliding one-second window limiting how often the OD table is rewritten.
+/// A limit of 0 disables rate limiting.
+#[derive(Debug)]
+pub struct ApplyRateLimiter {
+    max_per_second: u32,
+    recent: VecDeque<Instant>,
+}
+
+impl ApplyRateLimiter {
+    pub fn new(max_per_second: u32) -> Self {
+        Self {
+            max_per_second,
+            recent: VecDeque::with_capacity(max_per_second as usize),
+        }
+    }
+
+    pub fn allows(&mut self, now: Instant) -> bool {
+        if self.max_per_second == 0 {
+            return true;
+        }
+        while let Some(&oldest) = self.recent.front() {
+            if now.duration_since(oldest) >= Duration::from_secs(1) {
+                self.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.recent.len() < self.max_per_second as usize
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        if self.max_per_second > 0 {
+            self.recent.push_back(now);
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct RampRates {
+    pub up: f32,
+    pub down: f32,
+    pub burst: f32,
+    #[serde(rename = "up-medium")]
+    pub up_medium: f32,
+    #[serde(rename = "up-slow")]
+    pub up_slow: f32,
+    #[serde(rename = "up-crawl")]
+    pub up_crawl: f32,
+}
+
+impl Default for RampRates {
+    fn default() -> Self {
+        Self {
+            up: 50.0,
+            down: 0.24,
+            burst: 800.0,
+            up_medium: 25.0,
+            up_slow: 10.0,
+            up_crawl: 2.0,
+        }
+    }
+}
+
+/// `[timing] ema`: exponential moving averages of the busy signal in place
+/// of the ramp-up and ramp-down sample windows.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct EmaConfig {
+    pub enabled: bool,
+    /// Weight of each new sample in the busy ratio used to ramp up
+    #[serde(rename = "alpha-up")]
+    pub alpha_up: f32,
+    /// Weight of each new sample in the busy ratio used to ramp down
+    #[serde(rename = "alpha-down")]
+    pub alpha_down: f32,
+}
+
+impl Default for EmaConfig {
+    fn default() -> Self {
+        // 2 / (N + 1) matches an N-sample window's responsiveness for the default windows
+        Self { enabled: false, alpha_up: 0.03, alpha_down: 0.008 }
+    }
+}
+
+/// Partial RampRates used by profiles; unset fields keep the base value.
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct RampRatesOverride {
+    pub up: Option<f32>,
+    pub down: Option<f32>,
+    pub burst: Option<f32>,
+    #[serde(rename = "up-medium")]
+    pub up_medium: Option<f32>,
+    #[serde(rename = "up-slow")]
+    pub up_slow: Option<f32>,
+    #[serde(rename = "up-crawl")]
+    pub up_crawl: Option<f32>,
+}
+
+impl RampRates {
+    pub fn with_override(&self, o: &RampRatesOverride) -> RampRates {
+        RampRates {
+            up: o.up.unwrap_or(self.up),
+            down: o.down.unwrap_or(self.down),
+            burst: o.burst.unwrap_or(self.burst),
+            up_medium: o.up_medium.unwrap_or(self.up_medium),
+            up_slow: o.up_slow.unwrap_or(self.up_slow),
+            up_crawl: o.up_crawl.unwrap_or(self.up_crawl),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct LoadTarget {
+    pub upper: f32,         
+    pub medium: f32,
+    pub slow: f32,
+    pub crawl: f32,
+    pub lower: f32,
+}
+
+impl Default for LoadTarget {
+    fn default() -> Self {
+        Self {
+            upper: 0.90,
+            medium: 0.75,
+            slow: 0.60,
+            crawl: 0.50,
+            lower: 0.50,
+        }
+    }
+}
+
+/// Partial LoadTarget used by `[mode-load-targets.<mode>]` and profiles; unset fields keep the base value.
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct LoadTargetOverride {
+    pub upper: Option<f32>,
+    pub medium: Option<f32>,
+    pub slow: Option<f32>,
+    pub crawl: Option<f32>,
+    pub lower: Option<f32>,
+}
+
+impl LoadTarget {
+    pub fn with_override(self, o: &LoadTargetOverride) -> LoadTarget {
+        LoadTarget {
+            upper: o.upper.unwrap_or(self.upper),
+            medium: o.medium.unwrap_or(self.medium),
+            slow: o.slow.unwrap_or(self.slow),
+            crawl: o.crawl.unwrap_or(self.crawl),
+            lower: o.lower.unwrap_or(self.lower),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct PidGains {
+    /// Busy ratio the controller steers towards
+    pub setpoint: f32,
+    /// MHz per unit change of the busy error
+    pub kp: f32,
+    /// MHz per second per unit of busy error
+    pub ki: f32,
+    /// MHz·s per unit change of the error's rate
+    pub kd: f32,
+}
+
+impl Default for PidGains {
+    fn default() -> Self {
+        Self { setpoint: 0.75, kp: 500.0, ki: 4000.0, kd: 0.0 }
+    }
+}
+
+/// What a policy sees on each sample.
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyInput {
+    /// Target frequency (MHz) before this step
+    pub target_freq: f32,
+    /// Busy ratio over the ramp-up and ramp-down windows
+    pub busy_up: f32,
+    pub busy_down: f32,
+    /// Every sample in the burst window was busy and the thermal limit allows bursting
+    pub burst: bool,
+    /// Burst rate multiplier from the soft thermal limit (0.0-1.0)
+    pub burst_scale: f32,
+    /// Load thresholds resolved for the active performance mode
+    pub load_target: LoadTarget,
+    /// Milliseconds since the previous sample
+    pub dt_ms: f32,
+    /// Frame rate of the running game, while its frame times are known
+    pub fps: Option<f32>,
+    /// GPU temperature (°C), while a reading is known
+    pub gpu_temp: Option<f32>,
+    /// CPU temperature (°C), while a reading is known
+    pub cpu_temp: Option<f32>,
+    /// Performance mode the target is computed for
+    pub mode: PerformanceMode,
+}
+
+/// Frequency decision logic, called once per sample while the governor
+/// scales on its own. The returned target is clamped to the safe-point range
+/// (and the soft-start ceiling) by the caller.
+pub trait GovernorPolicy: Send {
+    fn name(&self) -> &'static str;
+
+    /// Next target frequency (MHz).
+    fn next_target(&mut self, input: &PolicyInput) -> f32;
+
+    /// Called when something else set the target (backoff, mirroring, the
+    /// max-performance lock), so stateful policies restart from it.
+    fn reset(&mut self) {}
+}
+
+/// Tiered ramp rates: the busier the GPU relative to the load-target tiers,
+/// the faster the target rises; below `lower` it decays.
+#[derive(Debug)]
+pub struct LadderPolicy {
+    rates: RampRates,
+}
+
+impl LadderPolicy {
+    pub fn new(rates: RampRates) -> Self {
+        Self { rates }
+    }
+}
+
+impl GovernorPolicy for LadderPolicy {
+    fn name(&self) -> &'static str {
+        "ladder"
+    }
+
+    fn next_target(&mut self, input: &PolicyInput) -> f32 {
+        let (load, rates, dt) = (&input.load_target, &self.rates, input.dt_ms);
+        let rate = if input.burst {
+            rates.burst * input.burst_scale
+        } else if input.busy_up > load.upper {
+            rates.up
+        } else if input.busy_up > load.medium {
+            rates.up_medium
+        } else if input.busy_up > load.slow {
+            rates.up_slow
+        } else if input.busy_up > load.crawl {
+            rates.up_crawl
+        } else if input.busy_down < load.lower {
+            -rates.down
+        } else {
+            0.0
+        };
+        input.target_freq + rate * dt
+    }
+}
+
+/// Velocity-form PID on the busy ratio. Each step adds a frequency change
+/// rather than setting an absolute frequency, so the target itself carries
+/// the integral: clamping it to the safe-point range is all the anti-windup
+/// needed, and bursts or other code moving the target cause no bump.
+#[derive(Debug)]
+pub struct PidPolicy {
+    gains: PidGains,
+    /// Bursts still jump at the ladder's burst rate
+    burst_rate: f32,
+    prev_error: Option<f32>,
+    prev_derivative: f32,
+}
+
+impl PidPolicy {
+    pub fn new(gains: PidGains, burst_rate: f32) -> Self {
+        Self { gains, burst_rate, prev_error: None, prev_derivative: 0.0 }
+    }
+}
+
+impl GovernorPolicy for PidPolicy {
+    fn name(&self) -> &'static str {
+        "pid"
+    }
+
+    fn next_target(&mut self, input: &PolicyInput) -> f32 {
+        if input.burst {
+            self.reset();
+            return input.target_freq + self.burst_rate * input.burst_scale * input.dt_ms;
+        }
+        let dt = input.dt_ms / 1000.0;
+        let error = input.busy_up - self.gains.setpoint;
+        let integral = self.gains.ki * error * dt;
+        let Some(prev) = self.prev_error.replace(error) else {
+            return input.target_freq + integral;
+        };
+        let derivative = if dt > 0.0 { (error - prev) / dt } else { 0.0 };
+        let delta = self.gains.kp * (error - prev) + integral + self.gains.kd * (derivative - self.prev_derivative);
+        self.prev_derivative = derivative;
+        input.target_freq + delta
+    }
+
+    fn reset(&mut self) {
+        self.prev_error = None;
+        self.prev_derivative = 0.0;
+    }
+}
+
+/// Frame rate the frametime policy holds.
+#[derive(Debug, Clone, Copy)]
+pub struct FrametimeTarget {
+    pub target_fps: f32,
+    /// Share below the target still counted as holding it
+    pub tolerance: f32,
+    /// MHz per second per percent of frame rate missing
+    pub up_gain: f32,
+    /// MHz per second shed while the target holds
+    pub down_rate: f32,
+}
+
+/// Scales to hold a frame rate instead of a busy ratio: while the game
+/// reaches the target the frequency drifts down, and when it falls short
+/// the frequency rises in proportion to the shortfall. With a frame rate
+/// capped by the display, this settles at the lowest clock that still
+/// keeps up. Without frame times, the ladder takes over.
+#[derive(Debug)]
+pub struct FrametimePolicy {
+    target: FrametimeTarget,
+    fallback: LadderPolicy,
+}
+
+impl FrametimePolicy {
+    pub fn new(target: FrametimeTarget, rates: RampRates) -> Self {
+        Self { target, fallback: LadderPolicy::new(rates) }
+    }
+}
+
+impl GovernorPolicy for FrametimePolicy {
+    fn name(&self) -> &'static str {
+        "frametime"
+    }
+
+    fn next_target(&mut self, input: &PolicyInput) -> f32 {
+        let Some(fps) = input.fps else {
+            return self.fallback.next_target(input);
+        };
+        let target = &self.target;
+        let dt = input.dt_ms / 1000.0;
+        if fps >= target.target_fps * (1.0 - target.tolerance) {
+            input.target_freq - target.down_rate * dt
+        } else {
+            let missing = (1.0 - fps / target.target_fps) * 100.0;
+            input.target_freq + target.up_gain * missing * dt
+        }
+    }
+}
+
+/// Min/avg/max of a value over the rolling window.
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct RollingStats {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+}
+
+/// Time-bounded window of periodic samples (e.g. applied frequency every 100ms).
+#[derive(Debug)]
+pub struct RollingWindow {
+    span: Duration,
+    samples: VecDeque<(Instant, f32)>,
+}
+
+impl RollingWindow {
+    pub fn new(span: Duration) -> Self {
+        Self { span, samples: VecDeque::new() }
+    }
+
+    pub fn push(&mut self, now: Instant, value: f32) {
+        self.samples.push_back((now, value));
+        while let Some(&(t, _)) = self.samples.front() {
+            if now.duration_since(t) > self.span {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn stats(&self) -> RollingStats {
+        if self.samples.is_empty() {
+            return RollingStats::default();
+        }
+        let (mut min, mut max, mut sum) = (f32::INFINITY, f32::NEG_INFINITY, 0.0);
+        for &(_, v) in &self.samples {
+            min = min.min(v);
+            max = max.max(v);
+            sum += v;
+        }
+        RollingStats { min, avg: sum / self.samples.len() as f32, max }
+    }
+}
+
+/// A loop iteration counts as an overrun when the time since the previous
+/// sample exceeds this multiple of the configured interval.
+pub const OVERRUN_FACTOR: u32 = 2;
+
+/// Burst test shared by the sample windows and the EMA: the newest `count`
+/// of `samples` (oldest first) exist and at least `needed` of them are busy.
+fn busy_among(samples: impl DoubleEndedIterator<Item = bool> + ExactSizeIterator, count: usize, needed: usize) -> bool {
+    count > 0 && samples.len() >= count && samples.rev().take(count).filter(|&b| b).count() >= needed
+}
+
+/// Busy ratios as exponential moving averages, with the newest samples kept
+/// for burst detection. Constant memory, whatever the sample rate.
+#[derive(Debug, Default)]
+pub struct BusyEma {
+    up: Option<f32>,
+    down: Option<f32>,
+    /// Newest last; as many as the largest `burst-samples`
+    recent: VecDeque<bool>,
+}
+
+impl BusyEma {
+    /// Folds in a sample taken `intervals` nominal sample intervals after the
+    /// previous one; a late sample weighs as much as the samples it replaces.
+    /// The first sample seeds both averages.
+    pub fn push(&mut self, busy: bool, config: &EmaConfig, intervals: f32) {
+        let sample = if busy { 1.0 } else { 0.0 };
+        let fold = |value: Option<f32>, alpha: f32| match value {
+            Some(v) => v + (1.0 - (1.0 - alpha).powf(intervals.max(0.0))) * (sample - v),
+            None => sample,
+        };
+        self.up = Some(fold(self.up, config.alpha_up));
+        self.down = Some(fold(self.down, config.alpha_down));
+        if self.recent.len() == u8::MAX as usize {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(busy);
+    }
+
+    pub fn busy_up(&self) -> f32 {
+        self.up.unwrap_or_default()
+    }
+
+    pub fn busy_down(&self) -> f32 {
+        self.down.unwrap_or_default()
+    }
+
+    /// True when at least `needed` of the newest `count` samples were busy.
+    pub fn busy_among(&self, count: usize, needed: usize) -> bool {
+        busy_among(self.recent.iter().copied(), count, needed)
+    }
+}
+
+/// Token bucket for time spent above a boost frequency. Time above it
+/// drains the bucket, faster the hotter the GPU runs; time at or below it
+/// refills the bucket, slower the hotter it runs. Over the last `taper`
+/// share of the bucket the ceiling falls from the top of the table to the
+/// boost frequency, so sustained boost eases off instead of stopping.
+#[derive(Debug)]
+pub struct BoostBudget {
+    boost: f32,
+    capacity: f32,
+    /// Seconds of budget regained per second, when cool
+    refill_rate: f32,
+    taper: f32,
+    /// Seconds of boost left
+    tokens: f32,
+}
+
+impl BoostBudget {
+    /// Starts full with `capacity` seconds above `boost` MHz, refilling from empty in `refill_secs`.
+    pub fn new(boost: f32, capacity: f32, refill_secs: f32, taper: f32) -> Self {
+        Self { boost, capacity, refill_rate: capacity / refill_secs, taper, tokens: capacity }
+    }
+
+    /// Share of the budget left (0.0-1.0).
+    pub fn level(&self) -> f32 {
+        self.tokens / self.capacity
+    }
+
+    /// Accounts `dt` seconds at `freq`, with `heat` 0 when cool and 1 at the
+    /// warning threshold, and returns the ceiling, None while the budget is
+    /// above the taper.
+    pub fn update(&mut self, dt: f32, freq: f32, heat: f32, max_freq: f32) -> Option<f32> {
+        let heat = heat.clamp(0.0, 1.0);
+        if freq > self.boost {
+            self.tokens -= dt * (1.0 + heat);
+        } else {
+            self.tokens += dt * self.refill_rate * (1.0 - heat);
+        }
+        self.tokens = self.tokens.clamp(0.0, self.capacity);
+        let level = self.level();
+        (level < self.taper && self.boost < max_freq).then(|| self.boost + (max_freq - self.boost) * level / self.taper)
+    }
+}
+
+/// Timestamped activity samples. Busy ratios are computed over wall-clock
+/// windows, so samples lost to scheduler preemption or slow register reads
+/// shrink the window's sample count instead of stretching its duration.
+#[derive(Debug)]
+pub struct SampleHistory {
+    capacity: usize,
+    samples: VecDeque<(Instant, bool)>,
+}
+
+impl SampleHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, samples: VecDeque::with_capacity(capacity) }
+    }
+
+    /// Changes the retained sample count, keeping the newest samples.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+        self.samples.reserve(capacity.saturating_sub(self.samples.len()));
+    }
+
+    pub fn push(&mut self, at: Instant, busy: bool) {
+        self.samples.push_back((at, busy));
+        if self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn busy_count(&self) -> usize {
+        self.samples.iter().filter(|&&(_, b)| b).count()
+    }
+
+    /// True when at least `needed` of the newest `count` samples are busy.
+    pub fn busy_among(&self, count: usize, needed: usize) -> bool {
+        busy_among(self.samples.iter().map(|&(_, b)| b), count, needed)
+    }
+
+    /// Fraction of busy samples taken within `window` of the newest one.
+    /// Until the history spans the window, all samples are used.
+    pub fn busy_ratio(&self, window: Duration) -> f32 {
+        let Some(&(newest, _)) = self.samples.back() else {
+            return 0.0;
+        };
+        let (busy, total) = self.samples.iter().rev()
+            .take_while(|&&(t, _)| newest.duration_since(t) < window)
+            .fold((0usize, 0usize), |(busy, total), &(_, b)| (busy + b as usize, total + 1));
+        busy as f32 / total as f32
+    }
+}
+
+/// Settings of the scaling step around the policy: the busy windows or
+/// EMA, burst detection and the apply thresholds.
+#[derive(Debug, Clone, Copy)]
+pub struct ScalingSettings {
+    pub sample_interval: Duration,
+    pub up_samples: usize,
+    pub down_samples: usize,
+    pub burst_samples: usize,
+    pub burst_busy: usize,
+    pub burst_rearm: Duration,
+    pub ema: EmaConfig,
+    pub adjust_mhz: u16,
+    pub finetune_mhz: u16,
+    pub deadband_mhz: u16,
+    pub deadband: Duration,
+    pub adjust_interval: Duration,
+    pub finetune_interval: Duration,
+    pub max_applies_per_second: u32,
+}
+
+impl ScalingSettings {
+    /// Samples the history keeps; none when the EMA stands in for the windows.
+    pub fn max_samples(&self) -> usize {
+        if self.ema.enabled {
+            return 0;
+        }
+        self.up_samples.max(self.down_samples).max(self.burst_samples)
+    }
+}
+
+/// The busy signal after one sample.
+#[derive(Debug, Clone, Copy)]
+pub struct BusySample {
+    /// A burst, held off for `burst-rearm-ms` after the previous one ended
+    pub burst: bool,
+    pub busy_up: f32,
+    pub busy_down: f32,
+    /// Time the sample stands for (ms), what the policy ramps by
+    pub dt_ms: f32,
+}
+
+/// What [`Scaler::decide`] made of the policy's target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApplyDecision {
+    Hold,
+    /// Wanted, but over `max-applies-per-second`
+    RateLimited,
+    /// Send the target to the setter
+    Apply,
+}
+
+/// The scaling step shared by the sampling loop and `replay`, on either
+/// side of the policy: [`Scaler::sample`] turns a busy sample into the
+/// busy ratios and the burst signal the policy reads, and
+/// [`Scaler::decide`] runs the policy's target through the deadband, the
+/// adjust and finetune thresholds and the rate limit. Every timer runs on
+/// the timestamps passed in, so a replay on virtual time decides exactly
+/// as the live loop would.
+#[derive(Debug)]
+pub struct Scaler {
+    settings: ScalingSettings,
+    history: SampleHistory,
+    ema: BusyEma,
+    bursting: bool,
+    burst_ended: Option<Instant>,
+    last_adjust: Instant,
+    last_finetune: Instant,
+    deadband_since: Option<Instant>,
+    rate_limiter: ApplyRateLimiter,
+}
+
+impl Scaler {
+    /// Starts with empty windows, the adjust and finetune timers running from `now`.
+    pub fn new(settings: ScalingSettings, now: Instant) -> Self {
+        Self {
+            settings,
+            history: SampleHistory::new(settings.max_samples()),
+            ema: BusyEma::default(),
+            bursting: false,
+            burst_ended: None,
+            last_adjust: now,
+            last_finetune: now,
+            deadband_since: None,
+            rate_limiter: ApplyRateLimiter::new(settings.max_applies_per_second),
+        }
+    }
+
+    pub fn settings(&self) -> &ScalingSettings {
+        &self.settings
+    }
+
+    pub fn history(&self) -> &SampleHistory {
+        &self.history
+    }
+
+    /// Takes reloaded settings, keeping the newest samples and the timers.
+    pub fn reconfigure(&mut self, settings: ScalingSettings) {
+        self.history.set_capacity(settings.max_samples());
+        self.rate_limiter = ApplyRateLimiter::new(settings.max_applies_per_second);
+        self.settings = settings;
+    }
+
+    /// Folds in a sample taken at `at`, standing for `step` of time: the
+    /// time since the previous sample, capped by the caller so a stall
+    /// cannot cause one huge ramp.
+    pub fn sample(&mut self, at: Instant, busy: bool, step: Duration) -> BusySample {
+        let s = &self.settings;
+        let burst = if s.ema.enabled {
+            self.ema.push(busy, &s.ema, step.as_secs_f32() / s.sample_interval.as_secs_f32());
+            self.ema.busy_among(s.burst_samples, s.burst_busy)
+        } else {
+            self.history.push(at, busy);
+            self.history.busy_among(s.burst_samples, s.burst_busy)
+        };
+
+        // A burst that just ended cannot start again until the re-arm delay has passed
+        let burst = burst && (self.bursting || self.burst_ended.is_none_or(|t| at.duration_since(t) >= s.burst_rearm));
+        if self.bursting && !burst {
+            self.burst_ended = Some(at);
+        }
+        self.bursting = burst;
+
+        // Windows are wall-clock spans of the nominal sample count, so late samples don't stretch them
+        let (busy_up, busy_down) = if s.ema.enabled {
+            (self.ema.busy_up(), self.ema.busy_down())
+        } else {
+            (self.history.busy_ratio(s.sample_interval * s.up_samples as u32),
+                self.history.busy_ratio(s.sample_interval * s.down_samples as u32))
+        };
+        BusySample { burst, busy_up, busy_down, dt_ms: step.as_secs_f32() * 1000.0 }
+    }
+
+    /// Decides at `now` whether to send `target` while `applied` is on the
+    /// card. A burst always sends. `force` (deep idle, an emergency clamp)
+    /// sends any difference at once. `blocked` (an apply still pending, an
+    /// apply backoff) sends nothing. Otherwise the difference has to clear
+    /// the adjust or finetune threshold once its interval has passed, and a
+    /// difference inside the deadband has to hold for `deadband-ms` first.
+    pub fn decide(&mut self, now: Instant, applied: u16, target: u16, burst: bool, force: bool, blocked: bool) -> ApplyDecision {
+        let s = &self.settings;
+        let diff = applied.abs_diff(target);
+
+        // A small difference has to hold for deadband-ms, so load hovering at a
+        // threshold does not rewrite the OD table back and forth
+        let outside_deadband = if diff >= s.deadband_mhz || diff < s.finetune_mhz {
+            self.deadband_since = None;
+            true
+        } else {
+            now.duration_since(*self.deadband_since.get_or_insert(now)) >= s.deadband
+        };
+        let should_adjust = now.duration_since(self.last_adjust) >= s.adjust_interval;
+        let should_finetune = now.duration_since(self.last_finetune) >= s.finetune_interval;
+
+        let wants_apply = !blocked && (
+            burst ||
+            (force && diff > 0) ||
+            (outside_deadband && should_adjust && diff >= s.adjust_mhz) ||
+            (outside_deadband && should_finetune && diff >= s.finetune_mhz)
+        );
+        if !wants_apply {
+            return ApplyDecision::Hold;
+        }
+        if !self.rate_limiter.allows(now) {
+            return ApplyDecision::RateLimited;
+        }
+        self.rate_limiter.record(now);
+        if diff >= s.adjust_mhz {
+            self.last_adjust = now;
+        }
+        if diff >= s.finetune_mhz {
+            self.last_finetune = now;
+        }
+        ApplyDecision::Apply
+    }
+
+    /// Time since the adjust and finetune thresholds last sent a frequency.
+    pub fn timers(&self, now: Instant) -> (Duration, Duration) {
+        (now.saturating_duration_since(self.last_adjust), now.saturating_duration_since(self.last_finetune))
+    }
+}
@@ -0,0 +1,314 @@
+//! Opening the GPU (or the mock), sampling its load, and writing the OD
+//! table and DPM levels.
+
+use std::{
+    fs::File,
+    io::{Error as IoError, Write},
+    os::fd::AsRawFd,
+    sync::Arc,
+};
+
+use libdrm_amdgpu_sys::AMDGPU::DeviceHandle;
+
+use crate::{
+    control,
+    mock::FaultInjector,
+    memclk::{self, DpmClock},
+    sampler::{BusyPercent, Dither, GpuMetrics, Metrics, SamplerKind},
+    od,
+    pci,
+    config::{BusyRegister, Config, Gpu},
+    safe_points::seed_safe_points,
+};
+
+pub(crate) const GRBM_STATUS_REG: u32 = 0x2004;
+
+pub(crate) const GPU_ACTIVE_BIT: u8 = 31;
+
+/// GRBM_STATUS2 reports compute (CPC) and other pipes that GUI_ACTIVE misses
+pub(crate) const GRBM_STATUS2_REG: u32 = 0x2002;
+
+pub(crate) const CPC_BUSY_BIT: u8 = 29;
+
+/// Where activity registers are read from: the real GPU or the fault-injection mock.
+pub(crate) enum RegisterSource {
+    Drm(DeviceHandle),
+    Mock(Arc<FaultInjector>),
+}
+
+impl RegisterSource {
+    fn read(&self, offset: u32) -> Result<u32, i32> {
+        match self {
+            Self::Drm(dev_handle) => dev_handle.read_mm_registers(offset),
+            Self::Mock(injector) => injector.read_register(offset),
+        }
+    }
+}
+
+/// The GPU's register source and sysfs directory, real or mocked.
+pub struct GpuDevice {
+    /// The render node must stay open for as long as the device handle is used
+    pub(crate) _card: Option<File>,
+    pub(crate) registers: RegisterSource,
+    pub sysfs_path: std::path::PathBuf,
+    /// Engine clock range reported by the driver (MHz), 0 under `--mock`
+    pub(crate) min_engine_clock: u64,
+    pub(crate) max_engine_clock: u64,
+}
+
+impl GpuDevice {
+    /// Fails while `offset` cannot be read, as during a GPU reset.
+    pub fn probe(&self, offset: u32) -> Result<(), String> {
+        self.registers.read(offset).map(|_| ()).map_err(|e| IoError::from_raw_os_error(e.abs()).to_string())
+    }
+}
+
+/// Opens the GPU on `pci_bus` (auto-detected when None), or sets up the
+/// mock backend starting at `lowest_freq`.
+pub fn open_gpu(pci_bus: Option<u8>, mock: Option<&Arc<FaultInjector>>, lowest_freq: u16) -> Result<GpuDevice, IoError> {
+    if let Some(injector) = mock {
+        injector.setup(lowest_freq)?;
+        return Ok(GpuDevice {
+            _card: None,
+            registers: RegisterSource::Mock(Arc::clone(injector)),
+            sysfs_path: injector.root.clone(),
+            min_engine_clock: 0,
+            max_engine_clock: 0,
+        });
+    }
+    let location = pci::resolve(pci_bus)?;
+    if pci_bus.is_none() {
+        println!("🔎 Found the BC-250 GPU at {location}");
+    }
+    let card = File::open(location.get_drm_render_path()?)?;
+    let (dev_handle, _, _) = DeviceHandle::init(card.as_raw_fd()).map_err(IoError::from_raw_os_error)?;
+    let info = dev_handle.device_info().map_err(IoError::from_raw_os_error)?;
+    let sysfs_path = dev_handle.get_sysfs_path().map_err(IoError::from_raw_os_error)?;
+    Ok(GpuDevice {
+        _card: Some(card),
+        registers: RegisterSource::Drm(dev_handle),
+        sysfs_path,
+        min_engine_clock: info.min_engine_clock / 1000,
+        max_engine_clock: info.max_engine_clock / 1000,
+    })
+}
+
+/// Opens the GPU and its OD file for a subcommand that writes clocks itself,
+/// refusing while the governor is running.
+/// Under `safe-points-from-card` the safe-points table is seeded from the card.
+pub fn open_gpu_exclusive(config: &mut Config, mock: Option<&Arc<FaultInjector>>)
+    -> Result<(GpuDevice, File), Box<dyn std::error::Error>> {
+    if mock.is_none() && control::call(std::path::Path::new(&config.control.socket), "status", serde_json::Value::Null).is_ok() {
+        return Err("the governor is running; stop it first".into());
+    }
+    let lowest = config.safe_points.iter().map(|p| p.frequency).min().unwrap_or_default();
+    let gpu = open_gpu(config.gpu.pci_bus, mock, lowest)?;
+    let od_path = gpu.sysfs_path.join("pp_od_clk_voltage");
+    seed_safe_points(config, &od_path)?;
+    let pp_file = std::fs::OpenOptions::new().write(true).open(od_path)?;
+    Ok((gpu, pp_file))
+}
+
+/// Register read to check that the GPU still answers: the first activity register.
+pub fn probe_register(config: &Config) -> u32 {
+    config.gpu.busy_registers.first().map_or(GRBM_STATUS_REG, |r| r.offset)
+}
+
+/// Reads the configured activity registers and returns (busy, compute busy).
+/// Unreadable registers count as idle and are only reported once.
+fn sample_busy(source: &RegisterSource, registers: &[BusyRegister], warned: &mut [bool], blend: Option<&mut Dither>) -> Option<(bool, bool)> {
+    let (mut busy, mut compute, mut read_any) = (false, false, false);
+    let (mut busy_weight, mut read_weight) = (0.0, 0.0);
+    for (reg, warned) in registers.iter().zip(warned.iter_mut()) {
+        match source.read(reg.offset) {
+            Ok(value) => {
+                let active = value & reg.mask != 0;
+                busy |= active;
+                compute |= active && reg.compute;
+                read_any = true;
+                read_weight += reg.weight;
+                if active {
+                    busy_weight += reg.weight;
+                }
+                *warned = false;
+            }
+            Err(e) if !*warned => {
+                eprintln!("⚠️  Failed to read {} (0x{:04x}): {}. Assuming idle.", reg.name, reg.offset, e);
+                *warned = true;
+            }
+            Err(_) => {}
+        }
+    }
+    // Registers that failed drop out of the blend instead of counting as idle
+    if let Some(dither) = blend.filter(|_| read_weight > 0.0) {
+        busy = dither.next(busy_weight / read_weight * 100.0);
+    }
+    read_any.then_some((busy, compute))
+}
+
+/// Reads one (busy, compute busy) sample per governor iteration from the
+/// configured `[gpu] sampler`. gpu_busy_percent and gpu_metrics carry no
+/// compute share.
+pub(crate) struct LoadSampler {
+    kind: SamplerKind,
+    source: RegisterSource,
+    registers: Vec<BusyRegister>,
+    warned: Vec<bool>,
+    busy_percent: Option<BusyPercent>,
+    gpu_metrics: Option<GpuMetrics>,
+    /// Turns the blended register load into samples with `[gpu] blend`
+    blend: Option<Dither>,
+    /// Set while "auto" reads gpu_busy_percent because no register could be read,
+    /// or while gpu_metrics reports no GFX activity and the registers are read
+    falling_back: bool,
+    /// gpu_busy_percent or gpu_metrics failed to read and was reported
+    read_warned: bool,
+}
+
+impl LoadSampler {
+    pub(crate) fn new(gpu: &Gpu, source: RegisterSource, sysfs_path: &std::path::Path) -> Self {
+        let (kind, registers) = (gpu.sampler, gpu.busy_registers.clone());
+        let busy_percent = match kind {
+            SamplerKind::BusyPercent | SamplerKind::Auto => BusyPercent::open(sysfs_path).map_err(|e| {
+                eprintln!("⚠️  {}/gpu_busy_percent unavailable: {}", sysfs_path.display(), e);
+            }).ok(),
+            _ => None,
+        };
+        let gpu_metrics = match kind {
+            SamplerKind::GpuMetrics => GpuMetrics::open(sysfs_path).map_err(|e| {
+                eprintln!("⚠️  {}/gpu_metrics unavailable: {}", sysfs_path.display(), e);
+            }).ok(),
+            _ => None,
+        };
+        let kind = match (kind, &busy_percent, &gpu_metrics) {
+            (SamplerKind::BusyPercent, None, _) | (SamplerKind::GpuMetrics, _, None) => {
+                eprintln!("⚠️  Sampling the activity registers instead");
+                SamplerKind::Registers
+            }
+            (kind, _, _) => kind,
+        };
+        if kind != SamplerKind::BusyPercent && registers.is_empty() && busy_percent.is_none() {
+            eprintln!("⚠️  No busy registers configured, the GPU will always look idle");
+        }
+        println!("📈 Load sampler: {:?}", kind);
+        let warned = vec![false; registers.len()];
+        let blend = gpu.blend.then(Dither::default);
+        Self { kind, source, registers, warned, busy_percent, gpu_metrics, blend, falling_back: false, read_warned: false }
+    }
+
+    /// Temperature and power from the latest gpu_metrics read, with the gpu-metrics sampler.
+    pub(crate) fn metrics(&self) -> Option<Metrics> {
+        self.gpu_metrics.as_ref().map(GpuMetrics::latest)
+    }
+
+    pub(crate) fn sample(&mut self) -> (bool, bool) {
+        if let Some(metrics) = self.gpu_metrics.as_mut() {
+            let sample = metrics.sample().unwrap_or_else(|e| {
+                if !self.read_warned {
+                    eprintln!("⚠️  Failed to read gpu_metrics: {}", e);
+                    self.read_warned = true;
+                }
+                None
+            });
+            if sample.is_some() == self.falling_back {
+                self.falling_back = sample.is_none();
+                if self.falling_back {
+                    eprintln!("⚠️  gpu_metrics reports no GFX activity, sampling the activity registers");
+                } else {
+                    println!("📈 gpu_metrics reports GFX activity again, sampling it");
+                }
+            }
+            return match sample {
+                Some(busy) => {
+                    self.read_warned = false;
+                    (busy, false)
+                }
+                None => sample_busy(&self.source, &self.registers, &mut self.warned, self.blend.as_mut()).unwrap_or_default(),
+            };
+        }
+        if self.kind != SamplerKind::BusyPercent {
+            let sample = sample_busy(&self.source, &self.registers, &mut self.warned, self.blend.as_mut());
+            if self.kind == SamplerKind::Registers || self.busy_percent.is_none() {
+                return sample.unwrap_or_default();
+            }
+            if sample.is_some() == self.falling_back {
+                self.falling_back = sample.is_none();
+                if self.falling_back {
+                    eprintln!("⚠️  No activity register can be read, sampling gpu_busy_percent");
+                } else {
+                    println!("📈 Activity registers readable again, sampling them");
+                }
+            }
+            if let Some(sample) = sample {
+                return sample;
+            }
+        }
+        match self.busy_percent.as_mut().map(BusyPercent::sample) {
+            Some(Ok(busy)) => {
+                self.read_warned = false;
+                (busy, false)
+            }
+            Some(Err(e)) if !self.read_warned => {
+                eprintln!("⚠️  Failed to read gpu_busy_percent: {}. Assuming idle.", e);
+                self.read_warned = true;
+                (false, false)
+            }
+            _ => (false, false),
+        }
+    }
+}
+
+/// Reads the frequency currently programmed in the OD_SCLK section.
+pub(crate) fn read_od_sclk(path: &std::path::Path) -> Option<u16> {
+    od::read(path).ok()?.sclk.first().copied()
+}
+
+/// Hands the OD table back to the driver's defaults ("r", then "c").
+pub(crate) fn reset_od(pp_file: &mut File, mock: Option<&FaultInjector>) -> Result<(), IoError> {
+    if let Some(injector) = mock {
+        if let Some(e) = injector.write_error() {
+            return Err(e);
+        }
+        return injector.reset();
+    }
+    pp_file.write_all(b"r")?;
+    pp_file.flush()?;
+    pp_file.write_all(b"c")?;
+    pp_file.flush()?;
+    Ok(())
+}
+
+/// Writes one frequency/voltage point to pp_od_clk_voltage and commits it.
+/// Under `--mock` the scenario's write faults apply instead.
+pub fn write_od(pp_file: &mut File, mock: Option<&FaultInjector>, freq: u16, vol: u16) -> Result<(), IoError> {
+    if let Some(injector) = mock {
+        if let Some(stall) = injector.setter_stall() {
+            std::thread::sleep(stall);
+        }
+        if let Some(e) = injector.write_error() {
+            return Err(e);
+        }
+        return injector.apply(freq, vol);
+    }
+    pp_file.write_all(format!("vc 0 {freq} {vol}").as_bytes())?;
+    pp_file.flush()?;
+    pp_file.write_all(b"c")?;
+    pp_file.flush()?;
+    Ok(())
+}
+
+/// Restricts a DPM clock to the given levels. Under `--mock` the mock file
+/// is rewritten the way the driver would report it.
+pub(crate) fn write_dpm(clock: &DpmLevels, mock: Option<&FaultInjector>, levels: &[usize]) -> Result<(), IoError> {
+    match mock {
+        Some(injector) => injector.apply_dpm(&clock.path, &clock.freqs, levels[0]),
+        None => memclk::write_levels(&clock.path, levels),
+    }
+}
+
+/// A governed DPM clock and the MHz of each of its levels.
+pub(crate) struct DpmLevels {
+    pub(crate) clock: DpmClock,
+    pub(crate) path: std::path::PathBuf,
+    pub(crate) freqs: Vec<u16>,
+}
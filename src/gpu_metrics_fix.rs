@@ -44,7 +44,7 @@ impl GpuUsageFix {
         // Read the current 128-byte metrics blob as baseline for the patched copy
         let mut raw = [0u8; 128];
         real_file.seek(SeekFrom::Start(0))?;
-        real_file.read(&mut raw)?;
+        let n = real_file.read(&mut raw)?;
 
         // Create the regular file that will shadow the sysfs path
         let mut patched_file = OpenOptions::new()
@@ -52,7 +52,7 @@ impl GpuUsageFix {
             .truncate(true)
             .write(true)
             .open(PATCHED_METRICS_PATH)?;
-        patched_file.write_all(&raw)?;
+        patched_file.write_all(&raw[..n])?;
         patched_file.flush()?;
 
         // Bind-mount the regular file over the sysfs path
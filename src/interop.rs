@@ -0,0 +1,176 @@
+use serde_yaml::{Mapping, Value};
+use std::{
+    fmt::Write as _,
+    io::{Error as IoError, ErrorKind},
+};
+
+/// Clock-table formats of other AMD tuning tools.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// LACT daemon config (/etc/lact/config.yaml)
+    Lact,
+    /// CoreCtrl profile.xml (extracted from a .ccpro archive)
+    CoreCtrl,
+    /// amdgpu-clocks state file (/etc/default/amdgpu-custom-state.cardN); export only
+    AmdgpuClocks,
+    /// Shell script writing pp_od_clk_voltage directly; export only
+    Script,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lact" => Some(Self::Lact),
+            "corectrl" => Some(Self::CoreCtrl),
+            "amdgpu-clocks" => Some(Self::AmdgpuClocks),
+            "script" => Some(Self::Script),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message.into())
+}
+
+/// Parses (frequency MHz, voltage mV) points from a LACT or CoreCtrl file.
+/// `gpu` selects a GPU entry in a LACT config holding several.
+pub fn import(format: Format, contents: &str, gpu: Option<&str>) -> Result<Vec<(u16, u16)>, IoError> {
+    let mut points = match format {
+        Format::Lact => import_lact(contents, gpu)?,
+        Format::CoreCtrl => import_corectrl(contents),
+        Format::AmdgpuClocks | Format::Script => {
+            return Err(IoError::new(ErrorKind::InvalidInput, "this format can only be exported"));
+        }
+    };
+    points.sort_unstable();
+    points.dedup_by_key(|p| p.0);
+    if points.is_empty() {
+        return Err(invalid("no frequency/voltage points found"));
+    }
+    Ok(points)
+}
+
+/// LACT stores the core clock range and voltage range per GPU; those become
+/// the two endpoints of the safe-point table.
+fn import_lact(contents: &str, gpu: Option<&str>) -> Result<Vec<(u16, u16)>, IoError> {
+    let root: Value = serde_yaml::from_str(contents).map_err(|e| invalid(e.to_string()))?;
+    let gpus = root.get("gpus").and_then(Value::as_mapping)
+        .ok_or_else(|| invalid("no 'gpus' section"))?;
+
+    let entry = match gpu {
+        Some(id) => gpus.get(id).ok_or_else(|| invalid(format!("GPU '{id}' not found")))?,
+        None if gpus.len() == 1 => gpus.values().next().unwrap(),
+        None => return Err(invalid("config has several GPUs, pick one with --gpu")),
+    };
+    // Newer LACT versions nest the clocks under clocks_configuration
+    let clocks = entry.get("clocks_configuration").unwrap_or(entry);
+    let field = |key: &str| clocks.get(key).and_then(Value::as_u64).and_then(|v| u16::try_from(v).ok());
+
+    let (min_clock, max_clock) = (field("min_core_clock"), field("max_core_clock"));
+    let (min_volt, max_volt) = (field("min_voltage"), field("max_voltage"));
+    let Some(max_clock) = max_clock else {
+        return Err(invalid("no max_core_clock set"));
+    };
+    let Some(max_volt) = max_volt else {
+        return Err(invalid("no max_voltage set; the voltage curve cannot be reconstructed"));
+    };
+
+    let mut points = vec![(max_clock, max_volt)];
+    if let (Some(min_clock), Some(min_volt)) = (min_clock, min_volt) {
+        points.push((min_clock, min_volt));
+    }
+    Ok(points)
+}
+
+/// Value of `name="..."` inside a single XML tag.
+fn xml_attr(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end = tag[start..].find('"')?;
+    Some(tag[start..start + end].to_string())
+}
+
+/// CoreCtrl writes each clock state or curve point as a tag carrying `freq`
+/// and `volt` attributes (e.g. `<STATE ... freq="1500" volt="850"/>`). Every
+/// active tag with both attributes is taken as a point.
+fn import_corectrl(contents: &str) -> Vec<(u16, u16)> {
+    contents.split('<')
+        .filter_map(|tag| tag.split('>').next())
+        .filter(|tag| xml_attr(tag, "active").as_deref() != Some("false"))
+        .filter_map(|tag| {
+            let freq = xml_attr(tag, "freq")?.parse().ok()?;
+            let volt = xml_attr(tag, "volt")?.parse().ok()?;
+            Some((freq, volt))
+        })
+        .collect()
+}
+
+/// Renders points as a `safe-points` block for this crate's config.
+pub fn to_safe_points_toml(points: &[(u16, u16)]) -> String {
+    let mut out = String::from("safe-points = [\n");
+    for (freq, volt) in points {
+        let _ = writeln!(out, "    {{ frequency = {freq}, voltage = {volt} }},");
+    }
+    out.push_str("]\n");
+    out
+}
+
+/// Renders a fixed clock as a boot-time fallback that works without the
+/// daemon. The BC-250 OD table holds a single point (`vc 0 <MHz> <mV>`), so
+/// a static setup pins one frequency/voltage pair.
+pub fn export_static(format: Format, freq: u16, volt: u16, pci_address: &str) -> Result<String, IoError> {
+    match format {
+        // amdgpu-clocks turns each OD_VDDC_CURVE line into a "vc" command
+        Format::AmdgpuClocks => Ok(format!(
+            "# Generated by bc-250-rust-governor: fixed {freq}MHz @ {volt}mV\n\
+             OD_VDDC_CURVE:\n\
+             0: {freq}MHz {volt}mV\n"
+        )),
+        Format::Script => Ok(format!(
+            "#!/bin/sh\n\
+             # Generated by bc-250-rust-governor: fixed {freq}MHz @ {volt}mV without the daemon\n\
+             set -e\n\
+             OD=/sys/bus/pci/devices/{pci_address}/pp_od_clk_voltage\n\
+             printf 'vc 0 {freq} {volt}' > \"$OD\"\n\
+             printf 'c' > \"$OD\"\n"
+        )),
+        Format::Lact | Format::CoreCtrl => Err(IoError::new(ErrorKind::InvalidInput, "not a static export format")),
+    }
+}
+
+/// Renders the safe-point table in the target tool's format.
+pub fn export(format: Format, points: &[(u16, u16)], gpu: &str) -> Result<String, IoError> {
+    let (Some(&(min_freq, min_volt)), Some(&(max_freq, max_volt))) = (points.first(), points.last()) else {
+        return Err(invalid("safe-points must not be empty"));
+    };
+
+    match format {
+        Format::Lact => {
+            // LACT only takes a clock and voltage range; it cannot express intermediate points
+            let mut clocks = Mapping::new();
+            clocks.insert("min_core_clock".into(), u64::from(min_freq).into());
+            clocks.insert("max_core_clock".into(), u64::from(max_freq).into());
+            clocks.insert("min_voltage".into(), u64::from(min_volt).into());
+            clocks.insert("max_voltage".into(), u64::from(max_volt).into());
+            let mut entry = Mapping::new();
+            entry.insert("performance_level".into(), "manual".into());
+            entry.insert("clocks_configuration".into(), Value::Mapping(clocks));
+            let mut gpus = Mapping::new();
+            gpus.insert(gpu.into(), Value::Mapping(entry));
+            let mut root = Mapping::new();
+            root.insert("gpus".into(), Value::Mapping(gpus));
+            serde_yaml::to_string(&root).map_err(|e| invalid(e.to_string()))
+        }
+        Format::CoreCtrl => {
+            let mut out = String::from("<AMD_PM_FV_STATE active=\"true\">\n <SCLK_STATES>\n");
+            for (index, (freq, volt)) in points.iter().enumerate() {
+                let _ = writeln!(out, "  <STATE active=\"true\" index=\"{index}\" freq=\"{freq}\" volt=\"{volt}\"/>");
+            }
+            out.push_str(" </SCLK_STATES>\n</AMD_PM_FV_STATE>\n");
+            Ok(out)
+        }
+        Format::AmdgpuClocks | Format::Script => {
+            Err(IoError::new(ErrorKind::InvalidInput, "static formats render a single point, use export_static"))
+        }
+    }
+}
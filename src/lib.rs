@@ -0,0 +1,88 @@
+//! GPU frequency and thermal governor for the AMD BC-250.
+//!
+//! The `bc-250-rust-governor` binary is a thin command line over this crate.
+//! [`run`] is the whole daemon: it governs every configured GPU until its
+//! shutdown flag is set, then hands the clocks and fans back. A supervisor
+//! can embed it instead of spawning the binary:
+//!
+//! ```no_run
+//! use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+//! use bc_250_rust_governor::RunOptions;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let shutdown = Arc::new(AtomicBool::new(false));
+//! let stop = Arc::clone(&shutdown);
+//! std::thread::spawn(move || {
+//!     // ... when the supervisor wants the GPU back:
+//!     stop.store(true, Ordering::SeqCst);
+//! });
+//! let options = RunOptions { config: Some("/etc/bc-250-rust-governor/config.toml".into()), ..Default::default() };
+//! bc_250_rust_governor::run(options, shutdown)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The building blocks are public too: [`config`] parses and validates the
+//! config file, [`safe_points`] interpolates voltages between safe points,
+//! [`governor`] holds the scaling policies and governor state, and
+//! [`thermal`] reads sensors and drives the fans.
+
+pub mod config;
+pub mod safe_points;
+pub mod thermal;
+pub mod governor;
+
+// Used by the binary's subcommands; not a stable API
+#[doc(hidden)]
+pub mod gpu;
+#[doc(hidden)]
+pub mod systemd;
+#[doc(hidden)]
+pub mod control;
+#[doc(hidden)]
+pub mod status;
+#[doc(hidden)]
+pub mod bench;
+#[doc(hidden)]
+pub mod interop;
+#[doc(hidden)]
+pub mod mock;
+#[doc(hidden)]
+pub mod dbus;
+#[doc(hidden)]
+pub mod replay;
+#[doc(hidden)]
+pub mod calibrate;
+#[doc(hidden)]
+pub mod fan_calibrate;
+#[doc(hidden)]
+pub mod stability;
+#[doc(hidden)]
+pub mod od;
+#[doc(hidden)]
+pub mod pci;
+#[doc(hidden)]
+pub mod tui;
+#[doc(hidden)]
+pub mod power;
+#[doc(hidden)]
+pub mod sampler;
+#[doc(hidden)]
+pub mod schedule;
+#[doc(hidden)]
+pub mod fdinfo;
+
+pub(crate) mod daemon;
+pub(crate) mod gpu_metrics_fix;
+pub(crate) mod alerts;
+pub(crate) mod apps;
+pub(crate) mod workload;
+pub(crate) mod cluster;
+pub(crate) mod gamemode;
+pub(crate) mod frametime;
+pub(crate) mod config_watch;
+pub(crate) mod memclk;
+pub(crate) mod cpufreq;
+
+pub use config::Config;
+pub use daemon::{run, RunOptions};
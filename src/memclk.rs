@@ -0,0 +1,60 @@
+use std::{fs, io::Error as IoError, path::Path};
+
+/// DPM clock domains governed next to SCLK.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DpmClock {
+    Mclk,
+    Fclk,
+}
+
+impl DpmClock {
+    pub const ALL: [DpmClock; 2] = [DpmClock::Mclk, DpmClock::Fclk];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DpmClock::Mclk => "mclk",
+            DpmClock::Fclk => "fclk",
+        }
+    }
+
+    /// sysfs file listing and forcing the clock's DPM levels.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            DpmClock::Mclk => "pp_dpm_mclk",
+            DpmClock::Fclk => "pp_dpm_fclk",
+        }
+    }
+}
+
+/// Parses the MHz of each level from pp_dpm_* contents ("0: 400Mhz *").
+pub fn parse_levels(text: &str) -> Vec<u16> {
+    text.lines()
+        .filter_map(|line| {
+            let (_, freq) = line.split_once(':')?;
+            freq.split_whitespace().next()?.trim_end_matches("Mhz").trim_end_matches("MHz").parse().ok()
+        })
+        .collect()
+}
+
+pub fn read_levels(path: &Path) -> Result<Vec<u16>, IoError> {
+    Ok(parse_levels(&fs::read_to_string(path)?))
+}
+
+/// Restricts the clock to the given level indices; all of them hands the
+/// choice back to the driver.
+pub fn write_levels(path: &Path, levels: &[usize]) -> Result<(), IoError> {
+    let mask: Vec<String> = levels.iter().map(|l| l.to_string()).collect();
+    fs::write(path, mask.join(" "))
+}
+
+/// sysfs file selecting how the driver picks DPM levels; forced levels
+/// only take effect while it reads `manual`.
+pub const PERFORMANCE_LEVEL_FILE: &str = "power_dpm_force_performance_level";
+
+pub fn read_performance_level(path: &Path) -> Result<String, IoError> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+pub fn write_performance_level(path: &Path, level: &str) -> Result<(), IoError> {
+    fs::write(path, level)
+}
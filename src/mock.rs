@@ -0,0 +1,358 @@
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::memclk;
+
+/// Tachometer RPM per raw PWM step of the mock fans (2040 RPM at full duty)
+const RPM_PER_PWM: u32 = 8;
+/// Raw PWM below which the mock fans stand still (20% duty), as many fans do
+const MIN_SPIN_PWM: u32 = 51;
+
+/// OD_RANGE of the BC-250's Cyan Skillfish GPU
+const OD_SCLK_RANGE: (u16, u16) = (1000, 2000);
+const OD_VDDC_RANGE: (u16, u16) = (700, 1129);
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FaultKind {
+    /// pp_od_clk_voltage writes fail with EBUSY
+    WriteEbusy,
+    /// The setter sleeps `value` ms before every write
+    SetterStall,
+    /// The GPU temperature sensor disappears
+    SensorDropout,
+    /// The GPU temperature reads `value` °C
+    Temperature,
+    /// Register reads and OD writes fail with ENODEV, as during a GPU reset
+    GpuReset,
+    /// The mock GPU is busy `value` (0.0-1.0) of the time
+    Load,
+    /// The board draws `value` W
+    Power,
+    /// OD writes succeed but the driver keeps its previous clock
+    WriteIgnored,
+    /// Register reads fail with EPERM, as when read_mm_registers is not allowed
+    RegisterDenied,
+    /// The fans stop turning: fanN_input reads 0 RPM whatever the PWM
+    FanStall,
+    /// Writes to the second fan's pwm2 fail, as when its controller hangs
+    FanWriteFail,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Fault {
+    pub kind: FaultKind,
+    #[serde(rename = "at-secs")]
+    pub at_secs: f32,
+    #[serde(rename = "duration-secs")]
+    pub duration_secs: f32,
+    #[serde(default)]
+    pub value: f32,
+}
+
+/// A fault-injection scenario for the mock backend.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Scenario {
+    /// The daemon shuts down after this many seconds (0 = run until stopped)
+    #[serde(rename = "duration-secs")]
+    pub duration_secs: f32,
+    /// Baseline busy ratio of the mock GPU
+    pub load: f32,
+    /// Baseline GPU temperature (°C)
+    pub temperature: f32,
+    /// Baseline board power (W)
+    pub power: f32,
+    /// Directory for the mock sysfs tree, left in place after the run; a per-process temporary directory when unset
+    pub root: Option<PathBuf>,
+    /// List three memory and fabric clock levels instead of the single one Cyan Skillfish exposes
+    #[serde(rename = "dpm-levels")]
+    pub dpm_levels: bool,
+    #[serde(rename = "fault")]
+    pub faults: Vec<Fault>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self { duration_secs: 0.0, load: 0.5, temperature: 55.0, power: 80.0, root: None, dpm_levels: false, faults: Vec::new() }
+    }
+}
+
+/// Replays a scenario against the mock GPU, OD file and hwmon tree.
+#[derive(Debug)]
+pub struct FaultInjector {
+    scenario: Scenario,
+    started: Instant,
+    samples: AtomicU64,
+    /// Offset of the first register read, which marks a new sampling pass
+    first_offset: AtomicU32,
+    /// Root of the mock sysfs: pp_od_clk_voltage and hwmon/
+    pub root: PathBuf,
+}
+
+impl FaultInjector {
+    pub fn load(path: &Path) -> Result<Self, IoError> {
+        let scenario: Scenario = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| IoError::new(ErrorKind::InvalidData, e.to_string()))?;
+        let root = scenario.root.clone().unwrap_or_else(|| std::env::temp_dir().join(format!("bc250-mock-{}", std::process::id())));
+        Ok(Self { scenario, started: Instant::now(), samples: AtomicU64::new(0), first_offset: AtomicU32::new(u32::MAX), root })
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.started.elapsed().as_secs_f32()
+    }
+
+    /// True once the scenario's duration has passed.
+    pub fn finished(&self) -> bool {
+        self.scenario.duration_secs > 0.0 && self.elapsed_secs() >= self.scenario.duration_secs
+    }
+
+    fn active(&self, kind: FaultKind) -> Option<&Fault> {
+        let now = self.elapsed_secs();
+        self.scenario.faults.iter()
+            .find(|f| f.kind == kind && now >= f.at_secs && now < f.at_secs + f.duration_secs)
+    }
+
+    pub fn od_path(&self) -> PathBuf {
+        self.root.join("pp_od_clk_voltage")
+    }
+
+    pub fn hwmon_root(&self) -> PathBuf {
+        self.root.join("hwmon")
+    }
+
+    pub fn cpufreq_root(&self) -> PathBuf {
+        self.root.join("cpufreq")
+    }
+
+    fn current_load(&self) -> f32 {
+        self.active(FaultKind::Load).map_or(self.scenario.load, |f| f.value).clamp(0.0, 1.0)
+    }
+
+    /// Writes gpu_busy_percent and gpu_metrics for the current load.
+    fn write_activity(&self, temperature: Option<f32>, power: f32) -> Result<(), IoError> {
+        let load = self.current_load();
+        fs::write(self.root.join("gpu_busy_percent"), format!("{}\n", (load * 100.0).round() as u32))?;
+        fs::write(self.root.join("gpu_metrics"), metrics_blob(load, temperature, power))
+    }
+
+    /// Creates the mock OD, DPM, performance level, gpu_busy_percent and gpu_metrics files, a cpufreq policy and an hwmon tree
+    /// with an amdgpu sensor (the edge channel only, as on Cyan Skillfish) and two nct6687 PWM channels with tachometers, so the thermal
+    /// code runs unmodified.
+    pub fn setup(&self, initial_freq: u16) -> Result<(), IoError> {
+        fs::create_dir_all(&self.root)?;
+        let initial_freq = if initial_freq == 0 { OD_SCLK_RANGE.0 } else { initial_freq };
+        fs::write(self.od_path(), od_table(initial_freq, OD_VDDC_RANGE.0))?;
+
+        let (mclk, fclk): (&[u16], &[u16]) = if self.scenario.dpm_levels {
+            (&[400, 1000, 1750], &[400, 800, 1200])
+        } else {
+            (&[1750], &[1200])
+        };
+        fs::write(self.root.join("pp_dpm_mclk"), dpm_table(mclk, 0))?;
+        fs::write(self.root.join("pp_dpm_fclk"), dpm_table(fclk, 0))?;
+        fs::write(self.root.join(memclk::PERFORMANCE_LEVEL_FILE), "auto\n")?;
+        self.write_activity(Some(self.scenario.temperature), self.scenario.power)?;
+
+        let cpu = self.cpufreq_root().join("policy0");
+        fs::create_dir_all(&cpu)?;
+        fs::write(cpu.join("scaling_min_freq"), "400000\n")?;
+        fs::write(cpu.join("scaling_max_freq"), "3500000\n")?;
+
+        let gpu = self.hwmon_root().join("hwmon0");
+        fs::create_dir_all(&gpu)?;
+        fs::write(gpu.join("name"), "amdgpu\n")?;
+        // amdgpu hides the junction and memory channels (temp2, temp3) on APUs such as Cyan Skillfish,
+        // and every tempN_crit/tempN_max, so the thresholds stay at 85°C/95°C
+        fs::write(gpu.join("temp1_label"), "edge\n")?;
+        write_gpu_temperature(&gpu, Some(self.scenario.temperature));
+        fs::write(gpu.join("power1_average"), format!("{}\n", (self.scenario.power * 1_000_000.0) as u64))?;
+
+        let fan = self.hwmon_root().join("hwmon1");
+        fs::create_dir_all(&fan)?;
+        fs::write(fan.join("name"), "nct6687\n")?;
+        for pwm in ["pwm1", "pwm2"] {
+            fs::write(fan.join(pwm), "128\n")?;
+            fs::write(fan.join(format!("{pwm}_enable")), "5\n")?;
+        }
+        for input in ["fan1_input", "fan2_input"] {
+            fs::write(fan.join(input), format!("{}\n", 128 * RPM_PER_PWM))?;
+        }
+        println!("🧪 Mock backend in {} ({} faults scheduled)", self.root.display(), self.scenario.faults.len());
+        Ok(())
+    }
+
+    /// Mock activity register: all bits set while busy. Busy samples are
+    /// spread evenly to match the current load ratio; every register read
+    /// in one sampling pass sees the same state, and a pass starts each time
+    /// the first register read is read again.
+    pub fn read_register(&self, offset: u32) -> Result<u32, i32> {
+        if self.active(FaultKind::GpuReset).is_some() {
+            return Err(libc::ENODEV);
+        }
+        if self.active(FaultKind::RegisterDenied).is_some() {
+            return Err(libc::EPERM);
+        }
+        let load = self.current_load();
+        let first = match self.first_offset.compare_exchange(u32::MAX, offset, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => offset,
+            Err(first) => first,
+        };
+        if offset == first {
+            self.samples.fetch_add(1, Ordering::Relaxed);
+        }
+        let n = self.samples.load(Ordering::Relaxed).saturating_sub(1) as f64;
+        let busy = ((n + 1.0) * load as f64).floor() > (n * load as f64).floor();
+        Ok(if busy { u32::MAX } else { 0 })
+    }
+
+    /// Records a successful OD write the way the driver reports it.
+    pub fn apply(&self, freq: u16, vol: u16) -> Result<(), IoError> {
+        if self.active(FaultKind::WriteIgnored).is_some() {
+            return Ok(());
+        }
+        fs::write(self.od_path(), od_table(freq, vol))
+    }
+
+    /// Records an OD reset; the mock's default point is the bottom of its range.
+    pub fn reset(&self) -> Result<(), IoError> {
+        fs::write(self.od_path(), od_table(OD_SCLK_RANGE.0, OD_VDDC_RANGE.0))
+    }
+
+    /// Records a forced DPM level the way the driver reports it; like
+    /// amdgpu, it refuses masks outside the manual performance level.
+    pub fn apply_dpm(&self, path: &Path, freqs: &[u16], level: usize) -> Result<(), IoError> {
+        if let Some(e) = self.write_error() {
+            return Err(e);
+        }
+        if memclk::read_performance_level(&self.root.join(memclk::PERFORMANCE_LEVEL_FILE))? != "manual" {
+            return Err(IoError::from_raw_os_error(libc::EINVAL));
+        }
+        fs::write(path, dpm_table(freqs, level))
+    }
+
+    /// Error an OD write should fail with right now, if any.
+    pub fn write_error(&self) -> Option<IoError> {
+        if self.active(FaultKind::GpuReset).is_some() {
+            Some(IoError::from_raw_os_error(libc::ENODEV))
+        } else if self.active(FaultKind::WriteEbusy).is_some() {
+            Some(IoError::from_raw_os_error(libc::EBUSY))
+        } else {
+            None
+        }
+    }
+
+    /// How long the setter should stall before writing.
+    pub fn setter_stall(&self) -> Option<Duration> {
+        self.active(FaultKind::SetterStall).map(|f| Duration::from_millis(f.value as u64))
+    }
+
+    /// Drives the mock temperature, power, fan RPM and gpu_busy_percent
+    /// readings and logs faults as they start and end.
+    pub fn start_driver(self: Arc<Self>, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            let gpu = self.hwmon_root().join("hwmon0");
+            let power_input = self.hwmon_root().join("hwmon0").join("power1_average");
+            let fans = self.hwmon_root().join("hwmon1");
+            let mut was_active = vec![false; self.scenario.faults.len()];
+            while !shutdown.load(Ordering::SeqCst) {
+                let now = self.elapsed_secs();
+                for (fault, was) in self.scenario.faults.iter().zip(was_active.iter_mut()) {
+                    let active = now >= fault.at_secs && now < fault.at_secs + fault.duration_secs;
+                    if active != *was {
+                        println!("💉 Fault {:?} {} at {:.1}s (value {})",
+                            fault.kind, if active { "started" } else { "ended" }, now, fault.value);
+                        *was = active;
+                    }
+                }
+
+                let temp = match self.active(FaultKind::SensorDropout) {
+                    Some(_) => None,
+                    None => Some(self.active(FaultKind::Temperature).map_or(self.scenario.temperature, |f| f.value)),
+                };
+                write_gpu_temperature(&gpu, temp);
+                let power = self.active(FaultKind::Power).map_or(self.scenario.power, |f| f.value);
+                let _ = fs::write(&power_input, format!("{}\n", (power * 1_000_000.0) as u64));
+                let _ = self.write_activity(temp, power);
+                // A directory in place of pwm2 fails every write to it
+                let pwm2 = fans.join("pwm2");
+                match (self.active(FaultKind::FanWriteFail).is_some(), pwm2.is_dir()) {
+                    (true, false) => { let _ = fs::remove_file(&pwm2).and_then(|()| fs::create_dir(&pwm2)); }
+                    (false, true) => { let _ = fs::remove_dir(&pwm2).and_then(|()| fs::write(&pwm2, "128\n")); }
+                    _ => {}
+                }
+                let stalled = self.active(FaultKind::FanStall).is_some();
+                for channel in [1, 2] {
+                    let pwm: u32 = fs::read_to_string(fans.join(format!("pwm{channel}"))).ok()
+                        .and_then(|pwm| pwm.trim().parse().ok()).unwrap_or(0);
+                    let rpm = if stalled || pwm < MIN_SPIN_PWM { 0 } else { pwm * RPM_PER_PWM };
+                    let _ = fs::write(fans.join(format!("fan{channel}_input")), format!("{rpm}\n"));
+                }
+
+                if self.finished() {
+                    println!("🧪 Scenario finished after {:.1}s, shutting down", now);
+                    shutdown.store(true, Ordering::SeqCst);
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })
+    }
+
+    /// Removes the mock sysfs tree, unless the scenario placed it.
+    pub fn cleanup(&self) {
+        if self.scenario.root.is_none() {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// Writes the edge input of the mock amdgpu hwmon at `gpu`, the only
+/// channel an APU has, or removes it when the sensor dropped out.
+fn write_gpu_temperature(gpu: &Path, edge: Option<f32>) {
+    let input = gpu.join("temp1_input");
+    match edge {
+        Some(temp) => { let _ = fs::write(input, format!("{}\n", (temp * 1000.0) as i32)); }
+        None => { let _ = fs::remove_file(input); }
+    }
+}
+
+/// pp_od_clk_voltage contents as Cyan Skillfish prints them.
+fn od_table(freq: u16, vol: u16) -> String {
+    format!("OD_SCLK:\n0: {freq}Mhz *\nOD_VDDC:\n0: {vol}mV\nOD_RANGE:\nSCLK: {:>7}Mhz {:>10}Mhz\nVDDC: {:>7}mV  {:>10}mV\n",
+        OD_SCLK_RANGE.0, OD_SCLK_RANGE.1, OD_VDDC_RANGE.0, OD_VDDC_RANGE.1)
+}
+
+/// A gpu_metrics v2_2 blob, as APUs report it, with the fields the governor
+/// reads filled in and the rest left unset. Power saturates at 65.5W, the
+/// most a u16 of milliwatts holds.
+fn metrics_blob(load: f32, temperature: Option<f32>, power: f32) -> Vec<u8> {
+    const SIZE: u16 = 128;
+    let mut blob = vec![0xFF; SIZE as usize];
+    blob[0..2].copy_from_slice(&SIZE.to_le_bytes());
+    (blob[2], blob[3]) = (2, 2);
+    let mut set = |offset: usize, value: f32| blob[offset..offset + 2].copy_from_slice(&(value.round().min(65534.0) as u16).to_le_bytes());
+    if let Some(t) = temperature {
+        set(4, t * 100.0);
+    }
+    set(28, load * 10_000.0);
+    set(40, power * 1000.0);
+    blob
+}
+
+/// pp_dpm_* contents with `current` marked active.
+fn dpm_table(freqs: &[u16], current: usize) -> String {
+    freqs.iter().enumerate()
+        .map(|(i, f)| format!("{i}: {f}Mhz{}\n", if i == current { " *" } else { "" }))
+        .collect()
+}
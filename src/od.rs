@@ -0,0 +1,76 @@
+use std::{fs, io::Error as IoError, path::Path};
+
+/// What pp_od_clk_voltage reports: the current point, the ranges the driver
+/// accepts and, on GPUs that have one, the voltage curve.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OdTable {
+    /// OD_SCLK levels (MHz)
+    pub sclk: Vec<u16>,
+    /// OD_VDDC levels (mV)
+    pub vddc: Vec<u16>,
+    /// OD_VDDC_CURVE points (MHz, mV)
+    pub vddc_curve: Vec<(u16, u16)>,
+    /// OD_RANGE SCLK (MHz)
+    pub sclk_range: Option<(u16, u16)>,
+    /// OD_RANGE VDDC (mV)
+    pub vddc_range: Option<(u16, u16)>,
+}
+
+/// "1000Mhz", "700mV" or "1000" as a number.
+fn number(token: &str) -> Option<u16> {
+    token.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse().ok()
+}
+
+/// Parses every section the driver prints. Unknown sections and lines
+/// (e.g. the VDDC_CURVE_SCLK[n] ranges) are skipped.
+pub fn parse(text: &str) -> OdTable {
+    let mut table = OdTable::default();
+    let mut section = "";
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(name) = line.strip_suffix(':').filter(|n| n.starts_with("OD_")) {
+            section = name;
+            continue;
+        }
+        let Some((key, rest)) = line.split_once(':') else { continue };
+        let values: Vec<u16> = rest.split_whitespace().filter_map(number).collect();
+        match (section, key.trim(), values.as_slice()) {
+            ("OD_SCLK", _, &[freq, ..]) => table.sclk.push(freq),
+            ("OD_VDDC", _, &[volt, ..]) => table.vddc.push(volt),
+            ("OD_VDDC_CURVE", _, &[freq, volt, ..]) => table.vddc_curve.push((freq, volt)),
+            ("OD_RANGE", "SCLK", &[low, high, ..]) => table.sclk_range = Some((low, high)),
+            ("OD_RANGE", "VDDC", &[low, high, ..]) => table.vddc_range = Some((low, high)),
+            _ => {}
+        }
+    }
+    table
+}
+
+pub fn read(path: &Path) -> Result<OdTable, IoError> {
+    Ok(parse(&fs::read_to_string(path)?))
+}
+
+impl OdTable {
+    /// Whether the current point is the one a reset leaves behind, the bottom
+    /// of OD_RANGE, so nothing had modified the table.
+    pub fn at_driver_default(&self) -> bool {
+        match (self.sclk.first(), self.vddc.first(), self.sclk_range, self.vddc_range) {
+            (Some(&sclk), Some(&vddc), Some((sclk_low, _)), Some((vddc_low, _))) => (sclk, vddc) == (sclk_low, vddc_low),
+            _ => false,
+        }
+    }
+
+    /// Safe points the card itself suggests, ascending: its voltage curve,
+    /// else the bottom and top of OD_RANGE, else the current point alone.
+    pub fn safe_points(&self) -> Vec<(u16, u16)> {
+        let mut points = if self.vddc_curve.len() >= 2 {
+            self.vddc_curve.clone()
+        } else if let (Some((sclk_low, sclk_high)), Some((vddc_low, vddc_high))) = (self.sclk_range, self.vddc_range) {
+            vec![(sclk_low, vddc_low), (sclk_high, vddc_high)]
+        } else {
+            self.sclk.iter().copied().zip(self.vddc.iter().copied()).take(1).collect()
+        };
+        points.sort_unstable();
+        points.dedup_by_key(|p| p.0);
+        points
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::Path,
+};
+
+use libdrm_amdgpu_sys::PCI::BUS_INFO;
+
+const DRM_CLASS_ROOT: &str = "/sys/class/drm";
+
+const VENDOR_AMD: u32 = 0x1002;
+/// PCI device IDs of Cyan Skillfish, the BC-250's GPU
+const CYAN_SKILLFISH_IDS: [u32; 2] = [0x13fe, 0x143f];
+
+fn read_id(path: &Path) -> Option<u32> {
+    let id = fs::read_to_string(path).ok()?;
+    u32::from_str_radix(id.trim().trim_start_matches("0x"), 16).ok()
+}
+
+/// PCI address, vendor and device ID of every render node under `drm_root`.
+fn render_nodes(drm_root: &Path) -> Result<Vec<(BUS_INFO, u32, u32)>, IoError> {
+    let mut nodes = Vec::new();
+    let entries = fs::read_dir(drm_root)
+        .map_err(|e| IoError::new(e.kind(), format!("cannot list DRM devices in {}: {e}", drm_root.display())))?;
+    for entry in entries {
+        let path = entry?.path();
+        if !path.file_name().is_some_and(|n| n.to_string_lossy().starts_with("renderD")) {
+            continue;
+        }
+        let device = path.join("device");
+        let Some(location) = fs::canonicalize(&device).ok()
+            .and_then(|d| d.file_name()?.to_str()?.parse::<BUS_INFO>().ok()) else { continue };
+        if let (Some(vendor), Some(id)) = (read_id(&device.join("vendor")), read_id(&device.join("device"))) {
+            nodes.push((location, vendor, id));
+        }
+    }
+    nodes.sort_by_key(|&(location, _, _)| (location.domain, location.bus, location.dev, location.func));
+    Ok(nodes)
+}
+
+fn is_bc250(vendor: u32, id: u32) -> bool {
+    vendor == VENDOR_AMD && CYAN_SKILLFISH_IDS.contains(&id)
+}
+
+/// Finds the BC-250 GPU among the DRM render nodes by its Cyan Skillfish
+/// device ID, wherever it was enumerated.
+pub fn find_bc250(drm_root: &Path) -> Result<BUS_INFO, IoError> {
+    let nodes = render_nodes(drm_root)?;
+    let mut matches = nodes.iter().filter(|&&(_, vendor, id)| is_bc250(vendor, id));
+    match (matches.next(), matches.next()) {
+        (Some(&(location, _, _)), None) => Ok(location),
+        (Some(&(location, _, _)), Some(_)) => {
+            eprintln!("⚠️  Several Cyan Skillfish GPUs found, using {location}. Set [gpu] pci_bus to pick another");
+            Ok(location)
+        }
+        (None, _) => {
+            let found: Vec<String> = nodes.iter().map(|(location, vendor, id)| format!("{location} ({vendor:04x}:{id:04x})")).collect();
+            Err(IoError::new(ErrorKind::NotFound, format!(
+                "no BC-250 (Cyan Skillfish) GPU among the render nodes [{}]; set [gpu] pci_bus", found.join(", "))))
+        }
+    }
+}
+
+/// Every Cyan Skillfish GPU in the system, in PCI order.
+pub fn find_all() -> Result<Vec<BUS_INFO>, IoError> {
+    Ok(render_nodes(Path::new(DRM_CLASS_ROOT))?.into_iter()
+        .filter(|&(_, vendor, id)| is_bc250(vendor, id))
+        .map(|(location, _, _)| location)
+        .collect())
+}
+
+/// The configured bus, or the auto-detected GPU when unset.
+pub fn resolve(pci_bus: Option<u8>) -> Result<BUS_INFO, IoError> {
+    match pci_bus {
+        Some(bus) => Ok(BUS_INFO { domain: 0, bus, dev: 0, func: 0 }),
+        None => find_bc250(Path::new(DRM_CLASS_ROOT)),
+    }
+}
@@ -0,0 +1,87 @@
+use glob::glob;
+use std::{
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::governor::RollingWindow;
+
+/// power1_average of the amdgpu hwmon device under `hwmon_root`, falling
+/// back to power1_input on kernels that only report instantaneous power.
+pub fn find_power_input(hwmon_root: &Path) -> Option<PathBuf> {
+    let pattern = format!("{}/hwmon*", hwmon_root.display());
+    glob(&pattern).ok()?.flatten()
+        .filter(|dir| fs::read_to_string(dir.join("name")).is_ok_and(|n| n.trim() == "amdgpu"))
+        .flat_map(|dir| [dir.join("power1_average"), dir.join("power1_input")])
+        .find(|path| path.exists())
+}
+
+/// Board power in watts (hwmon reports microwatts).
+pub fn read_watts(path: &Path) -> Result<f32, IoError> {
+    let microwatts: u64 = fs::read_to_string(path)?.trim().parse().map_err(IoError::other)?;
+    Ok(microwatts as f32 / 1_000_000.0)
+}
+
+/// Caps the target frequency while the average board power over `window`
+/// stays above `cap`. Over the cap the ceiling is pulled down by
+/// `mhz_per_watt` per watt of excess, at most once per window so the
+/// average can follow the last step; under the cap it rises by
+/// `recover_mhz` per reading until it clears the top of the table.
+#[derive(Debug)]
+pub struct PowerBudget {
+    cap: f32,
+    mhz_per_watt: f32,
+    recover_mhz: f32,
+    span: Duration,
+    started: Instant,
+    readings: RollingWindow,
+    ceiling: Option<f32>,
+    /// When the ceiling last stepped down
+    last_step: Option<Instant>,
+}
+
+impl PowerBudget {
+    pub fn new(cap: f32, window: Duration, mhz_per_watt: f32, recover_mhz: f32) -> Self {
+        Self {
+            cap,
+            mhz_per_watt,
+            recover_mhz,
+            span: window,
+            started: Instant::now(),
+            readings: RollingWindow::new(window),
+            ceiling: None,
+            last_step: None,
+        }
+    }
+
+    /// Average power over the window (W).
+    pub fn average(&self) -> f32 {
+        self.readings.stats().avg
+    }
+
+    /// Feeds one reading and returns the frequency ceiling, None while power
+    /// is within budget. Nothing is capped until a full window has been seen.
+    /// The ceiling never drops below `min_freq`.
+    pub fn update(&mut self, now: Instant, watts: f32, freq: f32, min_freq: f32, max_freq: f32) -> Option<f32> {
+        self.readings.push(now, watts);
+        if now.duration_since(self.started) < self.span {
+            return self.ceiling;
+        }
+        let excess = self.average() - self.cap;
+        if excess > 0.0 {
+            // The average still holds readings from before the last step; stepping on it
+            // every reading would wind the ceiling down to the floor before power could drop
+            if self.last_step.is_none_or(|at| now.duration_since(at) >= self.span) {
+                let from = self.ceiling.unwrap_or(freq).min(freq);
+                self.ceiling = Some((from - excess * self.mhz_per_watt).max(min_freq));
+                self.last_step = Some(now);
+            }
+        } else if let Some(ceiling) = self.ceiling {
+            let raised = ceiling + self.recover_mhz;
+            self.ceiling = (raised < max_freq).then_some(raised);
+        }
+        self.ceiling
+    }
+}
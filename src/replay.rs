@@ -0,0 +1,266 @@
+use std::{
+    fmt::Arguments,
+    fs::File,
+    io::{BufWriter, Error as IoError, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::Config,
+    daemon::GovernorTuning,
+    governor::{ApplyDecision, GovernorPolicy, LoadTarget, PerformanceMode, PolicyInput, Scaler, ScalingSettings, OVERRUN_FACTOR},
+    sampler::Dither,
+};
+
+/// One recorded sample.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceSample {
+    /// Milliseconds since the recording started
+    pub at_ms: f64,
+    pub busy: bool,
+}
+
+/// A parsed trace.
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub samples: Vec<TraceSample>,
+    /// Frequencies the recorded governor sent to the setter
+    pub recorded_applies: usize,
+}
+
+/// Parses a trace as `--record` writes it: `<ms>,<event>,<values>` rows, of
+/// which replay uses the `sample` events, and `#` comment lines. Plain
+/// `<ms> <busy>` lines are samples too. `busy` is 0 or 1 as recorded; a
+/// fraction is dithered into busy and idle samples, so hand-written traces
+/// can give a load level.
+pub fn parse_trace(text: &str) -> Result<Trace, IoError> {
+    let mut dither = Dither::default();
+    let mut trace = Trace::default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || IoError::new(ErrorKind::InvalidData, format!("trace line {}: expected '<ms>,sample,<busy>,...' or '<ms> <busy>', got '{}'", number + 1, line));
+        let fields: Vec<&str> = if line.contains(',') { line.split(',').map(str::trim).collect() } else { line.split_whitespace().collect() };
+        let (at, busy) = match fields.as_slice() {
+            [at, busy] => (at, busy),
+            [at, "sample", busy, ..] => (at, busy),
+            [_, "apply", ..] => {
+                trace.recorded_applies += 1;
+                continue;
+            }
+            [_, _, ..] => continue,
+            _ => return Err(invalid()),
+        };
+        let (at_ms, busy): (f64, f32) = (at.parse().map_err(|_| invalid())?, busy.parse().map_err(|_| invalid())?);
+        if trace.samples.last().is_some_and(|s| s.at_ms > at_ms) || !(0.0..=1.0).contains(&busy) {
+            return Err(invalid());
+        }
+        trace.samples.push(TraceSample { at_ms, busy: dither.next(busy * 100.0) });
+    }
+    Ok(trace)
+}
+
+/// Writes what the live governor sees and does as a CSV trace for `replay`
+/// and bug reports: every raw sample with the decision it led to, and every
+/// apply with its outcome, timestamped in milliseconds on the monotonic
+/// clock since recording started. A write error stops the recording.
+pub struct TraceRecorder {
+    out: Option<BufWriter<File>>,
+    path: PathBuf,
+    started: Instant,
+}
+
+impl TraceRecorder {
+    pub fn create(path: &Path, sample_interval: Duration) -> Result<Self, IoError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "# bc-250-rust-governor trace, {}μs sample interval", sample_interval.as_micros())?;
+        writeln!(out, "# ms,sample,busy,busy_up,busy_down,burst,target_mhz | ms,apply,mhz | ms,applied,mhz,latency_us | ms,failed,mhz,error")?;
+        Ok(Self { out: Some(out), path: path.to_path_buf(), started: Instant::now() })
+    }
+
+    fn write(&mut self, at: Instant, event: &str, values: Arguments) {
+        let Some(out) = self.out.as_mut() else { return };
+        let ms = at.saturating_duration_since(self.started).as_secs_f64() * 1000.0;
+        if let Err(e) = writeln!(out, "{ms:.3},{event},{values}") {
+            eprintln!("⚠️  Writing the trace to {} failed, recording stopped: {}", self.path.display(), e);
+            self.out = None;
+        }
+    }
+
+    /// A raw busy sample, the busy ratios it left and the target they led to.
+    pub fn sample(&mut self, at: Instant, busy: bool, busy_up: f32, busy_down: f32, burst: bool, target: f32) {
+        self.write(at, "sample", format_args!("{},{:.3},{:.3},{},{:.0}", u8::from(busy), busy_up, busy_down, u8::from(burst), target));
+    }
+
+    /// A frequency sent to the setter.
+    pub fn apply(&mut self, at: Instant, freq: u16) {
+        self.write(at, "apply", format_args!("{freq}"));
+    }
+
+    pub fn applied(&mut self, at: Instant, freq: u16, latency_us: u64) {
+        self.write(at, "applied", format_args!("{freq},{latency_us}"));
+    }
+
+    pub fn failed(&mut self, at: Instant, freq: u16, error: &str) {
+        // The error is the last column; commas in it would only confuse spreadsheets
+        self.write(at, "failed", format_args!("{},{}", freq, error.replace(',', ";")));
+    }
+}
+
+impl Drop for TraceRecorder {
+    fn drop(&mut self) {
+        if let Some(out) = self.out.as_mut() {
+            let _ = out.flush();
+        }
+    }
+}
+
+/// The governor settings a replay runs with.
+pub struct ReplayOptions {
+    pub policy: Box<dyn GovernorPolicy>,
+    pub load_target: LoadTarget,
+    pub scaling: ScalingSettings,
+    pub min_freq: u16,
+    /// Top of the safe points, lowered by the profile's cap
+    pub ceiling: u16,
+    /// Print the summary only, not every decision
+    pub summary_only: bool,
+}
+
+/// What the replayed governor did over the whole trace.
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub duration: Duration,
+    pub samples: usize,
+    pub applies: u64,
+    pub bursts: u64,
+    pub rate_limited: u64,
+    /// Time-weighted frequency (MHz)
+    pub avg_freq: f32,
+    pub max_freq: u16,
+    /// Share of the trace spent at the ceiling
+    pub at_ceiling: f32,
+}
+
+/// Runs `trace` through the scaling path of the sampling loop on virtual
+/// time: the same [`Scaler`] step around the policy as the live governor.
+/// The setter is a mock that applies each frequency instantly and never
+/// fails, so no apply is ever pending or backed off. Modes, thermal limits,
+/// the emergency clamp, the power and boost budgets and deep idle are left
+/// out.
+pub fn run(trace: &[TraceSample], mut opts: ReplayOptions) -> ReplaySummary {
+    let origin = Instant::now();
+    let at = |ms: f64| origin + Duration::from_secs_f64(ms / 1000.0);
+    let mut scaler = Scaler::new(opts.scaling, origin);
+    let mut summary = ReplaySummary { samples: trace.len(), max_freq: opts.min_freq, ..Default::default() };
+
+    let (mut target, mut applied) = (f32::from(opts.min_freq), opts.min_freq);
+    let mut bursting = false;
+    let mut last_at: Option<Instant> = None;
+    let mut weighted_freq = 0.0f64;
+    let mut at_ceiling = Duration::ZERO;
+
+    for sample in trace {
+        let now = at(sample.at_ms);
+        let since_last = last_at.map_or(opts.scaling.sample_interval, |t| now.duration_since(t));
+        // Time spent at the frequency applied before this sample
+        if last_at.is_some() {
+            weighted_freq += since_last.as_secs_f64() * f64::from(applied);
+            if applied >= opts.ceiling {
+                at_ceiling += since_last;
+            }
+        }
+        last_at = Some(now);
+        let step = since_last.min(opts.scaling.sample_interval * OVERRUN_FACTOR);
+
+        let busy = scaler.sample(now, sample.busy, step);
+        if busy.burst && !bursting {
+            summary.bursts += 1;
+        }
+        bursting = busy.burst;
+        let (burst, busy_up, busy_down) = (busy.burst, busy.busy_up, busy.busy_down);
+
+        target = opts.policy.next_target(&PolicyInput {
+            target_freq: target,
+            busy_up,
+            busy_down,
+            burst,
+            burst_scale: 1.0,
+            load_target: opts.load_target,
+            dt_ms: busy.dt_ms,
+            fps: None,
+            gpu_temp: None,
+            cpu_temp: None,
+            mode: PerformanceMode::Normal,
+        }).clamp(f32::from(opts.min_freq), f32::from(opts.ceiling));
+
+        let next = target as u16;
+        match scaler.decide(now, applied, next, burst, false, false) {
+            ApplyDecision::Hold => continue,
+            ApplyDecision::RateLimited => {
+                summary.rate_limited += 1;
+                continue;
+            }
+            ApplyDecision::Apply => {}
+        }
+        if !opts.summary_only {
+            println!("{:>10.3}s  {:>4}MHz → {:>4}MHz  busy {:>3.0}%/{:>3.0}%{}", sample.at_ms / 1000.0, applied, next,
+                busy_up * 100.0, busy_down * 100.0, if burst { "  burst" } else { "" });
+        }
+        applied = next;
+        summary.applies += 1;
+        summary.max_freq = summary.max_freq.max(next);
+    }
+
+    if let (Some(first), Some(last)) = (trace.first(), last_at) {
+        summary.duration = last.duration_since(at(first.at_ms));
+    }
+    let secs = summary.duration.as_secs_f64();
+    if secs > 0.0 {
+        summary.avg_freq = (weighted_freq / secs) as f32;
+        summary.at_ceiling = (at_ceiling.as_secs_f64() / secs) as f32;
+    }
+    summary
+}
+
+/// Replays the trace at `trace` with the scaling settings of `config` and
+/// its `profile` (the startup profile when None), printing every write
+/// unless `summary_only`, then the summary.
+pub fn replay_file(trace: &Path, config: &Config, profile: Option<&str>, summary_only: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let recorded = parse_trace(&std::fs::read_to_string(trace)?)?;
+    let samples = recorded.samples;
+    let min_freq = config.safe_points.iter().map(|p| p.frequency).min().unwrap_or_default();
+    let max_freq = config.safe_points.iter().map(|p| p.frequency).max().unwrap_or_default();
+    let tuning = GovernorTuning::from_config(config, min_freq, max_freq);
+    let requested = profile.map_or_else(|| tuning.profile.clone(), str::to_string);
+    if !tuning.profiles.contains_key(&requested) {
+        return Err(format!("no profile '{}' in the config", requested).into());
+    }
+    let (profile_name, profile) = tuning.profile(&requested);
+    let policy = tuning.build_policy(&profile);
+    println!("⏪ Replaying {} samples from {} with the {} policy, profile {}", samples.len(), trace.display(),
+        policy.name(), profile_name);
+    let opts = ReplayOptions {
+        policy,
+        load_target: tuning.load_target_for(&profile, PerformanceMode::Normal).0,
+        scaling: tuning.scaling(),
+        min_freq,
+        ceiling: profile.max_frequency.map_or(max_freq, |cap| cap.clamp(min_freq, max_freq)),
+        summary_only,
+    };
+    let ceiling = opts.ceiling;
+    let result = run(&samples, opts);
+    let secs = result.duration.as_secs_f32();
+    println!("📈 {:.1}s, {} samples: {} applies ({:.1}/s), {} bursts, {} rate-limited",
+        secs, result.samples, result.applies, result.applies as f32 / secs.max(f32::EPSILON), result.bursts, result.rate_limited);
+    println!("📈 Frequency avg {:.0}MHz, max {}MHz, {:.0}% of the time at the {}MHz ceiling",
+        result.avg_freq, result.max_freq, result.at_ceiling * 100.0, ceiling);
+    if recorded.recorded_applies > 0 {
+        println!("📼 The recorded run made {} applies ({:.1}/s)", recorded.recorded_applies,
+            recorded.recorded_applies as f32 / secs.max(f32::EPSILON));
+    }
+    Ok(())
+}
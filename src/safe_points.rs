@@ -0,0 +1,109 @@
+//! Safe points: the frequency/voltage pairs the governor may program, and
+//! the voltage it writes for frequencies between them.
+
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    io::{Error as IoError, ErrorKind},
+};
+
+use crate::{
+    od,
+    config::{Config, validate_config},
+};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SafePoint {
+    pub frequency: u16,
+    pub voltage: u16,
+    /// Optional label, e.g. for `startup.safe-point`
+    pub name: Option<String>,
+}
+
+/// Safe points to hold during `calibrate` or `stability`: the given
+/// frequencies with interpolated voltages, or the whole table.
+pub fn select_points(config: &Config, frequencies: &[u16]) -> Vec<(u16, u16)> {
+    let table: BTreeMap<u16, u16> = config.safe_points.iter().map(|p| (p.frequency, p.voltage)).collect();
+    let mut points: Vec<(u16, u16)> = if frequencies.is_empty() {
+        table.iter().map(|(&f, &v)| (f, v)).collect()
+    } else {
+        frequencies.iter().filter_map(|&f| Some((f, interpolate_voltage(f, &table)?))).collect()
+    };
+    points.sort_unstable();
+    points.dedup_by_key(|p| p.0);
+    points
+}
+
+/// Interpolates voltage between safe-points for a given frequency.
+/// Returns None if safe_points is empty.
+pub fn interpolate_voltage(freq: u16, safe_points: &BTreeMap<u16, u16>) -> Option<u16> {
+    if safe_points.is_empty() {
+        return None;
+    }
+
+    // If freq is at or below the minimum safe-point, use its voltage
+    if let Some((&first_freq, &first_vol)) = safe_points.first_key_value() {
+        if freq <= first_freq {
+            return Some(first_vol);
+        }
+    }
+
+    // If freq is at or above the maximum safe-point, use its voltage
+    if let Some((&last_freq, &last_vol)) = safe_points.last_key_value() {
+        if freq >= last_freq {
+            return Some(last_vol);
+        }
+    }
+
+    // Find the two safe-points that bracket our frequency
+    let lower = safe_points.range(..=freq).next_back();
+    let upper = safe_points.range(freq..).next();
+
+    match (lower, upper) {
+        (Some((&f1, &v1)), Some((&f2, &v2))) if f1 != f2 => {
+            // Linear interpolation: v = v1 + (v2 - v1) * (freq - f1) / (f2 - f1)
+            let ratio = (freq - f1) as f32 / (f2 - f1) as f32;
+            let interpolated = v1 as f32 + ratio * (v2 as f32 - v1 as f32);
+            Some(interpolated.round() as u16)
+        }
+        (Some((_, &v)), _) => Some(v),
+        (_, Some((_, &v))) => Some(v),
+        _ => None,
+    }
+}
+
+/// Applies a voltage offset, clamped between `floor` and the highest safe-point
+/// voltage so an undervolt or trim can never leave the allowed range.
+pub fn apply_voltage_offset(voltage: u16, offset_mv: i16, floor: u16, safe_points: &BTreeMap<u16, u16>) -> u16 {
+    if offset_mv == 0 {
+        return voltage;
+    }
+    let ceil = safe_points.values().copied().max().unwrap_or(voltage);
+    let adjusted = i32::from(voltage) + i32::from(offset_mv);
+    adjusted.clamp(i32::from(floor), i32::from(ceil)) as u16
+}
+
+/// The lowest safe point, with its voltage offset like every other point's.
+pub fn lowest_safe_point(safe_points: &BTreeMap<u16, u16>, offset_mv: i16, floor: u16) -> Option<(u16, u16)> {
+    let (&freq, &voltage) = safe_points.first_key_value()?;
+    Some((freq, apply_voltage_offset(voltage, offset_mv, floor, safe_points)))
+}
+
+/// Replaces the safe-points table with the card's OD table under
+/// `safe-points-from-card`. Those are the driver's limits rather than
+/// validated points, so it only happens when asked for.
+pub fn seed_safe_points(config: &mut Config, od_path: &std::path::Path) -> Result<(), IoError> {
+    if !config.safe_points_from_card {
+        return Ok(());
+    }
+    let points = od::read(od_path)?.safe_points();
+    if points.is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidData,
+            format!("safe-points-from-card is set and {} lists no usable points", od_path.display())));
+    }
+    let listed: Vec<String> = points.iter().map(|(f, v)| format!("{f}MHz@{v}mV")).collect();
+    println!("📋 safe-points-from-card: using the card's OD table: {}", listed.join(", "));
+    config.safe_points = points.into_iter().map(|(frequency, voltage)| SafePoint { frequency, voltage, name: None }).collect();
+    validate_config(config)
+}
@@ -0,0 +1,163 @@
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    io::{Error as IoError, ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// The driver averages gpu_busy_percent and gpu_metrics itself; re-reading them faster only costs syscalls
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+/// gpu_metrics fields the GPU does not provide read as all ones
+const NOT_PROVIDED: u16 = 0xFFFF;
+
+/// Where the governor reads GPU activity from.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SamplerKind {
+    /// The activity registers, through read_mm_registers
+    Registers,
+    /// amdgpu's gpu_busy_percent sysfs file
+    BusyPercent,
+    /// The binary gpu_metrics sysfs file, which also carries temperature and power
+    GpuMetrics,
+    /// The registers, falling back to gpu_busy_percent whenever none can be read
+    Auto,
+}
+
+/// Turns an activity figure into busy/idle samples: each sample adds the
+/// activity to a credit and is busy whenever the credit reaches 100%, so
+/// the share of busy samples follows the figure.
+#[derive(Debug, Default)]
+pub struct Dither {
+    /// Hundredths of a percent
+    credit: u32,
+}
+
+impl Dither {
+    pub fn next(&mut self, percent: f32) -> bool {
+        self.credit += (percent.clamp(0.0, 100.0) * 100.0).round() as u32;
+        let busy = self.credit >= 10_000;
+        if busy {
+            self.credit -= 10_000;
+        }
+        busy
+    }
+}
+
+/// Samples amdgpu's gpu_busy_percent.
+#[derive(Debug)]
+pub struct BusyPercent {
+    path: PathBuf,
+    percent: u32,
+    read_at: Option<Instant>,
+    dither: Dither,
+}
+
+impl BusyPercent {
+    /// Opens `gpu_busy_percent` under the GPU's sysfs directory, failing if it cannot be read.
+    pub fn open(sysfs_path: &Path) -> Result<Self, IoError> {
+        let mut reader = Self { path: sysfs_path.join("gpu_busy_percent"), percent: 0, read_at: None, dither: Dither::default() };
+        reader.refresh()?;
+        Ok(reader)
+    }
+
+    fn refresh(&mut self) -> Result<(), IoError> {
+        let text = fs::read_to_string(&self.path)?;
+        let percent: u32 = text.trim().parse().map_err(IoError::other)?;
+        self.percent = percent.min(100);
+        self.read_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// One busy/idle sample.
+    pub fn sample(&mut self) -> Result<bool, IoError> {
+        if self.read_at.is_none_or(|t| t.elapsed() >= REFRESH_INTERVAL) {
+            self.refresh()?;
+        }
+        Ok(self.dither.next(self.percent as f32))
+    }
+}
+
+/// What one gpu_metrics read reports; None where the GPU leaves a field unset.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// Average GFX activity (%)
+    pub gfx_activity: Option<f32>,
+    /// GFX (APU) or edge (dGPU) temperature (°C)
+    pub temperature: Option<f32>,
+    /// Average socket power (W)
+    pub watts: Option<f32>,
+}
+
+fn field(blob: &[u8], offset: usize) -> Option<u16> {
+    let value = u16::from_le_bytes(blob.get(offset..offset + 2)?.try_into().ok()?);
+    (value != NOT_PROVIDED).then_some(value)
+}
+
+/// Parses a gpu_metrics blob. Format 1 (dGPUs, v1_1 and later) reports
+/// °C, % and W; format 2 (APUs such as Cyan Skillfish, v2_1 and later)
+/// reports centi-°C, centi-% and mW. Older layouts put the timestamp
+/// first and are not supported.
+pub fn parse_metrics(blob: &[u8]) -> Result<Metrics, IoError> {
+    let unsupported = |what: String| IoError::new(ErrorKind::Unsupported, what);
+    let (&format, &content) = match blob {
+        [_, _, format, content, ..] => (format, content),
+        _ => return Err(IoError::new(ErrorKind::InvalidData, format!("gpu_metrics is only {} bytes", blob.len()))),
+    };
+    let (temperature, activity, power, scale, power_scale) = match (format, content) {
+        (1, 1..) => (4, 16, 22, 1.0, 1.0),
+        (2, 1..) => (4, 28, 40, 100.0, 1000.0),
+        _ => return Err(unsupported(format!("gpu_metrics v{}_{} is not supported", format, content))),
+    };
+    if blob.len() < power + 2 {
+        return Err(IoError::new(ErrorKind::InvalidData, format!("gpu_metrics v{}_{} is only {} bytes", format, content, blob.len())));
+    }
+    Ok(Metrics {
+        gfx_activity: field(blob, activity).map(|v| f32::from(v) / scale),
+        temperature: field(blob, temperature).map(|v| f32::from(v) / scale),
+        watts: field(blob, power).map(|v| f32::from(v) / power_scale),
+    })
+}
+
+/// Samples the GFX activity in gpu_metrics and keeps the temperature and
+/// power of the latest read for the governor.
+#[derive(Debug)]
+pub struct GpuMetrics {
+    /// Opened before the MangoHUD fix bind-mounts its copy over the path, so reads stay on the driver's file
+    file: File,
+    latest: Metrics,
+    read_at: Option<Instant>,
+    dither: Dither,
+}
+
+impl GpuMetrics {
+    /// Opens `gpu_metrics` under the GPU's sysfs directory, failing if it cannot be parsed.
+    pub fn open(sysfs_path: &Path) -> Result<Self, IoError> {
+        let file = File::open(sysfs_path.join("gpu_metrics"))?;
+        let mut reader = Self { file, latest: Metrics::default(), read_at: None, dither: Dither::default() };
+        reader.refresh()?;
+        Ok(reader)
+    }
+
+    fn refresh(&mut self) -> Result<(), IoError> {
+        let mut blob = Vec::with_capacity(256);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut blob)?;
+        self.latest = parse_metrics(&blob)?;
+        self.read_at = Some(Instant::now());
+        Ok(())
+    }
+
+    pub fn latest(&self) -> Metrics {
+        self.latest
+    }
+
+    /// One busy/idle sample, or None while the GPU does not report its GFX activity.
+    pub fn sample(&mut self) -> Result<Option<bool>, IoError> {
+        if self.read_at.is_none_or(|t| t.elapsed() >= REFRESH_INTERVAL) {
+            self.refresh()?;
+        }
+        Ok(self.latest.gfx_activity.map(|percent| self.dither.next(percent)))
+    }
+}
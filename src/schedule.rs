@@ -0,0 +1,69 @@
+/// Lower-case day names as written in `days`, Sunday first like `tm_wday`.
+pub const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A time window in which a profile applies.
+#[derive(Debug, Clone)]
+pub struct Window {
+    /// Minutes after midnight
+    pub from: u16,
+    pub to: u16,
+    /// Bit per day (bit 0 = Sunday) the window starts on
+    pub days: u8,
+    pub profile: String,
+}
+
+impl Window {
+    /// True at `minute` after midnight on `weekday` (0 = Sunday). A window
+    /// whose end is before its start runs past midnight into the next day,
+    /// and one that ends where it starts covers the whole day.
+    pub fn contains(&self, weekday: u8, minute: u16) -> bool {
+        let starts_on = |day: u8| self.days & (1 << (day % 7)) != 0;
+        if self.from < self.to {
+            starts_on(weekday) && (self.from..self.to).contains(&minute)
+        } else if self.from > self.to {
+            (starts_on(weekday) && minute >= self.from) || (starts_on(weekday + 6) && minute < self.to)
+        } else {
+            starts_on(weekday)
+        }
+    }
+}
+
+/// "HH:MM" as minutes after midnight.
+pub fn parse_time(text: &str) -> Option<u16> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let (hours, minutes): (u16, u16) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Day names as a bit mask; an empty list means every day.
+pub fn parse_days(names: &[String]) -> Option<u8> {
+    if names.is_empty() {
+        return Some(0x7F);
+    }
+    names.iter().try_fold(0u8, |mask, name| {
+        let day = DAY_NAMES.iter().position(|d| name.eq_ignore_ascii_case(d))?;
+        Some(mask | 1 << day)
+    })
+}
+
+/// The first window containing the local time, if any.
+pub fn active(windows: &[Window]) -> Option<&Window> {
+    let (weekday, minute) = local_time()?;
+    windows.iter().find(|w| w.contains(weekday, minute))
+}
+
+/// Local weekday (0 = Sunday) and minute of the day.
+fn local_time() -> Option<(u8, u16)> {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the call; localtime_r is the thread-safe variant
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return None;
+    }
+    Some((tm.tm_wday as u8, (tm.tm_hour * 60 + tm.tm_min) as u16))
+}
+
+/// "HH:MM" for minutes after midnight.
+pub fn format_time(minutes: u16) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
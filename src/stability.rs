@@ -0,0 +1,196 @@
+use std::{
+    fs::File,
+    io::{Error as IoError, ErrorKind, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::calibrate;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// The point is written again this often, as the governor would keep doing
+const APPLY_INTERVAL: Duration = Duration::from_secs(10);
+/// After an overheat the next point waits until the GPU is this far below the limit (°C)
+const COOLDOWN_MARGIN: f32 = 10.0;
+
+/// Options for the `stability` subcommand.
+#[derive(Debug)]
+pub struct StabilityOptions {
+    /// (MHz, mV) points to hold, ascending by frequency
+    pub points: Vec<(u16, u16)>,
+    /// How long each point is held
+    pub duration: Duration,
+    /// Load run while each point is held (through `sh -c`)
+    pub command: Option<String>,
+    /// A point fails once the GPU reaches this temperature (°C)
+    pub max_temp: f32,
+    /// CSV file every sample is appended to
+    pub log: Option<PathBuf>,
+}
+
+/// One reading taken while a point is held.
+#[derive(Debug, Default)]
+pub struct Sample {
+    pub temperature: Option<f32>,
+    pub watts: Option<f32>,
+}
+
+/// What was seen while one point was held.
+#[derive(Debug, Default)]
+struct PointReport {
+    freq: u16,
+    volt: u16,
+    max_temp: Option<f32>,
+    watts_sum: f32,
+    watts_count: u32,
+    peak_watts: Option<f32>,
+    apply_failures: u32,
+    failure: Option<String>,
+}
+
+impl PointReport {
+    fn record(&mut self, sample: &Sample) {
+        if let Some(t) = sample.temperature {
+            self.max_temp = Some(self.max_temp.map_or(t, |m| m.max(t)));
+        }
+        if let Some(w) = sample.watts {
+            self.watts_sum += w;
+            self.watts_count += 1;
+            self.peak_watts = Some(self.peak_watts.map_or(w, |m| m.max(w)));
+        }
+    }
+
+    fn avg_watts(&self) -> Option<f32> {
+        (self.watts_count > 0).then(|| self.watts_sum / self.watts_count as f32)
+    }
+
+    fn passed(&self) -> bool {
+        self.failure.is_none() && self.apply_failures == 0
+    }
+}
+
+fn format_opt(value: Option<f32>, unit: &str) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| format!("{v:.1}{unit}"))
+}
+
+/// Locks each point for `duration` under load, sampling temperature and
+/// power every second and re-applying the point every 10s. A point fails
+/// when the GPU stops answering, the load fails, `max_temp` is reached or
+/// any apply fails; after overheating, the next point waits at the lowest
+/// point for the GPU to cool down. The lowest point is written back when
+/// done. Returns whether every point held passed.
+pub fn run(
+    opts: &StabilityOptions,
+    apply: &mut dyn FnMut(u16, u16) -> Result<(), IoError>,
+    probe: &dyn Fn() -> Result<(), String>,
+    sample: &mut dyn FnMut() -> Sample,
+) -> Result<bool, IoError> {
+    let Some(&(base_freq, base_mv)) = opts.points.first() else {
+        return Err(IoError::new(ErrorKind::InvalidInput, "no safe points to test"));
+    };
+    let interrupted = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&interrupted))?;
+    }
+    let mut log = opts.log.as_ref().map(File::create).transpose()?;
+    if let Some(file) = log.as_mut() {
+        writeln!(file, "elapsed_secs,frequency,voltage,temperature,power,apply_failures")?;
+    }
+    let started = Instant::now();
+
+    let mut reports: Vec<PointReport> = Vec::new();
+    for &(freq, volt) in &opts.points {
+        println!("🔒 {}MHz @ {}mV: holding for {}s", freq, volt, opts.duration.as_secs());
+        let mut report = PointReport { freq, volt, ..Default::default() };
+        let outcome = match apply(freq, volt) {
+            Err(e) => {
+                report.apply_failures += 1;
+                Err(format!("apply failed: {e}"))
+            }
+            Ok(()) => {
+                let (mut last_sample, mut last_apply) = (None::<Instant>, Instant::now());
+                let mut tick = || -> Result<(), String> {
+                    if last_apply.elapsed() >= APPLY_INTERVAL {
+                        last_apply = Instant::now();
+                        if let Err(e) = apply(freq, volt) {
+                            report.apply_failures += 1;
+                            println!("⚠️  {}MHz @ {}mV: apply failed: {}", freq, volt, e);
+                        }
+                    }
+                    if last_sample.is_some_and(|t| t.elapsed() < SAMPLE_INTERVAL) {
+                        return Ok(());
+                    }
+                    last_sample = Some(Instant::now());
+                    let reading = sample();
+                    report.record(&reading);
+                    if let Some(file) = log.as_mut() {
+                        let field = |v: Option<f32>| v.map_or_else(String::new, |v| format!("{v:.1}"));
+                        writeln!(file, "{:.1},{},{},{},{},{}", started.elapsed().as_secs_f32(), freq, volt,
+                            field(reading.temperature), field(reading.watts), report.apply_failures)
+                            .map_err(|e| format!("cannot write log: {e}"))?;
+                    }
+                    match reading.temperature {
+                        Some(t) if t >= opts.max_temp => Err(format!("reached {:.1}°C", t)),
+                        _ => Ok(()),
+                    }
+                };
+                calibrate::hold(opts.command.as_deref(), opts.duration, probe, &interrupted, &mut tick)
+            }
+        };
+
+        match outcome {
+            Ok(()) if report.passed() => println!("✅ {}MHz @ {}mV passed", freq, volt),
+            Ok(()) => println!("❌ {}MHz @ {}mV: {} apply failures", freq, volt, report.apply_failures),
+            Err(reason) => {
+                println!("❌ {}MHz @ {}mV: {}", freq, volt, reason);
+                if reason.starts_with("GPU stopped responding") {
+                    calibrate::recover(probe)?;
+                }
+                report.failure = Some(reason);
+            }
+        }
+        let overheated = report.max_temp.is_some_and(|t| t >= opts.max_temp);
+        reports.push(report);
+
+        if overheated {
+            println!("🌡️  Cooling down below {:.1}°C before the next point", opts.max_temp - COOLDOWN_MARGIN);
+            if let Err(e) = apply(base_freq, base_mv) {
+                eprintln!("⚠️  Failed to drop to {}MHz @ {}mV: {}", base_freq, base_mv, e);
+            }
+            while !interrupted.load(Ordering::SeqCst)
+                && sample().temperature.is_some_and(|t| t >= opts.max_temp - COOLDOWN_MARGIN) {
+                std::thread::sleep(SAMPLE_INTERVAL);
+            }
+        }
+
+        if interrupted.load(Ordering::SeqCst) {
+            println!("🛑 Interrupted, stopping after {}MHz", freq);
+            break;
+        }
+    }
+
+    if let Err(e) = apply(base_freq, base_mv) {
+        eprintln!("⚠️  Failed to restore {}MHz @ {}mV: {}", base_freq, base_mv, e);
+    }
+
+    println!();
+    println!("{:>9} {:>8} {:>9} {:>10} {:>11} {:>15}  result", "frequency", "voltage", "max temp", "avg power", "peak power", "apply failures");
+    for r in &reports {
+        let result = match (&r.failure, r.passed()) {
+            (_, true) => "PASS".to_string(),
+            (Some(reason), _) => format!("FAIL ({reason})"),
+            (None, _) => "FAIL (apply failures)".to_string(),
+        };
+        println!("{:>6}MHz {:>6}mV {:>9} {:>10} {:>11} {:>15}  {}",
+            r.freq, r.volt, format_opt(r.max_temp, "°C"), format_opt(r.avg_watts(), "W"),
+            format_opt(r.peak_watts, "W"), r.apply_failures, result);
+    }
+    if let Some(path) = &opts.log {
+        println!("💾 Samples written to {}", path.display());
+    }
+    Ok(reports.len() == opts.points.len() && reports.iter().all(PointReport::passed))
+}
@@ -0,0 +1,299 @@
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    ffi::CString,
+    fs::{self, OpenOptions},
+    io::{Error as IoError, ErrorKind, Write},
+    os::unix::{ffi::OsStrExt, fs::{FileTypeExt, OpenOptionsExt}},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::fdinfo::ClientUsage;
+use crate::governor::{GovernorStats, RollingStats};
+use crate::thermal::FanResidency;
+use crate::workload::Fingerprint;
+
+pub const DEFAULT_STATUS_PATH: &str = "/run/bc250-governor/status.json";
+pub const DEFAULT_FIFO_PATH: &str = "/run/bc250-governor/status.fifo";
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ThermalSnapshot {
+    /// Hottest GPU channel
+    pub amdgpu: f32,
+    /// Every GPU channel (edge, junction, mem) by sensor name
+    pub gpu_channels: BTreeMap<String, f32>,
+    pub cpu: f32,
+    pub max: f32,
+    pub gpu_max: f32,
+    /// PWM duty and tachometer reading of the first driven fan, null without one
+    pub fan_pwm_percent: Option<u8>,
+    pub fan_rpm: Option<u32>,
+    /// A driven fan reads 0 RPM at a PWM duty where it should turn
+    pub fan_stalled: bool,
+    /// Every fan driven by a curve, in config order
+    pub fans: Vec<FanStatus>,
+    /// Seconds each fan has spent in each 20% PWM band this session
+    pub fan_residency: Vec<FanResidency>,
+}
+
+/// One fan as the thermal thread last saw it.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct FanStatus {
+    pub name: String,
+    /// Raw PWM value (0-255)
+    pub pwm: Option<u8>,
+    pub pwm_percent: Option<u8>,
+    pub rpm: Option<u32>,
+    /// Reading of the curve's temperature source (°C), null if the sensor is missing
+    pub temperature: Option<f32>,
+    /// Reading of the curve's ambient sensor (°C), null without one
+    pub ambient: Option<f32>,
+    pub stalled: bool,
+}
+
+/// Point-in-time view of the daemon, shared between the governor and thermal
+/// threads and published by the status writer.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StatusSnapshot {
+    /// Unix time (seconds) the snapshot was published
+    pub timestamp: u64,
+    pub applied_freq: u16,
+    pub target_freq: u16,
+    pub mode: String,
+    pub busy: f32,
+    pub thermal: ThermalSnapshot,
+    pub stats: GovernorStats,
+    /// Applied frequency (MHz) over the last minute
+    pub freq_1m: RollingStats,
+    /// Busy ratio over the last minute
+    pub busy_1m: RollingStats,
+    /// GPU-side temperature (°C) the governor saw over the last minute
+    pub gpu_temp_1m: RollingStats,
+    /// Workload fingerprint used by auto-mode (null until enough history)
+    pub fingerprint: Option<Fingerprint>,
+    /// Voltage (mV) of the last OD write (null until the first apply)
+    pub applied_voltage: Option<u16>,
+    /// Active scaling profile
+    pub profile: String,
+    /// Forced memory and fabric clocks (MHz), null while left to the driver
+    pub mclk: Option<u16>,
+    pub fclk: Option<u16>,
+    /// CPU minimum frequency (MHz) set by cpu-coupling, null when disabled
+    pub cpu_min_freq: Option<u32>,
+    /// Frame rate from MangoHud's log, null without recent frame times
+    pub fps: Option<f32>,
+    /// Busiest GPU clients over the last accounting interval, empty unless gpu-clients is enabled
+    pub gpu_clients: Vec<ClientUsage>,
+    /// Last board power reading (W), null unless power-budget is enabled
+    pub power_watts: Option<f32>,
+    /// Frequency ceiling (MHz) imposed by the power budget, null when not limiting
+    pub power_ceiling: Option<u16>,
+    /// Frequency ceiling (MHz) imposed by the thermal throttle curve, null when not limiting
+    pub thermal_ceiling: Option<u16>,
+    /// Share of the boost budget left (0.0-1.0), null unless boost-budget is enabled
+    pub boost_budget: Option<f32>,
+    /// Frequency ceiling (MHz) imposed by the boost budget, null when not limiting
+    pub boost_ceiling: Option<u16>,
+}
+
+impl StatusSnapshot {
+    /// Single-line `key=value` summary. Field order is stable; new fields are
+    /// only ever appended. Unavailable values are written as `na`.
+    pub fn summary_line(&self) -> String {
+        let fan = self.thermal.fan_pwm_percent
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "na".to_string());
+        let voltage = self.applied_voltage
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "na".to_string());
+        let rpm = self.thermal.fan_rpm
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| "na".to_string());
+        format!(
+            "freq={} target={} mode={} busy={:.2} temp={:.1} gpu_temp={:.1} fan={} \
+             freq_min={:.0} freq_avg={:.0} freq_max={:.0} busy_min={:.2} busy_avg={:.2} busy_max={:.2} voltage={} profile={} fan_rpm={}",
+            self.applied_freq, self.target_freq, self.mode, self.busy,
+            self.thermal.max, self.thermal.gpu_max, fan,
+            self.freq_1m.min, self.freq_1m.avg, self.freq_1m.max,
+            self.busy_1m.min, self.busy_1m.avg, self.busy_1m.max, voltage, self.profile, rpm,
+        )
+    }
+}
+
+/// Human-readable report of a snapshot fetched from a running daemon, for
+/// the `status` subcommand. Works on JSON so it tolerates older daemons.
+pub fn format_report(status: &serde_json::Value) -> String {
+    let num = |path: &str| status.pointer(path).and_then(|v| v.as_f64());
+    let int = |path: &str| num(path).map_or(0, |v| v as u64);
+    let opt = |path: &str, unit: &str| num(path).map_or_else(|| "na".to_string(), |v| format!("{v:.0}{unit}"));
+
+    let applies = int("/stats/total_applies");
+    let avg_latency = int("/stats/total_latency_us").checked_div(applies).unwrap_or(0);
+    [
+        format!("Frequency: {}MHz applied ({}), {}MHz target",
+            int("/applied_freq"), opt("/applied_voltage", "mV"), int("/target_freq")),
+        format!("Mode:      {} (profile {})", status.pointer("/mode").and_then(|v| v.as_str()).unwrap_or("na"),
+            status.pointer("/profile").and_then(|v| v.as_str()).unwrap_or("na")),
+        format!("Frames:    {}", opt("/fps", " fps")),
+        format!("Clients:   {}", gpu_clients(status)),
+        format!("Busy:      {:.0}% (last minute {:.0}-{:.0}%, avg {:.0}%)",
+            num("/busy").unwrap_or(0.0) * 100.0, num("/busy_1m/min").unwrap_or(0.0) * 100.0,
+            num("/busy_1m/max").unwrap_or(0.0) * 100.0, num("/busy_1m/avg").unwrap_or(0.0) * 100.0),
+        format!("Temps:     GPU {:.1}°C (hottest GPU sensor {:.1}°C), CPU {:.1}°C, max {:.1}°C",
+            num("/thermal/amdgpu").unwrap_or(0.0), num("/thermal/gpu_max").unwrap_or(0.0),
+            num("/thermal/cpu").unwrap_or(0.0), num("/thermal/max").unwrap_or(0.0)),
+        format!("Fan:       {}", fans(status)),
+        format!("Memory:    mclk {}, fclk {}", opt("/mclk", "MHz"), opt("/fclk", "MHz")),
+        format!("CPU min:   {}", opt("/cpu_min_freq", "MHz")),
+        format!("Power:     {}{}", opt("/power_watts", "W"),
+            num("/power_ceiling").map_or_else(String::new, |c| format!(", limited to {c:.0}MHz"))),
+        format!("Boost:     {}{}", num("/boost_budget").map_or_else(|| "na".to_string(), |b| format!("{:.0}% of budget left", b * 100.0)),
+            num("/boost_ceiling").map_or_else(String::new, |c| format!(", limited to {c:.0}MHz"))),
+        format!("Applies:   {} ({} failed, {} rejected), {} bursts, {} backoffs, {} rate-limited, latency avg {}μs max {}μs",
+            applies, int("/stats/failed_applies"), int("/stats/rejected_applies"),
+            int("/stats/burst_activations"), int("/stats/backoff_activations"),
+            int("/stats/rate_limited"), avg_latency, int("/stats/max_latency_us")),
+        format!("Thermal:   {} warnings, {} emergencies, {} burst caps, {} throttles, {} fan failures, {} fan stalls{}",
+            int("/stats/thermal_warnings"), int("/stats/thermal_emergencies"), int("/stats/throttle_activations"),
+            int("/stats/throttle_curve_activations"), int("/stats/fan_failures"), int("/stats/fan_stalls"),
+            num("/thermal_ceiling").map_or_else(String::new, |c| format!(", limited to {c:.0}MHz"))),
+    ].join("\n")
+}
+
+/// "name pwm%, rpm RPM" for each driven fan, or the first fan's PWM from
+/// daemons that do not list their fans.
+fn fans(status: &serde_json::Value) -> String {
+    let fans: Vec<String> = status.pointer("/thermal/fans").and_then(|v| v.as_array()).into_iter().flatten()
+        .map(|f| format!("{} {}{}{}", f["name"].as_str().unwrap_or("?"),
+            f["pwm_percent"].as_u64().map_or_else(|| "na".to_string(), |p| format!("{p}%")),
+            f["rpm"].as_u64().map_or_else(String::new, |rpm| format!(", {rpm} RPM")),
+            if f["stalled"].as_bool().unwrap_or(false) { " (stalled)" } else { "" }))
+        .collect();
+    if fans.is_empty() {
+        status.pointer("/thermal/fan_pwm_percent").and_then(|v| v.as_u64()).map_or_else(|| "na".to_string(), |p| format!("{p}%"))
+    } else {
+        fans.join(" | ")
+    }
+}
+
+/// "name (pid) busy%" for each client in the status, or "na".
+fn gpu_clients(status: &serde_json::Value) -> String {
+    let clients: Vec<String> = status.pointer("/gpu_clients").and_then(|v| v.as_array()).into_iter().flatten()
+        .map(|c| format!("{} ({}) {:.0}%", c["name"].as_str().unwrap_or("?"), c["pid"].as_u64().unwrap_or(0),
+            c["busy"].as_f64().unwrap_or(0.0) * 100.0))
+        .collect();
+    if clients.is_empty() { "na".to_string() } else { clients.join(", ") }
+}
+
+pub type SharedStatus = Arc<Mutex<StatusSnapshot>>;
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so readers never observe a partially written file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), IoError> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+pub fn mkfifo(path: &Path) -> Result<(), IoError> {
+    if let Ok(meta) = fs::symlink_metadata(path) {
+        if meta.file_type().is_fifo() {
+            return Ok(());
+        }
+        fs::remove_file(path)?;
+    }
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| IoError::new(ErrorKind::InvalidInput, e))?;
+    // SAFETY: c_path is a valid NUL-terminated string that outlives the call
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+        return Err(IoError::last_os_error());
+    }
+    Ok(())
+}
+
+/// Serves a named pipe that emits one summary line to every reader that opens it.
+pub fn start_fifo(path: PathBuf, status: SharedStatus, shutdown: Arc<AtomicBool>) -> Result<JoinHandle<()>, IoError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    mkfifo(&path)?;
+
+    Ok(std::thread::spawn(move || {
+        while !shutdown.load(Ordering::SeqCst) {
+            // Non-blocking open fails with ENXIO until a reader has the pipe open,
+            // which keeps this loop responsive to shutdown
+            let writer = OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(&path);
+            match writer {
+                Ok(mut pipe) => {
+                    let line = status.lock().unwrap().summary_line();
+                    let _ = pipe.write_all(format!("{line}\n").as_bytes());
+                    drop(pipe);
+                    // Give the reader time to see EOF and close before re-opening
+                    std::thread::sleep(Duration::from_millis(200));
+                }
+                Err(e) if e.raw_os_error() == Some(libc::ENXIO) => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    eprintln!("⚠️  Status FIFO {} failed: {}", path.display(), e);
+                    break;
+                }
+            }
+        }
+        let _ = fs::remove_file(&path);
+    }))
+}
+
+/// Publishes the shared snapshot to `path` every `interval` until shutdown.
+pub fn start_writer(
+    path: PathBuf,
+    status: SharedStatus,
+    interval: Duration,
+    shutdown: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>, IoError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    Ok(std::thread::spawn(move || {
+        let mut last_write: Option<Instant> = None;
+        let mut warned = false;
+        while !shutdown.load(Ordering::SeqCst) {
+            if last_write.is_none_or(|t| t.elapsed() >= interval) {
+                let mut snapshot = status.lock().unwrap().clone();
+                snapshot.timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+
+                let result = serde_json::to_vec_pretty(&snapshot)
+                    .map_err(IoError::other)
+                    .and_then(|json| write_atomic(&path, &json));
+                match result {
+                    Ok(()) => warned = false,
+                    Err(e) if !warned => {
+                        eprintln!("⚠️  Failed to write status file {}: {}", path.display(), e);
+                        warned = true;
+                    }
+                    Err(_) => {}
+                }
+                last_write = Some(Instant::now());
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let _ = fs::remove_file(&path);
+    }))
+}
@@ -0,0 +1,125 @@
+use std::{
+    fs,
+    io::Error as IoError,
+    os::{linux::net::SocketAddrExt, unix::net::{SocketAddr, UnixDatagram}},
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use crate::{config, dbus};
+
+pub const UNIT_NAME: &str = "bc-250-rust-governor.service";
+pub const UNIT_DIR: &str = "/etc/systemd/system";
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/bc-250-rust-governor/config.toml";
+
+/// Sends a state string (e.g. "READY=1", "WATCHDOG=1") to the service manager.
+/// Returns Ok(false) when not running under systemd (NOTIFY_SOCKET unset).
+pub fn notify(state: &str) -> Result<bool, IoError> {
+    let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+
+    let socket_path = socket_path.to_string_lossy().to_string();
+    let addr = match socket_path.strip_prefix('@') {
+        // Leading '@' denotes a socket in the abstract namespace
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes())?,
+        None => SocketAddr::from_pathname(&socket_path)?,
+    };
+
+    let sock = UnixDatagram::unbound()?;
+    sock.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(true)
+}
+
+/// How often to send WATCHDOG=1: half of the unit's WatchdogSec, as
+/// sd_watchdog_enabled(3) recommends. None when the watchdog is disabled or
+/// addressed to a different process.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    let pid = std::env::var("WATCHDOG_PID").ok().and_then(|p| p.parse::<u32>().ok());
+    if pid.is_some_and(|pid| pid != std::process::id()) {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// The unit file shipped next to the sources, which `render_unit` fills in
+const UNIT_TEMPLATE: &str = include_str!("../bc-250-rust-governor.service");
+/// Where the shipped unit expects the binary
+const DEFAULT_BINARY_PATH: &str = "/usr/local/bin/bc-250-rust-governor";
+
+/// Renders the shipped hardened unit file for the given binary and config path.
+pub fn render_unit(binary: &Path, config: &Path) -> String {
+    UNIT_TEMPLATE.replacen(
+        &format!("ExecStart={DEFAULT_BINARY_PATH} {DEFAULT_CONFIG_PATH}"),
+        &format!("ExecStart={} {}", exec_word(binary), exec_word(config)),
+        1,
+    )
+}
+
+/// Writes a path as one word of an Exec= line: `%` specifiers and `$`
+/// variables are escaped by doubling, and a path with whitespace, quotes or
+/// backslashes is double-quoted with C-style escapes, see systemd.service(5).
+fn exec_word(path: &Path) -> String {
+    let path = path.display().to_string().replace('%', "%%").replace('$', "$$");
+    if !path.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';')) {
+        return path;
+    }
+    let mut word = String::from('"');
+    for c in path.chars() {
+        match c {
+            '"' | '\\' => {
+                word.push('\\');
+                word.push(c);
+            }
+            '\t' => word.push_str("\\t"),
+            '\n' => word.push_str("\\n"),
+            c => word.push(c),
+        }
+    }
+    word.push('"');
+    word
+}
+
+/// Writes the unit to /etc/systemd/system and reloads systemd.
+/// When `enable` is set the service is also enabled and started.
+pub fn install_service(binary: &Path, config: &Path, enable: bool) -> Result<PathBuf, IoError> {
+    if !config.exists() {
+        eprintln!("⚠️  Config file {} does not exist yet; the service will not start until it is created.",
+            config.display());
+    }
+
+    let unit_path = Path::new(UNIT_DIR).join(UNIT_NAME);
+    fs::write(&unit_path, render_unit(binary, config))?;
+    println!("📝 Wrote {}", unit_path.display());
+
+    // Lets the daemon claim its D-Bus name on the system bus when [dbus] is enabled.
+    // Calls are open to the control socket's group; without a usable config only root may call.
+    let group = config::load_config(config).map(|c| c.control.group).unwrap_or_default();
+    match fs::write(dbus::POLICY_PATH, dbus::render_policy(&group)) {
+        Ok(()) => println!("📝 Wrote {}", dbus::POLICY_PATH),
+        Err(e) => eprintln!("⚠️  Could not write D-Bus policy {}: {}", dbus::POLICY_PATH, e),
+    }
+
+    systemctl(&["daemon-reload"])?;
+    if enable {
+        systemctl(&["enable", "--now", UNIT_NAME])?;
+        println!("✅ {} enabled and started", UNIT_NAME);
+    } else {
+        println!("ℹ️  Enable with: sudo systemctl enable --now {}", UNIT_NAME);
+    }
+
+    Ok(unit_path)
+}
+
+fn systemctl(args: &[&str]) -> Result<(), IoError> {
+    let status = Command::new("systemctl").args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(IoError::other(format!(
+            "systemctl {} failed with exit code: {}", args.join(" "), status
+        )))
+    }
+}
@@ -1,78 +1,317 @@
+//! Temperature sensors, fan control and the thermal throttle curve that caps
+//! the governor's frequency.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
     fs,
     io::{Error as IoError, ErrorKind},
     path::Path,
+    process::Command,
+    time::{Duration, Instant},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 use glob::glob;
 
+use crate::config::{CurveUnit, FanCurve, SensorAlias, Sensors, Smoothing, SmoothingFilter, ZeroRpm};
+
+/// hwmon name of the GPU, whose extra temperature channels are read too
+const GPU_CHIP: &str = "amdgpu";
+/// Highest tempN amdgpu exposes (edge, junction, mem)
+const TEMP_CHANNELS: u32 = 3;
+
+/// Sensor name of a chip's tempN beyond temp1: "amdgpu-junction", or
+/// "amdgpu-temp2" when the input has no label.
+fn channel_name(chip: &str, label: &str, index: u32) -> String {
+    if label.is_empty() {
+        format!("{chip}-temp{index}")
+    } else {
+        format!("{chip}-{}", label.to_lowercase().replace(' ', "-"))
+    }
+}
+
+/// Where temperatures are read from. Fans are always driven through sysfs.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThermalBackend {
+    /// temp1_input of each /sys/class/hwmon device
+    #[default]
+    Sysfs,
+    /// `sensors -j`, with sensors.conf labels, scaling and ignores applied
+    LmSensors,
+}
+
+/// A fan in the config: its index as `list` numbers them, or its name.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FanRef {
+    Index(usize),
+    Name(String),
+}
+
+impl fmt::Display for FanRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FanRef::Index(index) => write!(f, "fan {index}"),
+            FanRef::Name(name) => write!(f, "fan {name}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ThermalSensor {
     pub name: String,
     pub temp_input: String,
 }
 
+/// Fan controller families, told apart by their hwmon `name`. They share the
+/// pwmN/pwmN_enable interface but differ in which enable value means automatic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FanChip {
+    /// nct6687/nct6686 (out-of-tree nct6687d driver)
+    Nct6687,
+    /// nct6106-nct6799 and the Winbond parts of the in-kernel nct6775 driver
+    Nct6775,
+    /// ITE IT86xx/IT87xx (it87 driver)
+    It87,
+    /// Any other hwmon device with a pwmN and a matching fanN_input
+    Generic,
+}
+
+impl FanChip {
+    /// The chip family for an hwmon `name`. Generic for anything unknown;
+    /// whether it has usable fans is decided by its files.
+    pub fn detect(name: &str) -> Self {
+        if name.starts_with("nct6687") || name.starts_with("nct6686") {
+            FanChip::Nct6687
+        } else if name.starts_with("nct6") || name.starts_with("w836") {
+            FanChip::Nct6775
+        } else if name.starts_with("it8") {
+            FanChip::It87
+        } else {
+            FanChip::Generic
+        }
+    }
+
+    /// pwmN_enable value that hands a channel back to the chip, used when the
+    /// mode it was in at startup is unknown or was manual already.
+    pub fn auto_mode(self) -> &'static str {
+        match self {
+            // SmartFan IV, what boards ship with; 2 is thermal cruise
+            FanChip::Nct6775 => "5",
+            FanChip::Nct6687 | FanChip::It87 | FanChip::Generic => "2",
+        }
+    }
+
+    /// Whether a pwm channel without a matching fanN_input is driven. Known
+    /// chips number their PWM outputs independently of the tach inputs.
+    fn needs_tach(self) -> bool {
+        self == FanChip::Generic
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FanControl {
     pub name: String,
+    pub chip: FanChip,
     pub pwm_path: Option<String>,
     pub enable_path: Option<String>,
+    /// fanN_input of the same channel, if the chip has a tachometer there
+    pub rpm_path: Option<String>,
+    /// pwmN_enable as found at startup, written back on shutdown
+    pub original_enable: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ThermalManager {
     pub sensors: Vec<ThermalSensor>,
     pub fans: Vec<FanControl>,
-    pub nct6687_available: bool,
+    pub backend: ThermalBackend,
+    /// Sensor roles, selection and aliases
+    pub sensor_config: Sensors,
+    hwmon_root: String,
+    /// Inputs and PWM outputs the last hwmon scan found, to tell when devices come or go
+    found: Vec<String>,
 }
 
-impl ThermalManager {
-    pub fn new() -> Result<Self, IoError> {
-        Self::new_with_root("/sys/class/hwmon")
+/// Names and files of what a hwmon scan found; hwmon numbers can change
+/// when a driver is reloaded, so the paths count too.
+fn found_files(sensors: &[ThermalSensor], fans: &[FanControl]) -> Vec<String> {
+    sensors.iter().map(|s| format!("{} {}", s.name, s.temp_input))
+        .chain(fans.iter().map(|f| format!("{} {}", f.name, f.pwm_path.as_deref().unwrap_or_default())))
+        .collect()
+}
+
+/// tempN_input of the hwmon device named `alias.chip` that `alias` picks,
+/// by label or by number.
+fn find_alias_input(hwmon_root: &str, alias: &SensorAlias) -> Option<String> {
+    let pattern = format!("{}/hwmon*", hwmon_root.trim_end_matches('/'));
+    glob(&pattern).ok()?.flatten()
+        .filter(|dir| fs::read_to_string(dir.join("name")).is_ok_and(|n| n.trim() == alias.chip))
+        .find_map(|dir| {
+            let index = match (&alias.label, alias.input) {
+                (_, Some(n)) => n,
+                (Some(label), None) => glob(&format!("{}/temp*_label", dir.to_string_lossy())).ok()?.flatten()
+                    .filter(|path| fs::read_to_string(path).is_ok_and(|l| l.trim() == label))
+                    .find_map(|path| path.file_name()?.to_str()?.strip_prefix("temp")?.strip_suffix("_label")?.parse().ok())?,
+                (None, None) => return None,
+            };
+            let input = dir.join(format!("temp{index}_input"));
+            input.exists().then(|| input.to_string_lossy().to_string())
+        })
+}
+
+/// The `sensors -j` reading `alias` picks from one chip's features: the
+/// feature named after its label (lm-sensors keys features by label), or
+/// the one holding tempN_input.
+fn lm_sensors_alias(features: &serde_json::Map<String, Value>, alias: &SensorAlias) -> Option<f32> {
+    let temp_input = |subfeatures: &Value| subfeatures.as_object()?.iter()
+        .find(|(key, _)| key.starts_with("temp") && key.ends_with("_input"))
+        .and_then(|(_, value)| value.as_f64());
+    let temp = match (&alias.label, alias.input) {
+        (_, Some(n)) => features.values().find_map(|sub| sub.get(format!("temp{n}_input"))?.as_f64()),
+        (Some(label), None) => temp_input(features.get(label)?),
+        (None, None) => None,
+    };
+    temp.map(|t| t as f32)
+}
+
+/// PWM channel numbers of an hwmon directory (pwm1, pwm2, ...), ascending.
+/// pwmN_enable, pwmN_freq and the auto-point files are not channels.
+fn pwm_channels(hwmon_path: &Path) -> Vec<u32> {
+    let mut channels: Vec<u32> = fs::read_dir(hwmon_path).into_iter().flatten().flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.strip_prefix("pwm")?.parse().ok())
+        .collect();
+    channels.sort_unstable();
+    channels
+}
+
+/// Hottest reading whose sensor name is not in `excluded`.
+fn max_excluding(readings: &[(String, f32)], excluded: &[&str]) -> Result<f32, IoError> {
+    readings.iter()
+        .filter(|(name, _)| !excluded.contains(&name.as_str()))
+        .map(|&(_, temp)| temp)
+        .reduce(f32::max)
+        .ok_or_else(|| IoError::new(ErrorKind::NotFound, "No temperature readings available"))
+}
+
+/// Runs `sensors -j` and returns (hwmon-style name, chip, temperature) for
+/// every chip reporting one, and for each of `aliases` found. temp1 is preferred, as with the sysfs backend;
+/// otherwise the lowest-numbered temperature input is used.
+fn lm_sensors_readings(aliases: &[SensorAlias]) -> Result<Vec<(String, String, f32)>, IoError> {
+    let output = Command::new("sensors").arg("-j").output()?;
+    if !output.status.success() {
+        return Err(IoError::other(format!("sensors -j failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
     }
+    let root: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+    let chips = root.as_object()
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "sensors -j did not return an object"))?;
 
-    pub fn new_with_root(hwmon_root: &str) -> Result<Self, IoError> {
-        let mut sensors = Vec::new();
-        let mut fans = Vec::new();
-        let mut nct6687_available = false;
+    let mut readings = Vec::new();
+    for (chip, features) in chips {
+        let Some(features) = features.as_object() else { continue };
+        // "amdgpu-pci-0100" -> "amdgpu", matching the hwmon name
+        let name = chip.split('-').next().unwrap_or(chip).to_string();
+        for alias in aliases.iter().filter(|alias| alias.chip == name) {
+            if let Some(temp) = lm_sensors_alias(features, alias) {
+                readings.push((alias.name.clone(), format!("{chip} {}", alias.label.clone().unwrap_or_default()), temp));
+            }
+        }
+        // (feature label, tempN index, value) of every tempN_input subfeature on the chip
+        let temps: Vec<(&String, u32, f32)> = features.iter()
+            .filter_map(|(feature, subfeatures)| Some((feature, subfeatures.as_object()?)))
+            .flat_map(|(feature, subfeatures)| subfeatures.iter().map(move |sub| (feature, sub)))
+            .filter_map(|(feature, (key, value))| {
+                let index = key.strip_prefix("temp")?.strip_suffix("_input")?.parse::<u32>().ok()?;
+                Some((feature, index, value.as_f64()? as f32))
+            })
+            .collect();
+        let Some(&(_, first, temp)) = temps.iter().min_by_key(|&&(_, index, _)| index) else { continue };
+        if name == GPU_CHIP {
+            for &(feature, index, temp) in temps.iter().filter(|&&(_, index, _)| index != first) {
+                readings.push((channel_name(&name, feature, index), chip.clone(), temp));
+            }
+        }
+        readings.push((name, chip.clone(), temp));
+    }
+    Ok(readings)
+}
 
-        let pattern = format!("{}/hwmon*", hwmon_root.trim_end_matches('/'));
-        for hwmon_path in glob(&pattern).unwrap().flatten() {
-            if let Ok(name) = fs::read_to_string(hwmon_path.join("name")) {
-                let name = name.trim().to_string();
-                let path = hwmon_path.to_string_lossy().to_string();
+/// Sensors and fans of every hwmon device under `hwmon_root`, and the fan
+/// controller chips among them ("nct6687 (Nct6687)").
+fn scan_hwmon(hwmon_root: &str) -> (Vec<ThermalSensor>, Vec<FanControl>, Vec<String>) {
+    let mut sensors = Vec::new();
+    let mut fans = Vec::new();
+    let mut chips = Vec::new();
 
-                if hwmon_path.join("temp1_input").exists() {
+    let pattern = format!("{}/hwmon*", hwmon_root.trim_end_matches('/'));
+    for hwmon_path in glob(&pattern).unwrap().flatten() {
+        if let Ok(name) = fs::read_to_string(hwmon_path.join("name")) {
+            let name = name.trim().to_string();
+
+            if hwmon_path.join("temp1_input").exists() {
+                sensors.push(ThermalSensor {
+                    name: name.clone(),
+                    temp_input: hwmon_path.join("temp1_input").to_string_lossy().to_string(),
+                });
+            }
+            // temp1 is the edge; the hotspot (junction) and memory channels follow it
+            if name == GPU_CHIP {
+                for index in (2..=TEMP_CHANNELS).filter(|n| hwmon_path.join(format!("temp{n}_input")).exists()) {
+                    let label = fs::read_to_string(hwmon_path.join(format!("temp{index}_label"))).unwrap_or_default();
                     sensors.push(ThermalSensor {
-                        name: name.clone(),
-                        temp_input: hwmon_path.join("temp1_input").to_string_lossy().to_string(),
+                        name: channel_name(&name, label.trim(), index),
+                        temp_input: hwmon_path.join(format!("temp{index}_input")).to_string_lossy().to_string(),
                     });
                 }
+            }
 
-                if name.starts_with("nct6687") || name.starts_with("nct6686") {
-                    nct6687_available = true;
-                    
-                    for pwm_path in glob(&format!("{}/pwm*", path)).unwrap_or_else(|_| glob("").unwrap()).flatten() {
-                        if pwm_path.to_string_lossy().contains("_enable") {
-                            continue;
-                        }
-                        
-                        let pwm_name = pwm_path.file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-                        
-                        let enable_path = format!("{}_enable", pwm_path.to_string_lossy());
-                        let enable_exists = Path::new(&enable_path).exists();
-
-                        fans.push(FanControl {
-                            name: format!("{}_{}", name, pwm_name),
-                            pwm_path: Some(pwm_path.to_string_lossy().to_string()),
-                            enable_path: if enable_exists { Some(enable_path) } else { None },
-                        });
-                    }
+            // The GPU's own fan, if any, belongs to its SMU
+            if name == "amdgpu" {
+                continue;
+            }
+            let chip = FanChip::detect(&name);
+            let before = fans.len();
+            for channel in pwm_channels(&hwmon_path) {
+                if chip.needs_tach() && !hwmon_path.join(format!("fan{channel}_input")).exists() {
+                    continue;
                 }
+                let pwm_path = hwmon_path.join(format!("pwm{channel}"));
+                let enable_path = hwmon_path.join(format!("pwm{channel}_enable"));
+                let rpm_path = hwmon_path.join(format!("fan{channel}_input"));
+                let original_enable = fs::read_to_string(&enable_path).ok().map(|s| s.trim().to_string());
+
+                fans.push(FanControl {
+                    name: format!("{}_pwm{}", name, channel),
+                    chip,
+                    pwm_path: Some(pwm_path.to_string_lossy().to_string()),
+                    enable_path: enable_path.exists().then(|| enable_path.to_string_lossy().to_string()),
+                    rpm_path: rpm_path.exists().then(|| rpm_path.to_string_lossy().to_string()),
+                    original_enable,
+                });
+            }
+            if fans.len() > before {
+                chips.push(format!("{} ({:?})", name, chip));
             }
         }
+    }
+
+    (sensors, fans, chips)
+}
+
+impl ThermalManager {
+    pub fn new() -> Result<Self, IoError> {
+        Self::new_with_root("/sys/class/hwmon")
+    }
+
+    pub fn new_with_root(hwmon_root: &str) -> Result<Self, IoError> {
+        let (sensors, fans, chips) = scan_hwmon(hwmon_root);
 
         println!("🌡️  Thermal Manager initialized:");
         println!("   Sensors found: {}", sensors.len());
@@ -83,25 +322,141 @@ impl ThermalManager {
         for fan in &fans {
             println!("     - {}", fan.name);
         }
-        println!("   NCT6687 available: {}", nct6687_available);
 
-        if !nct6687_available {
-            println!("⚠️  NCT6687 not detected. Fan control disabled.");
-            println!("   To enable: sudo modprobe nct6687");
+        if chips.is_empty() {
+            println!("⚠️  No fan controller detected. Fan control disabled.");
+            println!("   To enable, load the board's Super I/O driver: sudo modprobe nct6687 (or nct6775, it87)");
+        } else {
+            println!("   Fan controllers: {}", chips.join(", "));
         }
 
         Ok(ThermalManager {
+            found: found_files(&sensors, &fans),
             sensors,
             fans,
-            nct6687_available,
+            backend: ThermalBackend::Sysfs,
+            sensor_config: Sensors::default(),
+            hwmon_root: hwmon_root.to_string(),
         })
     }
 
-    pub fn read_temperature(&self, sensor_name: &str) -> Result<f32, IoError> {
-        let sensor = self.sensors.iter()
-            .find(|s| s.name == sensor_name)
-            .ok_or_else(|| IoError::new(ErrorKind::NotFound, format!("Sensor {} not found", sensor_name)))?;
+    /// Scans the hwmon root again and takes on the sensors and fans that
+    /// appeared or vanished since the last scan, e.g. a Super I/O driver
+    /// loaded after startup or a USB fan hub. The `[thermal.sensors]` config
+    /// is applied to the new sensor list. A fan seen before keeps the enable
+    /// mode it had at startup. Returns the fans as they were when anything
+    /// changed, since fan indexes shift with the devices.
+    pub fn rescan(&mut self) -> Option<Vec<FanControl>> {
+        let (sensors, mut fans, chips) = scan_hwmon(&self.hwmon_root);
+        let found = found_files(&sensors, &fans);
+        if found == self.found {
+            return None;
+        }
+        self.found = found;
+
+        for fan in &mut fans {
+            if let Some(old) = self.fans.iter().find(|old| old.name == fan.name) {
+                fan.original_enable = old.original_enable.clone();
+            }
+        }
+        let sensor_names = |sensors: &[ThermalSensor]| sensors.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+        let fan_names = |fans: &[FanControl]| fans.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        let old_sensors = sensor_names(&self.sensors);
+        if self.backend == ThermalBackend::Sysfs {
+            self.sensors = sensors;
+        }
+        self.set_sensors(&self.sensor_config.clone());
+        let old_fans = std::mem::replace(&mut self.fans, fans);
+
+        let mut changes = Vec::new();
+        for (kind, old, new) in [("sensor", old_sensors, sensor_names(&self.sensors)), ("fan", fan_names(&old_fans), fan_names(&self.fans))] {
+            changes.extend(new.iter().filter(|n| !old.contains(n)).map(|n| format!("{kind} {n} appeared")));
+            changes.extend(old.iter().filter(|n| !new.contains(n)).map(|n| format!("{kind} {n} vanished")));
+        }
+        if changes.is_empty() {
+            changes.push("device paths moved".to_string());
+        }
+        println!("🔌 hwmon devices changed: {}", changes.join(", "));
+        if !chips.is_empty() {
+            println!("   Fan controllers: {}", chips.join(", "));
+        }
+        Some(old_fans)
+    }
+
+    /// Switches temperature reads to `backend`. For lm-sensors the sensor list
+    /// is replaced by the chips `sensors -j` reports; fans stay on sysfs.
+    /// On error the current backend is kept.
+    pub fn set_backend(&mut self, backend: ThermalBackend) -> Result<(), IoError> {
+        if backend == ThermalBackend::LmSensors {
+            let readings = lm_sensors_readings(&self.sensor_config.aliases)?;
+            self.sensors = readings.into_iter()
+                .map(|(name, chip, _)| ThermalSensor { name, temp_input: format!("sensors -j: {chip}") })
+                .collect();
+            println!("🌡️  Using lm-sensors for temperatures ({} chips)", self.sensors.len());
+        }
+        self.backend = backend;
+        Ok(())
+    }
+
+    /// Applies the `[thermal.sensors]` config: adds its aliases as sensors
+    /// and drops the sensors it does not include. Call it after
+    /// [`set_backend`](Self::set_backend).
+    pub fn set_sensors(&mut self, config: &Sensors) {
+        self.sensor_config = config.clone();
+        match self.backend {
+            ThermalBackend::Sysfs => {
+                for alias in &config.aliases {
+                    match find_alias_input(&self.hwmon_root, alias) {
+                        Some(temp_input) => self.sensors.push(ThermalSensor { name: alias.name.clone(), temp_input }),
+                        None => eprintln!("⚠️  Sensor alias {}: no matching input on {}", alias.name, alias.chip),
+                    }
+                }
+            }
+            // Re-read with the aliases, which sensors -j resolves on every read
+            ThermalBackend::LmSensors => if let Ok(readings) = lm_sensors_readings(&config.aliases) {
+                self.sensors = readings.into_iter()
+                    .map(|(name, chip, _)| ThermalSensor { name, temp_input: format!("sensors -j: {chip}") })
+                    .collect();
+            },
+        }
+        if !config.include.is_empty() {
+            self.sensors.retain(|sensor| config.include.contains(&sensor.name));
+        }
+        for role in [&config.gpu, &config.cpu] {
+            if !self.sensors.iter().any(|sensor| &sensor.name == role) {
+                eprintln!("⚠️  Sensor {} not found; its temperature will read 0", role);
+            }
+        }
+        for name in config.max_include.iter().chain(&config.max_exclude) {
+            if !self.sensors.iter().any(|sensor| &sensor.name == name) {
+                eprintln!("⚠️  Sensor {} in max-include/max-exclude not found", name);
+            }
+        }
+        if !config.max_include.is_empty() || !config.max_exclude.is_empty() {
+            println!("🌡️  Max temperature covers: {}", self.sensors.iter()
+                .filter(|s| config.in_max(&s.name)).map(|s| s.name.as_str()).collect::<Vec<_>>().join(", "));
+        }
+        println!("🌡️  Sensors: {} (GPU {}, CPU {})",
+            self.sensors.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(", "), config.gpu, config.cpu);
+    }
+
+    /// (chip, N, tempN_label) of every tempN_input under the hwmon root, for
+    /// picking `[[thermal.sensors.alias]]` entries. The label is empty when
+    /// the driver has none.
+    pub fn temperature_inputs(&self) -> Vec<(String, u32, String)> {
+        let mut inputs = Vec::new();
+        let pattern = format!("{}/hwmon*/temp*_input", self.hwmon_root.trim_end_matches('/'));
+        for input in glob(&pattern).into_iter().flatten().flatten() {
+            let (Some(dir), Some(index)) = (input.parent(), input.file_name().and_then(|f| f.to_str())
+                .and_then(|f| f.strip_prefix("temp")?.strip_suffix("_input")?.parse::<u32>().ok())) else { continue };
+            let chip = fs::read_to_string(dir.join("name")).unwrap_or_default().trim().to_string();
+            let label = fs::read_to_string(dir.join(format!("temp{index}_label"))).unwrap_or_default().trim().to_string();
+            inputs.push((chip, index, label));
+        }
+        inputs
+    }
 
+    fn read_sysfs_temperature(sensor: &ThermalSensor) -> Result<f32, IoError> {
         let temp_str = fs::read_to_string(&sensor.temp_input)?;
         let temp_millidegrees: i32 = temp_str.trim().parse()
             .map_err(|_| IoError::new(ErrorKind::InvalidData, "Invalid temperature data"))?;
@@ -109,26 +464,35 @@ impl ThermalManager {
         Ok(temp_millidegrees as f32 / 1000.0)
     }
 
-    pub fn get_max_temperature(&self) -> Result<f32, IoError> {
-        let mut max_temp: f32 = f32::NEG_INFINITY;
-        let mut found_any = false;
-
-        for sensor in &self.sensors {
-            if let Ok(temp) = self.read_temperature(&sensor.name) {
-                max_temp = max_temp.max(temp);
-                found_any = true;
-            }
+    /// (sensor name, °C) for every sensor that could be read. lm-sensors is
+    /// queried once per call.
+    fn read_all(&self) -> Vec<(String, f32)> {
+        match self.backend {
+            ThermalBackend::Sysfs => self.sensors.iter()
+                .filter_map(|sensor| Some((sensor.name.clone(), Self::read_sysfs_temperature(sensor).ok()?)))
+                .collect(),
+            ThermalBackend::LmSensors => lm_sensors_readings(&self.sensor_config.aliases)
+                .map(|readings| readings.into_iter()
+                    .filter(|(name, _, _)| self.sensor_config.include.is_empty() || self.sensor_config.include.contains(name))
+                    .map(|(name, _, temp)| (name, temp))
+                    .collect())
+                .unwrap_or_default(),
         }
+    }
 
-        if found_any {
-            Ok(max_temp)
-        } else {
-            Err(IoError::new(ErrorKind::NotFound, "No temperature readings available"))
-        }
+    /// True when a supported fan controller exposed at least one PWM channel.
+    pub fn fan_control_available(&self) -> bool {
+        !self.fans.is_empty()
     }
+
     pub fn set_fan_speed(&self, fan_index: usize, speed_percent: u8) -> Result<(), IoError> {
-        if !self.nct6687_available {
-            return Err(IoError::new(ErrorKind::Unsupported, "NCT6687 not available"));
+        self.set_fan_pwm(fan_index, CurveUnit::Percent.to_raw(speed_percent))
+    }
+
+    /// Writes `pwm_value` (0-255) to the fan as is, taking it off the chip's automatic mode.
+    pub fn set_fan_pwm(&self, fan_index: usize, pwm_value: u8) -> Result<(), IoError> {
+        if !self.fan_control_available() {
+            return Err(IoError::new(ErrorKind::Unsupported, "No fan controller available"));
         }
 
         let fan = self.fans.get(fan_index)
@@ -137,8 +501,6 @@ impl ThermalManager {
         let pwm_path = fan.pwm_path.as_ref()
             .ok_or_else(|| IoError::new(ErrorKind::NotFound, "PWM path not available"))?;
 
-        let pwm_value = (speed_percent.min(100) as u16 * 255 / 100) as u8;
-        
         if let Some(enable_path) = &fan.enable_path {
             fs::write(enable_path, "1")?;
         }
@@ -148,15 +510,55 @@ impl ThermalManager {
         Ok(())
     }
 
-    pub fn get_thermal_status(&self) -> ThermalStatus {
-        let max_temp = self.get_max_temperature().unwrap_or(0.0);
-        let amdgpu_temp = self.read_temperature("amdgpu").unwrap_or(0.0);
-        let cpu_temp = self.read_temperature("k10temp").unwrap_or(0.0);
+    /// `include_cpu` controls whether the CPU sensor counts towards `gpu_max_temperature`,
+    /// the aggregate used for GPU-side decisions. `max_temperature` always covers every sensor.
+    pub fn get_thermal_status(&self, include_cpu: bool) -> ThermalStatus {
+        // One snapshot, so lm-sensors runs once per status
+        self.status_from(self.read_all(), include_cpu)
+    }
+
+    /// Like `get_thermal_status`, with each reading passed through `filter` first.
+    pub fn get_smoothed_status(&self, include_cpu: bool, filter: &mut TemperatureFilter) -> ThermalStatus {
+        let readings = self.read_all().into_iter().map(|(name, temp)| {
+            let temp = filter.apply(&name, temp);
+            (name, temp)
+        }).collect();
+        self.status_from(readings, include_cpu)
+    }
+
+    fn status_from(&self, readings: Vec<(String, f32)>, include_cpu: bool) -> ThermalStatus {
+        let reading = |name: &str| readings.iter().find(|(n, _)| n == name).map_or(0.0, |&(_, t)| t);
+        // NVMe drives, Wi-Fi cards and the like can be kept out of the aggregates; should
+        // that leave nothing with a reading, every sensor counts rather than none
+        let counted: Vec<(String, f32)> = readings.iter().filter(|(n, _)| self.sensor_config.in_max(n)).cloned().collect();
+        let counted = if counted.is_empty() { &readings } else { &counted };
+        let max_temp = max_excluding(counted, &[]).unwrap_or(0.0);
+        let gpu_readings: Vec<(String, f32)> = counted.iter()
+            .filter(|(n, _)| include_cpu || n != &self.sensor_config.cpu)
+            .cloned()
+            .collect();
+        let gpu_max_temp = if include_cpu {
+            max_temp
+        } else {
+            max_excluding(counted, &[&self.sensor_config.cpu]).unwrap_or(0.0)
+        };
+        // The GPU reads as its hottest channel: the edge underestimates the hotspot on these APUs
+        let gpu = &self.sensor_config.gpu;
+        let gpu_channels: BTreeMap<String, f32> = readings.iter()
+            .filter(|(n, _)| n == gpu || n.strip_prefix(gpu.as_str()).is_some_and(|rest| rest.starts_with('-')))
+            .cloned()
+            .collect();
+        let amdgpu_temp = gpu_channels.values().copied().reduce(f32::max).unwrap_or(0.0);
+        let cpu_temp = reading(&self.sensor_config.cpu);
 
         ThermalStatus {
             max_temperature: max_temp,
+            gpu_max_temperature: gpu_max_temp,
             amdgpu_temperature: amdgpu_temp,
             cpu_temperature: cpu_temp,
+            gpu_channels,
+            gpu_readings,
+            readings,
         }
     }
 
@@ -171,12 +573,14 @@ impl ThermalManager {
                 .and_then(|p| fs::read_to_string(p).ok())
                 .map(|s| s.trim().to_string())
                 .unwrap_or_else(|| "N/A".to_string());
+            let rpm_str = self.fan_rpm(i).map_or_else(|| "N/A".to_string(), |rpm| rpm.to_string());
 
             println!(
-                "- Fan {}: {} | PWM: {}",
+                "- Fan {}: {} | PWM: {} | RPM: {}",
                 i,
                 fan.name,
-                pwm_str
+                pwm_str,
+                rpm_str
             );
         }
     }
@@ -204,6 +608,88 @@ impl ThermalManager {
         (pwm_opt, Some(fan_index))
     }
 
+    /// tempN_crit and tempN_max of the `gpu` sensor and each of its channels.
+    /// Drivers report 0 or a huge value for an unset limit, so only readings
+    /// between 0 and 150°C count. lm-sensors chips have no files and no limits,
+    /// and neither do APUs such as Cyan Skillfish.
+    pub fn gpu_limits(&self) -> TempLimits {
+        let gpu = &self.sensor_config.gpu;
+        let limit = |base: &str, suffix: &str| fs::read_to_string(format!("{base}_{suffix}")).ok()?
+            .trim().parse::<f32>().ok()
+            .map(|millidegrees| millidegrees / 1000.0)
+            .filter(|&t| t > 0.0 && t < 150.0);
+        let channels = self.sensors.iter()
+            .filter(|s| &s.name == gpu || s.name.strip_prefix(gpu.as_str()).is_some_and(|rest| rest.starts_with('-')))
+            .filter_map(|s| Some((s.name.clone(), s.temp_input.strip_suffix("_input")?)))
+            .map(|(name, base)| (name, ChannelLimits { crit: limit(base, "crit"), max: limit(base, "max") }))
+            .filter(|(_, limits)| limits.crit.is_some() || limits.max.is_some())
+            .collect();
+        TempLimits { channels }
+    }
+
+    /// Index of the fan `fan` refers to, if there is one.
+    pub fn find_fan(&self, fan: &FanRef) -> Option<usize> {
+        match fan {
+            FanRef::Index(index) => (*index < self.fans.len()).then_some(*index),
+            FanRef::Name(name) => self.fans.iter().position(|f| &f.name == name),
+        }
+    }
+
+    /// Names of the fans more than one of `curves` resolves to. Refs to fans
+    /// that do not exist are left to `fan_loops`.
+    pub fn duplicate_fans(&self, curves: &[FanCurve]) -> Vec<String> {
+        let mut seen = Vec::new();
+        let mut duplicates = Vec::new();
+        for index in curves.iter().filter_map(|curve| self.find_fan(&curve.fan)) {
+            if seen.contains(&index) {
+                if !duplicates.contains(&self.fans[index].name) {
+                    duplicates.push(self.fans[index].name.clone());
+                }
+            } else {
+                seen.push(index);
+            }
+        }
+        duplicates
+    }
+
+    /// Binds each of `curves` to its fan. Fans that do not exist are logged
+    /// and skipped; a fan already in `previous` keeps its speed and stall state.
+    pub fn fan_loops(&self, curves: Vec<FanCurve>, previous: Vec<FanLoop>) -> Vec<FanLoop> {
+        let mut previous = previous;
+        curves.into_iter().filter_map(|curve| {
+            let Some(index) = self.find_fan(&curve.fan) else {
+                eprintln!("⚠️  No {} for its fan curve, skipping it", curve.fan);
+                return None;
+            };
+            if !["max", "gpu-max"].contains(&curve.source.as_str()) && !self.sensors.iter().any(|s| s.name == curve.source) {
+                eprintln!("⚠️  {} follows sensor {}, which was not found; it will use the hottest sensor", curve.fan, curve.source);
+            }
+            if let Some(ambient) = curve.ambient.as_ref().filter(|a| !self.sensors.iter().any(|s| &s.name == *a)) {
+                eprintln!("⚠️  {} is relative to sensor {}, which was not found; its curve will see absolute temperatures", curve.fan, ambient);
+            }
+            Some(match previous.iter().position(|l| l.index == index) {
+                Some(i) => FanLoop { curve, ..previous.swap_remove(i) },
+                None => FanLoop {
+                    index,
+                    curve,
+                    speed: None,
+                    stall: FanStallWatch::default(),
+                    stall_active: false,
+                    last_write: None,
+                    stopped: false,
+                    spin_up_until: None,
+                    write_failures: 0,
+                },
+            })
+        }).collect()
+    }
+
+    /// Tachometer reading of a fan, None without a fanN_input or on a read error.
+    pub fn fan_rpm(&self, fan_index: usize) -> Option<u32> {
+        let path = self.fans.get(fan_index)?.rpm_path.as_ref()?;
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
     pub fn probe_fans(&self) {
         for (i, fan) in self.fans.iter().enumerate() {
             println!("--- PWM {}: {} ---", i, fan.name);
@@ -253,13 +739,13 @@ impl ThermalManager {
     }
 
     pub fn restore_auto_fan_control(&self) -> Result<(), IoError> {
-        if !self.nct6687_available {
-            return Ok(());
-        }
-
         for (i, fan) in self.fans.iter().enumerate() {
             if let Some(enable_path) = &fan.enable_path {
-                match fs::write(enable_path, "2") {
+                // Manual at startup means an earlier run never handed it back
+                let mode = fan.original_enable.as_deref()
+                    .filter(|&mode| mode != "1")
+                    .unwrap_or(fan.chip.auto_mode());
+                match fs::write(enable_path, mode) {
                     Ok(_) => println!("🔄 Fan {} restored to automatic control", i),
                     Err(e) => eprintln!("⚠️  Failed to restore fan {} to auto: {}", i, e),
                 }
@@ -273,11 +759,324 @@ impl ThermalManager {
 #[derive(Debug, Clone)]
 pub struct ThermalStatus {
     pub max_temperature: f32,
+    pub gpu_max_temperature: f32,
+    /// Hottest channel of the GPU sensor
     pub amdgpu_temperature: f32,
     pub cpu_temperature: f32,
+    /// Every channel of the GPU sensor (edge, junction, mem) by sensor name
+    pub gpu_channels: BTreeMap<String, f32>,
+    /// (sensor name, °C) of the readings `gpu_max_temperature` is the hottest of
+    pub gpu_readings: Vec<(String, f32)>,
+    /// (sensor name, °C) of every sensor read
+    pub readings: Vec<(String, f32)>,
+}
+
+impl ThermalStatus {
+    /// Temperature of a fan curve `source`: "max", "gpu-max" or a sensor
+    /// name. None when the sensor gave no reading.
+    pub fn source_temperature(&self, source: &str) -> Option<f32> {
+        match source {
+            "max" => Some(self.max_temperature),
+            "gpu-max" => Some(self.gpu_max_temperature),
+            name => self.readings.iter().find(|(n, _)| n == name).map(|&(_, t)| t),
+        }
+    }
+}
+
+/// Smooths each sensor's readings as `[thermal.smoothing]` says.
+#[derive(Debug)]
+pub struct TemperatureFilter {
+    config: Smoothing,
+    /// Last `window` readings per sensor for the median, or the running EMA alone
+    history: HashMap<String, VecDeque<f32>>,
+}
+
+impl TemperatureFilter {
+    pub fn new(config: &Smoothing) -> Self {
+        Self { config: config.clone(), history: HashMap::new() }
+    }
+
+    /// The smoothed value of sensor `name` after a new reading of `temp`. The
+    /// median of fewer readings than the window is their lower middle, and
+    /// the EMA starts at the first reading.
+    pub fn apply(&mut self, name: &str, temp: f32) -> f32 {
+        let window = match self.config.filter {
+            SmoothingFilter::None => return temp,
+            SmoothingFilter::Median => self.config.window.max(1),
+            SmoothingFilter::Ema => 1,
+        };
+        let history = self.history.entry(name.to_string()).or_default();
+        if self.config.filter == SmoothingFilter::Ema {
+            let ema = history.back().map_or(temp, |&ema| ema + self.config.alpha * (temp - ema));
+            history.clear();
+            history.push_back(ema);
+            return ema;
+        }
+        if history.len() == window {
+            history.pop_front();
+        }
+        history.push_back(temp);
+        let mut sorted: Vec<f32> = history.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        sorted[(sorted.len() - 1) / 2]
+    }
+}
+
+/// A fan the thermal thread drives along its curve and watches for stalls.
+#[derive(Debug)]
+pub struct FanLoop {
+    pub index: usize,
+    pub curve: FanCurve,
+    /// Duty last asked of the fan in the curve's unit, which the falling curve starts from
+    pub speed: Option<u8>,
+    pub stall: FanStallWatch,
+    pub stall_active: bool,
+    /// When the PWM was last written
+    pub last_write: Option<Instant>,
+    /// Stopped by zero-RPM mode
+    pub stopped: bool,
+    /// End of the spin-up kick after a zero-RPM restart or a start from 0
+    pub spin_up_until: Option<Instant>,
+    /// PWM writes that failed in a row
+    pub write_failures: u32,
+}
+
+impl FanLoop {
+    /// Whether the fan stalled or `max_write_failures` (0 = never) PWM writes
+    /// to it failed in a row.
+    pub fn failing(&self, max_write_failures: u32) -> bool {
+        self.stall_active || (max_write_failures > 0 && self.write_failures >= max_write_failures)
+    }
+
+    /// Whether moving the fan from `current` to `target` (in the curve's unit)
+    /// is worth a write: the change must be at least `min_step` and `min_interval`
+    /// must have passed since the last write. Full speed and the zero-RPM
+    /// spin-up kick are never held back.
+    pub fn should_write(&self, current: Option<u8>, target: u8, min_step: u8, min_interval: Duration) -> bool {
+        let Some(current) = current else {
+            return true;
+        };
+        if current == target {
+            return false;
+        }
+        if target == self.curve.unit.full() || self.spinning_up() {
+            return true;
+        }
+        current.abs_diff(target) >= min_step && self.last_write.is_none_or(|at| at.elapsed() >= min_interval)
+    }
+
+    /// Applies zero-RPM mode to a curve `target` for a fan whose source reads
+    /// `temperature`: 0 while stopped, and at least the spin-up duty for a
+    /// moment after a restart. Returns the target unchanged when disabled.
+    pub fn zero_rpm(&mut self, temperature: f32, target: u8, zero_rpm: &ZeroRpm) -> u8 {
+        if !zero_rpm.enabled {
+            self.stopped = false;
+            return target;
+        }
+        if self.stopped {
+            if temperature < zero_rpm.start_above {
+                return 0;
+            }
+            self.stopped = false;
+            self.spin_up_until = Some(Instant::now() + Duration::from_millis(zero_rpm.spin_up_ms));
+        } else if temperature < zero_rpm.stop_below {
+            self.stopped = true;
+            self.spin_up_until = None;
+            return 0;
+        }
+        match self.spin_up_until {
+            Some(until) if Instant::now() < until => target.max(self.curve.unit.from_percent(zero_rpm.spin_up_pwm)),
+            _ => target,
+        }
+    }
+
+    /// Applies the curve's `min-duty` and `kick-pwm` to a `target` for a fan
+    /// now at `current`: a target other than 0 is at least `min-duty`, and a
+    /// fan starting from 0 gets at least `kick-pwm` for `kick-ms`.
+    pub fn start_assist(&mut self, current: Option<u8>, target: u8) -> u8 {
+        if target == 0 {
+            return 0;
+        }
+        let curve = &self.curve;
+        if curve.kick_pwm > 0 && current == Some(0) && !self.spinning_up() {
+            self.spin_up_until = Some(Instant::now() + Duration::from_millis(curve.kick_ms));
+        }
+        let target = target.max(curve.min_duty);
+        if self.spinning_up() { target.max(curve.kick_pwm) } else { target }
+    }
+
+    /// True during the spin-up kick, which the slew rate does not hold back.
+    pub fn spinning_up(&self) -> bool {
+        self.spin_up_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// The next step from `current` toward `target` when the PWM may move at
+    /// most `rate` percentage points per second since the last write; at
+    /// least one step of the curve's unit, so a slow rate still gets there. A rate of 0, or no
+    /// earlier write to measure from, jumps straight to `target`. Steps never
+    /// land between 0 and `min-duty`, where the fan would not turn.
+    pub fn slewed(&self, current: Option<u8>, target: u8, rate: f32) -> u8 {
+        let (Some(current), Some(last_write)) = (current, self.last_write) else {
+            return target;
+        };
+        if rate <= 0.0 {
+            return target;
+        }
+        let full = f32::from(self.curve.unit.full());
+        let max_step = (rate * full / 100.0 * last_write.elapsed().as_secs_f32()).clamp(1.0, full) as u8;
+        let next = if target > current {
+            current.saturating_add(max_step).min(target)
+        } else {
+            current.saturating_sub(max_step).max(target)
+        };
+        if next > 0 && next < self.curve.min_duty {
+            if target > current { self.curve.min_duty } else { target }
+        } else {
+            next
+        }
+    }
+}
+
+/// tempN_crit and tempN_max of each GPU channel, by sensor name, for
+/// thresholds the config leaves unset. Channels without limits are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TempLimits {
+    pub channels: BTreeMap<String, ChannelLimits>,
+}
+
+/// One channel's tempN_crit and tempN_max (°C)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelLimits {
+    pub crit: Option<f32>,
+    pub max: Option<f32>,
+}
+
+/// Latest [`ThermalStatus`] published by the thermal thread, so the governor
+/// decides from the same readings as the fan curves and thresholds. None
+/// until the first reading.
+#[derive(Debug, Clone, Default)]
+pub struct SharedThermalStatus(Arc<Mutex<Option<ThermalStatus>>>);
+
+impl SharedThermalStatus {
+    pub fn store(&self, status: &ThermalStatus) {
+        *self.0.lock().unwrap() = Some(status.clone());
+    }
+
+    pub fn load(&self) -> Option<ThermalStatus> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Thermal event counts kept by the thermal thread and folded into the
+/// governor's stats.
+#[derive(Debug, Clone, Default)]
+pub struct ThermalCounters {
+    pub warnings: Arc<AtomicU64>,
+    pub emergencies: Arc<AtomicU64>,
+    pub fan_failures: Arc<AtomicU64>,
+    pub fan_stalls: Arc<AtomicU64>,
+}
+
+impl ThermalCounters {
+    pub fn bump(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Flags a fan whose tachometer reads 0 RPM while its PWM duty is high.
+#[derive(Debug, Default)]
+pub struct FanStallWatch {
+    since: Option<Instant>,
+}
+
+impl FanStallWatch {
+    /// Feeds one reading; true once the fan has stood still at `min_pwm`
+    /// percent or more for `hold`. A missing reading resets the watch.
+    pub fn update(&mut self, pwm_percent: Option<u8>, rpm: Option<u32>, min_pwm: u8, hold: Duration) -> bool {
+        if pwm_percent.is_some_and(|p| p >= min_pwm) && rpm == Some(0) {
+            self.since.get_or_insert_with(Instant::now).elapsed() >= hold
+        } else {
+            self.since = None;
+            false
+        }
+    }
+
+    /// How long the fan has been standing still, zero if it is turning.
+    pub fn stalled_for(&self) -> Duration {
+        self.since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
+
+/// Width of one PWM duty band in percent.
+pub const FAN_BAND_WIDTH: u8 = 20;
+const FAN_BANDS: usize = 5;
+
+/// Time a fan spent in each PWM band (0-20%, 20-40%, ... 80-100%).
+#[derive(Serialize, Debug, Clone)]
+pub struct FanResidency {
+    pub name: String,
+    pub band_secs: [f32; FAN_BANDS],
+}
+
+impl FanResidency {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), band_secs: [0.0; FAN_BANDS] }
+    }
+
+    /// Adds `elapsed` to the band containing the raw (0-255) PWM value.
+    pub fn record(&mut self, pwm_raw: u8, elapsed: Duration) {
+        let percent = (pwm_raw as u32 * 100 / 255) as usize;
+        let band = (percent / FAN_BAND_WIDTH as usize).min(FAN_BANDS - 1);
+        self.band_secs[band] += elapsed.as_secs_f32();
+    }
+
+    pub fn total_secs(&self) -> f32 {
+        self.band_secs.iter().sum()
+    }
+
+    /// Seconds spent at or above `percent` duty, rounded down to a band edge.
+    pub fn secs_above(&self, percent: u8) -> f32 {
+        let first = (percent / FAN_BAND_WIDTH) as usize;
+        self.band_secs.iter().skip(first).sum()
+    }
+
+    /// One-line summary for the session report.
+    pub fn summary(&self) -> String {
+        let total = self.total_secs().max(f32::EPSILON);
+        let bands: Vec<String> = self.band_secs.iter().enumerate()
+            .map(|(i, secs)| {
+                let low = i as u8 * FAN_BAND_WIDTH;
+                format!("{}-{}%: {:.1}%", low, low + FAN_BAND_WIDTH, secs * 100.0 / total)
+            })
+            .collect();
+        format!("{} | above 60%: {:.0}s of {:.0}s", bands.join(" "), self.secs_above(60), self.total_secs())
+    }
+}
+
+/// Picks the fan speed for `temp` given the last commanded speed. Rising
+/// temperatures follow `up_curve` immediately; falling temperatures follow
+/// `down_curve` (or `up_curve` shifted up by `down_hysteresis` degrees) and
+/// never drop below what `up_curve` asks for.
+pub fn next_fan_speed(temp: f32, current: Option<u8>, up_curve: &[(f32, u8)], down_curve: &[(f32, u8)], down_hysteresis: f32) -> u8 {
+    let up = interpolate_curve(temp, up_curve);
+    let Some(current) = current else {
+        return up;
+    };
+    if up >= current {
+        return up;
+    }
+    let down = if down_curve.is_empty() {
+        interpolate_curve(temp + down_hysteresis, up_curve)
+    } else {
+        interpolate_curve(temp, down_curve)
+    };
+    current.min(down).max(up)
 }
 
-pub fn calculate_fan_speed(temp: f32, curve: &[(f32, u8)]) -> u8 {
+/// Percentage a `[temperature, percent]` curve gives for `temp`: linear
+/// between points, flat beyond either end. Drives fan speeds and the
+/// thermal frequency throttle.
+pub fn interpolate_curve(temp: f32, curve: &[(f32, u8)]) -> u8 {
     if curve.is_empty() {
         return 0;
     }
@@ -0,0 +1,171 @@
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph},
+    Frame,
+};
+use serde_json::Value;
+use std::{
+    collections::VecDeque,
+    io::Error as IoError,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::control;
+
+/// Seconds of history kept on screen
+const WINDOW_SECS: f64 = 120.0;
+
+/// Legend name, colour and (seconds, value) points of one plotted line
+type Series<'a> = (&'a str, Color, &'a [(f64, f64)]);
+
+/// One status poll, as plotted.
+struct Point {
+    t: f64,
+    busy: f64,
+    target: f64,
+    applied: f64,
+    gpu_temp: f64,
+    cpu_temp: f64,
+    fan: Option<f64>,
+}
+
+#[derive(Default)]
+struct Dashboard {
+    history: VecDeque<Point>,
+    /// Last snapshot, for the header
+    latest: Option<Value>,
+    error: Option<String>,
+}
+
+impl Dashboard {
+    fn record(&mut self, t: f64, status: Value) {
+        let num = |path: &str| status.pointer(path).and_then(Value::as_f64);
+        self.history.push_back(Point {
+            t,
+            busy: num("/busy").unwrap_or(0.0) * 100.0,
+            target: num("/target_freq").unwrap_or(0.0),
+            applied: num("/applied_freq").unwrap_or(0.0),
+            gpu_temp: num("/thermal/amdgpu").unwrap_or(0.0),
+            cpu_temp: num("/thermal/cpu").unwrap_or(0.0),
+            fan: num("/thermal/fan_pwm_percent"),
+        });
+        while self.history.front().is_some_and(|p| t - p.t > WINDOW_SECS) {
+            self.history.pop_front();
+        }
+        self.latest = Some(status);
+        self.error = None;
+    }
+
+    fn series(&self, value: impl Fn(&Point) -> Option<f64>) -> Vec<(f64, f64)> {
+        self.history.iter().filter_map(|p| value(p).map(|v| (p.t, v))).collect()
+    }
+
+    fn header(&self) -> Line<'static> {
+        if let Some(e) = &self.error {
+            return Line::styled(format!("⚠️  {e}"), Style::default().fg(Color::Red));
+        }
+        let Some(status) = &self.latest else {
+            return Line::from("Waiting for the governor...");
+        };
+        let num = |path: &str| status.pointer(path).and_then(Value::as_f64).unwrap_or(0.0);
+        Line::from(format!(
+            "{} | {:.0}MHz applied, {:.0}MHz target | busy {:.0}% | GPU {:.1}°C CPU {:.1}°C | fan {} | q to quit",
+            status.pointer("/mode").and_then(Value::as_str).unwrap_or("na"),
+            num("/applied_freq"), num("/target_freq"), num("/busy") * 100.0,
+            num("/thermal/amdgpu"), num("/thermal/cpu"),
+            status.pointer("/thermal/fan_pwm_percent").and_then(Value::as_u64)
+                .map_or_else(|| "na".to_string(), |p| format!("{p}%")),
+        ))
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, busy, freq, temps, fan] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ]).areas(frame.area());
+        frame.render_widget(Paragraph::new(self.header()), header);
+
+        let end = self.history.back().map_or(WINDOW_SECS, |p| p.t.max(WINDOW_SECS));
+        let x = [end - WINDOW_SECS, end];
+
+        let busy_data = self.series(|p| Some(p.busy));
+        self.chart(frame, busy, "Busy %", x, [0.0, 100.0], vec![("busy", Color::Cyan, &busy_data)]);
+
+        let target = self.series(|p| Some(p.target));
+        let applied = self.series(|p| Some(p.applied));
+        let top = target.iter().chain(&applied).map(|p| p.1).fold(0.0, f64::max);
+        let top = ((top / 500.0).ceil() * 500.0).max(500.0);
+        self.chart(frame, freq, "Frequency MHz", x, [0.0, top],
+            vec![("target", Color::Yellow, &target), ("applied", Color::Green, &applied)]);
+
+        let gpu = self.series(|p| Some(p.gpu_temp));
+        let cpu = self.series(|p| Some(p.cpu_temp));
+        let hottest = gpu.iter().chain(&cpu).map(|p| p.1).fold(0.0, f64::max);
+        let top = ((hottest / 10.0).ceil() * 10.0 + 10.0).max(50.0);
+        self.chart(frame, temps, "Temperature °C", x, [20.0, top],
+            vec![("GPU", Color::Red, &gpu), ("CPU", Color::Magenta, &cpu)]);
+
+        let fan_data = self.series(|p| p.fan);
+        self.chart(frame, fan, "Fan PWM %", x, [0.0, 100.0], vec![("fan", Color::Blue, &fan_data)]);
+    }
+
+    fn chart(&self, frame: &mut Frame, area: Rect, title: &str, x: [f64; 2], y: [f64; 2], series: Vec<Series>) {
+        let datasets = series.into_iter().map(|(name, color, data)| {
+            Dataset::default()
+                .name(name.to_string())
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(data)
+        }).collect();
+        let chart = Chart::new(datasets)
+            .block(Block::bordered().title(title.to_string()))
+            .x_axis(Axis::default().bounds(x))
+            .y_axis(Axis::default().bounds(y).labels([
+                format!("{:.0}", y[0]), format!("{:.0}", (y[0] + y[1]) / 2.0), format!("{:.0}", y[1]),
+            ]));
+        frame.render_widget(chart, area);
+    }
+}
+
+/// Full-screen dashboard plotting the running governor's status over the
+/// last two minutes, polled from the control socket every `interval`.
+pub fn run(socket: &Path, interval: Duration) -> Result<(), IoError> {
+    let mut terminal = ratatui::init();
+    let started = Instant::now();
+    let mut dashboard = Dashboard::default();
+    let mut next_poll = Instant::now();
+
+    let result = loop {
+        if Instant::now() >= next_poll {
+            match control::call(socket, "status", Value::Null) {
+                Ok(status) => dashboard.record(started.elapsed().as_secs_f64(), status),
+                Err(e) => dashboard.error = Some(format!("{}: {}", socket.display(), e)),
+            }
+            next_poll += interval;
+        }
+        if let Err(e) = terminal.draw(|frame| dashboard.draw(frame)) {
+            break Err(e);
+        }
+        match event::poll(next_poll.saturating_duration_since(Instant::now())) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) => break Ok(()),
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            },
+            Ok(false) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    ratatui::restore();
+    result
+}
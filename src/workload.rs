@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::governor::PerformanceMode;
+
+/// Summary of recent GPU activity used to pick a mode automatically.
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct Fingerprint {
+    /// Mean busy ratio over the window (0.0-1.0)
+    pub busy: f32,
+    /// Standard deviation of the per-slice busy ratio; high for bursty loads
+    pub burstiness: f32,
+    /// Share of busy samples in which a compute register was active
+    pub compute_share: f32,
+}
+
+/// Busy/compute ratios of one slice of samples (one status tick).
+#[derive(Debug, Clone, Copy)]
+struct Slice {
+    at: Instant,
+    busy: f32,
+    busy_samples: u32,
+    compute_samples: u32,
+}
+
+/// Rolling window of sample slices from which a fingerprint is derived.
+#[derive(Debug)]
+pub struct FingerprintWindow {
+    span: Duration,
+    slices: VecDeque<Slice>,
+    samples: u32,
+    busy_samples: u32,
+    compute_samples: u32,
+}
+
+impl FingerprintWindow {
+    pub fn new(span: Duration) -> Self {
+        Self { span, slices: VecDeque::new(), samples: 0, busy_samples: 0, compute_samples: 0 }
+    }
+
+    pub fn set_span(&mut self, span: Duration) {
+        self.span = span;
+    }
+
+    pub fn push_sample(&mut self, busy: bool, compute: bool) {
+        self.samples += 1;
+        self.busy_samples += busy as u32;
+        self.compute_samples += compute as u32;
+    }
+
+    /// Closes the current slice and drops slices older than the window.
+    pub fn close_slice(&mut self, now: Instant) {
+        if self.samples > 0 {
+            self.slices.push_back(Slice {
+                at: now,
+                busy: self.busy_samples as f32 / self.samples as f32,
+                busy_samples: self.busy_samples,
+                compute_samples: self.compute_samples,
+            });
+        }
+        self.samples = 0;
+        self.busy_samples = 0;
+        self.compute_samples = 0;
+
+        while let Some(slice) = self.slices.front() {
+            if now.duration_since(slice.at) > self.span {
+                self.slices.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// None until the window holds at least half its span of history.
+    pub fn fingerprint(&self) -> Option<Fingerprint> {
+        let (first, last) = (self.slices.front()?, self.slices.back()?);
+        if last.at.duration_since(first.at) < self.span / 2 {
+            return None;
+        }
+
+        let n = self.slices.len() as f32;
+        let busy = self.slices.iter().map(|s| s.busy).sum::<f32>() / n;
+        let variance = self.slices.iter().map(|s| (s.busy - busy).powi(2)).sum::<f32>() / n;
+        let busy_samples: u32 = self.slices.iter().map(|s| s.busy_samples).sum();
+        let compute_samples: u32 = self.slices.iter().map(|s| s.compute_samples).sum();
+        let compute_share = if busy_samples > 0 {
+            compute_samples as f32 / busy_samples as f32
+        } else {
+            0.0
+        };
+
+        Some(Fingerprint { busy, burstiness: variance.sqrt(), compute_share })
+    }
+}
+
+/// Selects `mode` when every configured bound holds for the fingerprint.
+#[derive(Debug, Clone)]
+pub struct AutoRule {
+    pub mode: PerformanceMode,
+    pub min_busy: Option<f32>,
+    pub max_busy: Option<f32>,
+    pub min_burstiness: Option<f32>,
+    pub max_burstiness: Option<f32>,
+    pub min_compute: Option<f32>,
+    pub max_compute: Option<f32>,
+}
+
+impl AutoRule {
+    pub fn matches(&self, fp: &Fingerprint) -> bool {
+        let within = |value: f32, min: Option<f32>, max: Option<f32>| {
+            min.is_none_or(|m| value >= m) && max.is_none_or(|m| value <= m)
+        };
+        within(fp.busy, self.min_busy, self.max_busy)
+            && within(fp.burstiness, self.min_burstiness, self.max_burstiness)
+            && within(fp.compute_share, self.min_compute, self.max_compute)
+    }
+}
+
+/// Mode of the first rule matching the fingerprint.
+pub fn select(rules: &[AutoRule], fp: &Fingerprint) -> Option<PerformanceMode> {
+    rules.iter().find(|r| r.matches(fp)).map(|r| r.mode)
+}
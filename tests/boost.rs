@@ -0,0 +1,49 @@
+use bc_250_rust_governor::{config::BoostBudgetConfig, governor::BoostBudget};
+
+/// 10s above 1800MHz, refilled from empty in 20s, tapering over the last half.
+fn budget() -> BoostBudget {
+    BoostBudgetConfig { boost_mhz: 1800, budget_secs: 10.0, refill_secs: 20.0, taper: 0.5, ..Default::default() }.budget()
+}
+
+#[test]
+fn boost_drains_into_a_tapering_ceiling() {
+    let mut budget = budget();
+
+    // Cool at the top: a second of budget per second, no ceiling above the taper
+    let ceilings: Vec<Option<f32>> = (0..5).map(|_| budget.update(1.0, 2000.0, 0.0, 2000.0)).collect();
+    assert!(ceilings.iter().all(Option::is_none), "{ceilings:?}");
+    assert_eq!(budget.level(), 0.5);
+    // Below the taper the ceiling eases from the top of the table to the boost frequency
+    assert_eq!(budget.update(1.0, 2000.0, 0.0, 2000.0), Some(1960.0));
+    assert_eq!(budget.update(2.0, 2000.0, 0.0, 2000.0), Some(1880.0));
+    assert_eq!(budget.update(10.0, 2000.0, 0.0, 2000.0), Some(1800.0));
+    assert_eq!(budget.level(), 0.0);
+}
+
+#[test]
+fn heat_drains_faster_and_refills_slower() {
+    let mut hot = budget();
+    hot.update(1.0, 2000.0, 1.0, 2000.0);
+    assert_eq!(hot.level(), 0.8);
+    // Past the warning threshold counts as at it
+    hot.update(1.0, 2000.0, 3.0, 2000.0);
+    assert_eq!(hot.level(), 0.6);
+
+    // At or below the boost frequency the budget refills, half a second per second when cool
+    hot.update(2.0, 1800.0, 0.0, 2000.0);
+    assert_eq!(hot.level(), 0.7);
+    hot.update(2.0, 1500.0, 0.5, 2000.0);
+    assert_eq!(hot.level(), 0.75);
+    // Not at all at the warning threshold, and never past full
+    hot.update(10.0, 1500.0, 1.0, 2000.0);
+    assert_eq!(hot.level(), 0.75);
+    hot.update(100.0, 1500.0, 0.0, 2000.0);
+    assert_eq!(hot.level(), 1.0);
+}
+
+#[test]
+fn boost_at_the_top_of_the_table_never_caps() {
+    let mut budget = budget();
+    assert_eq!(budget.update(20.0, 2000.0, 0.0, 1800.0), None);
+    assert_eq!(budget.level(), 0.0);
+}
@@ -0,0 +1,281 @@
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use tempfile::TempDir;
+
+const BIN: &str = env!("CARGO_BIN_EXE_bc-250-rust-governor");
+/// A run that outlives this is killed, so a scenario without `duration-secs` cannot hang the suite
+const RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Safe points and the paths of everything the daemon writes, all inside
+/// the test's directory. Tests append their own sections after it.
+const BASE_CONFIG: &str = r#"
+safe-points = [
+    { frequency = 350, voltage = 700 },
+    { frequency = 1000, voltage = 700 },
+    { frequency = 1500, voltage = 800 },
+    { frequency = 2000, voltage = 950 },
+]
+
+[performance-mode]
+enabled = true
+control_file = "{dir}/max-performance"
+check_interval = 200
+
+[control]
+enabled = true
+socket = "{dir}/control.sock"
+fifo = "{dir}/command.fifo"
+
+[status]
+enabled = true
+file = "{dir}/status.json"
+interval = 100
+fifo = ""
+
+[startup]
+state-file = "{dir}/last-frequency"
+
+[config-watch]
+enabled = false
+"#;
+
+/// A governor run against the mock backend, with the mock sysfs tree
+/// (pp_od_clk_voltage, DPM tables, hwmon, cpufreq) in a temporary directory.
+pub struct Harness {
+    dir: TempDir,
+    config: String,
+    scenario: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+impl Harness {
+    /// `scenario` is a fault-injection scenario; give it a `duration-secs`
+    /// so the daemon shuts itself down.
+    pub fn new(scenario: &str) -> Self {
+        let dir = tempfile::tempdir().expect("cannot create a temporary directory");
+        let config = BASE_CONFIG.replace("{dir}", &dir.path().display().to_string());
+        Self { dir, config, scenario: scenario.to_string(), args: Vec::new(), env: Vec::new() }
+    }
+
+    /// Appends config sections; they must not repeat the base config's tables.
+    pub fn with_config(mut self, sections: &str) -> Self {
+        self.config.push_str(sections);
+        self
+    }
+
+    /// Adds top-level keys, which have to come before the base config's tables.
+    pub fn with_keys(mut self, keys: &str) -> Self {
+        self.config.insert_str(0, keys);
+        self
+    }
+
+    /// Passes extra options to the daemon.
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|a| a.to_string()));
+        self
+    }
+
+    /// Sets an environment variable for the daemon.
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.path("config.toml")
+    }
+
+    /// The mock sysfs tree, left in place after the run.
+    pub fn sysfs(&self) -> PathBuf {
+        self.path("sysfs")
+    }
+
+    pub fn spawn(&self) -> Running {
+        fs::write(self.config_path(), &self.config).unwrap();
+        // Top-level keys have to come before the [[fault]] tables
+        let scenario = format!("root = \"{}\"\n{}", self.sysfs().display(), self.scenario);
+        fs::write(self.path("scenario.toml"), scenario).unwrap();
+        let child = Command::new(BIN)
+            .arg(self.config_path())
+            .arg("--mock").arg(self.path("scenario.toml"))
+            .arg("--record").arg(self.path("trace.csv"))
+            .args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("cannot start the governor");
+        Running::new(child, self.dir.path().to_path_buf())
+    }
+
+    /// Runs the scenario to its end.
+    pub fn run(&self) -> Finished {
+        self.spawn().wait()
+    }
+
+    /// Runs a governor that is expected to refuse to start, returning its output.
+    pub fn run_refused(&self) -> String {
+        let mut running = self.spawn();
+        let status = running.child.wait().unwrap();
+        let log = running.log();
+        assert!(!status.success(), "the governor started:\n{log}");
+        log
+    }
+}
+
+pub struct Running {
+    child: Child,
+    dir: PathBuf,
+    /// Drain stdout and stderr while the daemon runs, so a full pipe cannot block it
+    output: Option<(JoinHandle<String>, JoinHandle<String>)>,
+    /// The daemon removes its status file on shutdown, so keep the last one seen
+    last_status: Option<serde_json::Value>,
+}
+
+impl Running {
+    fn new(mut child: Child, dir: PathBuf) -> Self {
+        let output = (drain(child.stdout.take().unwrap()), drain(child.stderr.take().unwrap()));
+        Self { child, dir, output: Some(output), last_status: None }
+    }
+
+    /// stdout followed by stderr, once the daemon has exited.
+    fn log(&mut self) -> String {
+        let (stdout, stderr) = self.output.take().expect("the log was already collected");
+        stdout.join().unwrap() + &stderr.join().unwrap()
+    }
+
+    /// Reads the status file, remembering it for [`Running::wait`].
+    fn poll_status(&mut self) -> Option<&serde_json::Value> {
+        if let Some(json) = fs::read_to_string(self.dir.join("status.json")).ok()
+            .and_then(|text| serde_json::from_str(&text).ok()) {
+            self.last_status = Some(json);
+        }
+        self.last_status.as_ref()
+    }
+
+    /// Blocks until the status file shows `what`, as `check` tells, and returns it.
+    pub fn wait_for_status(&mut self, what: &str, check: impl Fn(&serde_json::Value) -> bool) -> serde_json::Value {
+        let deadline = Instant::now() + RUN_TIMEOUT;
+        loop {
+            if let Some(status) = self.poll_status().filter(|status| check(status)) {
+                return status.clone();
+            }
+            if let Some(status) = self.child.try_wait().unwrap() {
+                panic!("the governor exited ({status}) before the status showed {what}:\n{}", self.log());
+            }
+            if Instant::now() >= deadline {
+                let _ = self.child.kill();
+                panic!("the status never showed {what} within {:?}", RUN_TIMEOUT);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    pub fn wait(mut self) -> Finished {
+        let deadline = Instant::now() + RUN_TIMEOUT;
+        let status = loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                break status;
+            }
+            self.poll_status();
+            if Instant::now() >= deadline {
+                let _ = self.child.kill();
+                panic!("the governor was still running after {:?}", RUN_TIMEOUT);
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+        let log = self.log();
+        assert!(status.success(), "the governor failed ({status}):\n{log}");
+
+        let status = self.last_status.unwrap_or_else(|| panic!("no status file was written:\n{log}"));
+        let trace = fs::read_to_string(self.dir.join("trace.csv")).unwrap_or_default();
+        Finished { log, status, trace: parse_trace(&trace) }
+    }
+}
+
+/// Reads `pipe` to its end on a background thread.
+fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// One row of the recorded trace.
+#[derive(Debug)]
+pub struct TraceRow {
+    pub ms: f64,
+    pub event: String,
+    pub values: Vec<String>,
+}
+
+fn parse_trace(text: &str) -> Vec<TraceRow> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(',');
+            let ms = fields.next()?.parse().ok()?;
+            let event = fields.next()?.to_string();
+            Some(TraceRow { ms, event, values: fields.map(str::to_string).collect() })
+        })
+        .collect()
+}
+
+pub struct Finished {
+    /// stdout followed by stderr
+    pub log: String,
+    /// The last status file seen before the daemon exited
+    pub status: serde_json::Value,
+    pub trace: Vec<TraceRow>,
+}
+
+impl Finished {
+    pub fn events<'a>(&'a self, event: &'a str) -> impl Iterator<Item = &'a TraceRow> + 'a {
+        self.trace.iter().filter(move |row| row.event == event)
+    }
+
+    /// Frequencies sent to the setter, in order.
+    pub fn applies(&self) -> Vec<u16> {
+        self.events("apply").map(|row| row.values[0].parse().unwrap()).collect()
+    }
+
+    pub fn applied_freq(&self) -> u64 {
+        self.status["applied_freq"].as_u64().unwrap()
+    }
+
+    pub fn stat(&self, name: &str) -> u64 {
+        self.status["stats"][name].as_u64().unwrap_or_else(|| panic!("no stat {name} in {}", self.status))
+    }
+}
+
+/// Runs the binary with `args` and returns its combined output, failing the test if it fails.
+pub fn run_command(args: &[&str]) -> String {
+    let output = Command::new(BIN).args(args).output().expect("cannot run the governor");
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(output.status.success(), "{args:?} failed:\n{text}");
+    text
+}
+
+/// Reads the OD_SCLK clock from a pp_od_clk_voltage file.
+pub fn od_sclk(path: &Path) -> u16 {
+    let table = fs::read_to_string(path).unwrap();
+    table.lines()
+        .skip_while(|line| !line.starts_with("OD_SCLK"))
+        .nth(1)
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|freq| freq.trim_end_matches("Mhz").parse().ok())
+        .unwrap_or_else(|| panic!("no OD_SCLK in {}:\n{}", path.display(), table))
+}
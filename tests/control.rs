@@ -0,0 +1,128 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use bc_250_rust_governor::control::{call, start_fifo, start_server, ControlContext, PerfRequests};
+use serde_json::{json, Value};
+
+/// Serves `ctx` on a socket in a temporary directory for the length of `test`.
+fn with_server(ctx: &ControlContext, test: impl FnOnce(&dyn Fn(&str, Value) -> Result<Value, String>)) {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("control.sock");
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let server = start_server(socket.clone(), "", ctx.clone(), Arc::clone(&shutdown)).unwrap();
+    test(&|method, params| call(&socket, method, params).map_err(|e| e.to_string()));
+    shutdown.store(true, Ordering::SeqCst);
+    server.join().unwrap();
+}
+
+#[test]
+fn perf_requests_are_refcounted_per_client() {
+    let ctx = ControlContext::default();
+    with_server(&ctx, |call| {
+        assert_eq!(call("perf.request", json!({ "client": "game" })).unwrap(), json!({ "active": 1 }));
+        assert_eq!(call("perf.request", json!({ "client": "bench", "pid": std::process::id() })).unwrap(), json!({ "active": 2 }));
+        // Renewing a hold does not count twice
+        assert_eq!(call("perf.request", json!({ "client": "game" })).unwrap(), json!({ "active": 2 }));
+        assert_eq!(call("perf.list", json!({})).unwrap(), json!({ "clients": ["bench", "game"] }));
+        assert_eq!(call("perf.release", json!({ "client": "game" })).unwrap(), json!({ "released": true, "active": 1 }));
+        assert_eq!(call("perf.release", json!({ "client": "game" })).unwrap(), json!({ "released": false, "active": 1 }));
+        assert!(call("perf.request", json!({})).is_err());
+    });
+    assert_eq!(ctx.perf_requests.lock().unwrap().clients(), ["bench"]);
+}
+
+#[test]
+fn mode_set_holds_max_performance_for_its_client() {
+    let ctx = ControlContext::default();
+    with_server(&ctx, |call| {
+        assert_eq!(call("mode.set", json!({ "mode": "max-performance" })).unwrap(),
+                   json!({ "mode": "max-performance", "client": "rpc", "active": 1 }));
+        call("mode.set", json!({ "mode": "max-performance", "client": "game" })).unwrap();
+        // Normal only releases the caller's own hold
+        assert_eq!(call("mode.set", json!({ "mode": "normal" })).unwrap()["active"], 1);
+        assert!(call("mode.set", json!({ "mode": "turbo" })).unwrap_err().contains("unknown mode 'turbo'"));
+        assert!(call("mode.set", json!({})).is_err());
+    });
+    assert_eq!(ctx.perf_requests.lock().unwrap().clients(), ["game"]);
+}
+
+#[test]
+fn frequency_lock_pause_and_reload_reach_the_governor() {
+    let ctx = ControlContext::default();
+    with_server(&ctx, |call| {
+        assert_eq!(call("freq.lock", json!({ "frequency": 1800 })).unwrap(), json!({ "frequency": 1800 }));
+        assert_eq!(*ctx.freq_lock.lock().unwrap(), Some(1800));
+        assert!(call("freq.lock", json!({ "frequency": 70000 })).is_err());
+        assert_eq!(*ctx.freq_lock.lock().unwrap(), Some(1800));
+        assert_eq!(call("freq.unlock", json!({})).unwrap(), json!({ "released": true }));
+        assert_eq!(call("freq.unlock", json!({})).unwrap(), json!({ "released": false }));
+        assert_eq!(*ctx.freq_lock.lock().unwrap(), None);
+
+        // Pausing is the default; resuming needs paused = false
+        assert_eq!(call("auto.pause", Value::Null).unwrap(), json!({ "paused": true }));
+        assert!(ctx.auto_mode_paused.load(Ordering::SeqCst));
+        call("auto.pause", json!({ "paused": false })).unwrap();
+        assert!(!ctx.auto_mode_paused.load(Ordering::SeqCst));
+
+        call("reload", json!({})).unwrap();
+        assert!(ctx.reload_request.load(Ordering::SeqCst));
+        assert!(call("governor.stop", json!({})).unwrap_err().contains("unknown method"));
+    });
+}
+
+#[test]
+fn holds_expire_with_their_ttl_or_process() {
+    let mut exited = Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+
+    let mut requests = PerfRequests::default();
+    requests.request("open-ended", None, None);
+    requests.request("short", None, Some(Duration::from_millis(50)));
+    requests.request("long", None, Some(Duration::from_secs(60)));
+    requests.request("alive", Some(std::process::id()), None);
+    requests.request("exited", Some(exited.id()), None);
+    assert_eq!(requests.active(), 4);
+    sleep(Duration::from_millis(100));
+    assert_eq!(requests.active(), 3);
+    assert_eq!(requests.clients(), ["alive", "long", "open-ended"]);
+}
+
+#[test]
+fn fifo_commands_run_as_control_methods() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("command.fifo");
+    let ctx = ControlContext::default();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let reader = start_fifo(path.clone(), "", ctx.clone(), Arc::clone(&shutdown)).unwrap();
+    let send = |lines: &str| OpenOptions::new().write(true).open(&path).unwrap().write_all(lines.as_bytes()).unwrap();
+    let wait_for = |done: &dyn Fn() -> bool| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "the FIFO commands were not applied");
+            sleep(Duration::from_millis(20));
+        }
+    };
+
+    // A bad line is logged and skipped without stopping the ones after it
+    send("mode max\nlock fast\n\nlock 1800\n");
+    wait_for(&|| *ctx.freq_lock.lock().unwrap() == Some(1800));
+    assert_eq!(ctx.perf_requests.lock().unwrap().clients(), ["fifo"]);
+
+    send("unlock\nmode normal\nreload\n");
+    wait_for(&|| ctx.reload_request.load(Ordering::SeqCst));
+    assert_eq!(*ctx.freq_lock.lock().unwrap(), None);
+    assert!(ctx.perf_requests.lock().unwrap().clients().is_empty());
+
+    shutdown.store(true, Ordering::SeqCst);
+    reader.join().unwrap();
+    assert!(!path.exists());
+}
@@ -0,0 +1,28 @@
+use bc_250_rust_governor::dbus::render_policy;
+
+/// The policy blocks as (opening tag, rules), in file order.
+fn policies(xml: &str) -> Vec<(&str, Vec<&str>)> {
+    xml.split("<policy ").skip(1)
+        .map(|block| {
+            let block = block.split("</policy>").next().unwrap();
+            let (tag, rules) = block.split_once('>').unwrap();
+            (tag, rules.lines().map(str::trim).filter(|l| !l.is_empty()).collect())
+        })
+        .collect()
+}
+
+#[test]
+fn only_root_and_the_control_group_may_call() {
+    assert_eq!(policies(&render_policy("games")), [
+        ("user=\"root\"", vec!["<allow own=\"org.bc250.Governor\"/>", "<allow send_destination=\"org.bc250.Governor\"/>"]),
+        ("group=\"games\"", vec!["<allow send_destination=\"org.bc250.Governor\"/>"]),
+        ("context=\"default\"", vec!["<deny send_destination=\"org.bc250.Governor\"/>"]),
+    ]);
+}
+
+#[test]
+fn without_a_group_only_root_may_call() {
+    let policy = render_policy("");
+    let tags: Vec<&str> = policies(&policy).into_iter().map(|(tag, _)| tag).collect();
+    assert_eq!(tags, ["user=\"root\"", "context=\"default\""]);
+}
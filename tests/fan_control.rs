@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use bc_250_rust_governor::{
+    config::{Config, CurveUnit, FanCurve, ZeroRpm},
+    fan_calibrate::{to_config_snippet, FanCalibration},
+    thermal::{FanLoop, FanRef, FanStallWatch},
+};
+
+fn fan_loop(last_write: Option<Instant>) -> FanLoop {
+    let curve = FanCurve { kick_ms: 0, ..FanCurve::new(FanRef::Index(0), "max", vec![(50.0, 20), (80.0, 80)]) };
+    FanLoop {
+        index: 0,
+        curve,
+        speed: None,
+        stall: FanStallWatch::default(),
+        stall_active: false,
+        last_write,
+        stopped: false,
+        spin_up_until: None,
+        write_failures: 0,
+    }
+}
+
+#[test]
+fn small_changes_are_held_back() {
+    let fan = fan_loop(None);
+    let any = Duration::ZERO;
+
+    assert!(!fan.should_write(Some(40), 42, 3, any));
+    assert!(fan.should_write(Some(40), 43, 3, any));
+    assert!(fan.should_write(Some(40), 37, 3, any));
+    assert!(!fan.should_write(Some(40), 40, 0, any));
+    // Unknown PWM is always written, and full speed is never held back
+    assert!(fan.should_write(None, 41, 3, any));
+    assert!(fan.should_write(Some(99), 100, 3, any));
+}
+
+#[test]
+fn writes_wait_for_the_minimum_interval() {
+    let interval = Duration::from_secs(5);
+
+    assert!(!fan_loop(Some(Instant::now())).should_write(Some(40), 60, 3, interval));
+    assert!(fan_loop(Some(Instant::now() - interval)).should_write(Some(40), 60, 3, interval));
+    assert!(fan_loop(None).should_write(Some(40), 60, 3, interval));
+    assert!(fan_loop(Some(Instant::now())).should_write(Some(40), 100, 3, interval));
+}
+
+#[test]
+fn stall_needs_zero_rpm_at_high_pwm_for_the_hold_time() {
+    let mut watch = FanStallWatch::default();
+
+    assert!(!watch.update(Some(30), Some(0), 40, Duration::ZERO), "low duty may legitimately stop the fan");
+    assert!(!watch.update(Some(60), Some(900), 40, Duration::ZERO));
+    assert!(!watch.update(Some(60), None, 40, Duration::ZERO), "no tachometer reading");
+    assert!(watch.update(Some(60), Some(0), 40, Duration::ZERO));
+    assert!(!watch.update(Some(60), Some(0), 40, Duration::from_secs(60)), "stalled for less than the hold time");
+    assert!(!watch.update(Some(60), Some(500), 40, Duration::ZERO), "a turning fan resets the watch");
+    assert_eq!(watch.stalled_for(), Duration::ZERO);
+}
+
+#[test]
+fn slew_rate_limits_each_step() {
+    let two_secs_ago = fan_loop(Some(Instant::now() - Duration::from_secs(2)));
+
+    assert_eq!(two_secs_ago.slewed(Some(30), 90, 10.0), 50);
+    assert_eq!(two_secs_ago.slewed(Some(90), 30, 10.0), 70);
+    assert_eq!(two_secs_ago.slewed(Some(30), 40, 10.0), 40, "never overshoots the target");
+    assert_eq!(two_secs_ago.slewed(Some(30), 90, 0.0), 90, "0 disables the limit");
+    assert_eq!(fan_loop(None).slewed(Some(30), 90, 10.0), 90, "nothing to measure from yet");
+    assert_eq!(fan_loop(Some(Instant::now())).slewed(Some(30), 90, 0.1), 31, "moves at least one point");
+}
+
+#[test]
+fn raw_curves_count_in_pwm_values() {
+    let mut fan = fan_loop(Some(Instant::now() - Duration::from_secs(2)));
+    fan.curve.unit = CurveUnit::Pwm;
+
+    assert!(fan.should_write(Some(250), 255, 10, Duration::ZERO), "full speed is 255");
+    assert!(!fan.should_write(Some(90), 100, 15, Duration::ZERO));
+    // 10 points per second is 25 PWM steps per second
+    assert_eq!(fan.slewed(Some(50), 200, 10.0), 101);
+    assert_eq!(CurveUnit::Pwm.from_percent(40), 102);
+    assert_eq!(CurveUnit::Pwm.to_raw(77), 77);
+    assert_eq!(CurveUnit::Percent.to_raw(30), 76);
+}
+
+#[test]
+fn zero_rpm_stops_and_restarts_with_hysteresis() {
+    let zero_rpm = ZeroRpm { enabled: true, stop_below: 45.0, start_above: 50.0, spin_up_pwm: 40, spin_up_ms: 60_000 };
+    let mut fan = fan_loop(None);
+
+    assert_eq!(fan.zero_rpm(55.0, 25, &zero_rpm), 25);
+    assert_eq!(fan.zero_rpm(44.0, 15, &zero_rpm), 0);
+    assert!(fan.stopped);
+    // Between the thresholds a stopped fan stays off
+    assert_eq!(fan.zero_rpm(48.0, 18, &zero_rpm), 0);
+    assert_eq!(fan.zero_rpm(50.0, 20, &zero_rpm), 40, "restarts at the spin-up duty");
+    assert!(fan.spinning_up());
+    assert_eq!(fan.zero_rpm(60.0, 70, &zero_rpm), 70, "the curve wins when it asks for more");
+    // Between the thresholds a running fan keeps running
+    assert_eq!(fan.zero_rpm(48.0, 18, &zero_rpm), 40);
+    assert!(!fan.stopped);
+}
+
+#[test]
+fn zero_rpm_disabled_leaves_the_curve_alone() {
+    let mut fan = fan_loop(None);
+    assert_eq!(fan.zero_rpm(20.0, 10, &ZeroRpm::default()), 10);
+    assert!(!fan.stopped);
+}
+
+#[test]
+fn min_duty_and_kick_get_the_fan_turning() {
+    let mut fan = fan_loop(Some(Instant::now() - Duration::from_secs(2)));
+    (fan.curve.min_duty, fan.curve.kick_pwm, fan.curve.kick_ms) = (25, 60, 60_000);
+
+    assert_eq!(fan.start_assist(Some(30), 10), 25, "raised to the minimum duty");
+    assert_eq!(fan.start_assist(Some(30), 0), 0, "0 still stops the fan");
+    assert!(!fan.spinning_up(), "a turning fan needs no kick");
+    assert_eq!(fan.start_assist(Some(0), 30), 60, "kicked when starting from 0");
+    assert!(fan.spinning_up());
+    assert_eq!(fan.start_assist(Some(60), 30), 60, "the kick is held");
+    // The slew rate never leaves the fan between 0 and the minimum duty
+    assert_eq!(fan.slewed(Some(0), 40, 10.0), 25);
+    assert_eq!(fan.slewed(Some(30), 0, 10.0), 0);
+}
+
+#[test]
+fn suggests_a_curve_from_the_start_duty_up() {
+    let fan = FanCalibration {
+        name: "nct6798_pwm2".to_string(),
+        samples: vec![(0, 0), (10, 0), (20, 0), (30, 600), (40, 900), (50, 1200), (60, 1150), (70, 1500), (80, 1700), (90, 1900), (100, 2000)],
+    };
+    assert_eq!(fan.start_duty(), Some(30));
+    // Half of 2000 RPM at 50%, three quarters at 70%
+    assert_eq!(fan.suggested_curve(), Some(vec![(45.0, 30), (60.0, 50), (75.0, 70), (85.0, 100)]));
+
+    let dead = FanCalibration { name: "nct6798_pwm3".to_string(), samples: vec![(0, 0), (100, 0)] };
+    assert_eq!(dead.suggested_curve(), None);
+
+    let snippet = to_config_snippet(&[fan, dead]);
+    assert!(snippet.contains("never turned"), "{snippet}");
+    let config: Config = toml::from_str(&snippet).unwrap_or_else(|e| panic!("{e}\n{snippet}"));
+    assert_eq!(config.thermal.fan_control.stall_pwm, 30);
+    assert_eq!(config.thermal.fan_curves().len(), 1);
+}
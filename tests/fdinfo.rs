@@ -0,0 +1,37 @@
+use bc_250_rust_governor::fdinfo::parse_fdinfo;
+
+const GAME: &str = "\
+pos:\t0
+flags:\t02100002
+mnt_id:\t24
+ino:\t1066
+drm-driver:\tamdgpu
+drm-pdev:\t0000:01:00.0
+drm-client-id:\t42
+drm-memory-vram:\t1048576 KiB
+drm-engine-gfx:\t1500000 ns
+drm-engine-compute:\t250000 ns
+drm-engine-dec:\t0 ns
+drm-engine-capacity-gfx:\t2
+";
+
+#[test]
+fn engine_time_adds_up_without_capacities() {
+    assert_eq!(parse_fdinfo(GAME, None), Some((42, 1_750_000)));
+    assert_eq!(parse_fdinfo(GAME, Some("0000:01:00.0")), Some((42, 1_750_000)));
+}
+
+#[test]
+fn other_gpus_and_drivers_are_skipped() {
+    assert_eq!(parse_fdinfo(GAME, Some("0000:02:00.0")), None);
+    assert_eq!(parse_fdinfo(&GAME.replace("amdgpu", "i915"), None), None);
+    // Render nodes opened before the client registered, and plain files
+    assert_eq!(parse_fdinfo(&GAME.replace("drm-client-id:\t42\n", ""), None), None);
+    assert_eq!(parse_fdinfo("pos:\t0\nflags:\t02100002\n", None), None);
+}
+
+#[test]
+fn client_without_engine_time_counts_zero() {
+    let idle = "drm-driver:\tamdgpu\ndrm-client-id:\t7\ndrm-engine-gfx:\t0 ns\n";
+    assert_eq!(parse_fdinfo(idle, None), Some((7, 0)));
+}
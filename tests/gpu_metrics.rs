@@ -0,0 +1,51 @@
+use std::io::ErrorKind;
+
+use bc_250_rust_governor::sampler::{parse_metrics, Dither, Metrics};
+
+/// A zeroed blob of `size` bytes with the given header and u16 fields set.
+fn blob(size: usize, format: u8, content: u8, fields: &[(usize, u16)]) -> Vec<u8> {
+    let mut blob = vec![0; size];
+    blob[..2].copy_from_slice(&(size as u16).to_le_bytes());
+    blob[2] = format;
+    blob[3] = content;
+    for &(offset, value) in fields {
+        blob[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+    blob
+}
+
+#[test]
+fn dgpu_metrics_are_whole_units() {
+    // v1_3: edge temperature, average GFX activity, average socket power
+    let metrics = parse_metrics(&blob(120, 1, 3, &[(4, 65), (16, 42), (22, 180)])).unwrap();
+    assert_eq!(metrics, Metrics { gfx_activity: Some(42.0), temperature: Some(65.0), watts: Some(180.0) });
+}
+
+#[test]
+fn apu_metrics_are_hundredths_and_milliwatts() {
+    // v2_1 as on Cyan Skillfish, with no power reported
+    let metrics = parse_metrics(&blob(96, 2, 1, &[(4, 6550), (28, 4250), (40, 0xFFFF)])).unwrap();
+    assert_eq!(metrics, Metrics { gfx_activity: Some(42.5), temperature: Some(65.5), watts: None });
+    let metrics = parse_metrics(&blob(96, 2, 1, &[(40, 15_250)])).unwrap();
+    assert_eq!(metrics.watts, Some(15.25));
+}
+
+#[test]
+fn unsupported_or_short_blobs_are_refused() {
+    let kind = |blob: &[u8]| parse_metrics(blob).unwrap_err().kind();
+    // v1_0 and v2_0 put a timestamp first
+    assert_eq!(kind(&blob(120, 1, 0, &[])), ErrorKind::Unsupported);
+    assert_eq!(kind(&blob(96, 2, 0, &[])), ErrorKind::Unsupported);
+    assert_eq!(kind(&blob(96, 3, 1, &[])), ErrorKind::Unsupported);
+    assert_eq!(kind(&[0, 0, 1]), ErrorKind::InvalidData);
+    assert_eq!(kind(&blob(40, 2, 1, &[])), ErrorKind::InvalidData);
+}
+
+#[test]
+fn dither_follows_the_activity() {
+    let mut dither = Dither::default();
+    let busy = (0..100).filter(|_| dither.next(42.5)).count();
+    assert!((42..=43).contains(&busy), "{busy}");
+    assert!((0..10).all(|_| dither.next(100.0)));
+    assert!((0..10).all(|_| !dither.next(0.0)));
+}
@@ -0,0 +1,291 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use bc_250_rust_governor::{
+    config::{FanCurve, Sensors, Smoothing, SmoothingFilter, Thermal},
+    thermal::{ChannelLimits, FanChip, FanRef, TempLimits, TemperatureFilter, ThermalManager, ThermalStatus},
+};
+
+/// Writes an hwmon device named `name` with the given files, each holding "128".
+fn hwmon(root: &Path, index: usize, name: &str, files: &[&str]) {
+    let dir = root.join(format!("hwmon{index}"));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("name"), format!("{name}\n")).unwrap();
+    for file in files {
+        fs::write(dir.join(file), "128\n").unwrap();
+    }
+}
+
+fn fan_names(tm: &ThermalManager) -> Vec<(&str, FanChip)> {
+    tm.fans.iter().map(|fan| (fan.name.as_str(), fan.chip)).collect()
+}
+
+#[test]
+fn detects_chip_families_by_name() {
+    assert_eq!(FanChip::detect("nct6687"), FanChip::Nct6687);
+    assert_eq!(FanChip::detect("nct6686"), FanChip::Nct6687);
+    assert_eq!(FanChip::detect("nct6798"), FanChip::Nct6775);
+    assert_eq!(FanChip::detect("w83627ehf"), FanChip::Nct6775);
+    assert_eq!(FanChip::detect("it8688"), FanChip::It87);
+    assert_eq!(FanChip::detect("it8772"), FanChip::It87);
+    assert_eq!(FanChip::detect("f71882fg"), FanChip::Generic);
+}
+
+#[test]
+fn duplicate_fans_are_found_through_index_and_name() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "nct6687", &["pwm1", "pwm1_enable", "pwm2", "pwm2_enable"]);
+    let tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+    let curve = |fan| FanCurve::new(fan, "max", vec![(50.0, 30)]);
+
+    // Index 1 and nct6687_pwm2 are the same fan; fan 7 does not exist
+    let curves = [
+        curve(FanRef::Index(0)),
+        curve(FanRef::Index(1)),
+        curve(FanRef::Name("nct6687_pwm2".to_string())),
+        curve(FanRef::Index(7)),
+    ];
+    assert_eq!(tm.duplicate_fans(&curves), ["nct6687_pwm2"]);
+    assert!(tm.duplicate_fans(&curves[..2]).is_empty());
+}
+
+#[test]
+fn finds_fans_on_every_supported_chip() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &["temp1_input", "pwm1", "pwm1_enable", "fan1_input"]);
+    hwmon(root.path(), 1, "it8688", &["pwm1", "pwm1_enable", "pwm1_freq", "pwm3", "pwm3_enable"]);
+    hwmon(root.path(), 2, "nct6798", &["pwm2", "pwm2_enable", "pwm2_auto_point1_pwm", "pwm10"]);
+    // Only the channel with a tachometer counts on an unknown chip
+    hwmon(root.path(), 3, "f71882fg", &["pwm1", "fan1_input", "pwm2"]);
+    hwmon(root.path(), 4, "k10temp", &["temp1_input"]);
+
+    let tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+
+    assert_eq!(fan_names(&tm), [
+        ("it8688_pwm1", FanChip::It87),
+        ("it8688_pwm3", FanChip::It87),
+        ("nct6798_pwm2", FanChip::Nct6775),
+        ("nct6798_pwm10", FanChip::Nct6775),
+        ("f71882fg_pwm1", FanChip::Generic),
+    ]);
+    assert!(tm.fan_control_available());
+    assert_eq!(tm.sensors.len(), 2);
+}
+
+#[test]
+fn no_fan_controller_disables_fan_control() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &["temp1_input", "pwm1", "fan1_input"]);
+
+    let tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+
+    assert!(tm.fans.is_empty());
+    assert!(!tm.fan_control_available());
+    assert!(tm.set_fan_speed(0, 50).is_err());
+}
+
+#[test]
+fn restores_the_startup_enable_mode() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "nct6798", &["pwm1", "pwm2"]);
+    let dir = root.path().join("hwmon0");
+    fs::write(dir.join("pwm1_enable"), "5\n").unwrap();
+    // Left in manual by a run that did not shut down cleanly
+    fs::write(dir.join("pwm2_enable"), "1\n").unwrap();
+
+    let tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+    tm.set_fan_speed(0, 100).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("pwm1_enable")).unwrap(), "1");
+    assert_eq!(fs::read_to_string(dir.join("pwm1")).unwrap(), "255");
+
+    tm.restore_auto_fan_control().unwrap();
+    assert_eq!(fs::read_to_string(dir.join("pwm1_enable")).unwrap(), "5");
+    assert_eq!(fs::read_to_string(dir.join("pwm2_enable")).unwrap(), FanChip::Nct6775.auto_mode());
+}
+
+#[test]
+fn sensor_aliases_pick_inputs_by_label_or_number() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &[]);
+    hwmon(root.path(), 1, "zenpower", &[]);
+    hwmon(root.path(), 2, "nct6798", &[]);
+    let write = |dir: &str, file: &str, contents: &str| fs::write(root.path().join(dir).join(file), contents).unwrap();
+    write("hwmon0", "temp1_input", "61000\n");
+    write("hwmon1", "temp1_input", "40000\n");
+    write("hwmon1", "temp1_label", "Tdie\n");
+    write("hwmon1", "temp2_input", "47000\n");
+    write("hwmon1", "temp2_label", "Tctl\n");
+    write("hwmon2", "temp1_input", "30000\n");
+    write("hwmon2", "temp3_input", "52000\n");
+
+    let mut tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+    let sensors: Sensors = toml::from_str(r#"
+cpu = "cpu"
+include = ["amdgpu", "cpu", "vrm"]
+alias = [
+    { name = "cpu", chip = "zenpower", label = "Tctl" },
+    { name = "vrm", chip = "nct6798", input = 3 },
+]
+"#).unwrap();
+    tm.set_sensors(&sensors);
+
+    let names: Vec<&str> = tm.sensors.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["amdgpu", "cpu", "vrm"]);
+    let status = tm.get_thermal_status(false);
+    assert_eq!(status.cpu_temperature, 47.0);
+    assert_eq!(status.amdgpu_temperature, 61.0);
+    assert_eq!(status.source_temperature("vrm"), Some(52.0));
+    assert_eq!(status.gpu_max_temperature, 61.0, "the CPU alias is left out of GPU-side decisions");
+    assert_eq!(status.max_temperature, 61.0);
+    assert!(tm.temperature_inputs().contains(&("zenpower".to_string(), 2, "Tctl".to_string())));
+}
+
+#[test]
+fn gpu_reads_as_its_hottest_channel() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &[]);
+    hwmon(root.path(), 1, "k10temp", &[]);
+    let write = |dir: &str, file: &str, contents: &str| fs::write(root.path().join(dir).join(file), contents).unwrap();
+    write("hwmon0", "temp1_input", "61000\n");
+    write("hwmon0", "temp1_label", "edge\n");
+    write("hwmon0", "temp2_input", "74000\n");
+    write("hwmon0", "temp2_label", "junction\n");
+    write("hwmon0", "temp3_input", "58000\n");
+    write("hwmon1", "temp1_input", "80000\n");
+
+    let tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+
+    let names: Vec<&str> = tm.sensors.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["amdgpu", "amdgpu-junction", "amdgpu-temp3", "k10temp"]);
+    let status = tm.get_thermal_status(false);
+    assert_eq!(status.amdgpu_temperature, 74.0);
+    assert_eq!(status.gpu_max_temperature, 74.0);
+    assert_eq!(status.max_temperature, 80.0);
+    assert_eq!(status.gpu_channels.get("amdgpu"), Some(&61.0), "{:?}", status.gpu_channels);
+    assert_eq!(status.gpu_channels.len(), 3);
+    assert_eq!(status.source_temperature("amdgpu-junction"), Some(74.0));
+}
+
+#[test]
+fn apu_edge_channel_alone_is_the_gpu_temperature() {
+    // amdgpu hides temp2/temp3 and the limits on APUs such as Cyan Skillfish
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &[]);
+    fs::write(root.path().join("hwmon0/temp1_input"), "58000\n").unwrap();
+
+    let tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+
+    let names: Vec<&str> = tm.sensors.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["amdgpu"]);
+    let status = tm.get_thermal_status(false);
+    assert_eq!(status.amdgpu_temperature, 58.0);
+    assert_eq!(status.gpu_max_temperature, 58.0);
+    assert_eq!(status.gpu_channels.len(), 1, "{:?}", status.gpu_channels);
+    assert_eq!(status.source_temperature("amdgpu-junction"), None);
+    assert_eq!(tm.gpu_limits(), TempLimits::default());
+}
+
+#[test]
+fn max_temperature_covers_only_the_listed_sensors() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &[]);
+    hwmon(root.path(), 1, "nvme", &[]);
+    hwmon(root.path(), 2, "iwlwifi_1", &[]);
+    for (dir, temp) in [("hwmon0", "62000\n"), ("hwmon1", "71000\n"), ("hwmon2", "55000\n")] {
+        fs::write(root.path().join(dir).join("temp1_input"), temp).unwrap();
+    }
+    let mut tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+    assert_eq!(tm.get_thermal_status(true).max_temperature, 71.0);
+
+    tm.set_sensors(&Sensors { max_exclude: vec!["nvme".to_string()], ..Default::default() });
+    let status = tm.get_thermal_status(true);
+    assert_eq!(status.max_temperature, 62.0);
+    assert_eq!(status.gpu_max_temperature, 62.0);
+    // Still readable as a fan curve source
+    assert_eq!(status.source_temperature("nvme"), Some(71.0));
+
+    tm.set_sensors(&Sensors { max_include: vec!["amdgpu".to_string(), "iwlwifi_1".to_string()], ..Default::default() });
+    assert_eq!(tm.get_thermal_status(true).max_temperature, 62.0);
+}
+
+#[test]
+fn median_filter_drops_a_lone_spike() {
+    let mut filter = TemperatureFilter::new(&Smoothing { filter: SmoothingFilter::Median, window: 3, alpha: 0.5 });
+    let smoothed: Vec<f32> = [60.0, 61.0, 250.0, 62.0, 62.0].iter().map(|&t| filter.apply("amdgpu", t)).collect();
+    assert_eq!(smoothed, [60.0, 60.0, 61.0, 62.0, 62.0]);
+    // Sensors are filtered independently
+    assert_eq!(filter.apply("k10temp", 40.0), 40.0);
+}
+
+#[test]
+fn ema_filter_follows_gradually() {
+    let mut filter = TemperatureFilter::new(&Smoothing { filter: SmoothingFilter::Ema, window: 3, alpha: 0.5 });
+    let smoothed: Vec<f32> = [60.0, 80.0, 80.0].iter().map(|&t| filter.apply("amdgpu", t)).collect();
+    assert_eq!(smoothed, [60.0, 70.0, 75.0]);
+
+    let mut off = TemperatureFilter::new(&Smoothing::default());
+    assert_eq!(off.apply("amdgpu", 250.0), 250.0);
+}
+
+#[test]
+fn rescan_follows_devices_that_come_and_go() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &["temp1_input"]);
+    let mut tm = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap();
+    assert!(tm.rescan().is_none(), "nothing changed");
+
+    // modprobe nct6687 after startup
+    hwmon(root.path(), 1, "nct6687", &["temp1_input", "pwm1", "pwm1_enable", "fan1_input"]);
+    let before = tm.rescan().expect("missed the new device");
+    assert!(before.is_empty());
+    assert_eq!(fan_names(&tm), [("nct6687_pwm1", FanChip::Nct6687)]);
+    assert_eq!(tm.sensors.len(), 2);
+
+    fs::remove_dir_all(root.path().join("hwmon1")).unwrap();
+    assert_eq!(tm.rescan().expect("missed the removal").len(), 1);
+    assert!(tm.fans.is_empty());
+    assert_eq!(tm.sensors.len(), 1);
+}
+
+#[test]
+fn unset_thresholds_come_from_the_gpu_limits() {
+    let root = tempfile::tempdir().unwrap();
+    hwmon(root.path(), 0, "amdgpu", &["temp1_input", "temp2_input"]);
+    let dir = root.path().join("hwmon0");
+    fs::write(dir.join("temp1_crit"), "100000\n").unwrap();
+    fs::write(dir.join("temp2_crit"), "110000\n").unwrap();
+    // What drivers report for a limit they do not set
+    fs::write(dir.join("temp2_max"), "2147483647\n").unwrap();
+
+    let limits = ThermalManager::new_with_root(&root.path().to_string_lossy()).unwrap().gpu_limits();
+    let crit = |crit| ChannelLimits { crit: Some(crit), max: None };
+    assert_eq!(limits.channels, BTreeMap::from([("amdgpu".to_string(), crit(100.0)), ("amdgpu-temp2".to_string(), crit(110.0))]));
+
+    let mut thermal = Thermal::default();
+    assert_eq!(thermal.derive_thresholds(&limits), [
+        "amdgpu warning 85.0°C, emergency 95.0°C",
+        "amdgpu-temp2 warning 95.0°C, emergency 105.0°C",
+    ]);
+    // Each channel is held to its own limits: the hotspot running 10°C over the edge is no emergency
+    let status = |edge: f32, hotspot: f32| ThermalStatus {
+        max_temperature: hotspot,
+        gpu_max_temperature: hotspot,
+        amdgpu_temperature: hotspot,
+        cpu_temperature: 0.0,
+        gpu_channels: BTreeMap::new(),
+        gpu_readings: vec![("amdgpu".to_string(), edge), ("amdgpu-temp2".to_string(), hotspot)],
+        readings: Vec::new(),
+    };
+    assert_eq!(thermal.warning_exceeded(&status(80.0, 90.0)), None);
+    assert_eq!(thermal.warning_exceeded(&status(80.0, 100.0)), Some((100.0, 95.0)));
+    assert_eq!(thermal.emergency_exceeded(&status(80.0, 100.0)), None);
+    assert_eq!(thermal.emergency_exceeded(&status(96.0, 100.0)), Some((96.0, 95.0)));
+
+    // A configured threshold is kept and the other derived
+    let mut thermal = Thermal { max_safe_temp: Some(80.0), ..Default::default() };
+    thermal.derive_thresholds(&TempLimits { channels: BTreeMap::from([("amdgpu".to_string(), crit(90.0))]) });
+    assert_eq!((thermal.max_safe_temp(), thermal.emergency_temp()), (80.0, 85.0));
+    // Nothing is derived that would leave the warning above the emergency threshold
+    let mut thermal = Thermal { max_safe_temp: Some(90.0), ..Default::default() };
+    assert!(thermal.derive_thresholds(&TempLimits { channels: BTreeMap::from([("amdgpu".to_string(), crit(90.0))]) }).is_empty());
+    assert_eq!(thermal.emergency_temp(), 95.0);
+}
@@ -0,0 +1,113 @@
+use bc_250_rust_governor::interop::{export, export_static, import, to_safe_points_toml, Format};
+
+const LACT_NESTED: &str = "\
+daemon:
+  log_level: info
+gpus:
+  1002:13FE-1002:13FE-0000:01:00.0:
+    fan_control_enabled: false
+    performance_level: manual
+    clocks_configuration:
+      min_core_clock: 1000
+      max_core_clock: 2000
+      min_voltage: 800
+      max_voltage: 1050
+";
+
+/// Older LACT versions keep the clocks directly on the GPU entry
+const LACT_FLAT: &str = "\
+gpus:
+  1002:13FE-1002:13FE-0000:01:00.0:
+    max_core_clock: 2000
+    max_voltage: 1050
+    min_core_clock: 1000
+    min_voltage: 800
+";
+
+const CORECTRL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<PROFILE active="true" name="bc-250">
+ <AMD_PM_ADVANCED active="true">
+  <AMD_PM_FV_STATE active="true">
+   <SCLK_STATES>
+    <STATE active="true" index="0" freq="1000" volt="800"/>
+    <STATE active="false" index="1" freq="1500" volt="900"/>
+    <STATE active="true" index="2" freq="2000" volt="1050"/>
+    <STATE active="true" index="3" freq="1000" volt="820"/>
+   </SCLK_STATES>
+  </AMD_PM_FV_STATE>
+ </AMD_PM_ADVANCED>
+</PROFILE>
+"#;
+
+#[test]
+fn lact_clock_ranges_import_nested_or_flat() {
+    for contents in [LACT_NESTED, LACT_FLAT] {
+        assert_eq!(import(Format::Lact, contents, None).unwrap(), [(1000, 800), (2000, 1050)]);
+    }
+    // Without a minimum only the top point is known
+    let top_only = "gpus:\n  card:\n    max_core_clock: 2000\n    max_voltage: 1050\n";
+    assert_eq!(import(Format::Lact, top_only, None).unwrap(), [(2000, 1050)]);
+}
+
+#[test]
+fn lact_import_picks_the_named_gpu() {
+    let several = format!("{LACT_FLAT}  other:\n    max_core_clock: 1800\n    max_voltage: 1000\n");
+    assert!(import(Format::Lact, &several, None).is_err());
+    assert_eq!(import(Format::Lact, &several, Some("other")).unwrap(), [(1800, 1000)]);
+    assert!(import(Format::Lact, &several, Some("missing")).is_err());
+}
+
+#[test]
+fn lact_import_needs_the_top_of_the_curve() {
+    for contents in ["daemon: {}\n", "gpus:\n  card:\n    max_voltage: 1050\n", "gpus:\n  card:\n    max_core_clock: 2000\n"] {
+        assert!(import(Format::Lact, contents, None).is_err(), "{contents}");
+    }
+}
+
+#[test]
+fn corectrl_import_skips_inactive_states() {
+    // Sorted, and the first point at a frequency wins
+    assert_eq!(import(Format::CoreCtrl, CORECTRL, None).unwrap(), [(1000, 800), (2000, 1050)]);
+    assert!(import(Format::CoreCtrl, "<PROFILE active=\"true\"/>", None).is_err());
+}
+
+#[test]
+fn exports_import_back() {
+    let points = [(1000, 800), (1500, 900), (2000, 1050)];
+    let lact = export(Format::Lact, &points, "card").unwrap();
+    // LACT only holds the range, so the middle point is lost
+    assert_eq!(import(Format::Lact, &lact, Some("card")).unwrap(), [(1000, 800), (2000, 1050)]);
+    let corectrl = export(Format::CoreCtrl, &points, "card").unwrap();
+    assert_eq!(import(Format::CoreCtrl, &corectrl, None).unwrap(), points);
+
+    assert!(export(Format::Lact, &[], "card").is_err());
+    assert!(export(Format::Script, &points, "card").is_err());
+    assert!(import(Format::AmdgpuClocks, "", None).is_err());
+}
+
+#[test]
+fn imported_points_render_as_safe_points() {
+    let points = import(Format::CoreCtrl, CORECTRL, None).unwrap();
+    let table: toml::Table = toml::from_str(&to_safe_points_toml(&points)).unwrap();
+    let rendered: Vec<(i64, i64)> = table["safe-points"].as_array().unwrap().iter()
+        .map(|p| (p["frequency"].as_integer().unwrap(), p["voltage"].as_integer().unwrap()))
+        .collect();
+    assert_eq!(rendered, [(1000, 800), (2000, 1050)]);
+}
+
+#[test]
+fn static_exports_pin_one_point() {
+    let state = export_static(Format::AmdgpuClocks, 1500, 900, "0000:01:00.0").unwrap();
+    assert!(state.lines().skip_while(|l| *l != "OD_VDDC_CURVE:").eq(["OD_VDDC_CURVE:", "0: 1500MHz 900mV"]), "{state}");
+
+    let script = export_static(Format::Script, 1500, 900, "0000:01:00.0").unwrap();
+    assert!(script.starts_with("#!/bin/sh\n"));
+    let writes: Vec<&str> = script.lines().filter(|l| l.starts_with("OD=") || l.starts_with("printf")).collect();
+    assert_eq!(writes, [
+        "OD=/sys/bus/pci/devices/0000:01:00.0/pp_od_clk_voltage",
+        "printf 'vc 0 1500 900' > \"$OD\"",
+        "printf 'c' > \"$OD\"",
+    ]);
+
+    assert!(export_static(Format::Lact, 1500, 900, "0000:01:00.0").is_err());
+}
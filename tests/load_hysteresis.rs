@@ -0,0 +1,41 @@
+use std::time::{Duration, Instant};
+
+use bc_250_rust_governor::{
+    config::{CpuCouplingConfig, MemoryClocks},
+    governor::LoadLevel,
+};
+
+#[test]
+fn memory_clocks_rise_at_once_and_drop_after_the_hold() {
+    // Up at 80% busy, down after 3s at or below 40%
+    let mut hysteresis = MemoryClocks::default().hysteresis();
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+
+    assert_eq!(hysteresis.update(0.5, false, at(0)), Some(LoadLevel::Low));
+    assert_eq!(hysteresis.update(0.7, false, at(100)), None);
+    assert_eq!(hysteresis.update(0.8, false, at(200)), Some(LoadLevel::High));
+    // Inside the band the high level holds and keeps the hold timer fresh
+    assert_eq!(hysteresis.update(0.5, false, at(1000)), None);
+    assert_eq!(hysteresis.update(0.3, false, at(3900)), None);
+    assert_eq!(hysteresis.update(0.3, false, at(4000)), Some(LoadLevel::Low));
+    // Back inside the band from below stays low
+    assert_eq!(hysteresis.update(0.6, false, at(4100)), None);
+}
+
+#[test]
+fn cpu_coupling_follows_bursts_and_short_dips_do_not_drop_it() {
+    // Up at 85% busy, down after 5s at or below 40%
+    let mut hysteresis = CpuCouplingConfig::default().hysteresis();
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+
+    // A burst or max-performance raises it whatever the load
+    assert_eq!(hysteresis.update(0.0, true, at(0)), Some(LoadLevel::High));
+    assert_eq!(hysteresis.update(0.1, false, at(4000)), None);
+    // A dip shorter than the hold, then load again, keeps it up
+    assert_eq!(hysteresis.update(0.9, false, at(4500)), None);
+    assert_eq!(hysteresis.update(0.1, false, at(9000)), None);
+    assert_eq!(hysteresis.update(0.1, false, at(9500)), Some(LoadLevel::Low));
+    assert_eq!(hysteresis.update(0.1, true, at(9600)), Some(LoadLevel::High));
+}
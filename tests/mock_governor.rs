@@ -0,0 +1,861 @@
+mod common;
+
+use std::{fs, os::unix::{fs::PermissionsExt, net::UnixDatagram}, process::Command, thread, time::{Duration, Instant}};
+
+use bc_250_rust_governor::{
+    governor::{ApplyDecision, BusyEma, EmaConfig, LadderPolicy, LoadTarget, RampRates, Scaler, ScalingSettings},
+    replay::{self, ReplayOptions, TraceSample},
+};
+use common::{od_sclk, run_command, Harness};
+
+/// The shipped config, for commands that need one
+const DEFAULT_CONFIG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/default-config.toml");
+
+/// Ramps fast enough for the short scenarios below
+const FAST_TIMING: &str = r#"
+[timing]
+burst-samples = 20
+intervals = { sample = 2000, adjust = 8000, finetune = 50000 }
+ramp-rates = { burst = 1000, up = 50, up-medium = 25, up-slow = 10, up-crawl = 2, down = 5.0 }
+soft-start-secs = 0
+"#;
+
+#[test]
+fn ramps_to_the_top_under_full_load() {
+    let run = Harness::new("duration-secs = 3\nload = 1.0\n").with_config(FAST_TIMING).run();
+
+    assert_eq!(run.applied_freq(), 2000, "{}", run.log);
+    let applies = run.applies();
+    assert!(!applies.is_empty());
+    assert!(applies.windows(2).all(|w| w[0] <= w[1]), "frequency went down under full load: {applies:?}");
+    assert_eq!(run.events("failed").count(), 0);
+    assert_eq!(run.stat("failed_applies"), 0);
+}
+
+#[test]
+fn drops_to_the_floor_once_idle() {
+    let scenario = r#"
+duration-secs = 4
+load = 0.0
+
+[[fault]]
+kind = "load"
+at-secs = 0
+duration-secs = 1.5
+value = 1.0
+"#;
+    let run = Harness::new(scenario).with_config(FAST_TIMING).run();
+
+    assert_eq!(run.applied_freq(), 350, "{}", run.log);
+    assert!(run.applies().contains(&2000), "never reached the top under load: {:?}", run.applies());
+    let floor = run.events("apply").find(|row| row.values[0] == "350").expect("never applied the floor");
+    assert!(floor.ms >= 1500.0, "dropped to the floor at {}ms, before the load ended", floor.ms);
+}
+
+#[test]
+fn backs_off_after_repeated_write_failures() {
+    let scenario = r#"
+duration-secs = 3
+load = 1.0
+
+[[fault]]
+kind = "write-ebusy"
+at-secs = 0
+duration-secs = 10
+"#;
+    let config = format!("{FAST_TIMING}\n[apply-backoff]\nmax-consecutive-failures = 3\nhold-ms = 60000\n");
+    let run = Harness::new(scenario).with_config(&config).run();
+
+    assert_eq!(run.stat("backoff_activations"), 1, "{}", run.log);
+    assert!(run.stat("failed_applies") >= 3);
+    assert!(run.events("failed").count() >= 3);
+    assert_eq!(run.events("applied").count(), 0);
+    assert!(run.log.contains("consecutive apply failures"), "{}", run.log);
+}
+
+#[test]
+fn thermal_throttle_caps_the_frequency() {
+    let scenario = r#"
+duration-secs = 4
+load = 1.0
+temperature = 84.0
+"#;
+    let config = format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+max_safe_temp = 90.0
+emergency_temp = 95.0
+throttle_curve = [[70.0, 100], [80.0, 50]]
+");
+    let run = Harness::new(scenario).with_config(&config).run();
+
+    assert_eq!(run.status["thermal_ceiling"], 1000, "{}", run.status);
+    // The edge is the APU's only channel
+    assert_eq!(run.status["stats"]["max_gpu_temp"], 84.0, "{}", run.status);
+    assert_eq!(run.status["gpu_temp_1m"]["avg"], 84.0, "{}", run.status);
+    assert!(run.applied_freq() <= 1000, "{}", run.log);
+    assert!(run.log.contains("Thermal throttle"), "{}", run.log);
+    assert_eq!(run.stat("throttle_curve_activations"), 1, "{}", run.log);
+    assert_eq!(run.stat("throttle_activations"), 0, "{}", run.log);
+}
+
+#[test]
+fn auto_mode_waits_out_the_dwell_time() {
+    let auto_mode = |dwell: u64| format!("{FAST_TIMING}
+[auto-mode]
+enabled = true
+window-secs = 1
+dwell-secs = {dwell}
+rules = [{{ mode = \"max-performance\", min-busy = 0.7 }}]
+");
+    let scenario = "duration-secs = 3\nload = 1.0\n";
+
+    let run = Harness::new(scenario).with_config(&auto_mode(0)).run();
+    assert!(run.log.contains("Workload fingerprint selects"), "{}", run.log);
+    assert_eq!(run.status["mode"], "max-performance", "{}", run.status);
+
+    // The same load, matching the rule for less than the dwell time
+    let run = Harness::new(scenario).with_config(&auto_mode(60)).run();
+    assert!(!run.log.contains("Workload fingerprint selects"), "{}", run.log);
+    assert_eq!(run.status["mode"], "normal", "{}", run.status);
+}
+
+#[test]
+fn control_file_switches_to_max_performance() {
+    let harness = Harness::new("duration-secs = 3\nload = 0.0\n").with_config(FAST_TIMING);
+    let mut running = harness.spawn();
+    running.wait_for_status("the floor", |status| status["applied_freq"] == 350);
+    fs::write(harness.path("max-performance"), "").unwrap();
+    let run = running.wait();
+
+    assert_eq!(run.status["mode"], "max-performance", "{}", run.log);
+    assert_eq!(run.applied_freq(), 2000);
+}
+
+#[test]
+fn shutdown_resets_the_od_table() {
+    let harness = Harness::new("duration-secs = 2\nload = 1.0\n").with_config(FAST_TIMING);
+    let run = harness.run();
+
+    assert_eq!(run.applied_freq(), 2000, "{}", run.log);
+    // The mock's reset leaves the bottom of OD_RANGE
+    assert!(run.log.contains("Reset the OD table to driver defaults"), "{}", run.log);
+    assert_eq!(od_sclk(&harness.sysfs().join("pp_od_clk_voltage")), 1000);
+    // Only the resume startup policy reads the state file, so nothing else writes it
+    assert!(!harness.path("last-frequency").exists());
+}
+
+#[test]
+fn shutdown_restores_the_original_clock() {
+    let harness = Harness::new("duration-secs = 2\nload = 1.0\n").with_config(&format!("{FAST_TIMING}
+[shutdown]
+policy = \"original\"
+"));
+    let run = harness.run();
+
+    assert_eq!(run.applied_freq(), 2000, "{}", run.log);
+    // The mock starts at the lowest safe point, off the driver's default, which "original" writes back
+    assert_eq!(od_sclk(&harness.sysfs().join("pp_od_clk_voltage")), 350);
+}
+
+#[test]
+fn minimum_shutdown_keeps_the_voltage_offset() {
+    let harness = Harness::new("duration-secs = 2\nload = 1.0\n")
+        .with_keys("voltage-offset-mv = 50\n")
+        .with_config(&format!("{FAST_TIMING}\n[shutdown]\npolicy = \"minimum\"\n"));
+    let run = harness.run();
+
+    assert_eq!(run.applied_freq(), 2000, "{}", run.log);
+    // 700mV plus the offset, as while governing
+    let table = fs::read_to_string(harness.sysfs().join("pp_od_clk_voltage")).unwrap();
+    assert!(table.contains("0: 350Mhz *\nOD_VDDC:\n0: 750mV"), "{table}");
+    assert!(run.log.contains("Restored lowest safe point: 350MHz @ 750mV"), "{}", run.log);
+}
+
+#[test]
+fn unparsable_config_refuses_to_start() {
+    let harness = Harness::new("duration-secs = 2\n").with_config("\nnot a toml line\n");
+    let log = harness.run_refused();
+
+    assert!(log.contains("invalid config file"), "{log}");
+    assert!(!harness.sysfs().join("pp_od_clk_voltage").exists(), "opened the card anyway");
+}
+
+#[test]
+fn control_socket_and_fifo_are_closed_to_other_users() {
+    let harness = Harness::new("duration-secs = 2\nload = 0.5\n");
+    let mut running = harness.spawn();
+    running.wait_for_status("a running governor", |status| status["applied_freq"].is_u64());
+    let mode = |name: &str| fs::metadata(harness.path(name)).unwrap().permissions().mode() & 0o777;
+    let (socket_mode, fifo_mode) = (mode("control.sock"), mode("command.fifo"));
+    running.wait();
+
+    assert_eq!(socket_mode, 0o660);
+    assert_eq!(fifo_mode, 0o620);
+}
+
+#[test]
+fn dry_run_leaves_the_clock_alone() {
+    let harness = Harness::new("duration-secs = 3\nload = 1.0\n").with_config(FAST_TIMING).with_args(&["--dry-run"]);
+    let mut running = harness.spawn();
+    running.wait_for_status("the top clock", |status| status["applied_freq"] == 2000);
+    let od_mid_run = od_sclk(&harness.sysfs().join("pp_od_clk_voltage"));
+    let run = running.wait();
+
+    assert_eq!(run.applied_freq(), 2000, "{}", run.log);
+    assert_eq!(od_mid_run, 350);
+    assert!(run.log.contains("would write vc 0 2000 950"), "{}", run.log);
+    assert!(!harness.path("last-frequency").exists());
+}
+
+#[test]
+fn memory_clocks_switch_to_the_manual_performance_level() {
+    let harness = Harness::new("duration-secs = 2\nload = 1.0\ndpm-levels = true\n")
+        .with_config(&format!("{FAST_TIMING}\n[memory-clocks]\nenabled = true\n"));
+    let run = harness.run();
+
+    // The mock refuses masks outside the manual level, so these were forced
+    assert_eq!(run.status["mclk"], 1750, "{}", run.log);
+    assert_eq!(run.status["fclk"], 1200, "{}", run.log);
+    assert!(run.log.contains("Performance level auto -> manual"), "{}", run.log);
+    assert!(run.log.contains("Restored the auto performance level"), "{}", run.log);
+    assert_eq!(fs::read_to_string(harness.sysfs().join("power_dpm_force_performance_level")).unwrap(), "auto");
+}
+
+#[test]
+fn single_level_memory_clocks_are_left_to_the_driver() {
+    let harness = Harness::new("duration-secs = 1\nload = 1.0\n")
+        .with_config(&format!("{FAST_TIMING}\n[memory-clocks]\nenabled = true\n"));
+    let run = harness.run();
+
+    assert!(run.log.contains("has a single level, leaving mclk to the driver"), "{}", run.log);
+    assert!(run.log.contains("has a single level, leaving fclk to the driver"), "{}", run.log);
+    assert!(run.status["mclk"].is_null(), "{}", run.status);
+    assert_eq!(fs::read_to_string(harness.sysfs().join("power_dpm_force_performance_level")).unwrap(), "auto\n");
+}
+
+#[test]
+fn replay_matches_the_recorded_run() {
+    let harness = Harness::new("duration-secs = 4\nload = 0.7\n").with_config(FAST_TIMING);
+    let run = harness.run();
+    let recorded = run.applies().len();
+
+    let trace = harness.path("trace.csv");
+    let config = harness.config_path();
+    let output = run_command(&["replay", &trace.to_string_lossy(), "--config", &config.to_string_lossy(), "--summary"]);
+    let replayed: usize = output.lines()
+        .find_map(|line| line.split(" samples: ").nth(1)?.split(' ').next()?.parse().ok())
+        .unwrap_or_else(|| panic!("no apply count in:\n{output}"));
+
+    // Live timing jitters the adjust and finetune intervals a little
+    assert!(recorded.abs_diff(replayed) <= 2.max(recorded / 10), "recorded {recorded} applies, replayed {replayed}\n{output}");
+}
+
+#[test]
+fn default_config_is_valid() {
+    let output = run_command(&["check-config", concat!(env!("CARGO_MANIFEST_DIR"), "/default-config.toml")]);
+    assert!(output.contains("Config is valid"), "{output}");
+}
+
+#[test]
+fn check_config_bounds_the_voltage_offset_and_floor() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    fs::write(&config, "voltage-offset-mv = -300\nvoltage-floor-mv = 600\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bc-250-rust-governor"))
+        .args(["check-config", &config.to_string_lossy()])
+        .output()
+        .unwrap();
+
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(!output.status.success(), "{text}");
+    assert!(text.contains("voltage-floor-mv must be at least 700mV"), "{text}");
+    assert!(text.contains("voltage-offset-mv must be between -200 and 200mV"), "{text}");
+}
+
+#[test]
+fn check_config_requires_the_gamemode_bus() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    // Under root the service has no session bus to fall back on
+    fs::write(&config, "[gamemode]\nenabled = true\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bc-250-rust-governor"))
+        .args(["check-config", &config.to_string_lossy()])
+        .output()
+        .unwrap();
+
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(!output.status.success(), "{text}");
+    assert!(text.contains("gamemode needs a bus-address"), "{text}");
+}
+
+#[test]
+fn fan_stall_takes_the_emergency_path() {
+    let scenario = r#"
+duration-secs = 6
+load = 0.5
+
+[[fault]]
+kind = "fan-stall"
+at-secs = 1
+duration-secs = 10
+"#;
+    let config = format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+curve = [[20.0, 60], [100.0, 60]]
+stall-secs = 1
+");
+    let run = Harness::new(scenario).with_config(&config).run();
+
+    assert!(run.log.contains("Fan nct6687_pwm1 stalled"), "{}", run.log);
+    assert!(run.log.contains("ALERT [fan-stall]"), "{}", run.log);
+    // The shutdown follows the stall before the status file catches up, so check the final stats line
+    assert!(run.log.contains("FanStalls=1"), "{}", run.log);
+    assert!(!run.log.contains("Scenario finished"), "ran to the end of the scenario despite the stall");
+}
+
+#[test]
+fn each_fan_follows_its_own_curve() {
+    let harness = Harness::new("duration-secs = 2\nload = 0.5\ntemperature = 60.0\n").with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+
+[[thermal.fan-control.fans]]
+fan = 0
+source = \"amdgpu\"
+curve = [[50.0, 20], [70.0, 40]]
+
+[[thermal.fan-control.fans]]
+fan = \"nct6687_pwm2\"
+curve = [[20.0, 80], [100.0, 80]]
+"));
+    let run = harness.run();
+
+    let pwm = |channel: u8| fs::read_to_string(harness.sysfs().join(format!("hwmon/hwmon1/pwm{channel}"))).unwrap();
+    // 30% and 80% of 255
+    assert_eq!(pwm(1), "76", "{}", run.log);
+    assert_eq!(pwm(2), "204", "{}", run.log);
+    let fans = run.status["thermal"]["fans"].as_array().unwrap();
+    assert_eq!(fans.len(), 2, "{}", run.status);
+    assert_eq!(fans[0]["temperature"], 60.0);
+    assert_eq!(fans[1]["name"], "nct6687_pwm2");
+}
+
+#[test]
+fn one_fan_by_index_and_name_refuses_to_start() {
+    let harness = Harness::new("duration-secs = 2\n").with_config(&format!("{FAST_TIMING}
+[thermal.fan-control]
+enabled = true
+
+[[thermal.fan-control.fans]]
+fan = 1
+curve = [[50.0, 20], [70.0, 40]]
+
+[[thermal.fan-control.fans]]
+fan = \"nct6687_pwm2\"
+curve = [[20.0, 80], [100.0, 80]]
+"));
+    let log = harness.run_refused();
+
+    assert!(log.contains("thermal fan-control drives nct6687_pwm2 more than once"), "{log}");
+}
+
+#[test]
+fn raw_pwm_curves_are_written_as_given() {
+    let harness = Harness::new("duration-secs = 2\nload = 0.5\ntemperature = 60.0\n").with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+
+[[thermal.fan-control.fans]]
+fan = 1
+unit = \"pwm\"
+curve = [[50.0, 60], [70.0, 100]]
+"));
+    let run = harness.run();
+
+    // The edge reads 60°C, halfway between 60 and 100, and no whole percent
+    let pwm2 = fs::read_to_string(harness.sysfs().join("hwmon/hwmon1/pwm2")).unwrap();
+    assert_eq!(pwm2, "80", "{}", run.log);
+    assert_eq!(run.status["thermal"]["fans"][0]["pwm"], 80, "{}", run.status);
+}
+
+#[test]
+fn max_performance_switches_to_the_performance_curve() {
+    let harness = Harness::new("duration-secs = 3\nload = 0.0\n").with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+curve = [[20.0, 30], [100.0, 30]]
+performance-curve = [[20.0, 80], [100.0, 80]]
+"));
+    let mut running = harness.spawn();
+    running.wait_for_status("the quiet curve", |status| status["thermal"]["fans"][0]["pwm_percent"] == 30);
+    let pwm_quiet = fs::read_to_string(harness.sysfs().join("hwmon/hwmon1/pwm1")).unwrap();
+    fs::write(harness.path("max-performance"), "").unwrap();
+    let run = running.wait();
+
+    assert_eq!(pwm_quiet, "76", "{}", run.log);
+    assert!(run.log.contains("fans on their performance curves"), "{}", run.log);
+    assert_eq!(run.status["thermal"]["fans"][0]["pwm_percent"], 80, "{}", run.status);
+}
+
+#[test]
+fn grouped_fans_follow_one_curve_with_their_offsets() {
+    let harness = Harness::new("duration-secs = 2\nload = 0.5\n").with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+curve = [[20.0, 30], [100.0, 30]]
+group = [{{ fan = \"nct6687_pwm2\", offset = 10 }}]
+"));
+    let run = harness.run();
+
+    let hwmon = harness.sysfs().join("hwmon/hwmon1");
+    assert_eq!(fs::read_to_string(hwmon.join("pwm1")).unwrap(), "76", "{}", run.log);
+    assert_eq!(fs::read_to_string(hwmon.join("pwm2")).unwrap(), "102", "{}", run.log);
+    let fans = run.status["thermal"]["fans"].as_array().unwrap();
+    assert_eq!(fans.len(), 2, "{}", run.status);
+}
+
+#[test]
+fn fans_loaded_after_startup_are_picked_up() {
+    let harness = Harness::new("duration-secs = 3\nload = 0.5\n").with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+rescan-secs = 1
+
+[thermal.fan-control]
+enabled = true
+
+[[thermal.fan-control.fans]]
+fan = \"it8688_pwm1\"
+curve = [[20.0, 50], [100.0, 50]]
+"));
+    let mut running = harness.spawn();
+    running.wait_for_status("a GPU temperature", |status| status["thermal"]["amdgpu"].as_f64() > Some(0.0));
+    let chip = harness.sysfs().join("hwmon/hwmon2");
+    fs::create_dir_all(&chip).unwrap();
+    for (file, value) in [("pwm1", "0"), ("pwm1_enable", "2"), ("fan1_input", "900"), ("name", "it8688")] {
+        fs::write(chip.join(file), value).unwrap();
+    }
+    let run = running.wait();
+
+    assert!(run.log.contains("No fan it8688_pwm1 for its fan curve"), "{}", run.log);
+    assert!(run.log.contains("fan it8688_pwm1 appeared"), "{}", run.log);
+    // Driven along its curve, then handed back to automatic control
+    assert_eq!(fs::read_to_string(chip.join("pwm1")).unwrap(), "127", "{}", run.log);
+    assert_eq!(fs::read_to_string(chip.join("pwm1_enable")).unwrap().trim(), "2");
+}
+
+#[test]
+fn ambient_curves_follow_the_rise_over_the_intake() {
+    let harness = Harness::new("duration-secs = 2\nload = 0.5\ntemperature = 60.0\n").with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+
+[[thermal.fan-control.fans]]
+fan = 1
+source = \"amdgpu\"
+ambient = \"intake\"
+curve = [[30.0, 20], [40.0, 60]]
+"));
+    // An intake sensor next to the mock's own chips
+    let intake = harness.sysfs().join("hwmon/hwmon2");
+    fs::create_dir_all(&intake).unwrap();
+    fs::write(intake.join("name"), "intake\n").unwrap();
+    fs::write(intake.join("temp1_input"), "25000\n").unwrap();
+    let run = harness.run();
+
+    // 35°C over the intake: 40%, whatever the absolute temperature
+    let pwm2 = fs::read_to_string(harness.sysfs().join("hwmon/hwmon1/pwm2")).unwrap();
+    assert_eq!(pwm2, "102", "{}", run.log);
+    let fan = &run.status["thermal"]["fans"][0];
+    assert_eq!((fan["temperature"].as_f64(), fan["ambient"].as_f64()), (Some(60.0), Some(25.0)), "{}", run.status);
+}
+
+#[test]
+fn exit_emergency_leaves_the_floor_and_the_fans_flat_out() {
+    let scenario = r#"
+duration-secs = 5
+load = 1.0
+temperature = 60.0
+
+[[fault]]
+kind = "temperature"
+at-secs = 1.5
+duration-secs = 10
+value = 99.0
+"#;
+    let harness = Harness::new(scenario).with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+curve = [[20.0, 30], [100.0, 30]]
+"));
+    let run = harness.run();
+
+    assert!(run.log.contains("EMERGENCY: Temp"), "{}", run.log);
+    assert!(run.log.contains("holding the lowest safe point 350MHz"), "{}", run.log);
+    assert!(run.log.contains("STATE DUMP"), "{}", run.log);
+    assert!(!run.log.contains("Scenario finished"), "ran to the end of the scenario despite the emergency");
+    assert!(run.applies().contains(&2000), "{:?}", run.applies());
+    // The default shutdown policy would reset to 1000MHz; the emergency keeps the floor
+    assert_eq!(od_sclk(&harness.sysfs().join("pp_od_clk_voltage")), 350);
+    for pwm in ["pwm1", "pwm2"] {
+        let hwmon = harness.sysfs().join("hwmon/hwmon1");
+        assert_eq!(fs::read_to_string(hwmon.join(pwm)).unwrap(), "255", "{}", run.log);
+        assert_eq!(fs::read_to_string(hwmon.join(format!("{pwm}_enable"))).unwrap().trim(), "1", "{}", run.log);
+    }
+}
+
+#[test]
+fn min_clock_emergency_holds_the_floor_and_keeps_running() {
+    let scenario = r#"
+duration-secs = 5
+load = 1.0
+temperature = 60.0
+
+[[fault]]
+kind = "temperature"
+at-secs = 1.5
+duration-secs = 1.5
+value = 99.0
+"#;
+    let config = format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+emergency-action = \"min-clock\"
+");
+    let run = Harness::new(scenario).with_config(&config).run();
+
+    assert!(run.log.contains("Holding the lowest safe point"), "{}", run.log);
+    assert!(run.log.contains("releasing the lowest safe point"), "{}", run.log);
+    assert!(run.log.contains("Scenario finished"), "shut down on the emergency:\n{}", run.log);
+    let applies = run.applies();
+    let floor = applies.iter().position(|&f| f == 350).unwrap_or_else(|| panic!("never held the floor: {applies:?}"));
+    assert!(applies[..floor].contains(&2000), "{applies:?}");
+    assert_eq!(run.applied_freq(), 2000, "did not ramp back up after the emergency: {applies:?}");
+    assert_eq!(run.stat("thermal_emergencies"), 1);
+}
+
+#[test]
+fn script_emergency_runs_the_script_and_holds_the_floor() {
+    let scenario = r#"
+duration-secs = 5
+load = 1.0
+temperature = 60.0
+
+[[fault]]
+kind = "temperature"
+at-secs = 1.5
+duration-secs = 1.5
+value = 99.0
+"#;
+    let harness = Harness::new(scenario);
+    let marker = harness.path("emergency.txt");
+    let harness = harness.with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+emergency-action = \"script\"
+emergency-script = \"echo $BC250_TEMPERATURE > {}\"
+", marker.display()));
+    let run = harness.run();
+
+    assert_eq!(fs::read_to_string(&marker).unwrap_or_default().trim(), "99.0", "{}", run.log);
+    assert!(run.log.contains("releasing the lowest safe point"), "{}", run.log);
+    let applies = run.applies();
+    let floor = applies.iter().position(|&f| f == 350).unwrap_or_else(|| panic!("never held the floor: {applies:?}"));
+    assert!(applies[..floor].contains(&2000), "{applies:?}");
+    assert_eq!(run.applied_freq(), 2000, "did not ramp back up after the emergency: {applies:?}");
+}
+
+#[test]
+fn failing_fan_writes_run_the_other_fans_flat_out() {
+    let scenario = r#"
+duration-secs = 3
+load = 1.0
+temperature = 50.0
+
+[[fault]]
+kind = "fan-write-fail"
+at-secs = 1
+duration-secs = 10
+"#;
+    let harness = Harness::new(scenario).with_config(&format!("{FAST_TIMING}
+[thermal]
+monitor_interval = 200
+
+[thermal.fan-control]
+enabled = true
+max-write-failures = 2
+
+[[thermal.fan-control.fans]]
+fan = 0
+curve = [[20.0, 30], [100.0, 30]]
+
+[[thermal.fan-control.fans]]
+fan = 1
+curve = [[20.0, 30], [100.0, 30]]
+"));
+    let run = harness.run();
+
+    assert!(run.log.contains("ALERT [fan-failure]: Fan nct6687_pwm2: 2 PWM writes in a row failed"), "{}", run.log);
+    assert!(run.log.contains("Fan failure: running every fan at 100%"), "{}", run.log);
+    let pwm1 = fs::read_to_string(harness.sysfs().join("hwmon/hwmon1/pwm1")).unwrap();
+    assert_eq!(pwm1, "255", "{}", run.log);
+    assert!(run.applies().contains(&2000), "{:?}", run.applies());
+    assert_eq!(run.applied_freq(), 350, "{}", run.log);
+}
+
+#[test]
+fn calibrates_the_mock_fans() {
+    let dir = tempfile::tempdir().unwrap();
+    let scenario = dir.path().join("scenario.toml");
+    fs::write(&scenario, format!("root = \"{}\"\n", dir.path().join("sysfs").display())).unwrap();
+    let output = dir.path().join("fans.toml");
+
+    let log = run_command(&["fan", "calibrate", "--config", DEFAULT_CONFIG, "--mock", &scenario.to_string_lossy(), "--fans", "1",
+        "--step", "20", "--settle-ms", "300", "--output", &output.to_string_lossy()]);
+
+    // The mock fans stand still below 20% and reach 2040 RPM at full speed
+    assert!(log.contains("Calibrating nct6687_pwm2"), "{log}");
+    assert!(!log.contains("Calibrating nct6687_pwm1"), "{log}");
+    let snippet = fs::read_to_string(&output).unwrap();
+    assert!(snippet.contains("turns from 20% (408 RPM), 2040 RPM at full speed"), "{snippet}");
+    assert!(snippet.contains("curve = [[45.0, 20], [60.0, 60], [75.0, 80], [85.0, 100]]"), "{snippet}");
+    // Handed back to the chip's automatic mode
+    assert_eq!(fs::read_to_string(dir.path().join("sysfs/hwmon/hwmon1/pwm2_enable")).unwrap().trim(), "5");
+}
+
+#[test]
+fn fan_calibration_stops_when_the_gpu_overheats() {
+    let dir = tempfile::tempdir().unwrap();
+    let scenario = dir.path().join("scenario.toml");
+    // Past the 85°C warning threshold from the second second on
+    fs::write(&scenario, format!("root = \"{}\"\n\n[[fault]]\nkind = \"temperature\"\nat-secs = 1\nduration-secs = 60\nvalue = 90.0\n",
+        dir.path().join("sysfs").display())).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bc-250-rust-governor"))
+        .args(["fan", "calibrate", "--config", DEFAULT_CONFIG, "--mock", &scenario.to_string_lossy(), "--fans", "1",
+            "--step", "20", "--settle-ms", "300"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{stderr}");
+    assert!(stderr.contains("GPU at 90.0°C > 85.0°C, calibration aborted"), "{stderr}");
+    // Handed back to the chip's automatic mode all the same
+    assert_eq!(fs::read_to_string(dir.path().join("sysfs/hwmon/hwmon1/pwm2_enable")).unwrap().trim(), "5");
+}
+
+#[test]
+fn calibrate_rejects_a_zero_voltage_step() {
+    let output = Command::new(env!("CARGO_BIN_EXE_bc-250-rust-governor"))
+        .args(["calibrate", "--step", "0", "--yes"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{stderr}");
+    assert!(stderr.contains("--step"), "{stderr}");
+}
+
+#[test]
+fn several_gpus_report_ready_once_and_share_the_watchdog() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("notify.sock");
+    let systemd = UnixDatagram::bind(&socket).unwrap();
+    // Read as systemd does, so the daemon never blocks on a full socket queue
+    systemd.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let listener = thread::spawn(move || {
+        let mut buf = [0u8; 64];
+        let mut messages = Vec::new();
+        while let Ok(n) = systemd.recv(&mut buf) {
+            messages.push(String::from_utf8_lossy(&buf[..n]).into_owned());
+        }
+        messages
+    });
+    let run = Harness::new("duration-secs = 3\nload = 0.5\n")
+        .with_config(&format!("{FAST_TIMING}\n[devices.left]\npci-bus = 1\n\n[devices.right]\npci-bus = 2\n"))
+        .with_env("NOTIFY_SOCKET", &socket.to_string_lossy())
+        // Pings every 200ms
+        .with_env("WATCHDOG_USEC", "400000")
+        .run();
+
+    let messages = listener.join().unwrap();
+    assert!(run.log.contains("Governing 2 GPUs"), "{}", run.log);
+    assert_eq!(messages.iter().filter(|m| *m == "READY=1").count(), 1, "{messages:?}");
+    let ready = messages.iter().position(|m| m == "READY=1").unwrap();
+    assert!(!messages[..ready].iter().any(|m| m == "WATCHDOG=1"), "pinged before READY: {messages:?}");
+    assert!(messages.iter().filter(|m| *m == "WATCHDOG=1").count() >= 5, "{messages:?}");
+}
+
+/// Scaling settings on 10ms samples, with no interval or rate limit in the way.
+fn scaling() -> ScalingSettings {
+    ScalingSettings {
+        sample_interval: Duration::from_millis(10),
+        up_samples: 8,
+        down_samples: 8,
+        burst_samples: 4,
+        burst_busy: 4,
+        burst_rearm: Duration::ZERO,
+        ema: EmaConfig::default(),
+        adjust_mhz: 100,
+        finetune_mhz: 25,
+        deadband_mhz: 75,
+        deadband: Duration::from_millis(200),
+        adjust_interval: Duration::ZERO,
+        finetune_interval: Duration::ZERO,
+        max_applies_per_second: 0,
+    }
+}
+
+#[test]
+fn deadband_holds_small_changes_until_they_last() {
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    let mut scaler = Scaler::new(scaling(), start);
+
+    // 50MHz is past the finetune threshold but inside the 75MHz deadband
+    assert_eq!(scaler.decide(at(0), 1000, 1050, false, false, false), ApplyDecision::Hold);
+    assert_eq!(scaler.decide(at(150), 1000, 1050, false, false, false), ApplyDecision::Hold);
+    assert_eq!(scaler.decide(at(200), 1000, 1050, false, false, false), ApplyDecision::Apply);
+    assert_eq!(scaler.decide(at(210), 1050, 1050, false, false, false), ApplyDecision::Hold);
+
+    // Leaving the band restarts the clock
+    assert_eq!(scaler.decide(at(300), 1050, 1100, false, false, false), ApplyDecision::Hold);
+    assert_eq!(scaler.decide(at(400), 1050, 1050, false, false, false), ApplyDecision::Hold);
+    assert_eq!(scaler.decide(at(550), 1050, 1100, false, false, false), ApplyDecision::Hold);
+    // Beyond the deadband, bursts and forced changes do not wait
+    assert_eq!(scaler.decide(at(560), 1050, 1150, false, false, false), ApplyDecision::Apply);
+    assert_eq!(scaler.decide(at(570), 1150, 1100, true, false, false), ApplyDecision::Apply);
+    assert_eq!(scaler.decide(at(580), 1100, 1150, false, true, false), ApplyDecision::Apply);
+    // Nothing goes out while an apply is pending
+    assert_eq!(scaler.decide(at(590), 1150, 1300, true, true, true), ApplyDecision::Hold);
+}
+
+#[test]
+fn ema_smooths_and_weighs_late_samples_like_the_ones_they_replace() {
+    let config = EmaConfig { enabled: true, alpha_up: 0.5, alpha_down: 0.1 };
+    let mut ema = BusyEma::default();
+
+    // The first sample seeds both averages
+    ema.push(true, &config, 1.0);
+    assert_eq!((ema.busy_up(), ema.busy_down()), (1.0, 1.0));
+    ema.push(false, &config, 1.0);
+    assert!((ema.busy_up() - 0.5).abs() < 1e-6, "{}", ema.busy_up());
+    assert!((ema.busy_down() - 0.9).abs() < 1e-6, "{}", ema.busy_down());
+
+    // One sample two intervals late moves the averages as far as two on time
+    let mut on_time = BusyEma::default();
+    let mut late = BusyEma::default();
+    on_time.push(true, &config, 1.0);
+    late.push(true, &config, 1.0);
+    on_time.push(false, &config, 1.0);
+    on_time.push(false, &config, 1.0);
+    late.push(false, &config, 2.0);
+    assert!((on_time.busy_up() - late.busy_up()).abs() < 1e-6, "{} vs {}", on_time.busy_up(), late.busy_up());
+    assert!((on_time.busy_down() - late.busy_down()).abs() < 1e-6, "{} vs {}", on_time.busy_down(), late.busy_down());
+    assert!((late.busy_up() - 0.25).abs() < 1e-6, "{}", late.busy_up());
+
+    // The scaler reads the averages in place of the windows
+    let mut scaler = Scaler::new(ScalingSettings { ema: config, ..scaling() }, Instant::now());
+    assert_eq!(scaler.history().len(), 0);
+    let start = Instant::now();
+    scaler.sample(start, true, Duration::from_millis(10));
+    let sample = scaler.sample(start + Duration::from_millis(10), false, Duration::from_millis(10));
+    assert!((sample.busy_up - 0.5).abs() < 1e-6, "{sample:?}");
+    assert!((sample.busy_down - 0.9).abs() < 1e-6, "{sample:?}");
+}
+
+#[test]
+fn bursts_need_their_share_of_busy_samples_and_wait_out_the_rearm() {
+    let settings = ScalingSettings { burst_busy: 3, burst_rearm: Duration::from_millis(100), ..scaling() };
+    let mut scaler = Scaler::new(settings, Instant::now());
+    let start = Instant::now();
+    let mut sample = |i: u64, busy: bool| scaler.sample(start + Duration::from_millis(10 * i), busy, Duration::from_millis(10)).burst;
+
+    // 3 of the newest 4 busy is enough, an idle sample in between or not
+    assert!(!sample(0, true));
+    assert!(!sample(1, true));
+    assert!(!sample(2, false));
+    assert!(sample(3, true));
+    // Down to 2 of 4 ends it at 40ms
+    assert!(!sample(4, false));
+    // 3 of 4 again, but within 100ms of the end
+    assert!(!sample(5, true));
+    assert!(!sample(6, true));
+    for i in 7..14 {
+        assert!(!sample(i, true), "burst again at {}ms", i * 10);
+    }
+    assert!(sample(14, true));
+}
+
+#[test]
+fn check_config_bounds_the_burst_busy_samples() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    for busy in [0, 7] {
+        fs::write(&config, format!("[timing]\nburst-samples = 6\nburst-busy-samples = {busy}\n")).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_bc-250-rust-governor"))
+            .args(["check-config", &config.to_string_lossy()])
+            .output()
+            .unwrap();
+
+        let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        assert!(!output.status.success(), "{text}");
+        assert!(text.contains("burst-busy-samples must be between 1 and burst-samples"), "{text}");
+    }
+}
+
+/// Replays `trace` with the ladder policy between 500MHz and 1800MHz.
+fn replay_ladder(trace: &[TraceSample]) -> replay::ReplaySummary {
+    replay::run(trace, ReplayOptions {
+        policy: Box::new(LadderPolicy::new(RampRates::default())),
+        load_target: LoadTarget::default(),
+        scaling: scaling(),
+        min_freq: 500,
+        ceiling: 1800,
+        summary_only: true,
+    })
+}
+
+#[test]
+fn replay_ramps_to_the_ceiling_under_load_and_rests_when_idle() {
+    // A second of 10ms samples, idle for the first half
+    let trace: Vec<TraceSample> = (0..100).map(|i| TraceSample { at_ms: f64::from(i) * 10.0, busy: i >= 50 }).collect();
+
+    let idle = replay_ladder(&trace[..50]);
+    assert_eq!((idle.applies, idle.bursts, idle.max_freq), (0, 0, 500));
+    assert_eq!(idle.avg_freq, 500.0);
+
+    let summary = replay_ladder(&trace);
+    assert_eq!(summary.samples, 100);
+    assert_eq!(summary.duration, Duration::from_millis(990));
+    assert_eq!(summary.bursts, 1);
+    assert_eq!(summary.max_freq, 1800);
+    assert!(summary.applies > 0);
+    assert!(summary.avg_freq > 500.0 && summary.avg_freq < 1800.0, "{summary:?}");
+    assert!(summary.at_ceiling > 0.0 && summary.at_ceiling < 0.5, "{summary:?}");
+}
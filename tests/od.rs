@@ -0,0 +1,65 @@
+use bc_250_rust_governor::od::{parse, OdTable};
+
+/// Cyan Skillfish (BC-250): a single point and no voltage curve
+const SINGLE_POINT: &str = "\
+OD_SCLK:
+0:       1500Mhz
+OD_VDDC:
+0:        900mV
+OD_RANGE:
+SCLK:     350Mhz       2000Mhz
+VDDC:     700mV        1050mV
+";
+
+/// Navi 10 style, with a voltage curve and per-point ranges
+const WITH_CURVE: &str = "\
+OD_SCLK:
+0: 800Mhz
+1: 2100Mhz
+OD_MCLK:
+1: 875MHz
+OD_VDDC_CURVE:
+0: 1455MHz 801mV
+1: 800MHz 711mV
+2: 2100MHz 1191mV
+OD_RANGE:
+SCLK:     800Mhz       2150Mhz
+MCLK:     625Mhz        950Mhz
+VDDC_CURVE_SCLK[0]:     800Mhz       2150Mhz
+VDDC_CURVE_VOLT[0]:     750mV        1200mV
+";
+
+#[test]
+fn single_point_table_seeds_its_range() {
+    let table = parse(SINGLE_POINT);
+    assert_eq!(table, OdTable {
+        sclk: vec![1500],
+        vddc: vec![900],
+        vddc_curve: vec![],
+        sclk_range: Some((350, 2000)),
+        vddc_range: Some((700, 1050)),
+    });
+    assert_eq!(table.safe_points(), [(350, 700), (2000, 1050)]);
+    assert!(!table.at_driver_default());
+    assert!(parse(&SINGLE_POINT.replace("1500Mhz", "350Mhz").replace("900mV", "700mV")).at_driver_default());
+}
+
+#[test]
+fn voltage_curve_seeds_the_safe_points() {
+    let table = parse(WITH_CURVE);
+    assert_eq!(table.sclk, [800, 2100]);
+    assert_eq!(table.vddc_curve, [(1455, 801), (800, 711), (2100, 1191)]);
+    // The MCLK and per-point ranges are skipped
+    assert_eq!(table.sclk_range, Some((800, 2150)));
+    assert_eq!(table.vddc_range, None);
+    assert_eq!(table.safe_points(), [(800, 711), (1455, 801), (2100, 1191)]);
+    // Without OD_VDDC it cannot tell where a reset leaves it
+    assert!(!table.at_driver_default());
+}
+
+#[test]
+fn current_point_is_the_last_resort() {
+    let table = parse("OD_SCLK:\n0: 1500Mhz\nOD_VDDC:\n0: 900mV\n");
+    assert_eq!(table.safe_points(), [(1500, 900)]);
+    assert!(parse("").safe_points().is_empty());
+}
@@ -0,0 +1,87 @@
+use bc_250_rust_governor::governor::{
+    FrametimePolicy, FrametimeTarget, GovernorPolicy, LadderPolicy, LoadTarget, PerformanceMode, PidGains, PidPolicy,
+    PolicyInput, RampRates,
+};
+
+/// A sample at 1000MHz with the default load thresholds.
+fn input(busy_up: f32, busy_down: f32, dt_ms: f32) -> PolicyInput {
+    PolicyInput {
+        target_freq: 1000.0,
+        busy_up,
+        busy_down,
+        burst: false,
+        burst_scale: 1.0,
+        load_target: LoadTarget::default(),
+        dt_ms,
+        fps: None,
+        gpu_temp: Some(60.0),
+        cpu_temp: Some(50.0),
+        mode: PerformanceMode::Normal,
+    }
+}
+
+fn burst(scale: f32) -> PolicyInput {
+    PolicyInput { burst: true, burst_scale: scale, ..input(1.0, 1.0, 1.0) }
+}
+
+fn fps(fps: Option<f32>, busy_up: f32, dt_ms: f32) -> PolicyInput {
+    PolicyInput { fps, ..input(busy_up, busy_up, dt_ms) }
+}
+
+fn assert_near(got: f32, want: f32, case: &str) {
+    assert!((got - want).abs() < 1e-3, "{case}: got {got}, want {want}");
+}
+
+#[test]
+fn ladder_ramps_by_the_load_tier() {
+    // Default rates are MHz per ms: burst 800, up 50/25/10/2, down 0.24
+    let cases = [
+        ("burst", burst(1.0), 1800.0),
+        ("burst at half scale", burst(0.5), 1400.0),
+        ("above upper", input(0.95, 0.95, 1.0), 1050.0),
+        ("above medium", input(0.80, 0.80, 1.0), 1025.0),
+        ("above slow", input(0.65, 0.65, 1.0), 1010.0),
+        ("above crawl", input(0.55, 0.55, 1.0), 1002.0),
+        ("below lower", input(0.40, 0.40, 1.0), 999.76),
+        ("between crawl and lower", input(0.50, 0.50, 1.0), 1000.0),
+        ("twice the time", input(0.95, 0.95, 2.0), 1100.0),
+    ];
+    let mut policy = LadderPolicy::new(RampRates::default());
+    for (case, input, want) in cases {
+        assert_near(policy.next_target(&input), want, case);
+    }
+}
+
+#[test]
+fn pid_steps_on_the_busy_error() {
+    // Each row feeds on the previous target: (case, input, expected target)
+    let cases = [
+        ("first sample: integral only", input(0.85, 0.85, 100.0), 1040.0),
+        ("steady error: integral again", input(0.85, 0.85, 100.0), 1080.0),
+        ("error gone: proportional undoes a step", input(0.75, 0.75, 100.0), 1030.0),
+        ("burst jumps and resets", burst(1.0), 1830.0),
+        ("after the reset: integral only", input(0.65, 0.65, 100.0), 1790.0),
+    ];
+    let gains = PidGains { setpoint: 0.75, kp: 500.0, ki: 4000.0, kd: 0.0 };
+    let mut policy = PidPolicy::new(gains, 800.0);
+    let mut target = 1000.0;
+    for (case, input, want) in cases {
+        target = policy.next_target(&PolicyInput { target_freq: target, ..input });
+        assert_near(target, want, case);
+    }
+}
+
+#[test]
+fn frametime_holds_the_frame_rate() {
+    let target = FrametimeTarget { target_fps: 60.0, tolerance: 0.05, up_gain: 10.0, down_rate: 100.0 };
+    let cases = [
+        ("at the target: drift down", fps(Some(60.0), 0.5, 100.0), 990.0),
+        ("within the tolerance", fps(Some(58.0), 0.5, 100.0), 990.0),
+        ("25% short: rise with the shortfall", fps(Some(45.0), 0.5, 100.0), 1025.0),
+        ("no frame times: the ladder", fps(None, 0.95, 1.0), 1050.0),
+    ];
+    let mut policy = FrametimePolicy::new(target, RampRates::default());
+    for (case, input, want) in cases {
+        assert_near(policy.next_target(&input), want, case);
+    }
+}
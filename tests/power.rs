@@ -0,0 +1,25 @@
+use std::time::{Duration, Instant};
+
+use bc_250_rust_governor::power::PowerBudget;
+
+#[test]
+fn ceiling_steps_down_once_per_window() {
+    let mut budget = PowerBudget::new(100.0, Duration::from_secs(1), 10.0, 50.0);
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+
+    // 20W over the cap for two windows, read every 100ms
+    let ceilings: Vec<Option<f32>> = (0..=20).map(|i| budget.update(at(i * 100), 120.0, 2000.0, 350.0, 2000.0)).collect();
+
+    assert!(ceilings[..10].iter().all(Option::is_none), "capped before a full window: {ceilings:?}");
+    // The average has not seen the lower ceiling yet, so the next readings hold it
+    assert!(ceilings[10..20].iter().all(|&c| c == Some(1800.0)), "{ceilings:?}");
+    assert_eq!(ceilings[20], Some(1600.0));
+
+    // Back under the cap, the ceiling recovers every reading
+    let mut last = ceilings[20];
+    for i in 21..=40 {
+        last = budget.update(at(i * 100), 80.0, 1600.0, 350.0, 2000.0);
+    }
+    assert_eq!(last, None, "did not recover");
+}
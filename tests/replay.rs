@@ -0,0 +1,71 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    time::{Duration, Instant},
+};
+
+use bc_250_rust_governor::replay::{parse_trace, TraceRecorder};
+
+#[test]
+fn recorded_rows_and_plain_lines_parse() {
+    let trace = parse_trace("\
+# bc-250-rust-governor trace, 1000μs sample interval
+0.000,sample,1,0.500,0.500,0,1000
+1.000,apply,1100
+1.200,applied,1100,180
+2.000,sample,0,0.400,0.450,0,1100
+3.000 1   # a hand-written sample
+
+3.500,failed,1200,busy
+").unwrap();
+    let samples: Vec<(f64, bool)> = trace.samples.iter().map(|s| (s.at_ms, s.busy)).collect();
+    assert_eq!(samples, [(0.0, true), (2.0, false), (3.0, true)]);
+    assert_eq!(trace.recorded_applies, 1);
+}
+
+#[test]
+fn fractional_busy_dithers_to_that_share() {
+    let text: String = (0..100).map(|ms| format!("{ms} 0.25\n")).collect();
+    let busy = parse_trace(&text).unwrap().samples.iter().filter(|s| s.busy).count();
+    assert_eq!(busy, 25);
+}
+
+#[test]
+fn malformed_traces_name_the_line() {
+    for (text, line) in [
+        ("0 1\nnonsense\n", 2),
+        ("0 1\n1 1.5\n", 2),
+        ("5 1\n4 0\n", 2),
+        ("0,sample,yes\n", 1),
+    ] {
+        let error = parse_trace(text).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with(&format!("trace line {line}:")), "{text:?}: {error}");
+    }
+}
+
+#[test]
+fn recordings_replay_sample_for_sample() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.csv");
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    {
+        let mut recorder = TraceRecorder::create(&path, Duration::from_millis(1)).unwrap();
+        recorder.sample(at(1), true, 1.0, 0.5, true, 1800.0);
+        recorder.apply(at(1), 1800);
+        recorder.applied(at(2), 1800, 250);
+        recorder.sample(at(2), false, 0.5, 0.5, false, 1800.0);
+        recorder.failed(at(3), 1000, "write failed, device busy");
+        recorder.sample(at(3), false, 0.0, 0.0, false, 1000.0);
+    }
+
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("# bc-250-rust-governor trace, 1000μs sample interval\n"), "{text}");
+    assert!(text.lines().any(|l| l.ends_with(",failed,1000,write failed; device busy")), "{text}");
+
+    let trace = parse_trace(&text).unwrap();
+    assert_eq!(trace.samples.iter().map(|s| s.busy).collect::<Vec<_>>(), [true, false, false]);
+    assert!(trace.samples.windows(2).all(|w| w[0].at_ms < w[1].at_ms));
+    assert_eq!(trace.recorded_applies, 1);
+}
@@ -0,0 +1,65 @@
+use bc_250_rust_governor::schedule::{format_time, parse_days, parse_time, Window};
+
+const SUN: u8 = 0;
+const FRI: u8 = 5;
+const SAT: u8 = 6;
+
+fn window(from: &str, to: &str, days: &[&str]) -> Window {
+    let days: Vec<String> = days.iter().map(|d| d.to_string()).collect();
+    Window {
+        from: parse_time(from).unwrap(),
+        to: parse_time(to).unwrap(),
+        days: parse_days(&days).unwrap(),
+        profile: "quiet".to_string(),
+    }
+}
+
+#[test]
+fn times_and_days_parse() {
+    assert_eq!(parse_time("23:00"), Some(1380));
+    assert_eq!(parse_time(" 7:05 "), Some(425));
+    assert_eq!(parse_time("00:00"), Some(0));
+    for bad in ["24:00", "12:60", "1200", "12:", "-1:00"] {
+        assert_eq!(parse_time(bad), None, "{bad}");
+    }
+    assert_eq!(format_time(425), "07:05");
+
+    assert_eq!(parse_days(&[]), Some(0x7F));
+    assert_eq!(parse_days(&["Sun".to_string(), "sat".to_string()]), Some(0b100_0001));
+    assert_eq!(parse_days(&["someday".to_string()]), None);
+}
+
+#[test]
+fn daytime_window_starts_inclusive_and_ends_exclusive() {
+    let day = window("09:00", "17:00", &["mon", "tue", "wed", "thu", "fri"]);
+    assert!(day.contains(FRI, 9 * 60));
+    assert!(day.contains(FRI, 17 * 60 - 1));
+    assert!(!day.contains(FRI, 17 * 60));
+    assert!(!day.contains(FRI, 9 * 60 - 1));
+    assert!(!day.contains(SAT, 12 * 60));
+}
+
+#[test]
+fn overnight_window_runs_into_the_next_day() {
+    let night = window("23:00", "07:00", &["fri"]);
+    assert!(night.contains(FRI, 23 * 60));
+    assert!(night.contains(SAT, 0));
+    assert!(night.contains(SAT, 7 * 60 - 1));
+    assert!(!night.contains(SAT, 7 * 60));
+    // Friday morning belongs to Thursday night, which the window does not start on
+    assert!(!night.contains(FRI, 3 * 60));
+    assert!(!night.contains(SAT, 23 * 60));
+
+    // Saturday night wraps into Sunday, across the end of the week
+    let weekend = window("22:00", "02:00", &["sat"]);
+    assert!(weekend.contains(SUN, 60));
+    assert!(!weekend.contains(SUN, 22 * 60));
+}
+
+#[test]
+fn window_ending_where_it_starts_covers_the_day() {
+    let sunday = window("06:00", "06:00", &["sun"]);
+    assert!(sunday.contains(SUN, 0));
+    assert!(sunday.contains(SUN, 24 * 60 - 1));
+    assert!(!sunday.contains(SAT, 12 * 60));
+}
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use bc_250_rust_governor::systemd::render_unit;
+
+#[test]
+fn installed_unit_is_the_shipped_one_with_the_given_paths() {
+    let shipped = include_str!("../bc-250-rust-governor.service");
+    let unit = render_unit(Path::new("/opt/governor/bin/bc-250-rust-governor"), Path::new("/srv/governor.toml"));
+
+    assert!(unit.contains("\nExecStart=/opt/governor/bin/bc-250-rust-governor /srv/governor.toml\n"), "{unit}");
+    // Every other line is the shipped file's
+    let differing: Vec<(&str, &str)> = shipped.lines().zip(unit.lines()).filter(|(a, b)| a != b).collect();
+    assert_eq!(differing.len(), 1, "{differing:?}");
+    assert_eq!(shipped.lines().count(), unit.lines().count());
+}
+
+#[test]
+fn paths_with_spaces_and_specifiers_stay_one_word() {
+    let unit = render_unit(Path::new("/opt/my governor/bc-250-rust-governor"), Path::new("/srv/100% \"tuned\".toml"));
+
+    assert!(unit.contains("\nExecStart=\"/opt/my governor/bc-250-rust-governor\" \"/srv/100%% \\\"tuned\\\".toml\"\n"), "{unit}");
+}